@@ -1,4 +1,6 @@
 use d3dx::*;
+use geometry::{Aabb, Ray};
+use crate::cpu_mesh::*;
 
 // Bounding Volumes
 #[derive(Clone)]
@@ -26,6 +28,48 @@ impl Default for AABB {
 }
 
 impl AABB {
+    pub fn from_aabb(b: &Aabb) -> AABB {
+        AABB { min_pt: to_d3dx_vec3(b.min), max_pt: to_d3dx_vec3(b.max) }
+    }
+
+    pub fn to_aabb(&self) -> Aabb {
+        Aabb::new(to_vec3(&self.min_pt), to_vec3(&self.max_pt))
+    }
+
+    // True for the default (inverted) box until something is added to it.
+    pub fn is_empty(&self) -> bool {
+        self.to_aabb().is_empty()
+    }
+
+    pub fn merge(&mut self, other: &AABB) {
+        *self = AABB::from_aabb(&self.to_aabb().union(&other.to_aabb()));
+    }
+
+    // Union with a point.
+    pub fn grow(&mut self, p: &D3DXVECTOR3) {
+        let mut b = self.to_aabb();
+        b.grow(to_vec3(p));
+        *self = AABB::from_aabb(&b);
+    }
+
+    pub fn contains_point(&self, p: &D3DXVECTOR3) -> bool {
+        self.to_aabb().contains_point(to_vec3(p))
+    }
+
+    pub fn contains(&self, other: &AABB) -> bool {
+        self.to_aabb().contains(&other.to_aabb())
+    }
+
+    pub fn intersects(&self, other: &AABB) -> bool {
+        self.to_aabb().intersects(&other.to_aabb())
+    }
+
+    // Parameter range [t_near, t_far] the ray spends inside the box, for rays
+    // starting at 'origin'.  Replaces D3DXBoxBoundProbe.
+    pub fn intersect_ray(&self, origin: &D3DXVECTOR3, dir: &D3DXVECTOR3) -> Option<(f32, f32)> {
+        self.to_aabb().intersect_ray(&Ray::new(to_vec3(origin), to_vec3(dir)), 0.0, f32::MAX)
+    }
+
     pub fn center(&self) -> D3DXVECTOR3 {
         D3DXVECTOR3 {
             x: 0.5 * (self.min_pt.x + self.max_pt.x),
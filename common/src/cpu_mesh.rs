@@ -0,0 +1,145 @@
+// Glue between the CPU-side types of the geometry crate and D3DX.
+
+use std::slice::{from_raw_parts, from_raw_parts_mut};
+use libc::c_void;
+use windows::Win32::Graphics::Direct3D9::*;
+use d3dx::*;
+use geometry::{attribute_table, Mat4, Mesh, Vec3};
+use crate::*;
+
+pub fn to_vec3(v: &D3DXVECTOR3) -> Vec3 {
+    Vec3 { x: v.x, y: v.y, z: v.z }
+}
+
+pub fn to_d3dx_vec3(v: Vec3) -> D3DXVECTOR3 {
+    D3DXVECTOR3 { x: v.x, y: v.y, z: v.z }
+}
+
+pub fn to_mat4(m: &D3DXMATRIX) -> Mat4 {
+    let mut res = Mat4::IDENTITY;
+    for i in 0..4 {
+        for j in 0..4 {
+            res.m[i][j] = unsafe { m.Anonymous.m[i * 4 + j] };
+        }
+    }
+    res
+}
+
+pub fn to_d3dx_matrix(m: &Mat4) -> D3DXMATRIX {
+    let mut res = D3DXMATRIX::default();
+    for i in 0..4 {
+        for j in 0..4 {
+            unsafe { res.Anonymous.m[i * 4 + j] = m.m[i][j]; }
+        }
+    }
+    res
+}
+
+// Copies the vertex buffer of a mesh into CPU memory, for instance for
+// geometry::Aabb::from_points.  The mesh vertex format has to have the same
+// layout as V.
+pub fn read_d3dx_vertices<V: Copy>(mesh: LPD3DXMESH) -> Vec<V> {
+    unsafe {
+        let num_vertices = ID3DXBaseMesh_GetNumVertices(mesh) as usize;
+
+        assert_eq!(ID3DXBaseMesh_GetNumBytesPerVertex(mesh) as usize, std::mem::size_of::<V>(),
+                   "Mesh vertex format does not match the requested vertex type");
+
+        let mut v: *mut c_void = std::ptr::null_mut();
+        HR!(ID3DXBaseMesh_LockVertexBuffer(mesh, D3DLOCK_READONLY as u32, &mut v));
+        let vertices: Vec<V> = from_raw_parts(v as *const V, num_vertices).to_vec();
+        HR!(ID3DXBaseMesh_UnlockVertexBuffer(mesh));
+
+        vertices
+    }
+}
+
+// Copies the vertex, index and attribute buffers of a mesh into CPU memory.  The
+// mesh vertex format has to have the same layout as V.
+pub fn read_d3dx_mesh<V: Copy>(mesh: LPD3DXMESH) -> Mesh<V> {
+    unsafe {
+        let num_faces = ID3DXBaseMesh_GetNumFaces(mesh) as usize;
+
+        let vertices: Vec<V> = read_d3dx_vertices(mesh);
+
+        let mut i: *mut c_void = std::ptr::null_mut();
+        HR!(ID3DXBaseMesh_LockIndexBuffer(mesh, D3DLOCK_READONLY as u32, &mut i));
+        let indices: Vec<u32> = if ID3DXBaseMesh_GetOptions(mesh) & D3DXMESH_32BIT != 0 {
+            from_raw_parts(i as *const u32, num_faces * 3).to_vec()
+        } else {
+            from_raw_parts(i as *const u16, num_faces * 3).iter().map(|i| *i as u32).collect()
+        };
+        HR!(ID3DXBaseMesh_UnlockIndexBuffer(mesh));
+
+        let mut a: *mut u32 = std::ptr::null_mut();
+        HR!(ID3DXMesh_LockAttributeBuffer(mesh, D3DLOCK_READONLY as u32, &mut a));
+        let attributes: Vec<u32> = from_raw_parts(a, num_faces).to_vec();
+        HR!(ID3DXMesh_UnlockAttributeBuffer(mesh));
+
+        Mesh { vertices, indices, attributes }
+    }
+}
+
+// Creates an ID3DXMesh holding a CPU-side mesh.  'decl' must describe V.  32-bit
+// indices are used only when the vertex count does not fit 16 bits.  The faces
+// must be sorted by attribute (see geometry::attribute_sort, which
+// geometry::optimize_mesh runs); the attribute table is set from them so
+// DrawSubset can be used right away, and faces and vertices keep their order.
+pub fn create_d3dx_mesh<V: Copy>(d3d_device: IDirect3DDevice9, decl: &Option<IDirect3DVertexDeclaration9>,
+                                 mesh: &Mesh<V>, options: u32) -> LPD3DXMESH {
+    unsafe {
+        let num_vertices = mesh.vertices.len();
+        let num_faces = mesh.indices.len() / 3;
+        let use_32bit = num_vertices > u16::MAX as usize;
+
+        let mut elems: [D3DVERTEXELEMENT9; MAX_FVF_DECL_SIZE as usize] = [D3DVERTEXELEMENT9::default(); MAX_FVF_DECL_SIZE as usize];
+        let mut num_elems: u32 = 0;
+        if let Some(decl) = decl {
+            HR!(decl.GetDeclaration(elems.as_mut_ptr(), &mut num_elems));
+        }
+
+        let mut out: LPD3DXMESH = std::ptr::null_mut();
+        HR!(D3DXCreateMesh(num_faces as u32, num_vertices as u32,
+            options | if use_32bit { D3DXMESH_32BIT } else { 0 },
+            elems.as_mut_ptr(), d3d_device, &mut out));
+
+        let mut v: *mut c_void = std::ptr::null_mut();
+        HR!(ID3DXBaseMesh_LockVertexBuffer(out, 0, &mut v));
+        from_raw_parts_mut(v as *mut V, num_vertices).copy_from_slice(&mesh.vertices);
+        HR!(ID3DXBaseMesh_UnlockVertexBuffer(out));
+
+        let mut i: *mut c_void = std::ptr::null_mut();
+        HR!(ID3DXBaseMesh_LockIndexBuffer(out, 0, &mut i));
+        if use_32bit {
+            from_raw_parts_mut(i as *mut u32, num_faces * 3).copy_from_slice(&mesh.indices);
+        } else {
+            let indices: &mut [u16] = from_raw_parts_mut(i.cast(), num_faces * 3);
+            for (dst, src) in indices.iter_mut().zip(&mesh.indices) {
+                *dst = *src as u16;
+            }
+        }
+        HR!(ID3DXBaseMesh_UnlockIndexBuffer(out));
+
+        let mut a: *mut u32 = std::ptr::null_mut();
+        HR!(ID3DXMesh_LockAttributeBuffer(out, 0, &mut a));
+        from_raw_parts_mut(a, num_faces).copy_from_slice(&mesh.attributes);
+        HR!(ID3DXMesh_UnlockAttributeBuffer(out));
+
+        let table: Vec<D3DXATTRIBUTERANGE> = attribute_table(mesh).iter()
+            .map(|r| D3DXATTRIBUTERANGE {
+                AttribId: r.attrib_id,
+                FaceStart: r.face_start,
+                FaceCount: r.face_count,
+                VertexStart: r.vertex_start,
+                VertexCount: r.vertex_count,
+            })
+            .collect();
+        for (i, range) in table.iter().enumerate() {
+            assert!(table[..i].iter().all(|r| r.AttribId != range.AttribId),
+                    "Mesh faces are not sorted by attribute");
+        }
+        HR!(ID3DXMesh_SetAttributeTable(out, table.as_ptr(), table.len() as u32));
+
+        out
+    }
+}
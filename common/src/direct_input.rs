@@ -4,14 +4,115 @@ use windows::{
 
 use winapi::um::dinput::{c_dfDIKeyboard, c_dfDIMouse2};
 
+use input::*;
+
 use crate::*;
 
+// Read from the working directory, like the demo resources.  Actions it lists
+// replace the defaults below, which are only kept here; a missing file keeps
+// them all.
+pub const BINDINGS_FILE: &str = "bindings.cfg";
+
+// Actions of single demos, bound on top of input::DEFAULT_BINDINGS.
+pub const DEMO_BINDINGS: &str = "\
+# Sprite demo
+Turn = +A, -D
+Thrust = +W, -S
+
+# Robot arm demo
+RotateBone = +A, -D
+SelectBone1 = 1
+SelectBone2 = 2
+SelectBone3 = 3
+SelectBone4 = 4
+SelectBone5 = 5
+
+# Solar system demo
+FollowPlanet = F
+
+# Terrain demo
+Sculpt = Mouse0
+RaiseBrush = 1
+LowerBrush = 2
+FlattenBrush = 3
+SmoothBrush = 4
+NoiseBrush = 5
+BrushRadius = +RBracket, -LBracket
+SaveHeightmap = F5
+SunAzimuth = +Right, -Left
+BakeLighting = L
+
+# Camera demo
+WalkCamera = 1
+FlyCamera = 2
+OrbitCamera = 3
+PathCamera = 4
+AddPathKey = K
+PlayPath = P
+ScrubPath = +Right, -Left
+";
+
+pub fn load_bindings() -> ActionMap {
+    let mut bindings = ActionMap::standard();
+    bindings.extend(&ActionMap::parse(DEMO_BINDINGS).unwrap());
+
+    match ActionMap::load(BINDINGS_FILE) {
+        Ok(file_bindings) => bindings.extend(&file_bindings),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => println!("[load_bindings] {}: {}", BINDINGS_FILE, err),
+    }
+
+    bindings
+}
+
+// Where the input of a frame comes from.
+enum InputSession {
+    Live,
+    // Reading the devices and keeping every frame, to save to the path on exit.
+    Recording(InputRecording, String),
+    Playback(InputPlayback),
+}
+
+// A demo started with '--record-input <file>' records its session, one started
+// with '--replay-input <file>' replays it.
+fn session_from_args() -> InputSession {
+    let args: Vec<String> = std::env::args().collect();
+    let value = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned();
+
+    if let Some(path) = value("--replay-input") {
+        match InputRecording::load(&path) {
+            Ok(recording) => {
+                println!("[DirectInput] replaying {} frames from {}", recording.len(), path);
+                return InputSession::Playback(InputPlayback::new(recording));
+            }
+            Err(err) => println!("[DirectInput] {}: {}", path, err),
+        }
+    }
+
+    if let Some(path) = value("--record-input") {
+        return InputSession::Recording(InputRecording::new(rand::random()), path);
+    }
+
+    InputSession::Live
+}
+
+// The time to update the frame with: 'dt' as measured, or the recorded frame
+// time when the demo's DirectInput replays a session.  Called once a frame by
+// every demo's main loop.
+pub fn get_frame_dt(direct_input: &mut Option<DirectInput>, dt: f32) -> f32 {
+    direct_input.as_mut().map_or(dt, |dinput| dinput.frame_dt(dt))
+}
+
 pub struct DirectInput {
     keyboard: Option<IDirectInputDevice8A>,
     mouse: Option<IDirectInputDevice8A>,
     keyboard_state: [u8; 256],
     mouse_state: DIMOUSESTATE2,
     dinput: Option<IDirectInput8A>,
+    bindings: ActionMap,
+    session: InputSession,
+    // Time of the frame being polled, as passed to frame_dt.
+    dt: f32,
 }
 
 impl DirectInput {
@@ -23,6 +124,9 @@ impl DirectInput {
                 keyboard_state: [0; 256],
                 mouse_state: std::mem::zeroed(),
                 dinput: None,
+                bindings: load_bindings(),
+                session: session_from_args(),
+                dt: 0.0,
             };
 
             HR!(DirectInput8Create(
@@ -72,9 +176,79 @@ impl DirectInput {
             unsafe { let _result = mouse.Unacquire(); }
             // drop(&mouse);
         }
+
+        if let InputSession::Recording(recording, path) = &self.session {
+            match recording.save(path) {
+                Ok(()) => println!("[DirectInput] recorded {} frames to {}", recording.len(), path),
+                Err(err) => println!("[DirectInput] {}: {}", path, err),
+            }
+        }
+    }
+
+    // Seed for the random number generators of the demo: the recorded one when
+    // replaying, so random effects come out the same, and a fresh one otherwise.
+    pub fn get_seed(&self) -> u64 {
+        match &self.session {
+            InputSession::Live => rand::random(),
+            InputSession::Recording(recording, _) => recording.seed,
+            InputSession::Playback(playback) => playback.get_recording().seed,
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self.session, InputSession::Playback(_))
+    }
+
+    // Called once a frame with the measured frame time, before the scene is
+    // updated.  Returns the time to update with, which is the recorded one when
+    // replaying.
+    pub fn frame_dt(&mut self, dt: f32) -> f32 {
+        self.dt = dt;
+
+        match &self.session {
+            InputSession::Playback(playback) => playback.peek().map_or(dt, |frame| frame.dt),
+            _ => dt,
+        }
     }
 
     pub fn poll(&mut self) {
+        if let InputSession::Playback(playback) = &mut self.session {
+            // Replay the recorded snapshot instead of reading the devices.  Once the
+            // recording runs out everything reads as released.
+            let frame = playback.next_frame();
+            if frame.is_some() && playback.is_finished() {
+                println!("[DirectInput] replay finished after {} frames", playback.get_position());
+            }
+
+            self.set_state(&frame.unwrap_or_default().input);
+            return;
+        }
+
+        self.poll_devices();
+
+        if matches!(self.session, InputSession::Recording(..)) {
+            let snapshot = MemoryInput::from_state(self);
+            if let InputSession::Recording(recording, _) = &mut self.session {
+                recording.push(self.dt, &snapshot);
+            }
+        }
+    }
+
+    fn set_state(&mut self, input: &MemoryInput) {
+        for (state, down) in self.keyboard_state.iter_mut().zip(input.get_keys()) {
+            *state = if *down { 0x80 } else { 0 };
+        }
+
+        for (state, down) in self.mouse_state.rgbButtons.iter_mut().zip(input.get_mouse_buttons()) {
+            *state = if *down { 0x80 } else { 0 };
+        }
+
+        self.mouse_state.lX = input.mouse_dx() as i32;
+        self.mouse_state.lY = input.mouse_dy() as i32;
+        self.mouse_state.lZ = input.mouse_dz() as i32;
+    }
+
+    fn poll_devices(&mut self) {
         unsafe {
             // Poll keyboard.
             if let Some(keyboard) = &self.keyboard {
@@ -104,23 +278,64 @@ impl DirectInput {
         }
     }
 
-    pub fn key_down(&mut self, key: usize) -> bool {
-        self.keyboard_state[key] & 0x80 != 0
+    // False for keys and buttons past the end of the state, like MemoryInput.
+    pub fn key_down(&self, key: usize) -> bool {
+        key < self.keyboard_state.len() && self.keyboard_state[key] & 0x80 != 0
     }
 
-    pub fn mouse_dx(&mut self) -> f32 {
+    pub fn mouse_dx(&self) -> f32 {
         self.mouse_state.lX as f32
     }
 
-    pub fn mouse_dy(&mut self) -> f32 {
+    pub fn mouse_dy(&self) -> f32 {
         self.mouse_state.lY as f32
     }
 
-    pub fn mouse_dz(&mut self) -> f32 {
+    pub fn mouse_dz(&self) -> f32 {
         self.mouse_state.lZ as f32
     }
 
-    pub fn mouse_button_down(&mut self, button: u32) -> bool {
-        self.mouse_state.rgbButtons[button as usize] & 0x80 != 0
+    pub fn mouse_button_down(&self, button: u32) -> bool {
+        let button = button as usize;
+        button < self.mouse_state.rgbButtons.len() && self.mouse_state.rgbButtons[button] & 0x80 != 0
+    }
+
+    pub fn get_bindings(&self) -> &ActionMap {
+        &self.bindings
+    }
+
+    pub fn set_bindings(&mut self, bindings: ActionMap) {
+        self.bindings = bindings;
+    }
+
+    // Action queries against the current snapshot, see input::ActionMap.
+    pub fn action_down(&self, action: &str) -> bool {
+        self.bindings.down(self, action)
+    }
+
+    pub fn action_axis(&self, action: &str) -> f32 {
+        self.bindings.axis(self, action)
+    }
+}
+
+impl InputState for DirectInput {
+    fn key_down(&self, key: usize) -> bool {
+        DirectInput::key_down(self, key)
+    }
+
+    fn mouse_dx(&self) -> f32 {
+        DirectInput::mouse_dx(self)
+    }
+
+    fn mouse_dy(&self) -> f32 {
+        DirectInput::mouse_dy(self)
+    }
+
+    fn mouse_dz(&self) -> f32 {
+        DirectInput::mouse_dz(self)
+    }
+
+    fn mouse_button_down(&self, button: u32) -> bool {
+        DirectInput::mouse_button_down(self, button)
     }
 }
\ No newline at end of file
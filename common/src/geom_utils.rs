@@ -8,201 +8,38 @@ use d3dx::*;
 use crate::*;
 use crate::mtrl::Mtrl;
 
-pub fn gen_tri_grid(num_vert_rows: i32, num_vert_cols: i32, dx: f32, dz: f32,
-                    center: D3DXVECTOR3, verts: &mut Vec<D3DXVECTOR3>, indices: &mut Vec<u16>) {
-    let num_vertices = num_vert_rows * num_vert_cols;
-    let num_cell_rows = num_vert_rows - 1;
-    let num_cell_cols = num_vert_cols - 1;
-
-    let num_tris = num_cell_rows * num_cell_cols * 2;
-
-    let width: f32 = num_cell_cols as f32 * dx;
-    let depth: f32 = num_cell_rows as f32 * dz;
-
-    //===========================================
-    // Build vertices.
-
-    // We first build the grid geometry centered about the origin and on
-    // the xz-plane, row-by-row and in a top-down fashion.  We then translate
-    // the grid vertices so that they are centered about the specified
-    // parameter 'center'.
-
-    verts.resize(num_vertices as usize, D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 });
-
-    // Offsets to translate grid from quadrant 4 to center of
-    // coordinate system.
-    let x_offset: f32 = -width * 0.5;
-    let z_offset: f32 =  depth * 0.5;
-
-    let mut k = 0;
-    for i in 0..num_vert_rows {
-        for j in 0..num_vert_cols {
-            // Negate the depth coordinate to put in quadrant four.
-            // Then offset to center about coordinate system.
-            verts[k].x = j as f32 * dx + x_offset;
-            verts[k].z = -i as f32 * dz + z_offset;
-            verts[k].y = 0.0;
-
-            // Translate so that the center of the grid is at the
-            // specified 'center' parameter.
-
-            unsafe {
-                let mut t: D3DXMATRIX = std::mem::zeroed();
-                D3DXMatrixTranslation(&mut t, center.x, center.y, center.z);
-                D3DXVec3TransformCoord(&mut verts[k], &verts[k], &t);
-            }
-
-            // Next vertex
-            k += 1;
-        }
-    }
-
-    //===========================================
-    // Build indices.
-
-    indices.resize((num_tris * 3) as usize, 0);
+pub use geometry::GridError;
 
-    // Generate indices for each quad.
-    k = 0;
-    for i in 0..num_cell_rows {
-        for j in 0..num_cell_cols {
-            indices[k]     = (i * num_vert_cols + j) as u16;
-            indices[k + 1] = (i * num_vert_cols + j + 1) as u16;
-            indices[k + 2] = ((i + 1) * num_vert_cols + j) as u16;
-
-            indices[k + 3] = ((i + 1) * num_vert_cols + j) as u16;
-            indices[k + 4] = (i * num_vert_cols + j + 1) as u16;
-            indices[k + 5] = ((i + 1) * num_vert_cols + j + 1) as u16;
-
-            // next quad
-            k += 6;
-        }
-    }
+pub fn gen_tri_grid(num_vert_rows: i32, num_vert_cols: i32, dx: f32, dz: f32, center: D3DXVECTOR3,
+                    verts: &mut Vec<D3DXVECTOR3>, indices: &mut Vec<u16>) -> Result<(), GridError> {
+    gen_tri_grid_generic(num_vert_rows, num_vert_cols, dx, dz, center, verts, indices)
 }
 
-pub fn gen_tri_grid_32(num_vert_rows: i32, num_vert_cols: i32, dx: f32, dz: f32,
-                    center: D3DXVECTOR3, verts: &mut Vec<D3DXVECTOR3>, indices: &mut Vec<u32>) {
-    let num_vertices = num_vert_rows * num_vert_cols;
-    let num_cell_rows = num_vert_rows - 1;
-    let num_cell_cols = num_vert_cols - 1;
-
-    let num_tris = num_cell_rows * num_cell_cols * 2;
-
-    let width: f32 = num_cell_cols as f32 * dx;
-    let depth: f32 = num_cell_rows as f32 * dz;
-
-    //===========================================
-    // Build vertices.
-
-    // We first build the grid geometry centered about the origin and on
-    // the xz-plane, row-by-row and in a top-down fashion.  We then translate
-    // the grid vertices so that they are centered about the specified
-    // parameter 'center'.
-
-    verts.resize(num_vertices as usize, D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 });
-
-    // Offsets to translate grid from quadrant 4 to center of
-    // coordinate system.
-    let x_offset: f32 = -width * 0.5;
-    let z_offset: f32 =  depth * 0.5;
-
-    let mut k = 0;
-    for i in 0..num_vert_rows {
-        for j in 0..num_vert_cols {
-            // Negate the depth coordinate to put in quadrant four.
-            // Then offset to center about coordinate system.
-            verts[k].x = j as f32 * dx + x_offset;
-            verts[k].z = -i as f32 * dz + z_offset;
-            verts[k].y = 0.0;
-
-            // Translate so that the center of the grid is at the
-            // specified 'center' parameter.
-
-            unsafe {
-                let mut t: D3DXMATRIX = std::mem::zeroed();
-                D3DXMatrixTranslation(&mut t, center.x, center.y, center.z);
-                D3DXVec3TransformCoord(&mut verts[k], &verts[k], &t);
-            }
-
-            // Next vertex
-            k += 1;
-        }
-    }
-
-    //===========================================
-    // Build indices.
-
-    indices.resize((num_tris * 3) as usize, 0);
-
-    // Generate indices for each quad.
-    k = 0;
-    for i in 0..num_cell_rows {
-        for j in 0..num_cell_cols {
-            indices[k]     = (i * num_vert_cols + j) as u32;
-            indices[k + 1] = (i * num_vert_cols + j + 1) as u32;
-            indices[k + 2] = ((i + 1) * num_vert_cols + j) as u32;
-
-            indices[k + 3] = ((i + 1) * num_vert_cols + j) as u32;
-            indices[k + 4] = (i * num_vert_cols + j + 1) as u32;
-            indices[k + 5] = ((i + 1) * num_vert_cols + j + 1) as u32;
+pub fn gen_tri_grid_32(num_vert_rows: i32, num_vert_cols: i32, dx: f32, dz: f32, center: D3DXVECTOR3,
+                       verts: &mut Vec<D3DXVECTOR3>, indices: &mut Vec<u32>) -> Result<(), GridError> {
+    gen_tri_grid_generic(num_vert_rows, num_vert_cols, dx, dz, center, verts, indices)
+}
 
-            // next quad
-            k += 6;
-        }
-    }
+// Builds a grid centered about 'center' on a plane parallel to the xz-plane.  Fails
+// if the grid has no cells or more vertices than the index type can address (use
+// gen_tri_grid_32 or geometry::build_tri_grid for big grids).
+fn gen_tri_grid_generic<I: geometry::GridIndex>(num_vert_rows: i32, num_vert_cols: i32, dx: f32, dz: f32,
+                                                center: D3DXVECTOR3, verts: &mut Vec<D3DXVECTOR3>,
+                                                indices: &mut Vec<I>) -> Result<(), GridError> {
+    let options = geometry::GridOptions { center: to_vec3(&center), ..Default::default() };
+
+    // Negative sizes have no cells either.
+    let (grid_verts, grid_indices) =
+        geometry::build_tri_grid::<geometry::Vec3, I>(num_vert_rows.max(0) as u32, num_vert_cols.max(0) as u32,
+                                                      dx, dz, &options)?;
+
+    *verts = grid_verts.iter().map(|p| to_d3dx_vec3(*p)).collect();
+    *indices = grid_indices;
+    Ok(())
 }
 
 pub fn gen_spherical_tex_coords(d3d_device: IDirect3DDevice9, sphere: &mut LPD3DXMESH) {
-    // D3DXCreate* functions generate vertices with position
-    // and normal data.  But for texturing, we also need
-    // tex-coords.  So clone the mesh to change the vertex
-    // format to a format with tex-coords.
-    let mut elements: [D3DVERTEXELEMENT9; 64] = [D3DVERTEXELEMENT9::default(); 64];
-    let mut num_elements: u32 = 0;
-    unsafe {
-        if let Some(decl) = &VERTEX_PNT_DECL {
-            HR!(decl.GetDeclaration(elements.as_mut_ptr(), &mut num_elements));
-        }
-
-        let mut temp: LPD3DXMESH = std::ptr::null_mut();
-        HR!(ID3DXBaseMesh_CloneMesh(*sphere, D3DXMESH_SYSTEMMEM, elements.as_mut_ptr(),
-                d3d_device.clone(), &mut temp));
-
-        ReleaseCOM(*sphere);
-
-        // Now generate texture coordinates for each vertex.
-        let mut verts: *mut c_void = std::ptr::null_mut();
-        HR!(ID3DXBaseMesh_LockVertexBuffer(temp, 0, &mut verts));
-
-        let num_vertices: usize = ID3DXBaseMesh_GetNumVertices(temp) as usize;
-        let vertices: &mut [VertexPNT] = from_raw_parts_mut(verts as *mut VertexPNT, num_vertices);
-
-        for i in 0..num_vertices {
-            // Convert to spherical coordinates.
-            let p: D3DXVECTOR3 = vertices[i].pos;
-
-            let theta: f32 = p.z.atan2(p.x);
-            let phi: f32 = (p.y / (p.x * p.x + p.y * p.y + p.z * p.z).sqrt()).acos();
-
-            // Phi and theta give the texture coordinates, but are not in
-            // the range [0, 1], so scale them into that range.
-
-            let u: f32 = theta / (2.0 * D3DX_PI);
-            let v: f32 = phi / D3DX_PI;
-
-            // Save texture coordinates.
-            vertices[i].tex0.x = u;
-            vertices[i].tex0.y = v;
-        }
-
-        HR!(ID3DXBaseMesh_UnlockVertexBuffer(temp));
-
-        // Clone back to a hardware mesh.
-        HR!(ID3DXBaseMesh_CloneMesh(temp, D3DXMESH_MANAGED | D3DXMESH_WRITEONLY, elements.as_mut_ptr(),
-                d3d_device.clone(), sphere));
-
-        ReleaseCOM(temp);
-    }
+    gen_tex_coords(d3d_device, sphere, geometry::UvProjection::Spherical, &to_d3dx_matrix(&geometry::Mat4::IDENTITY));
 }
 
 // Cylinder Axis
@@ -213,6 +50,20 @@ pub enum Axis {
 }
 
 pub fn gen_cyl_tex_coords(d3d_device: IDirect3DDevice9, cylinder: &mut LPD3DXMESH, axis: Axis) {
+    let axis = match axis {
+        Axis::X => geometry::Vec3::X,
+        Axis::Y => geometry::Vec3::Y,
+        Axis::Z => geometry::Vec3::Z,
+    };
+
+    gen_tex_coords(d3d_device, cylinder, geometry::UvProjection::Cylindrical(axis), &to_d3dx_matrix(&geometry::Mat4::IDENTITY));
+}
+
+// Replaces 'mesh' with a VertexPNT copy whose tex-coords come from 'projection',
+// applied to the positions transformed by 'transform'.  Vertices on texture seams
+// are duplicated, so the face count stays the same but the vertex count may grow.
+pub fn gen_tex_coords(d3d_device: IDirect3DDevice9, mesh: &mut LPD3DXMESH, projection: geometry::UvProjection,
+                      transform: &D3DXMATRIX) {
     // D3DXCreate* functions generate vertices with position
     // and normal data.  But for texturing, we also need
     // tex-coords.  So clone the mesh to change the vertex
@@ -225,111 +76,66 @@ pub fn gen_cyl_tex_coords(d3d_device: IDirect3DDevice9, cylinder: &mut LPD3DXMES
         }
 
         let mut temp: LPD3DXMESH = std::ptr::null_mut();
-        HR!(ID3DXBaseMesh_CloneMesh(*cylinder, D3DXMESH_SYSTEMMEM, elements.as_mut_ptr(),
+        HR!(ID3DXBaseMesh_CloneMesh(*mesh, D3DXMESH_SYSTEMMEM, elements.as_mut_ptr(),
                 d3d_device.clone(), &mut temp));
 
-        ReleaseCOM(*cylinder);
+        ReleaseCOM(*mesh);
 
         // Now generate texture coordinates for each vertex.
-        let mut verts: *mut c_void = std::ptr::null_mut();
-        HR!(ID3DXBaseMesh_LockVertexBuffer(temp, 0, &mut verts));
-
-        // We need to get the height of the cylinder we are projecting the
-        // vertices onto.  That height depends on which axis the client has
-        // specified that the cylinder lies on.  The height is determined by
-        // finding the height of the bounding cylinder on the specified axis.
-
-        let mut max_point = D3DXVECTOR3 { x: -f32::MAX, y: -f32::MAX, z: -f32::MAX };
-        let mut min_point = D3DXVECTOR3 { x: f32::MAX, y: f32::MAX, z: f32::MAX };
+        let mut cpu_mesh: geometry::Mesh<geometry::VertexPNT> = read_d3dx_mesh(temp);
+        geometry::generate_tex_coords(&mut cpu_mesh, projection, &to_mat4(transform));
 
-        let num_vertices: usize = ID3DXBaseMesh_GetNumVertices(temp) as usize;
-        let vertices: &mut [VertexPNT] = from_raw_parts_mut(verts as *mut VertexPNT, num_vertices);
-
-        for i in 0..num_vertices {
-            D3DXVec3Maximize(&mut max_point, &max_point, &vertices[i].pos);
-            D3DXVec3Minimize(&mut min_point, &min_point, &vertices[i].pos);
-        }
+        // An unoptimized mesh may not have its subsets contiguous.  The sort is
+        // stable, so the faces of a sorted mesh keep their order.
+        geometry::attribute_sort(&mut cpu_mesh);
 
-        let a: f32;
-        let b: f32;
-        let h: f32;
-
-        match axis {
-            Axis::X => {
-                a = min_point.x;
-                b = max_point.x;
-                h = b - a;
-            },
-            Axis::Y => {
-                a = min_point.y;
-                b = max_point.y;
-                h = b - a;
-            },
-            Axis::Z => {
-                a = min_point.z;
-                b = max_point.z;
-                h = b - a;
-            }
-        }
-
-        // Iterate over each vertex and compute its texture coordinate.
-
-        for i in 0..num_vertices {
-            // Get the coordinates along the axes orthogonal to the
-            // axis the cylinder is aligned with.
-            let x: f32;
-            let y: f32;
-            let z: f32;
-
-            match axis {
-                Axis::X => {
-                    x = vertices[i].pos.y;
-                    z = vertices[i].pos.z;
-                    y = vertices[i].pos.x;
-                },
-                Axis::Y => {
-                    x = vertices[i].pos.x;
-                    z = vertices[i].pos.z;
-                    y = vertices[i].pos.y;
-                },
-                Axis::Z => {
-                    x = vertices[i].pos.x;
-                    z = vertices[i].pos.y;
-                    y = vertices[i].pos.z;
-                }
-            }
-
-            // Convert to cylindrical coordinates.
-
-            let theta = z.atan2(x);
-            let y2 = y - b; // Transform [a, b] --> [-h, 0]
-
-            // Transform theta from [0, 2*pi] to [0, 1] range and
-            // transform y2 from [-h, 0] to [0, 1].
-
-            let u: f32 = theta / (2.0 * D3DX_PI);
-            let v = y2 / -h;
+        ReleaseCOM(temp);
 
-            // Save texture coordinates.
+        // Back to a hardware mesh.
+        *mesh = create_d3dx_mesh(d3d_device, &VERTEX_PNT_DECL, &cpu_mesh, D3DXMESH_MANAGED);
+    }
+}
 
-            vertices[i].tex0.x = u;
-            vertices[i].tex0.y = v;
-        }
+// Replacements for D3DXCreateSphere, D3DXCreateCylinder, D3DXCreateBox and
+// D3DXCreateTeapot: the geometry crate's primitives uploaded as VertexPNT
+// meshes, so they come with tex-coords and need no gen_*_tex_coords pass.  The
+// vertex declarations have to be initialized (init_all_vertex_declarations).
+pub fn create_sphere_mesh(d3d_device: IDirect3DDevice9, radius: f32, slices: u32, stacks: u32) -> LPD3DXMESH {
+    let sphere = geometry::create_sphere(radius, slices, stacks);
+    unsafe { create_d3dx_mesh(d3d_device, &VERTEX_PNT_DECL, &sphere, D3DXMESH_MANAGED) }
+}
 
-        HR!(ID3DXBaseMesh_UnlockVertexBuffer(temp));
+// Along the z-axis like D3DXCreateCylinder, with caps.
+pub fn create_cylinder_mesh(d3d_device: IDirect3DDevice9, radius1: f32, radius2: f32, length: f32,
+                            slices: u32, stacks: u32) -> LPD3DXMESH {
+    let mut cylinder = geometry::create_cylinder(radius1, radius2, length, slices, stacks, true);
+    cylinder.transform(&geometry::Mat4::rotation_x(D3DX_PI * 0.5));
+    unsafe { create_d3dx_mesh(d3d_device, &VERTEX_PNT_DECL, &cylinder, D3DXMESH_MANAGED) }
+}
 
-        // Clone back to a hardware mesh.
-        HR!(ID3DXBaseMesh_CloneMesh(temp, D3DXMESH_MANAGED | D3DXMESH_WRITEONLY, elements.as_mut_ptr(),
-                d3d_device.clone(), cylinder));
+pub fn create_box_mesh(d3d_device: IDirect3DDevice9, width: f32, height: f32, depth: f32) -> LPD3DXMESH {
+    let box_mesh = geometry::create_box(width, height, depth);
+    unsafe { create_d3dx_mesh(d3d_device, &VERTEX_PNT_DECL, &box_mesh, D3DXMESH_MANAGED) }
+}
 
-        ReleaseCOM(temp);
-    }
+// The size of D3DXCreateTeapot's, mapped spherically like the book's demos
+// mapped it with gen_spherical_tex_coords.
+pub fn create_teapot_mesh(d3d_device: IDirect3DDevice9) -> LPD3DXMESH {
+    let mut teapot = geometry::create_teapot(1.575, 8);
+    geometry::generate_tex_coords(&mut teapot, geometry::UvProjection::Spherical, &geometry::Mat4::IDENTITY);
+    unsafe { create_d3dx_mesh(d3d_device, &VERTEX_PNT_DECL, &teapot, D3DXMESH_MANAGED) }
 }
 
+// What load_x_file did to a mesh on its way to the GPU.
+#[derive(Clone, Debug)]
+pub struct XFileReport {
+    pub clean: geometry::CleanReport,
+    pub optimize: geometry::OptimizeReport,
+}
 
-pub fn load_x_file(base_path: &str, filename: &str, d3d_device: IDirect3DDevice9) -> (LPD3DXMESH, Vec<Mtrl>, Vec<*mut c_void>) {
+pub fn load_x_file(base_path: &str, filename: &str,
+                   d3d_device: IDirect3DDevice9) -> (LPD3DXMESH, Vec<Mtrl>, Vec<*mut c_void>, XFileReport) {
     unsafe {
-        let mut mesh_out: LPD3DXMESH = std::ptr::null_mut();
         let mut mtrls = Vec::new();
         let mut texs = Vec::new();
 
@@ -382,28 +188,28 @@ pub fn load_x_file(base_path: &str, filename: &str, d3d_device: IDirect3DDevice9
             mesh_sys = temp;
         }
 
-        // Step 4: If the mesh did not have normals, generate them.
+        // Step 4: Clean the mesh: weld duplicate vertices, drop degenerate and
+        // duplicate faces and split bowties, which trip up normal generation.
+
+        let mut cpu_mesh: geometry::Mesh<geometry::VertexPNT> = read_d3dx_mesh(mesh_sys);
+        let clean_report = geometry::clean_mesh(&mut cpu_mesh, &geometry::CleanOptions::default());
+
+        // Step 5: If the mesh did not have normals, generate them.
 
         if has_normals == false {
-            HR!(D3DXComputeNormals(mesh_sys, std::ptr::null()));
+            cpu_mesh.compute_normals();
         }
 
-        // Step 5: Optimize the mesh.
+        // Step 6: Optimize the mesh (compact, sort by attribute, reorder for the vertex cache).
 
-        let buf_pointer: *mut u32 = ID3DXBuffer_GetBufferPointer(adj_buffer).cast();
+        let optimize_report = geometry::optimize_mesh(&mut cpu_mesh, &geometry::OptimizeOptions::default());
 
-        HR!(ID3DXMesh_Optimize(*&mesh_sys,
-                D3DXMESH_MANAGED | D3DXMESHOPT_COMPACT | D3DXMESHOPT_ATTRSORT | D3DXMESHOPT_VERTEXCACHE,
-                buf_pointer,
-                std::ptr::null_mut(),
-                std::ptr::null_mut(),
-                std::ptr::null_mut(),
-                &mut mesh_out));
+        let mesh_out = create_d3dx_mesh(d3d_device.clone(), &VERTEX_PNT_DECL, &cpu_mesh, D3DXMESH_MANAGED);
 
         ReleaseCOM(mesh_sys);   // Done w/ system mesh.
         ReleaseCOM(adj_buffer); // Done with buffer.
 
-        // Step 6: Extract the materials and load the textures.
+        // Step 7: Extract the materials and load the textures.
 
         if mtrl_buffer != std::ptr::null_mut() && num_mtrls != 0 {
             let d3dxmtrls_ptr: *mut D3DXMATERIAL = ID3DXBuffer_GetBufferPointer(mtrl_buffer).cast();
@@ -444,6 +250,6 @@ pub fn load_x_file(base_path: &str, filename: &str, d3d_device: IDirect3DDevice9
 
         ReleaseCOM(mtrl_buffer); // done w/ buffer
 
-        (mesh_out, mtrls, texs)
+        (mesh_out, mtrls, texs, XFileReport { clean: clean_report, optimize: optimize_report })
     }
 }
\ No newline at end of file
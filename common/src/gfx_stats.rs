@@ -17,6 +17,8 @@ pub struct GfxStats {
     millisec_per_frame: f32,
     num_tris: u32,
     num_vertices: u32,
+    // Demo specific lines shown under the stats.
+    info: String,
 }
 
 impl GfxStats {
@@ -28,6 +30,7 @@ impl GfxStats {
             millisec_per_frame: 0.0,
             num_tris: 0,
             num_vertices: 0,
+            info: String::new(),
         };
 
         let font_desc = D3DXFONT_DESC {
@@ -84,6 +87,10 @@ impl GfxStats {
         self.num_vertices = n;
     }
 
+    pub fn set_info(&mut self, info: &str) {
+        self.info = info.to_string();
+    }
+
     pub fn update(&mut self, dt: f32) {
         unsafe {
             // Make static so that their values persist across function calls.
@@ -123,8 +130,9 @@ impl GfxStats {
         let buffer: String = format!("Frames Per Second = {:.2}\n\
                 Milliseconds Per Frame = {:.4}\n\
                 Triangle Count = {}\n\
-                Vertex Count = {}\0",
-                         self.fps, self.millisec_per_frame, self.num_tris, self.num_vertices);
+                Vertex Count = {}\n\
+                {}\0",
+                         self.fps, self.millisec_per_frame, self.num_tris, self.num_vertices, self.info);
 
         let r = RECT { left: 5, top: 5, right: 0, bottom: 0 };
 
@@ -1,18 +1,24 @@
 pub mod aabb;
+pub mod cpu_mesh;
 pub mod d3d9_extra;
 pub mod direct_input;
 pub mod geom_utils;
 pub mod gfx_stats;
-pub mod heightmap;
 pub mod mtrl;
+pub mod picking;
+pub mod terrain;
+pub mod tiled_terrain;
 pub mod utils;
 pub mod vertex;
 
 pub use crate::aabb::*;
+pub use crate::cpu_mesh::*;
 pub use crate::d3d9_extra::*;
 pub use crate::direct_input::*;
 pub use crate::geom_utils::*;
 pub use crate::gfx_stats::*;
-pub use crate::heightmap::*;
+pub use crate::picking::*;
+pub use crate::terrain::*;
+pub use crate::tiled_terrain::*;
 pub use crate::utils::*;
 pub use crate::vertex::*;
\ No newline at end of file
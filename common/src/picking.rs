@@ -0,0 +1,47 @@
+// Glue between geometry::Picker and Direct3D/Win32: the device's viewport and
+// the mouse cursor.  The picking itself depends on neither.
+
+use windows::{
+    Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::Graphics::Gdi::*,
+    Win32::UI::WindowsAndMessaging::*,
+};
+use d3dx::*;
+use geometry::{Picker, Ray, Viewport};
+use crate::*;
+
+pub fn to_viewport(vp: &D3DVIEWPORT9) -> Viewport {
+    Viewport {
+        x: vp.X as f32,
+        y: vp.Y as f32,
+        width: vp.Width as f32,
+        height: vp.Height as f32,
+        min_z: vp.MinZ,
+        max_z: vp.MaxZ,
+    }
+}
+
+// The mouse cursor relative to the client area of the window.
+pub fn get_cursor_client_pos(hwnd: HWND) -> (f32, f32) {
+    unsafe {
+        let mut s: POINT = POINT::default();
+        GetCursorPos(&mut s);
+        ScreenToClient(hwnd, &mut s);
+        (s.x as f32, s.y as f32)
+    }
+}
+
+// Picker for the device's current viewport and the camera's matrices.
+pub fn get_device_picker(d3d_device: &IDirect3DDevice9, view: &D3DXMATRIX, proj: &D3DXMATRIX) -> Option<Picker> {
+    let mut vp = D3DVIEWPORT9::default();
+    unsafe {
+        HR!(d3d_device.GetViewport(&mut vp));
+    }
+    Picker::new(to_viewport(&vp), &to_mat4(view), &to_mat4(proj))
+}
+
+// World space ray through the pixel under the mouse cursor.
+pub fn get_cursor_picking_ray(hwnd: HWND, d3d_device: &IDirect3DDevice9,
+                              view: &D3DXMATRIX, proj: &D3DXMATRIX) -> Option<Ray> {
+    let (x, y) = get_cursor_client_pos(hwnd);
+    get_device_picker(d3d_device, view, proj).map(|picker| picker.get_ray(x, y))
+}
@@ -0,0 +1,605 @@
+// Direct3D side of the shared terrain: a terrain::HeightField cut into sub-grids,
+// frustum culled through a quadtree and drawn front to back with a blend-mapped
+// effect.
+//
+// Each sub-grid has its own vertex buffer and is drawn at the geomipmap level
+// picked for the camera, with an index buffer shared by all the sub-grids of the
+// same size, level and stitched edges.  Morphed heights are written to the
+// vertex buffers when the level or morph of a sub-grid or its edges changes;
+// morphs move in steps (see GeomipmapOptions::morph_steps), so a moving camera
+// rewrites a few buffers a frame rather than all of them.  Brushes edit the
+// heights in place, and only the sub-grids they touch are rebuilt.
+//
+// The sub-grid size, the level of detail, the texture layers and the effect are
+// set by a TerrainDesc; Terrain::new keeps the layout of the book's demos (three
+// tiled textures and a blend map drawn with Terrain.fx).  Layers can be added or
+// reloaded later with set_layer, or uploaded from memory with set_layer_bgra8
+// for textures baked from the terrain itself such as a lightmap drawn with
+// TerrainEffect::lightmapped.
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use libc::c_void;
+use windows::Win32::Foundation::PSTR;
+use windows::Win32::Graphics::Direct3D9::*;
+use d3dx::*;
+use geometry::{CullStats, Frustum, QuadTree};
+use ::terrain::*;
+use crate::*;
+
+// A texture and the effect parameter it is bound to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainLayer {
+    pub param: String,
+    pub file: String,
+}
+
+impl TerrainLayer {
+    pub fn new(param: &str, file: &str) -> TerrainLayer {
+        TerrainLayer { param: param.to_string(), file: file.to_string() }
+    }
+}
+
+// The effect and the names of its technique and per-frame parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainEffect {
+    pub file: String,
+    pub technique: String,
+    pub view_proj: String,
+    pub dir_to_sun_w: String,
+}
+
+impl Default for TerrainEffect {
+    fn default() -> Self {
+        TerrainEffect {
+            file: "Terrain.fx".to_string(),
+            technique: "TerrainTech".to_string(),
+            view_proj: "gViewProj".to_string(),
+            dir_to_sun_w: "gDirToSunW".to_string(),
+        }
+    }
+}
+
+impl TerrainEffect {
+    // TerrainLightmap.fx: the blend-mapped textures of Terrain.fx with the sun's
+    // visibility and the ambient occlusion of a terrain::Lightmap bound to
+    // gLightMap.
+    pub fn lightmapped() -> TerrainEffect {
+        TerrainEffect {
+            file: "TerrainLightmap.fx".to_string(),
+            technique: "TerrainLightmapTech".to_string(),
+            ..TerrainEffect::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainDesc {
+    // 8-bit RAW heightmap of vert_rows x vert_cols heights.
+    pub heightmap_file: String,
+    pub vert_rows: usize,
+    pub vert_cols: usize,
+    pub height_scale: f32,
+    pub y_offset: f32,
+
+    // Spacing of the vertices.
+    pub dx: f32,
+    pub dz: f32,
+
+    // Vertices along each side of a sub-grid, 2^n + 1 for the most levels.
+    pub sub_grid_size: usize,
+    pub lod: GeomipmapOptions,
+
+    pub layers: Vec<TerrainLayer>,
+    pub effect: TerrainEffect,
+}
+
+impl TerrainDesc {
+    // The book's layout: tex0 to tex2 blended by the blend map with Terrain.fx,
+    // in 33x33 sub-grids with the default level of detail.
+    pub fn new(vert_rows: usize, vert_cols: usize, dx: f32, dz: f32,
+               heightmap_file: &str, tex0_file: &str, tex1_file: &str,
+               tex2_file: &str, blend_map_file: &str,
+               height_scale: f32, y_offset: f32) -> TerrainDesc {
+        TerrainDesc {
+            heightmap_file: heightmap_file.to_string(),
+            vert_rows,
+            vert_cols,
+            height_scale,
+            y_offset,
+            dx,
+            dz,
+            sub_grid_size: DEFAULT_SUB_GRID_SIZE,
+            lod: GeomipmapOptions::default(),
+            layers: vec![
+                TerrainLayer::new("gTex0", tex0_file),
+                TerrainLayer::new("gTex1", tex1_file),
+                TerrainLayer::new("gTex2", tex2_file),
+                TerrainLayer::new("gBlendMap", blend_map_file),
+            ],
+            effect: TerrainEffect::default(),
+        }
+    }
+}
+
+// The triangles of one level of a sub-grid size, with some edges stitched.
+pub(crate) struct LodIndices {
+    pub(crate) ib: IDirect3DIndexBuffer9,
+    pub(crate) num_triangles: u32,
+}
+
+impl LodIndices {
+    pub(crate) fn new(d3d_device: IDirect3DDevice9, num_rows: usize, num_cols: usize, level: usize,
+                      stitch_mask: u32) -> LodIndices {
+        let indices = build_lod_indices(num_rows, num_cols, level, stitch_mask);
+        LodIndices {
+            ib: create_index_buffer(d3d_device, &indices, (num_rows * num_cols) as u32),
+            num_triangles: (indices.len() / 3) as u32,
+        }
+    }
+}
+
+pub struct Terrain {
+    d3d_device: IDirect3DDevice9,
+
+    height_field: HeightField,
+
+    geomipmap: Geomipmap,
+    lod_options: GeomipmapOptions,
+
+    // Over the sub-grids' bounds, and the work culling them took in the last
+    // draw.
+    quad_tree: QuadTree,
+    cull_stats: CullStats,
+
+    // Per sub-grid: its vertices, row by row, at full resolution heights, its
+    // vertex buffer, and the levels and morphs of it and its edges the buffer's
+    // heights were morphed for.
+    sub_grid_vertices: Vec<Vec<geometry::VertexPNT>>,
+    vertex_buffers: Vec<IDirect3DVertexBuffer9>,
+    vertex_lods: Vec<Option<[SubGridLod; 5]>>,
+
+    // By sub-grid rows, columns, level and stitch mask.
+    index_buffers: HashMap<(usize, usize, usize, u32), LodIndices>,
+
+    num_triangles_drawn: u32,
+
+    // The effect parameters of the layers and their textures, in the same
+    // order, and where the layers' files are read from.
+    base_path: String,
+    layer_params: Vec<String>,
+    textures: Vec<*mut c_void>,  //IDirect3DTexture9
+
+    fx: LPD3DXEFFECT,
+
+    h_tech: D3DXHANDLE,
+    h_view_proj: D3DXHANDLE,
+    h_dir_to_sun_w: D3DXHANDLE,
+}
+
+impl Terrain {
+    pub fn new(d3d_device: IDirect3DDevice9, vert_rows: u32, vert_cols: u32,
+               dx: f32, dz: f32,
+               heightmap_file: &str, tex0_file: &str, tex1_file: &str,
+               tex2_file: &str, blend_map_file: &str, base_path: &str,
+               height_scale: f32, y_offset: f32) -> Terrain {
+        let desc = TerrainDesc::new(vert_rows as usize, vert_cols as usize, dx, dz,
+                                    heightmap_file, tex0_file, tex1_file, tex2_file, blend_map_file,
+                                    height_scale, y_offset);
+        Terrain::from_desc(d3d_device, &desc, base_path)
+    }
+
+    // Files are read from 'base_path'.
+    pub fn from_desc(d3d_device: IDirect3DDevice9, desc: &TerrainDesc, base_path: &str) -> Terrain {
+        let heightmap_path = format!("{}{}", base_path, desc.heightmap_file);
+        let heightmap = Heightmap::load_raw(desc.vert_rows, desc.vert_cols, &heightmap_path,
+                                            desc.height_scale, desc.y_offset)
+            .unwrap_or_else(|err| panic!("Failed to load RAW file {}: {}", heightmap_path, err));
+        let height_field = HeightField::new(heightmap, desc.dx, desc.dz);
+
+        // Cut the field into sub-grids, each drawn at its own level of detail.
+        let field_vertices = height_field.build_vertices();
+        let geomipmap = Geomipmap::new(&height_field, build_sub_grids(&height_field, desc.sub_grid_size));
+
+        let mut sub_grid_vertices = Vec::new();
+        let mut vertex_buffers = Vec::new();
+        let mut index_buffers = HashMap::new();
+        for sub_grid in geomipmap.get_sub_grids() {
+            let mut vertices = Vec::with_capacity(sub_grid.num_rows * sub_grid.num_cols);
+            for i in sub_grid.row..sub_grid.row + sub_grid.num_rows {
+                let first = i * height_field.num_cols() + sub_grid.col;
+                vertices.extend_from_slice(&field_vertices[first..first + sub_grid.num_cols]);
+            }
+
+            vertex_buffers.push(create_vertex_buffer(d3d_device.clone(), &vertices));
+            sub_grid_vertices.push(vertices);
+
+            // Every level and stitch mask of the sub-grid's size.
+            for level in 0..get_num_lod_levels(sub_grid.num_rows, sub_grid.num_cols) {
+                for stitch_mask in 0..NUM_STITCH_MASKS {
+                    index_buffers.entry((sub_grid.num_rows, sub_grid.num_cols, level, stitch_mask))
+                        .or_insert_with(|| LodIndices::new(d3d_device.clone(), sub_grid.num_rows, sub_grid.num_cols,
+                                                           level, stitch_mask));
+                }
+            }
+        }
+        let vertex_lods = vec![None; vertex_buffers.len()];
+
+        let sub_grid_bounds: Vec<_> = geomipmap.get_sub_grids().iter().map(|s| s.bounds).collect();
+        let quad_tree = QuadTree::build(&sub_grid_bounds);
+
+        let (fx, h_tech, h_view_proj, h_dir_to_sun_w) =
+            Terrain::build_effect(d3d_device.clone(), base_path, &desc.effect);
+
+        let textures = load_layers(d3d_device.clone(), fx, base_path, &desc.layers);
+
+        Terrain {
+            d3d_device,
+
+            height_field,
+
+            geomipmap,
+            lod_options: desc.lod,
+
+            quad_tree,
+            cull_stats: CullStats::default(),
+
+            sub_grid_vertices,
+            vertex_buffers,
+            vertex_lods,
+
+            index_buffers,
+
+            num_triangles_drawn: 0,
+
+            base_path: base_path.to_string(),
+            layer_params: desc.layers.iter().map(|l| l.param.clone()).collect(),
+            textures,
+
+            fx,
+
+            h_tech,
+            h_view_proj,
+            h_dir_to_sun_w,
+        }
+    }
+
+    pub fn release_com_objects(&self) {
+        ReleaseCOM(self.fx);
+
+        for tex in &self.textures {
+            ReleaseCOM(tex.cast());
+        }
+    }
+
+    // Loads the layer's texture and binds it to its parameter, replacing the
+    // layer already bound there.  Setting a layer again reloads its file.
+    pub fn set_layer(&mut self, layer: TerrainLayer) {
+        let tex = load_layer(self.d3d_device.clone(), self.fx, &self.base_path, &layer);
+        self.replace_layer(&layer.param, tex);
+    }
+
+    // Like set_layer, but for 'width' x 'height' texels in memory, 4 bytes each
+    // in the layout of D3DFMT_A8R8G8B8 (such as terrain::Lightmap::to_bgra8).
+    // No file is written or read.
+    pub fn set_layer_bgra8(&mut self, param: &str, width: usize, height: usize, bgra: &[u8]) {
+        let tex = create_texture_bgra8(self.d3d_device.clone(), width, height, bgra);
+        bind_layer(self.fx, param, tex);
+        self.replace_layer(param, tex);
+    }
+
+    fn replace_layer(&mut self, param: &str, tex: *mut c_void) {
+        match self.layer_params.iter().position(|p| p == param) {
+            Some(i) => {
+                ReleaseCOM(self.textures[i].cast());
+                self.textures[i] = tex;
+            }
+            None => {
+                self.layer_params.push(param.to_string());
+                self.textures.push(tex);
+            }
+        }
+    }
+
+    pub fn on_lost_device(&self) {
+        HR!(ID3DXEffect_OnLostDevice(self.fx));
+    }
+
+    pub fn on_reset_device(&self) {
+        HR!(ID3DXEffect_OnResetDevice(self.fx));
+    }
+
+    pub fn set_dir_to_sun_w(&self, d: D3DXVECTOR3) {
+        HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_dir_to_sun_w, &d as *const _ as _,
+            std::mem::size_of::<D3DXVECTOR3>() as u32));
+    }
+
+    // At full resolution.
+    pub fn get_num_vertices(&self) -> u32 {
+        self.sub_grid_vertices.iter().map(|v| v.len() as u32).sum()
+    }
+
+    // At full resolution.
+    pub fn get_num_triangles(&self) -> u32 {
+        self.geomipmap.get_sub_grids().iter().map(|s| ((s.num_rows - 1) * (s.num_cols - 1) * 2) as u32).sum()
+    }
+
+    // By the last draw.
+    pub fn get_num_triangles_drawn(&self) -> u32 {
+        self.num_triangles_drawn
+    }
+
+    // Nodes visited and boxes tested to cull the sub-grids in the last draw.
+    pub fn get_cull_stats(&self) -> &CullStats {
+        &self.cull_stats
+    }
+
+    pub fn get_lod_options(&self) -> &GeomipmapOptions {
+        &self.lod_options
+    }
+
+    pub fn set_lod_options(&mut self, options: GeomipmapOptions) {
+        self.lod_options = options;
+    }
+
+    pub fn get_height_field(&self) -> &HeightField {
+        &self.height_field
+    }
+
+    pub fn get_geomipmap(&self) -> &Geomipmap {
+        &self.geomipmap
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.height_field.get_width()
+    }
+
+    pub fn get_depth(&self) -> f32 {
+        self.height_field.get_depth()
+    }
+
+    pub fn get_height(&self, x: f32, z: f32) -> f32 {
+        self.height_field.get_height(x, z)
+    }
+
+    // Sculpts the terrain with 'brush' centered on (x, z) (see Brush::apply)
+    // and rebuilds the bounds, level errors and vertices of the sub-grids it
+    // touched.  Returns whether any heights changed.
+    pub fn apply_brush(&mut self, brush: &Brush, x: f32, z: f32, amount: f32) -> bool {
+        let edited = match brush.apply(&mut self.height_field, x, z, amount) {
+            Some(rect) => rect,
+            None => return false,
+        };
+
+        // Normals change one vertex beyond the edited heights.
+        let rect = edited.grow(1, self.height_field.num_rows(), self.height_field.num_cols());
+        let normals = self.height_field.build_vertex_normals(&rect);
+
+        for i in self.geomipmap.update_sub_grids(&self.height_field, &rect) {
+            let sub_grid = self.geomipmap.get_sub_grids()[i];
+            self.quad_tree.set_item_bounds(i, sub_grid.bounds);
+            for r in 0..sub_grid.num_rows {
+                for c in 0..sub_grid.num_cols {
+                    let (row, col) = (sub_grid.row + r, sub_grid.col + c);
+                    if rect.contains(row, col) {
+                        let v = &mut self.sub_grid_vertices[i][r * sub_grid.num_cols + c];
+                        v.pos.y = self.height_field.get_heightmap().at(row, col);
+                        v.normal = normals[(row - rect.row) * rect.num_cols + col - rect.col];
+                    }
+                }
+            }
+            // Rewritten by the next draw.
+            self.vertex_lods[i] = None;
+        }
+        true
+    }
+
+    // The edited heights as an 8-bit RAW file, see Heightmap::save_raw.
+    pub fn save_heightmap(&self, path: &str, height_scale: f32, y_offset: f32) -> std::io::Result<()> {
+        self.height_field.get_heightmap().save_raw(path, height_scale, y_offset)
+    }
+
+    // Draws the sub-grids in the view frustum at the levels of detail for
+    // 'eye_pos', nearest first to reduce overdraw (the depth test rejects what is
+    // behind).
+    pub fn draw(&mut self, view_proj: &D3DXMATRIX, eye_pos: &D3DXVECTOR3) {
+        unsafe {
+            let view_proj_m = to_mat4(view_proj);
+            let frustum = Frustum::from_matrix(&view_proj_m);
+            let eye = to_vec3(eye_pos);
+
+            let mut viewport = D3DVIEWPORT9::default();
+            HR!(self.d3d_device.GetViewport(&mut viewport));
+            let lods = self.geomipmap.select(eye, &view_proj_m, viewport.Height as f32, &self.lod_options);
+
+            let mut visible = Vec::new();
+            self.cull_stats = self.quad_tree.cull(&frustum, eye, &mut visible);
+
+            for i in &visible {
+                self.update_vertices(*i, &lods);
+            }
+
+            HR!(ID3DXBaseEffect_SetMatrix(self.fx, self.h_view_proj, view_proj));
+
+            HR!(ID3DXEffect_SetTechnique(self.fx, self.h_tech));
+
+            let mut num_passes: u32 = 0;
+            HR!(ID3DXEffect_Begin(self.fx, &mut num_passes, 0));
+
+            HR!(ID3DXEffect_BeginPass(self.fx, 0));
+
+            HR!(self.d3d_device.SetVertexDeclaration(&VERTEX_PNT_DECL));
+
+            self.num_triangles_drawn = 0;
+            for i in visible {
+                let sub_grid = &self.geomipmap.get_sub_grids()[i];
+                let stitch_mask = self.geomipmap.get_stitch_mask(&lods, i);
+                let indices = &self.index_buffers[&(sub_grid.num_rows, sub_grid.num_cols, lods[i].level, stitch_mask)];
+
+                HR!(self.d3d_device.SetStreamSource(0, &self.vertex_buffers[i], 0,
+                    std::mem::size_of::<geometry::VertexPNT>() as u32));
+                HR!(self.d3d_device.SetIndices(&indices.ib));
+                HR!(self.d3d_device.DrawIndexedPrimitive(D3DPT_TRIANGLELIST, 0, 0, vertices_len(sub_grid), 0,
+                    indices.num_triangles));
+
+                self.num_triangles_drawn += indices.num_triangles;
+            }
+
+            HR!(ID3DXEffect_EndPass(self.fx));
+            HR!(ID3DXEffect_End(self.fx));
+        }
+    }
+
+    // Writes the sub-grid's morphed heights, unless its vertex buffer already
+    // has them.
+    fn update_vertices(&mut self, i: usize, lods: &[SubGridLod]) {
+        let edge_lods = self.geomipmap.get_edge_lods(lods, i);
+        let key = [lods[i], edge_lods[0], edge_lods[1], edge_lods[2], edge_lods[3]];
+        if self.vertex_lods[i] == Some(key) {
+            return;
+        }
+        self.vertex_lods[i] = Some(key);
+
+        let heights = self.geomipmap.morph_heights(&self.height_field, lods, i);
+        let mut vertices = self.sub_grid_vertices[i].clone();
+        for (v, h) in vertices.iter_mut().zip(heights) {
+            v.pos.y = h;
+        }
+        write_vertex_buffer(&self.vertex_buffers[i], &vertices);
+    }
+
+    pub(crate) fn build_effect(d3d_device: IDirect3DDevice9, base_path: &str, effect: &TerrainEffect)
+        -> (LPD3DXEFFECT, D3DXHANDLE, D3DXHANDLE, D3DXHANDLE) {
+        // Create the FX from a .fx file.
+        let mut fx: LPD3DXEFFECT = std::ptr::null_mut();
+        let mut errors: LPD3DXBUFFER = std::ptr::null_mut();
+
+        HR!(D3DXCreateEffectFromFile(d3d_device,
+            PSTR(c_resource_path(base_path, &effect.file).as_str().as_ptr() as _),
+            std::ptr::null(), std::ptr::null(), D3DXSHADER_DEBUG,
+            std::ptr::null(), &mut fx, &mut errors));
+
+        unsafe {
+            if !errors.is_null() {
+                let errors_ptr: *mut c_void = ID3DXBuffer_GetBufferPointer(errors);
+
+                let c_str: &CStr = CStr::from_ptr(errors_ptr.cast());
+                let str_slice: &str = c_str.to_str().unwrap_or("<unknown error>");
+                message_box(str_slice);
+                // the original sample code will also crash at this point
+            }
+        }
+
+        // Obtain handles.
+        let technique = format!("{}\0", effect.technique);
+        let view_proj = format!("{}\0", effect.view_proj);
+        let dir_to_sun_w = format!("{}\0", effect.dir_to_sun_w);
+
+        let h_tech = ID3DXBaseEffect_GetTechniqueByName(fx, PSTR(technique.as_ptr() as _));
+        let h_view_proj = ID3DXBaseEffect_GetParameterByName(fx, std::ptr::null(), PSTR(view_proj.as_ptr() as _));
+        let h_dir_to_sun_w = ID3DXBaseEffect_GetParameterByName(fx, std::ptr::null(), PSTR(dir_to_sun_w.as_ptr() as _));
+
+        (fx, h_tech, h_view_proj, h_dir_to_sun_w)
+    }
+}
+
+// Loads the layers' textures and binds them to their effect parameters.
+pub(crate) fn load_layers(d3d_device: IDirect3DDevice9, fx: LPD3DXEFFECT, base_path: &str,
+                          layers: &[TerrainLayer]) -> Vec<*mut c_void> {
+    layers.iter().map(|layer| load_layer(d3d_device.clone(), fx, base_path, layer)).collect()
+}
+
+pub(crate) fn load_layer(d3d_device: IDirect3DDevice9, fx: LPD3DXEFFECT, base_path: &str,
+                         layer: &TerrainLayer) -> *mut c_void {
+    let mut tex = std::ptr::null_mut();
+    HR!(D3DXCreateTextureFromFile(d3d_device,
+        PSTR(c_resource_path(base_path, &layer.file).as_str().as_ptr() as _), &mut tex));
+
+    bind_layer(fx, &layer.param, tex);
+
+    tex
+}
+
+pub(crate) fn bind_layer(fx: LPD3DXEFFECT, param: &str, tex: *mut c_void) {
+    let param = format!("{}\0", param);
+    let h_tex = ID3DXBaseEffect_GetParameterByName(fx, std::ptr::null(), PSTR(param.as_ptr() as _));
+    HR!(ID3DXBaseEffect_SetTexture(fx, h_tex, tex));
+}
+
+// A managed A8R8G8B8 texture, one mip level, holding 'bgra' row by row.
+pub(crate) fn create_texture_bgra8(d3d_device: IDirect3DDevice9, width: usize, height: usize,
+                                   bgra: &[u8]) -> *mut c_void {
+    assert_eq!(bgra.len(), width * height * 4, "Texels do not match the texture size");
+
+    let mut tex = std::ptr::null_mut();
+    HR!(D3DXCreateTexture(d3d_device, width as u32, height as u32, 1, 0,
+                          D3DFMT_A8R8G8B8, D3DPOOL_MANAGED, &mut tex));
+
+    // Rows of the locked texture are 'Pitch' bytes apart, which may be more
+    // than a row of texels.
+    let mut locked = D3DLOCKED_RECT::default();
+    HR!(IDirect3DTexture9_LockRect(tex, 0, &mut locked, std::ptr::null(), 0));
+    let row_size = width * 4;
+    for (i, row) in bgra.chunks_exact(row_size).enumerate() {
+        unsafe {
+            let dst = (locked.pBits as *mut u8).add(i * locked.Pitch as usize);
+            std::ptr::copy_nonoverlapping(row.as_ptr(), dst, row_size);
+        }
+    }
+    HR!(IDirect3DTexture9_UnlockRect(tex, 0));
+
+    tex
+}
+
+pub(crate) fn vertices_len(sub_grid: &SubGrid) -> u32 {
+    (sub_grid.num_rows * sub_grid.num_cols) as u32
+}
+
+pub(crate) fn create_vertex_buffer(d3d_device: IDirect3DDevice9, vertices: &[geometry::VertexPNT]) -> IDirect3DVertexBuffer9 {
+    unsafe {
+        let mut vb: Option<IDirect3DVertexBuffer9> = None;
+        HR!(d3d_device.CreateVertexBuffer((vertices.len() * std::mem::size_of::<geometry::VertexPNT>()) as u32,
+            D3DUSAGE_WRITEONLY as u32, 0, D3DPOOL_MANAGED, &mut vb, std::ptr::null_mut()));
+
+        let vb = vb.expect("Failed to create the vertex buffer");
+        write_vertex_buffer(&vb, vertices);
+        vb
+    }
+}
+
+pub(crate) fn write_vertex_buffer(vb: &IDirect3DVertexBuffer9, vertices: &[geometry::VertexPNT]) {
+    unsafe {
+        let mut v = std::ptr::null_mut();
+        HR!(vb.Lock(0, 0, &mut v, 0));
+        std::ptr::copy_nonoverlapping(vertices.as_ptr(), v as *mut geometry::VertexPNT, vertices.len());
+        HR!(vb.Unlock());
+    }
+}
+
+// 16-bit indices when the vertices allow.
+pub(crate) fn create_index_buffer(d3d_device: IDirect3DDevice9, indices: &[u32], num_vertices: u32) -> IDirect3DIndexBuffer9 {
+    unsafe {
+        let use_32bit = num_vertices > u16::MAX as u32 + 1;
+        let (format, index_size) = if use_32bit {
+            (D3DFMT_INDEX32, std::mem::size_of::<u32>())
+        } else {
+            (D3DFMT_INDEX16, std::mem::size_of::<u16>())
+        };
+
+        let mut ib: Option<IDirect3DIndexBuffer9> = None;
+        HR!(d3d_device.CreateIndexBuffer((indices.len() * index_size) as u32,
+            D3DUSAGE_WRITEONLY as u32, format, D3DPOOL_MANAGED, &mut ib, std::ptr::null_mut()));
+
+        let ib = ib.expect("Failed to create the index buffer");
+        let mut i = std::ptr::null_mut();
+        HR!(ib.Lock(0, 0, &mut i, 0));
+        if use_32bit {
+            std::ptr::copy_nonoverlapping(indices.as_ptr(), i as *mut u32, indices.len());
+        } else {
+            let indices16: Vec<u16> = indices.iter().map(|i| *i as u16).collect();
+            std::ptr::copy_nonoverlapping(indices16.as_ptr(), i as *mut u16, indices16.len());
+        }
+        HR!(ib.Unlock());
+        ib
+    }
+}
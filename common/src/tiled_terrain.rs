@@ -0,0 +1,307 @@
+// Direct3D side of a terrain::TiledWorld: the resident tiles' sub-grids drawn
+// like Terrain's, with vertex buffers created as tiles are paged in,
+// rewritten when their stepped morphs change and released as they are paged
+// out.
+//
+// The sub-grids of all the tiles are selected as one geomipmap, so levels and
+// stitching carry across the tiles' borders, and culled through one quadtree,
+// rebuilt when tiles come and go.  Each tile's tex-coords go [0, 1]
+// across the tile, so the blend map repeats from tile to tile.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use libc::c_void;
+use windows::Win32::Graphics::Direct3D9::*;
+use d3dx::*;
+use geometry::{CullStats, Frustum, QuadTree};
+use ::terrain::*;
+use crate::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledTerrainDesc {
+    pub world: TiledWorldDesc,
+    pub lod: GeomipmapOptions,
+
+    pub layers: Vec<TerrainLayer>,
+    pub effect: TerrainEffect,
+}
+
+impl TiledTerrainDesc {
+    // The book's layout, as TerrainDesc::new.
+    pub fn new(world: TiledWorldDesc, tex0_file: &str, tex1_file: &str, tex2_file: &str,
+               blend_map_file: &str) -> TiledTerrainDesc {
+        TiledTerrainDesc {
+            world,
+            lod: GeomipmapOptions::default(),
+            layers: vec![
+                TerrainLayer::new("gTex0", tex0_file),
+                TerrainLayer::new("gTex1", tex1_file),
+                TerrainLayer::new("gTex2", tex2_file),
+                TerrainLayer::new("gBlendMap", blend_map_file),
+            ],
+            effect: TerrainEffect::default(),
+        }
+    }
+}
+
+// Per sub-grid of a tile: its vertex buffer and the levels and morphs its
+// heights were morphed for.
+struct TileBuffers {
+    vertex_buffers: Vec<IDirect3DVertexBuffer9>,
+    vertex_lods: Vec<Option<[SubGridLod; 5]>>,
+}
+
+pub struct TiledTerrain {
+    d3d_device: IDirect3DDevice9,
+
+    world: TiledWorld,
+    lod_options: GeomipmapOptions,
+
+    // Over the bounds of the world's sub-grids, and the work culling them took
+    // in the last draw.
+    quad_tree: QuadTree,
+    cull_stats: CullStats,
+
+    tile_buffers: HashMap<TileCoord, TileBuffers>,
+
+    // By sub-grid rows, columns, level and stitch mask, created as needed.
+    index_buffers: HashMap<(usize, usize, usize, u32), LodIndices>,
+
+    num_triangles_drawn: u32,
+
+    textures: Vec<*mut c_void>,  //IDirect3DTexture9
+
+    fx: LPD3DXEFFECT,
+
+    h_tech: D3DXHANDLE,
+    h_view_proj: D3DXHANDLE,
+    h_dir_to_sun_w: D3DXHANDLE,
+}
+
+impl TiledTerrain {
+    // Textures and the effect are read from 'base_path'; the tiles come from
+    // 'source' as the camera moves (see update).
+    pub fn new(d3d_device: IDirect3DDevice9, desc: &TiledTerrainDesc, source: Arc<dyn TileSource>,
+               base_path: &str) -> TiledTerrain {
+        let (fx, h_tech, h_view_proj, h_dir_to_sun_w) =
+            Terrain::build_effect(d3d_device.clone(), base_path, &desc.effect);
+        let textures = load_layers(d3d_device.clone(), fx, base_path, &desc.layers);
+
+        TiledTerrain {
+            d3d_device,
+
+            world: TiledWorld::new(desc.world, source),
+            lod_options: desc.lod,
+
+            quad_tree: QuadTree::default(),
+            cull_stats: CullStats::default(),
+
+            tile_buffers: HashMap::new(),
+
+            index_buffers: HashMap::new(),
+
+            num_triangles_drawn: 0,
+
+            textures,
+
+            fx,
+
+            h_tech,
+            h_view_proj,
+            h_dir_to_sun_w,
+        }
+    }
+
+    pub fn release_com_objects(&self) {
+        ReleaseCOM(self.fx);
+
+        for tex in &self.textures {
+            ReleaseCOM(tex.cast());
+        }
+    }
+
+    pub fn on_lost_device(&self) {
+        HR!(ID3DXEffect_OnLostDevice(self.fx));
+    }
+
+    pub fn on_reset_device(&self) {
+        HR!(ID3DXEffect_OnResetDevice(self.fx));
+    }
+
+    pub fn set_dir_to_sun_w(&self, d: D3DXVECTOR3) {
+        HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_dir_to_sun_w, &d as *const _ as _,
+            std::mem::size_of::<D3DXVECTOR3>() as u32));
+    }
+
+    // By the last draw.
+    pub fn get_num_triangles_drawn(&self) -> u32 {
+        self.num_triangles_drawn
+    }
+
+    // Nodes visited and boxes tested to cull the sub-grids in the last draw.
+    pub fn get_cull_stats(&self) -> &CullStats {
+        &self.cull_stats
+    }
+
+    pub fn get_lod_options(&self) -> &GeomipmapOptions {
+        &self.lod_options
+    }
+
+    pub fn set_lod_options(&mut self, options: GeomipmapOptions) {
+        self.lod_options = options;
+    }
+
+    // Also the ground for the demos' cameras and characters.
+    pub fn get_world(&self) -> &TiledWorld {
+        &self.world
+    }
+
+    pub fn get_height(&self, x: f32, z: f32) -> f32 {
+        self.world.get_height(x, z)
+    }
+
+    // Pages tiles in and out around 'eye_pos' and updates the vertex buffers
+    // for what changed.  Call once a frame.
+    pub fn update(&mut self, eye_pos: &D3DXVECTOR3) -> TileChanges {
+        let changes = self.world.update(to_vec3(eye_pos));
+        self.apply(&changes);
+        changes
+    }
+
+    // Like update, but waits for the tiles around 'eye_pos' to load.  For
+    // startup and teleports.
+    pub fn update_blocking(&mut self, eye_pos: &D3DXVECTOR3) -> TileChanges {
+        let changes = self.world.update_blocking(to_vec3(eye_pos));
+        self.apply(&changes);
+        changes
+    }
+
+    // Draws the resident sub-grids in the view frustum at the levels of detail
+    // for 'eye_pos', nearest first.
+    pub fn draw(&mut self, view_proj: &D3DXMATRIX, eye_pos: &D3DXVECTOR3) {
+        unsafe {
+            let view_proj_m = to_mat4(view_proj);
+            let frustum = Frustum::from_matrix(&view_proj_m);
+            let eye = to_vec3(eye_pos);
+
+            let mut viewport = D3DVIEWPORT9::default();
+            HR!(self.d3d_device.GetViewport(&mut viewport));
+            let lods = self.world.get_geomipmap().select(eye, &view_proj_m, viewport.Height as f32, &self.lod_options);
+
+            let mut visible = Vec::new();
+            self.cull_stats = self.quad_tree.cull(&frustum, eye, &mut visible);
+
+            for i in &visible {
+                self.update_vertices(*i, &lods);
+            }
+
+            HR!(ID3DXBaseEffect_SetMatrix(self.fx, self.h_view_proj, view_proj));
+
+            HR!(ID3DXEffect_SetTechnique(self.fx, self.h_tech));
+
+            let mut num_passes: u32 = 0;
+            HR!(ID3DXEffect_Begin(self.fx, &mut num_passes, 0));
+
+            HR!(ID3DXEffect_BeginPass(self.fx, 0));
+
+            HR!(self.d3d_device.SetVertexDeclaration(&VERTEX_PNT_DECL));
+
+            self.num_triangles_drawn = 0;
+            for i in visible {
+                let geomipmap = self.world.get_geomipmap();
+                let sub_grid = &geomipmap.get_sub_grids()[i];
+                let stitch_mask = geomipmap.get_stitch_mask(&lods, i);
+                let d3d_device = &self.d3d_device;
+                let indices = self.index_buffers
+                    .entry((sub_grid.num_rows, sub_grid.num_cols, lods[i].level, stitch_mask))
+                    .or_insert_with(|| LodIndices::new(d3d_device.clone(), sub_grid.num_rows, sub_grid.num_cols,
+                                                       lods[i].level, stitch_mask));
+
+                let (coord, j) = self.world.get_sub_grid_tile(i);
+                HR!(self.d3d_device.SetStreamSource(0, &self.tile_buffers[&coord].vertex_buffers[j], 0,
+                    std::mem::size_of::<geometry::VertexPNT>() as u32));
+                HR!(self.d3d_device.SetIndices(&indices.ib));
+                HR!(self.d3d_device.DrawIndexedPrimitive(D3DPT_TRIANGLELIST, 0, 0, vertices_len(sub_grid), 0,
+                    indices.num_triangles));
+
+                self.num_triangles_drawn += indices.num_triangles;
+            }
+
+            HR!(ID3DXEffect_EndPass(self.fx));
+            HR!(ID3DXEffect_End(self.fx));
+        }
+    }
+
+    // Vertex buffers for the loaded tiles, rewritten ones for the tiles whose
+    // normals changed, and none for the evicted ones.
+    fn apply(&mut self, changes: &TileChanges) {
+        for coord in &changes.evicted {
+            self.tile_buffers.remove(coord);
+        }
+
+        for coord in &changes.loaded {
+            let tile = match self.world.get_tile(*coord) {
+                Some(tile) => tile,
+                None => continue,
+            };
+            let sub_grids = tile.get_geomipmap().get_sub_grids();
+            let vertex_buffers = sub_grids.iter()
+                .map(|s| create_vertex_buffer(self.d3d_device.clone(), &get_sub_grid_vertices(tile, s)))
+                .collect();
+            self.tile_buffers.insert(*coord, TileBuffers { vertex_buffers, vertex_lods: vec![None; sub_grids.len()] });
+        }
+
+        // Rewritten by the next draw.
+        for coord in &changes.updated {
+            if let Some(buffers) = self.tile_buffers.get_mut(coord) {
+                buffers.vertex_lods.iter_mut().for_each(|l| *l = None);
+            }
+        }
+
+        // The world's sub-grids are renumbered when tiles come and go.
+        if !changes.loaded.is_empty() || !changes.evicted.is_empty() {
+            let sub_grid_bounds: Vec<_> = self.world.get_geomipmap().get_sub_grids().iter().map(|s| s.bounds).collect();
+            self.quad_tree = QuadTree::build(&sub_grid_bounds);
+        }
+    }
+
+    // Writes the sub-grid's morphed heights, unless its vertex buffer already
+    // has them.
+    fn update_vertices(&mut self, i: usize, lods: &[SubGridLod]) {
+        let geomipmap = self.world.get_geomipmap();
+        let edge_lods = geomipmap.get_edge_lods(lods, i);
+        let key = [lods[i], edge_lods[0], edge_lods[1], edge_lods[2], edge_lods[3]];
+
+        let (coord, j) = self.world.get_sub_grid_tile(i);
+        let buffers = match self.tile_buffers.get_mut(&coord) {
+            Some(buffers) => buffers,
+            None => return,
+        };
+        if buffers.vertex_lods[j] == Some(key) {
+            return;
+        }
+        buffers.vertex_lods[j] = Some(key);
+
+        let tile = match self.world.get_tile(coord) {
+            Some(tile) => tile,
+            None => return,
+        };
+        let heights = self.world.morph_heights(lods, i);
+        let mut vertices = get_sub_grid_vertices(tile, &geomipmap.get_sub_grids()[i]);
+        for (v, h) in vertices.iter_mut().zip(heights) {
+            v.pos.y = h;
+        }
+        write_vertex_buffer(&buffers.vertex_buffers[j], &vertices);
+    }
+}
+
+// The sub-grid's part of the tile's vertices.
+fn get_sub_grid_vertices(tile: &Tile, sub_grid: &SubGrid) -> Vec<geometry::VertexPNT> {
+    let num_cols = tile.get_field().num_cols();
+    let mut vertices = Vec::with_capacity(sub_grid.num_rows * sub_grid.num_cols);
+    for i in sub_grid.row..sub_grid.row + sub_grid.num_rows {
+        let first = i * num_cols + sub_grid.col;
+        vertices.extend_from_slice(&tile.get_vertices()[first..first + sub_grid.num_cols]);
+    }
+    vertices
+}
@@ -1,7 +1,6 @@
 // Some utilities
 
 use rand::Rng;
-use rand::rngs::ThreadRng;
 use regex::Regex;
 
 use windows::{
@@ -57,7 +56,7 @@ pub fn clean_func_call(s: &str) -> String {
     tmp.chars().filter(|c| *c != '\n' && *c != '\r').collect()
 }
 
-pub fn get_random_float(rng: &mut ThreadRng, a: f32, b: f32) -> f32 {
+pub fn get_random_float<R: Rng>(rng: &mut R, a: f32, b: f32) -> f32 {
     if a >= b { // bad input
         return a
     }
@@ -68,7 +67,7 @@ pub fn get_random_float(rng: &mut ThreadRng, a: f32, b: f32) -> f32 {
     (f * (b - a)) + a
 }
 
-pub fn get_random_vec(rng: &mut ThreadRng, out: &mut D3DXVECTOR3) {
+pub fn get_random_vec<R: Rng>(rng: &mut R, out: &mut D3DXVECTOR3) {
     out.x = get_random_float(rng, -1.0, 1.0);
     out.y = get_random_float(rng, -1.0, 1.0);
     out.z = get_random_float(rng, -1.0, 1.0);
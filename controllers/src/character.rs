@@ -0,0 +1,237 @@
+// First person character on a height field: walks with MoveForward and Strafe,
+// falls under gravity and jumps with Jump.  Slopes steeper than max_slope
+// cannot be climbed, except for ledges up to step_height, and the character
+// slides down them.  The character stays inside the ground's extent.
+//
+// The camera is at the eyes, eye_height above the feet.
+
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+use geometry::*;
+use input::*;
+use crate::controller::*;
+use crate::walk::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CharacterController {
+    // Units per second.
+    pub walk_speed: f32,
+    // Radians per unit of Pitch and Yaw (per mickey with the default bindings).
+    pub look_speed: f32,
+    // Largest angle above or below the horizon.
+    pub max_pitch: f32,
+    pub eye_height: f32,
+
+    // Units per second squared, downwards.
+    pub gravity: f32,
+    // Upward speed at the start of a jump; the jump is jump_speed^2 / (2 gravity)
+    // high.
+    pub jump_speed: f32,
+    // Steepest climbable slope, in radians from the horizontal.
+    pub max_slope: f32,
+    // Highest steep rise that can be stepped up onto, and drop that is stepped
+    // down from rather than fallen off.
+    pub step_height: f32,
+    // Kept from the edges of the ground.
+    pub edge_margin: f32,
+
+    // The feet.
+    pos: Vec3,
+    velocity: Vec3,
+    on_ground: bool,
+    // Ground height where the slope was last walkable, which limits how far a
+    // steep rise can be stepped up.
+    walkable_height: f32,
+    // False until the first update, which starts from the camera's position.
+    initialized: bool,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        CharacterController {
+            walk_speed: 5.0,
+            look_speed: 1.0 / 150.0,
+            max_pitch: FRAC_PI_2 * 0.95,
+            eye_height: 1.8,
+            gravity: 20.0,
+            jump_speed: 7.0,
+            max_slope: FRAC_PI_4,
+            step_height: 0.5,
+            edge_margin: 0.5,
+            pos: Vec3::ZERO,
+            velocity: Vec3::ZERO,
+            on_ground: false,
+            walkable_height: 0.0,
+            initialized: false,
+        }
+    }
+}
+
+impl CharacterController {
+    pub fn new(walk_speed: f32, eye_height: f32) -> CharacterController {
+        CharacterController { walk_speed, eye_height, ..Default::default() }
+    }
+
+    // Where the feet are.
+    pub fn get_position(&self) -> Vec3 {
+        self.pos
+    }
+
+    pub fn get_velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    pub fn is_on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    // Puts the feet at 'pos' at rest; they fall if 'pos' is above the ground.
+    pub fn teleport(&mut self, pos: Vec3) {
+        self.pos = pos;
+        self.velocity = Vec3::ZERO;
+        self.on_ground = false;
+        // Unknown until it lands on walkable ground.
+        self.walkable_height = f32::MIN;
+        self.initialized = true;
+    }
+
+    pub fn walk(&mut self, camera: &mut Camera, input: &dyn InputState, bindings: &ActionMap, dt: f32,
+                ground: &dyn Ground) {
+        if !self.initialized {
+            self.teleport(camera.get_pos() - Vec3::Y * self.eye_height);
+        }
+
+        let dir = walk_direction(camera, input, bindings);
+        let (min, max) = self.get_limits(ground);
+
+        let ground_height = ground.get_height(self.pos.x, self.pos.z);
+        let normal = ground.get_normal(self.pos.x, self.pos.z);
+        let steep = normal.y < self.max_slope.cos();
+        // Steep ground no higher than a step above the last walkable ground is
+        // a ledge the character has stepped onto, and holds it.
+        let on_ledge = (0.0..=self.step_height).contains(&(ground_height - self.walkable_height));
+
+        if self.on_ground {
+            if steep && !on_ledge {
+                // Slide: gravity along the surface, whose horizontal part is
+                // gravity * n.y * n.xz.  Walking cannot hold the character on.
+                let slide = Vec3::new(normal.x, 0.0, normal.z) * (self.gravity * normal.y);
+                self.velocity += slide * dt;
+            } else {
+                if !steep {
+                    self.walkable_height = ground_height;
+                }
+                self.velocity = if dir.length_sq() > 0.0 { dir.normalize() * self.walk_speed } else { Vec3::ZERO };
+
+                if bindings.down(input, JUMP) {
+                    self.velocity.y = self.jump_speed;
+                    self.on_ground = false;
+                }
+            }
+        }
+        if !self.on_ground {
+            self.velocity.y -= self.gravity * dt;
+        }
+
+        // Horizontal move, kept inside the ground and off unclimbable slopes.
+        let step = Vec3::new(self.velocity.x, 0.0, self.velocity.z) * dt;
+        if step.length_sq() > 0.0 {
+            let to = self.constrain_step(ground, step, min, max);
+
+            // Stop running into the edges.
+            if to.x != self.pos.x + step.x { self.velocity.x = 0.0; }
+            if to.z != self.pos.z + step.z { self.velocity.z = 0.0; }
+
+            self.pos.x = to.x;
+            self.pos.z = to.z;
+        }
+
+        // Vertical move.
+        let height = ground.get_height(self.pos.x, self.pos.z);
+        if self.on_ground {
+            // Follow the ground down slopes, including the steep one it may be
+            // sliding down, and small drops; fall off higher ones.
+            let slope = (1.0 - normal.y * normal.y).max(0.0).sqrt() / normal.y;
+            let max_drop = self.step_height + step.length() * slope.max(self.max_slope.tan());
+            if self.pos.y - height <= max_drop {
+                self.pos.y = height;
+                self.velocity.y = 0.0;
+            } else {
+                self.on_ground = false;
+                self.velocity.y = 0.0;
+            }
+        } else {
+            self.pos.y += self.velocity.y * dt;
+            if self.pos.y <= height {
+                self.pos.y = height;
+                self.velocity.y = 0.0;
+                self.on_ground = true;
+            }
+        }
+
+        camera.set_pos(self.pos + Vec3::Y * self.eye_height);
+        look_around(camera, input, bindings, self.look_speed, self.max_pitch);
+    }
+
+    // The corners of the area the feet may be in.
+    fn get_limits(&self, ground: &dyn Ground) -> (Vec2, Vec2) {
+        match ground.get_extent() {
+            Some((min, max)) => {
+                let margin = self.edge_margin.min(0.5 * (max.x - min.x)).min(0.5 * (max.y - min.y));
+                (Vec2::new(min.x + margin, min.y + margin), Vec2::new(max.x - margin, max.y - margin))
+            }
+            None => (Vec2::new(f32::MIN, f32::MIN), Vec2::new(f32::MAX, f32::MAX)),
+        }
+    }
+
+    // Where a horizontal step from the feet ends: clamped to the limits and not
+    // into ground it cannot climb, else sliding along it.
+    fn constrain_step(&self, ground: &dyn Ground, step: Vec3, min: Vec2, max: Vec2) -> Vec3 {
+        let clamp = |p: Vec3| Vec3::new(p.x.clamp(min.x, max.x), p.y, p.z.clamp(min.y, max.y));
+
+        let to = clamp(self.pos + step);
+        if self.can_move_to(ground, to) {
+            return to;
+        }
+
+        // Drop the uphill part of the step, so the character slides along the
+        // slope instead of stopping dead.
+        let normal = ground.get_normal(to.x, to.z);
+        let downhill = Vec3::new(normal.x, 0.0, normal.z);
+        if downhill.length_sq() > 0.0 {
+            let downhill = downhill.normalize();
+            let uphill = -step.dot(downhill);
+            if uphill > 0.0 {
+                let along = clamp(self.pos + step + downhill * uphill);
+                if self.can_move_to(ground, along) {
+                    return along;
+                }
+            }
+        }
+
+        self.pos
+    }
+
+    // True if the feet may move horizontally to 'to': not uphill onto steep
+    // ground, unless it is no higher than a step above the last walkable ground,
+    // and in the air not into ground above a step below the feet.
+    fn can_move_to(&self, ground: &dyn Ground, to: Vec3) -> bool {
+        let height = ground.get_height(to.x, to.z);
+        if !self.on_ground {
+            return height <= self.pos.y + self.step_height;
+        }
+        if height <= self.pos.y {
+            return true;
+        }
+
+        let run = Vec2::new(to.x - self.pos.x, to.z - self.pos.z).length();
+        let rise = height - self.pos.y;
+        rise <= run * self.max_slope.tan() || height - self.walkable_height <= self.step_height
+    }
+}
+
+impl CameraController for CharacterController {
+    // On flat ground at height 0; use walk for other ground.
+    fn update(&mut self, camera: &mut Camera, input: &dyn InputState, bindings: &ActionMap, dt: f32) {
+        self.walk(camera, input, bindings, dt, &FlatGround(0.0));
+    }
+}
@@ -0,0 +1,40 @@
+// What the camera controllers have in common.
+
+use geometry::*;
+use input::*;
+
+// Moves a camera from one frame of input.  The input and bindings are passed in,
+// rather than read from the demos' global DirectInput, so controllers can be
+// swapped at run time and driven by a recording or a MemoryInput.
+pub trait CameraController {
+    fn update(&mut self, camera: &mut Camera, input: &dyn InputState, bindings: &ActionMap, dt: f32);
+}
+
+// Height of the ground under a point, for controllers that walk on terrain.
+pub trait Ground {
+    fn get_height(&self, x: f32, z: f32) -> f32;
+
+    // The area the ground covers, as the minimum and maximum corners in x and
+    // z.  None for ground without edges.
+    fn get_extent(&self) -> Option<(Vec2, Vec2)> {
+        None
+    }
+
+    // Upward unit normal, by default from central differences of the height.
+    fn get_normal(&self, x: f32, z: f32) -> Vec3 {
+        const STEP: f32 = 0.1;
+        let dhdx = (self.get_height(x + STEP, z) - self.get_height(x - STEP, z)) / (2.0 * STEP);
+        let dhdz = (self.get_height(x, z + STEP) - self.get_height(x, z - STEP)) / (2.0 * STEP);
+        Vec3::new(-dhdx, 1.0, -dhdz).normalize()
+    }
+}
+
+// Flat ground at a fixed height.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FlatGround(pub f32);
+
+impl Ground for FlatGround {
+    fn get_height(&self, _x: f32, _z: f32) -> f32 {
+        self.0
+    }
+}
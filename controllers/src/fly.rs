@@ -0,0 +1,49 @@
+// Free-flying camera with six degrees of freedom: moves along its own axes and
+// turns about them, including roll, so there is no fixed up direction.
+
+use geometry::*;
+use input::*;
+use crate::controller::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlyController {
+    // Units per second.
+    pub speed: f32,
+    // Radians per unit of Pitch and Yaw (per mickey with the default bindings).
+    pub look_speed: f32,
+    // Radians per second at full Roll.
+    pub roll_speed: f32,
+}
+
+impl Default for FlyController {
+    fn default() -> Self {
+        FlyController {
+            speed: 50.0,
+            look_speed: 1.0 / 150.0,
+            roll_speed: 1.5,
+        }
+    }
+}
+
+impl FlyController {
+    pub fn new(speed: f32) -> FlyController {
+        FlyController { speed, ..Default::default() }
+    }
+}
+
+impl CameraController for FlyController {
+    fn update(&mut self, camera: &mut Camera, input: &dyn InputState, bindings: &ActionMap, dt: f32) {
+        let dir = camera.get_look() * bindings.axis(input, MOVE_FORWARD)
+            + camera.get_right() * bindings.axis(input, STRAFE)
+            + camera.get_up() * bindings.axis(input, MOVE_UP);
+        if dir.length_sq() > 0.0 {
+            camera.translate(dir.normalize() * (self.speed * dt));
+        }
+
+        // About the camera's own axes, each after the previous turn.
+        camera.rotate(camera.get_right(), bindings.axis(input, PITCH) * self.look_speed);
+        camera.rotate(camera.get_up(), bindings.axis(input, YAW) * self.look_speed);
+        // Positive roll banks to the right.
+        camera.rotate(camera.get_look(), -bindings.axis(input, ROLL) * self.roll_speed * dt);
+    }
+}
@@ -0,0 +1,118 @@
+// Third person camera that trails a moving target, like a chase camera behind a
+// car or a character.  It springs toward a spot behind and above the target, so
+// it lags a little when the target speeds up or turns, and looks just above the
+// target.  Yaw swings it around the target and Zoom changes the distance.
+
+use std::f32::consts::PI;
+use geometry::*;
+use input::*;
+use crate::controller::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FollowController {
+    // Behind the target, in the horizontal plane.
+    pub distance: f32,
+    // Above the target.
+    pub height: f32,
+    // The camera looks at this height above the target.
+    pub look_height: f32,
+    // About the time the camera takes to catch up.  0 sticks to the target.
+    pub smooth_time: f32,
+    // Angle about +y from straight behind the target, changed with Yaw.
+    pub yaw_offset: f32,
+    // Radians per unit of Yaw (per mickey with the default bindings).
+    pub rotate_speed: f32,
+    // The distance is scaled by exp(-zoom * zoom_speed) within these limits.
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+
+    target_pos: Vec3,
+    target_forward: Vec3,
+    pos: Vec3,
+    velocity: Vec3,
+    // False until the first update, which puts the camera in place at once.
+    initialized: bool,
+}
+
+impl FollowController {
+    pub fn new(distance: f32, height: f32) -> FollowController {
+        FollowController {
+            distance,
+            height,
+            look_height: 0.0,
+            smooth_time: 0.3,
+            yaw_offset: 0.0,
+            rotate_speed: 0.01,
+            zoom_speed: 0.001,
+            min_distance: 1.0,
+            max_distance: 1000.0,
+            target_pos: Vec3::ZERO,
+            target_forward: Vec3::Z,
+            pos: Vec3::ZERO,
+            velocity: Vec3::ZERO,
+            initialized: false,
+        }
+    }
+
+    // Call every frame before update with where the target is and which way it
+    // faces.  Only the horizontal part of 'forward' is used.
+    pub fn set_target(&mut self, pos: Vec3, forward: Vec3) {
+        self.target_pos = pos;
+
+        let flat = Vec3::new(forward.x, 0.0, forward.z);
+        if flat.length_sq() > 0.0 {
+            self.target_forward = flat.normalize();
+        }
+    }
+
+    pub fn get_target(&self) -> Vec3 {
+        self.target_pos
+    }
+
+    // Where the camera heads for.
+    pub fn get_desired_position(&self) -> Vec3 {
+        let back = Mat4::rotation_y(self.yaw_offset).transform_normal(-self.target_forward);
+        self.target_pos + back * self.distance + Vec3::Y * self.height
+    }
+
+    // Jumps to the desired position on the next update, e.g. after the target
+    // teleported.
+    pub fn snap(&mut self) {
+        self.initialized = false;
+    }
+}
+
+impl CameraController for FollowController {
+    fn update(&mut self, camera: &mut Camera, input: &dyn InputState, bindings: &ActionMap, dt: f32) {
+        self.yaw_offset = (self.yaw_offset + bindings.axis(input, YAW) * self.rotate_speed).rem_euclid(2.0 * PI);
+        self.distance *= (-bindings.axis(input, ZOOM) * self.zoom_speed).exp();
+        self.distance = self.distance.clamp(self.min_distance, self.max_distance);
+
+        let desired = self.get_desired_position();
+        if !self.initialized || self.smooth_time <= 0.0 {
+            self.pos = desired;
+            self.velocity = Vec3::ZERO;
+            self.initialized = true;
+        } else {
+            // Critically damped spring: closes in as fast as it can without
+            // overshooting.  The exponential is approximated as in Game
+            // Programming Gems 4, 1.10, which stays stable for large dt.
+            let omega = 2.0 / self.smooth_time;
+            let x = omega * dt;
+            let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+
+            let offset = self.pos - desired;
+            let temp = (self.velocity + offset * omega) * dt;
+            self.velocity = (self.velocity - temp * omega) * decay;
+            self.pos = desired + (offset + temp) * decay;
+        }
+
+        let look_target = self.target_pos + Vec3::Y * self.look_height;
+        if (look_target - self.pos).length_sq() > 0.0 {
+            camera.look_at(self.pos, look_target, Vec3::Y);
+        } else {
+            camera.set_pos(self.pos);
+        }
+    }
+}
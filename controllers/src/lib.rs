@@ -0,0 +1,17 @@
+pub mod character;
+pub mod controller;
+pub mod fly;
+pub mod follow;
+pub mod orbit;
+pub mod path;
+pub mod player;
+pub mod walk;
+
+pub use crate::character::*;
+pub use crate::controller::*;
+pub use crate::fly::*;
+pub use crate::follow::*;
+pub use crate::orbit::*;
+pub use crate::path::*;
+pub use crate::player::*;
+pub use crate::walk::*;
@@ -0,0 +1,73 @@
+// Orbit camera: circles a target point on a sphere, within elevation and zoom
+// limits.  Yaw and Pitch turn it around the target, Zoom moves it in and out.
+// The early demos orbit their scenes with it.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+use geometry::*;
+use input::*;
+use crate::controller::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    // Angle about +y, 0 on the +x axis, with the camera at
+    // (cos(yaw), _, sin(yaw)) like the early demos.
+    pub yaw: f32,
+    // Elevation above the target's horizontal plane.
+    pub pitch: f32,
+
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+
+    // Radians per unit of Yaw and Pitch (per mickey with the default bindings).
+    pub rotate_speed: f32,
+    // The distance is scaled by exp(-zoom * zoom_speed), so a wheel notch (120)
+    // moves about 11% closer.
+    pub zoom_speed: f32,
+}
+
+impl OrbitController {
+    pub fn new(target: Vec3, distance: f32) -> OrbitController {
+        OrbitController {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 0.0,
+            min_distance: 1.0,
+            max_distance: 1000.0,
+            // Short of straight up or down, where the view's up vector flips.
+            min_pitch: -FRAC_PI_2 + 0.01,
+            max_pitch: FRAC_PI_2 - 0.01,
+            rotate_speed: 0.01,
+            zoom_speed: 0.001,
+        }
+    }
+
+    pub fn get_position(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        self.target + Vec3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw) * self.distance
+    }
+
+    // Places the camera without input, e.g. after changing the target.
+    pub fn apply(&mut self, camera: &mut Camera) {
+        self.yaw = self.yaw.rem_euclid(2.0 * PI);
+        self.pitch = self.pitch.clamp(self.min_pitch, self.max_pitch);
+        self.distance = self.distance.clamp(self.min_distance, self.max_distance);
+
+        camera.look_at(self.get_position(), self.target, Vec3::Y);
+    }
+}
+
+impl CameraController for OrbitController {
+    fn update(&mut self, camera: &mut Camera, input: &dyn InputState, bindings: &ActionMap, _dt: f32) {
+        self.yaw += bindings.axis(input, YAW) * self.rotate_speed;
+        self.pitch += bindings.axis(input, PITCH) * self.rotate_speed;
+        self.distance *= (-bindings.axis(input, ZOOM) * self.zoom_speed).exp();
+
+        self.apply(camera);
+    }
+}
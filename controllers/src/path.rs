@@ -0,0 +1,456 @@
+// Camera paths for fly-throughs and benchmark captures: keyframes of position,
+// orientation and field of view, played back along a spline.
+//
+// Positions and fields of view follow a Catmull-Rom or Kochanek-Bartels spline
+// through the keys, and orientations a squad curve.  With constant speed on, the
+// path is reparameterized by arc length so the camera moves at an even pace
+// however the keys are spaced; the key times then only set the duration.
+//
+// A path file has one setting or key per line:
+//
+//     # Comment
+//     spline = KochanekBartels
+//     constant_speed = true
+//     key = 0  -200 300 0  0 0 0 1  0.785  0 0 0
+//
+// where a key is the time, position, orientation (x, y, z, w), vertical field
+// of view, and tension, continuity and bias.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use geometry::*;
+
+// Samples per segment of the arc length table.
+const ARC_SAMPLES: usize = 32;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SplineKind {
+    // Through the keys with the tangents of their neighbours; ignores the keys'
+    // tension, continuity and bias.
+    CatmullRom,
+    // Catmull-Rom shaped by each key's tension, continuity and bias.
+    KochanekBartels,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraKey {
+    // Seconds.
+    pub time: f32,
+    pub pos: Vec3,
+    pub orientation: Quat,
+    pub fov_y: f32,
+
+    // Kochanek-Bartels parameters, each in [-1, 1].  Tension tightens the curve
+    // at the key, continuity lets it turn a corner there and bias leans it
+    // towards the previous (-1) or next (+1) key.  All 0 is Catmull-Rom.
+    pub tension: f32,
+    pub continuity: f32,
+    pub bias: f32,
+}
+
+impl CameraKey {
+    pub fn new(time: f32, pos: Vec3, orientation: Quat, fov_y: f32) -> CameraKey {
+        CameraKey { time, pos, orientation, fov_y, tension: 0.0, continuity: 0.0, bias: 0.0 }
+    }
+
+    // Where the camera is now, to record a path while flying around.
+    pub fn from_camera(camera: &Camera, time: f32) -> CameraKey {
+        CameraKey::new(time, camera.get_pos(), camera.get_orientation(), camera.get_fov_y())
+    }
+}
+
+// A point of a path.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraPose {
+    pub pos: Vec3,
+    pub orientation: Quat,
+    pub fov_y: f32,
+}
+
+impl CameraPose {
+    // Places the camera with look_at and set_lens, keeping its aspect ratio and
+    // clip planes.
+    pub fn apply(&self, camera: &mut Camera) {
+        let (_, up, look) = self.orientation.to_axes();
+        camera.look_at(self.pos, self.pos + look, up);
+
+        if self.fov_y != camera.get_fov_y() {
+            camera.set_lens(self.fov_y, camera.get_aspect(), camera.get_near_z(), camera.get_far_z());
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathError {
+    // 1-based.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PathError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraPath {
+    spline: SplineKind,
+    constant_speed: bool,
+    // Sorted by time.
+    keys: Vec<CameraKey>,
+
+    // Rebuilt from the keys whenever they change.
+    //
+    // Outgoing and incoming tangents at each key of the position (xyz) and the
+    // field of view (w), which share the spline.
+    out_tangents: Vec<Vec4>,
+    in_tangents: Vec<Vec4>,
+    // The key orientations with signs flipped so neighbours are on the same side
+    // of the 4D sphere, and their squad control points.
+    rotations: Vec<Quat>,
+    controls: Vec<Quat>,
+    // Arc length from the first key at ARC_SAMPLES steps through each segment.
+    arc_lengths: Vec<f32>,
+}
+
+impl Default for CameraPath {
+    fn default() -> Self {
+        CameraPath::new()
+    }
+}
+
+impl CameraPath {
+    // An empty Catmull-Rom path at constant speed.
+    pub fn new() -> CameraPath {
+        CameraPath {
+            spline: SplineKind::CatmullRom,
+            constant_speed: true,
+            keys: Vec::new(),
+            out_tangents: Vec::new(),
+            in_tangents: Vec::new(),
+            rotations: Vec::new(),
+            controls: Vec::new(),
+            arc_lengths: Vec::new(),
+        }
+    }
+
+    pub fn get_spline(&self) -> SplineKind {
+        self.spline
+    }
+
+    pub fn set_spline(&mut self, spline: SplineKind) {
+        self.spline = spline;
+        self.rebuild();
+    }
+
+    pub fn is_constant_speed(&self) -> bool {
+        self.constant_speed
+    }
+
+    // Off, the camera passes each key at its time, speeding up and slowing down
+    // with the spacing of the keys.
+    pub fn set_constant_speed(&mut self, constant_speed: bool) {
+        self.constant_speed = constant_speed;
+    }
+
+    pub fn get_keys(&self) -> &[CameraKey] {
+        &self.keys
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // Inserts a key by its time, after any keys with the same time, and returns
+    // its index.
+    pub fn add_key(&mut self, key: CameraKey) -> usize {
+        let i = self.keys.partition_point(|k| k.time <= key.time);
+        self.keys.insert(i, key);
+        self.rebuild();
+        i
+    }
+
+    // Replaces a key, which moves if its time changed, and returns its new index.
+    pub fn set_key(&mut self, i: usize, key: CameraKey) -> usize {
+        self.keys.remove(i);
+        self.add_key(key)
+    }
+
+    pub fn remove_key(&mut self, i: usize) -> CameraKey {
+        let key = self.keys.remove(i);
+        self.rebuild();
+        key
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.rebuild();
+    }
+
+    pub fn get_start_time(&self) -> f32 {
+        self.keys.first().map_or(0.0, |k| k.time)
+    }
+
+    pub fn get_end_time(&self) -> f32 {
+        self.keys.last().map_or(0.0, |k| k.time)
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self.get_end_time() - self.get_start_time()
+    }
+
+    // Length of the curve through the keys.
+    pub fn get_length(&self) -> f32 {
+        self.arc_lengths.last().copied().unwrap_or(0.0)
+    }
+
+    // The pose at 'time', clamped to the path.  None for an empty path.
+    pub fn sample(&self, time: f32) -> Option<CameraPose> {
+        match self.keys.len() {
+            0 => None,
+            1 => {
+                let key = &self.keys[0];
+                Some(CameraPose { pos: key.pos, orientation: key.orientation, fov_y: key.fov_y })
+            }
+            _ => {
+                let (segment, u) = self.find_segment(time);
+                Some(self.eval(segment, u))
+            }
+        }
+    }
+
+    // Places the camera at 'time' on the path, if it has keys.
+    pub fn apply(&self, camera: &mut Camera, time: f32) {
+        if let Some(pose) = self.sample(time) {
+            pose.apply(camera);
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<CameraPath, PathError> {
+        let mut path = CameraPath::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: String| PathError { line: i + 1, message };
+
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (name, value) = line.split_once('=').ok_or_else(|| error(format!("expected 'name = value', got '{}'", line)))?;
+            let value = value.trim();
+            match name.trim() {
+                "spline" => {
+                    path.spline = match value {
+                        "CatmullRom" => SplineKind::CatmullRom,
+                        "KochanekBartels" => SplineKind::KochanekBartels,
+                        _ => return Err(error(format!("unknown spline '{}'", value))),
+                    };
+                }
+                "constant_speed" => {
+                    path.constant_speed = value.parse().map_err(|_| error(format!("expected true or false, got '{}'", value)))?;
+                }
+                "key" => {
+                    let v: Vec<f32> = value
+                        .split_whitespace()
+                        .map(|t| t.parse::<f32>().ok().filter(|f| f.is_finite()))
+                        .collect::<Option<_>>()
+                        .ok_or_else(|| error(format!("bad number in key '{}'", value)))?;
+                    if v.len() != 12 {
+                        return Err(error(format!("expected 12 numbers in a key, got {}", v.len())));
+                    }
+
+                    let mut orientation = Quat::new(v[4], v[5], v[6], v[7]);
+                    let length = orientation.length();
+                    if length == 0.0 {
+                        return Err(error("zero orientation".to_string()));
+                    }
+                    // Hand-edited keys need not be unit length; saved ones are
+                    // kept as they are, so a path reloads exactly.
+                    if (length - 1.0).abs() > 1e-4 {
+                        orientation = orientation.normalize();
+                    }
+
+                    path.keys.push(CameraKey {
+                        time: v[0],
+                        pos: Vec3::new(v[1], v[2], v[3]),
+                        orientation,
+                        fov_y: v[8],
+                        tension: v[9],
+                        continuity: v[10],
+                        bias: v[11],
+                    });
+                }
+                name => return Err(error(format!("unknown setting '{}'", name))),
+            }
+        }
+
+        // Stable, so keys with the same time keep the file's order.
+        path.keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        path.rebuild();
+        Ok(path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<CameraPath> {
+        let text = fs::read_to_string(path)?;
+        CameraPath::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    fn rebuild(&mut self) {
+        let n = self.keys.len();
+
+        self.rotations.clear();
+        for (i, key) in self.keys.iter().enumerate() {
+            let q = if i == 0 { key.orientation } else { key.orientation.align_to(self.rotations[i - 1]) };
+            self.rotations.push(q);
+        }
+
+        self.controls.clear();
+        self.out_tangents.clear();
+        self.in_tangents.clear();
+        for i in 0..n {
+            let r = &self.rotations;
+            self.controls.push(Quat::squad_control(r[i.saturating_sub(1)], r[i], r[(i + 1).min(n - 1)]));
+
+            let (out_tangent, in_tangent) = self.tangents(i);
+            self.out_tangents.push(out_tangent);
+            self.in_tangents.push(in_tangent);
+        }
+
+        self.arc_lengths.clear();
+        if n >= 2 {
+            let mut length = 0.0;
+            let mut prev = self.keys[0].pos;
+            self.arc_lengths.push(0.0);
+            for segment in 0..n - 1 {
+                for j in 1..=ARC_SAMPLES {
+                    let p = self.eval_spline(segment, j as f32 / ARC_SAMPLES as f32).xyz();
+                    length += p.distance(prev);
+                    prev = p;
+                    self.arc_lengths.push(length);
+                }
+            }
+        }
+    }
+
+    // Outgoing and incoming tangents at key i, scaled for the lengths of the
+    // segments on either side so uneven key times do not kink the curve.
+    fn tangents(&self, i: usize) -> (Vec4, Vec4) {
+        let n = self.keys.len();
+        if n < 2 {
+            return (Vec4::default(), Vec4::default());
+        }
+
+        let point = |k: &CameraKey| Vec4::from_vec3(k.pos, k.fov_y);
+        let p = point(&self.keys[i]);
+        // Past the ends, mirror the neighbour so the curve leaves straight.
+        let prev = if i > 0 { point(&self.keys[i - 1]) } else { p * 2.0 - point(&self.keys[1]) };
+        let next = if i + 1 < n { point(&self.keys[i + 1]) } else { p * 2.0 - point(&self.keys[n - 2]) };
+
+        let key = &self.keys[i];
+        let (t, c, b) = match self.spline {
+            SplineKind::CatmullRom => (0.0, 0.0, 0.0),
+            SplineKind::KochanekBartels => (key.tension, key.continuity, key.bias),
+        };
+
+        let d0 = p - prev;
+        let d1 = next - p;
+        let out_tangent = d0 * ((1.0 - t) * (1.0 + c) * (1.0 + b) * 0.5) + d1 * ((1.0 - t) * (1.0 - c) * (1.0 - b) * 0.5);
+        let in_tangent = d0 * ((1.0 - t) * (1.0 - c) * (1.0 + b) * 0.5) + d1 * ((1.0 - t) * (1.0 + c) * (1.0 - b) * 0.5);
+
+        let dt0 = if i > 0 { key.time - self.keys[i - 1].time } else { self.keys[1].time - key.time };
+        let dt1 = if i + 1 < n { self.keys[i + 1].time - key.time } else { dt0 };
+        if dt0 + dt1 > 0.0 {
+            (out_tangent * (2.0 * dt1 / (dt0 + dt1)), in_tangent * (2.0 * dt0 / (dt0 + dt1)))
+        } else {
+            (out_tangent, in_tangent)
+        }
+    }
+
+    // Segment and parameter within it at 'time'.  Needs two keys.
+    fn find_segment(&self, time: f32) -> (usize, f32) {
+        let n = self.keys.len();
+        let start = self.get_start_time();
+        let duration = self.get_duration();
+        let length = self.get_length();
+
+        if self.constant_speed && length > 0.0 {
+            let fraction = if duration > 0.0 { ((time - start) / duration).clamp(0.0, 1.0) } else { 0.0 };
+            let s = fraction * length;
+
+            // Last sample at or before s.
+            let j = self.arc_lengths.partition_point(|&a| a <= s).clamp(1, self.arc_lengths.len() - 1) - 1;
+            let span = self.arc_lengths[j + 1] - self.arc_lengths[j];
+            let local = if span > 0.0 { ((s - self.arc_lengths[j]) / span).clamp(0.0, 1.0) } else { 0.0 };
+
+            let param = (j as f32 + local) / ARC_SAMPLES as f32;
+            let segment = (param as usize).min(n - 2);
+            (segment, (param - segment as f32).clamp(0.0, 1.0))
+        } else {
+            let time = time.clamp(start, self.get_end_time());
+            let segment = self.keys.partition_point(|k| k.time <= time).clamp(1, n - 1) - 1;
+            let t0 = self.keys[segment].time;
+            let t1 = self.keys[segment + 1].time;
+            let u = if t1 > t0 { (time - t0) / (t1 - t0) } else { 0.0 };
+            (segment, u)
+        }
+    }
+
+    fn eval(&self, segment: usize, u: f32) -> CameraPose {
+        let v = self.eval_spline(segment, u);
+        let orientation = Quat::squad(
+            self.rotations[segment],
+            self.controls[segment],
+            self.controls[segment + 1],
+            self.rotations[segment + 1],
+            u);
+
+        CameraPose { pos: v.xyz(), orientation, fov_y: v.w }
+    }
+
+    // Cubic Hermite curve of position and field of view over a segment.
+    fn eval_spline(&self, segment: usize, u: f32) -> Vec4 {
+        let k0 = &self.keys[segment];
+        let k1 = &self.keys[segment + 1];
+        let p0 = Vec4::from_vec3(k0.pos, k0.fov_y);
+        let p1 = Vec4::from_vec3(k1.pos, k1.fov_y);
+        let m0 = self.out_tangents[segment];
+        let m1 = self.in_tangents[segment + 1];
+
+        let u2 = u * u;
+        let u3 = u2 * u;
+        p0 * (2.0 * u3 - 3.0 * u2 + 1.0) + m0 * (u3 - 2.0 * u2 + u) + p1 * (-2.0 * u3 + 3.0 * u2) + m1 * (u3 - u2)
+    }
+}
+
+impl fmt::Display for CameraPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let spline = match self.spline {
+            SplineKind::CatmullRom => "CatmullRom",
+            SplineKind::KochanekBartels => "KochanekBartels",
+        };
+        writeln!(f, "spline = {}", spline)?;
+        writeln!(f, "constant_speed = {}", self.constant_speed)?;
+        writeln!(f, "# time, position, orientation, fov_y, tension, continuity, bias")?;
+
+        for k in &self.keys {
+            let q = k.orientation;
+            writeln!(f, "key = {}  {} {} {}  {} {} {} {}  {}  {} {} {}",
+                     k.time, k.pos.x, k.pos.y, k.pos.z, q.x, q.y, q.z, q.w, k.fov_y, k.tension, k.continuity, k.bias)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+// Timeline playback of a camera path: play, pause, change speed or direction
+// and scrub to any time.  As a CameraController it takes over the camera in
+// place of the other controllers, ignoring the input.
+
+use geometry::*;
+use input::*;
+use crate::controller::*;
+use crate::path::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathPlayer {
+    path: CameraPath,
+    // On the path's timeline, from its start to its end time.
+    time: f32,
+    playing: bool,
+
+    // 1 plays in real time, negative backwards.
+    pub speed: f32,
+    // Wraps around at the ends instead of stopping.
+    pub looping: bool,
+}
+
+impl PathPlayer {
+    // Paused at the start of the path.
+    pub fn new(path: CameraPath) -> PathPlayer {
+        let time = path.get_start_time();
+        PathPlayer { path, time, playing: false, speed: 1.0, looping: false }
+    }
+
+    pub fn get_path(&self) -> &CameraPath {
+        &self.path
+    }
+
+    // For recording or editing keys.  The time is kept on the path on the next
+    // advance or seek.
+    pub fn get_path_mut(&mut self) -> &mut CameraPath {
+        &mut self.path
+    }
+
+    pub fn get_time(&self) -> f32 {
+        self.time
+    }
+
+    // 0 at the start of the path, 1 at the end.
+    pub fn get_progress(&self) -> f32 {
+        let duration = self.path.get_duration();
+        if duration > 0.0 { (self.time - self.path.get_start_time()) / duration } else { 0.0 }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    // True when stopped at the end it was playing towards.
+    pub fn is_finished(&self) -> bool {
+        !self.looping && if self.speed < 0.0 {
+            self.time <= self.path.get_start_time()
+        } else {
+            self.time >= self.path.get_end_time()
+        }
+    }
+
+    // Plays on from the current time, or from the start (the end when playing
+    // backwards) if finished.
+    pub fn play(&mut self) {
+        if self.is_finished() {
+            self.time = if self.speed < 0.0 { self.path.get_end_time() } else { self.path.get_start_time() };
+        }
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    // Pauses and goes back to the start.
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.time = self.path.get_start_time();
+    }
+
+    // Jumps to a time on the timeline, clamped to the path.
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.clamp(self.path.get_start_time(), self.path.get_end_time());
+    }
+
+    // Moves the time on by dt times the speed while playing.
+    pub fn advance(&mut self, dt: f32) {
+        if !self.playing {
+            self.seek(self.time);
+            return;
+        }
+
+        let start = self.path.get_start_time();
+        let duration = self.path.get_duration();
+        let time = self.time + dt * self.speed;
+
+        if self.looping && duration > 0.0 {
+            self.time = start + (time - start).rem_euclid(duration);
+        } else {
+            self.seek(time);
+            if self.is_finished() {
+                self.playing = false;
+            }
+        }
+    }
+
+    // Places the camera at the current time without advancing it.
+    pub fn apply(&self, camera: &mut Camera) {
+        self.path.apply(camera, self.time);
+    }
+}
+
+impl CameraController for PathPlayer {
+    fn update(&mut self, camera: &mut Camera, _input: &dyn InputState, _bindings: &ActionMap, dt: f32) {
+        self.advance(dt);
+        self.apply(camera);
+    }
+}
@@ -0,0 +1,102 @@
+// First person camera that walks: moves in the horizontal plane, keeps its eyes
+// a fixed height above the ground and cannot look past straight up or down.
+// With no ground it keeps its height, like a spectator.
+
+use std::f32::consts::FRAC_PI_2;
+use geometry::*;
+use input::*;
+use crate::controller::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WalkController {
+    // Units per second.
+    pub speed: f32,
+    // Radians per unit of Pitch and Yaw (per mickey with the default bindings).
+    pub look_speed: f32,
+    // Height of the eyes above the ground.
+    pub eye_height: f32,
+    // Largest angle above or below the horizon.
+    pub max_pitch: f32,
+}
+
+impl Default for WalkController {
+    fn default() -> Self {
+        WalkController {
+            speed: 100.0,
+            look_speed: 1.0 / 150.0,
+            eye_height: 5.0,
+            max_pitch: FRAC_PI_2 * 0.95,
+        }
+    }
+}
+
+impl WalkController {
+    pub fn new(speed: f32, eye_height: f32) -> WalkController {
+        WalkController { speed, eye_height, ..Default::default() }
+    }
+
+    pub fn walk(&mut self, camera: &mut Camera, input: &dyn InputState, bindings: &ActionMap, dt: f32,
+                ground: Option<&dyn Ground>) {
+        let dir = walk_direction(camera, input, bindings);
+        if dir.length_sq() > 0.0 {
+            let step = dir.normalize() * (self.speed * dt);
+            let pos = camera.get_pos();
+            let mut new_pos = pos + step;
+
+            if let Some(ground) = ground {
+                // The new position is likely off the ground, so project it onto
+                // the ground.  The difference of the new and old positions then
+                // approximates a tangent along the slope, and moving along it
+                // keeps the speed the same uphill and downhill.
+                new_pos.y = ground.get_height(new_pos.x, new_pos.z) + self.eye_height;
+                let tangent = new_pos - pos;
+                if tangent.length_sq() > 0.0 {
+                    new_pos = pos + tangent.normalize() * (self.speed * dt);
+                }
+            }
+            camera.set_pos(new_pos);
+        }
+
+        // The tangent is only an approximation, so put the eyes back at the
+        // right height, also when standing still on moving ground.
+        if let Some(ground) = ground {
+            let mut pos = camera.get_pos();
+            pos.y = ground.get_height(pos.x, pos.z) + self.eye_height;
+            camera.set_pos(pos);
+        }
+
+        look_around(camera, input, bindings, self.look_speed, self.max_pitch);
+    }
+}
+
+impl CameraController for WalkController {
+    fn update(&mut self, camera: &mut Camera, input: &dyn InputState, bindings: &ActionMap, dt: f32) {
+        self.walk(camera, input, bindings, dt, None);
+    }
+}
+
+// Horizontal direction of MoveForward and Strafe, not normalized.  Levels the
+// camera first, in case another controller rolled it.
+pub(crate) fn walk_direction(camera: &mut Camera, input: &dyn InputState, bindings: &ActionMap) -> Vec3 {
+    if camera.get_right().y != 0.0 {
+        camera.set_orientation(camera.get_look(), Vec3::Y);
+    }
+
+    // Forward is the look direction flattened, so looking down does not slow
+    // the walk.
+    let right = camera.get_right();
+    let forward = right.cross(Vec3::Y);
+
+    forward * bindings.axis(input, MOVE_FORWARD) + right * bindings.axis(input, STRAFE)
+}
+
+// Turns with Yaw about the world y-axis and with Pitch up and down, no further
+// than 'max_pitch' from the horizon.
+pub(crate) fn look_around(camera: &mut Camera, input: &dyn InputState, bindings: &ActionMap, look_speed: f32, max_pitch: f32) {
+    // Positive pitch looks down.
+    let current = (-camera.get_look().y).clamp(-1.0, 1.0).asin();
+    let pitch = (current + bindings.axis(input, PITCH) * look_speed).clamp(-max_pitch, max_pitch);
+    camera.rotate(camera.get_right(), pitch - current);
+
+    camera.rotate(Vec3::Y, bindings.axis(input, YAW) * look_speed);
+}
@@ -11,6 +11,15 @@ extern "C" HRESULT D3DX_IDirect3DTexture9_GetSurfaceLevel(LPDIRECT3DTEXTURE9 sel
     return self->GetSurfaceLevel(Level, ppSurfaceLevel);
 }
 
+extern "C" HRESULT D3DX_IDirect3DTexture9_LockRect(LPDIRECT3DTEXTURE9 self, UINT Level, D3DLOCKED_RECT *pLockedRect,
+                                                   const RECT *pRect, DWORD Flags) {
+    return self->LockRect(Level, pLockedRect, pRect, Flags);
+}
+
+extern "C" HRESULT D3DX_IDirect3DTexture9_UnlockRect(LPDIRECT3DTEXTURE9 self, UINT Level) {
+    return self->UnlockRect(Level);
+}
+
 // D3DX Misc
 
 extern "C" void D3DX_Release(IUnknown *self) {
@@ -219,6 +228,10 @@ extern "C" DWORD D3DX_ID3DXBaseMesh_GetNumBytesPerVertex(LPD3DXMESH self) {
     return self->GetNumBytesPerVertex();
 }
 
+extern "C" DWORD D3DX_ID3DXBaseMesh_GetOptions(LPD3DXMESH self) {
+    return self->GetOptions();
+}
+
 extern "C" HRESULT D3DX_ID3DXBaseMesh_DrawSubset(LPD3DXMESH self, DWORD AttribId) {
     return self->DrawSubset(AttribId);
 }
@@ -271,6 +284,11 @@ extern "C" HRESULT D3DX_ID3DXMesh_UnlockAttributeBuffer(LPD3DXMESH self) {
     return self->UnlockAttributeBuffer();
 }
 
+extern "C" HRESULT D3DX_ID3DXMesh_SetAttributeTable(LPD3DXMESH self, const D3DXATTRIBUTERANGE *pAttribTable,
+                                                    DWORD cAttribTableSize) {
+    return self->SetAttributeTable(pAttribTable, cAttribTableSize);
+}
+
 extern "C" HRESULT D3DX_LoadMeshFromX(LPCTSTR pFilename, DWORD Options, LPDIRECT3DDEVICE9 pD3DDevice,
         LPD3DXBUFFER *ppAdjacency, LPD3DXBUFFER *ppMaterials, LPD3DXBUFFER *ppEffectInstances,
         DWORD *pNumMaterials, LPD3DXMESH *ppMesh) {
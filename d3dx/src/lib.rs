@@ -18,6 +18,13 @@ type D3DCOLOR = u32;
 extern {
     // HRESULT GetSurfaceLevel(UINT Level, IDirect3DSurface9 **ppSurfaceLevel);
     fn D3DX_IDirect3DTexture9_GetSurfaceLevel(texture: *mut c_void, Level: u32, ppSurfaceLevel: *mut *mut c_void) -> D3DX_HRESULT;
+
+    // HRESULT LockRect(UINT Level, D3DLOCKED_RECT *pLockedRect, const RECT *pRect, DWORD Flags);
+    fn D3DX_IDirect3DTexture9_LockRect(texture: *mut c_void, Level: u32, pLockedRect: *mut D3DLOCKED_RECT,
+                                       pRect: *const RECT, Flags: u32) -> D3DX_HRESULT;
+
+    // HRESULT UnlockRect(UINT Level);
+    fn D3DX_IDirect3DTexture9_UnlockRect(texture: *mut c_void, Level: u32) -> D3DX_HRESULT;
 }
 
 #[allow(non_snake_case)]
@@ -25,6 +32,17 @@ pub fn IDirect3DTexture9_GetSurfaceLevel(texture: *mut c_void, Level: u32, ppSur
     unsafe { to_result(D3DX_IDirect3DTexture9_GetSurfaceLevel(texture, Level, ppSurfaceLevel)) }
 }
 
+#[allow(non_snake_case)]
+pub fn IDirect3DTexture9_LockRect(texture: *mut c_void, Level: u32, pLockedRect: *mut D3DLOCKED_RECT,
+                                  pRect: *const RECT, Flags: u32) -> Result<()> {
+    unsafe { to_result(D3DX_IDirect3DTexture9_LockRect(texture, Level, pLockedRect, pRect, Flags)) }
+}
+
+#[allow(non_snake_case)]
+pub fn IDirect3DTexture9_UnlockRect(texture: *mut c_void, Level: u32) -> Result<()> {
+    unsafe { to_result(D3DX_IDirect3DTexture9_UnlockRect(texture, Level)) }
+}
+
 
 // D3DX9
 
@@ -191,6 +209,18 @@ pub const D3DXMESHOPT_COMPACT: u32 = 0x01000000;
 pub const D3DXMESHOPT_ATTRSORT: u32 = 0x02000000;
 pub const D3DXMESHOPT_VERTEXCACHE: u32 = 0x04000000;
 
+// struct D3DXATTRIBUTERANGE
+#[allow(non_snake_case)]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct D3DXATTRIBUTERANGE {
+    pub AttribId: u32,
+    pub FaceStart: u32,
+    pub FaceCount: u32,
+    pub VertexStart: u32,
+    pub VertexCount: u32,
+}
+
 
 // D3DX Functions
 
@@ -365,6 +395,9 @@ extern {
     // DWORD GetNumBytesPerVertex();
     fn D3DX_ID3DXBaseMesh_GetNumBytesPerVertex(pMesh: *const c_void) -> u32;
 
+    // DWORD GetOptions();
+    fn D3DX_ID3DXBaseMesh_GetOptions(pMesh: *const c_void) -> u32;
+
     // HRESULT ID3DXBaseMesh::DrawSubset(LPD3DXMESH self, DWORD AttribId)
     fn D3DX_ID3DXBaseMesh_DrawSubset(pMesh: *const c_void, AttribId: u32) -> D3DX_HRESULT;
 
@@ -409,6 +442,10 @@ extern {
     // HRESULT UnlockAttributeBuffer();
     fn D3DX_ID3DXMesh_UnlockAttributeBuffer(pMesh: *const c_void) -> D3DX_HRESULT;
 
+    // HRESULT SetAttributeTable(const D3DXATTRIBUTERANGE *pAttribTable, DWORD cAttribTableSize);
+    fn D3DX_ID3DXMesh_SetAttributeTable(pMesh: *const c_void, pAttribTable: *const D3DXATTRIBUTERANGE,
+                                        cAttribTableSize: u32) -> D3DX_HRESULT;
+
     // HRESULT D3DXLoadMeshFromX(LPCTSTR pFilename, DWORD Options, LPDIRECT3DDEVICE9 pD3DDevice,
     //      LPD3DXBUFFER *ppAdjacency, LPD3DXBUFFER *ppMaterials, LPD3DXBUFFER *ppEffectInstances,
     //      DWORD *pNumMaterials, LPD3DXMESH *ppMesh)
@@ -826,6 +863,11 @@ pub fn ID3DXBaseMesh_GetNumBytesPerVertex(pMesh: *const c_void) -> u32 {
     unsafe { D3DX_ID3DXBaseMesh_GetNumBytesPerVertex(pMesh) }
 }
 
+#[allow(non_snake_case)]
+pub fn ID3DXBaseMesh_GetOptions(pMesh: *const c_void) -> u32 {
+    unsafe { D3DX_ID3DXBaseMesh_GetOptions(pMesh) }
+}
+
 #[allow(non_snake_case)]
 pub fn ID3DXBaseMesh_DrawSubset(pMesh: *const c_void, AttribId: u32) -> Result<()> {
     unsafe { to_result(D3DX_ID3DXBaseMesh_DrawSubset(pMesh, AttribId)) }
@@ -898,6 +940,12 @@ pub fn ID3DXMesh_UnlockAttributeBuffer(pMesh: *const c_void) -> Result<()> {
     unsafe { to_result(D3DX_ID3DXMesh_UnlockAttributeBuffer(pMesh)) }
 }
 
+#[allow(non_snake_case)]
+pub fn ID3DXMesh_SetAttributeTable(pMesh: *const c_void, pAttribTable: *const D3DXATTRIBUTERANGE,
+                                   cAttribTableSize: u32) -> Result<()> {
+    unsafe { to_result(D3DX_ID3DXMesh_SetAttributeTable(pMesh, pAttribTable, cAttribTableSize)) }
+}
+
 #[allow(non_snake_case)]
 pub fn D3DXLoadMeshFromX(pFilename: PSTR, Options: u32, pDevice: IDirect3DDevice9,
                       ppAdjacency: *mut LPD3DXBUFFER, ppMaterials: *mut LPD3DXBUFFER,
@@ -0,0 +1,845 @@
+// Bounding volumes: axis-aligned boxes, spheres and oriented boxes, with the
+// overlap and ray tests culling and picking need.
+
+use crate::math::*;
+use crate::mesh::*;
+use crate::ray::*;
+
+//===============================================================
+// Axis-aligned boxes.
+
+// The default box is empty (inverted), so growing it by the first point or box
+// gives exactly that point or box.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb::EMPTY
+    }
+}
+
+impl Aabb {
+    pub const EMPTY: Aabb = Aabb {
+        min: Vec3::splat(f32::MAX),
+        max: Vec3::splat(f32::MIN),
+    };
+
+    pub fn new(min: Vec3, max: Vec3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    pub fn from_center_extent(center: Vec3, extent: Vec3) -> Aabb {
+        Aabb { min: center - extent, max: center + extent }
+    }
+
+    // Box around the vertex positions, like D3DXComputeBoundingBox.  Empty for no
+    // points.
+    pub fn from_points<V: Vertex>(points: &[V]) -> Aabb {
+        let mut res = Aabb::EMPTY;
+        for p in points {
+            res.grow(p.pos());
+        }
+        res
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    // Half the size along each axis.
+    pub fn extent(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    // The eight corners; bit 0 of the index picks max.x, bit 1 max.y, bit 2 max.z.
+    pub fn corners(&self) -> [Vec3; 8] {
+        let mut res = [Vec3::ZERO; 8];
+        for (i, c) in res.iter_mut().enumerate() {
+            *c = Vec3::new(if i & 1 != 0 { self.max.x } else { self.min.x },
+                           if i & 2 != 0 { self.max.y } else { self.min.y },
+                           if i & 4 != 0 { self.max.z } else { self.min.z });
+        }
+        res
+    }
+
+    // Union with a point.
+    pub fn grow(&mut self, p: Vec3) {
+        self.min = self.min.min(p);
+        self.max = self.max.max(p);
+    }
+
+    pub fn merge(&mut self, other: &Aabb) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        p.x >= self.min.x && p.y >= self.min.y && p.z >= self.min.z &&
+        p.x <= self.max.x && p.y <= self.max.y && p.z <= self.max.z
+    }
+
+    // Whether 'other' lies entirely inside.  An empty box is inside everything.
+    pub fn contains(&self, other: &Aabb) -> bool {
+        other.is_empty() || (self.contains_point(other.min) && self.contains_point(other.max))
+    }
+
+    // Touching boxes intersect.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x &&
+        self.min.y <= other.max.y && self.max.y >= other.min.y &&
+        self.min.z <= other.max.z && self.max.z >= other.min.z
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.distance_sq(sphere.center) <= sphere.radius * sphere.radius
+    }
+
+    pub fn closest_point(&self, p: Vec3) -> Vec3 {
+        p.max(self.min).min(self.max)
+    }
+
+    // Squared distance from a point to the box, 0 inside.
+    pub fn distance_sq(&self, p: Vec3) -> f32 {
+        (self.closest_point(p) - p).length_sq()
+    }
+
+    // Box around the transformed box, same as AABB::xform in common.
+    pub fn transform(&self, m: &Mat4) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+
+        let c = m.transform_coord(self.center());
+        let e = self.extent();
+        let e = Vec3::new(
+            e.x * m.m[0][0].abs() + e.y * m.m[1][0].abs() + e.z * m.m[2][0].abs(),
+            e.x * m.m[0][1].abs() + e.y * m.m[1][1].abs() + e.z * m.m[2][1].abs(),
+            e.x * m.m[0][2].abs() + e.y * m.m[1][2].abs() + e.z * m.m[2][2].abs());
+
+        Aabb::from_center_extent(c, e)
+    }
+
+    // Slab test.  Returns the parameter range [t_near, t_far] the ray spends
+    // inside the box, clipped to [t_min, t_max].  Replaces D3DXBoxBoundProbe.
+    pub fn intersect_ray(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        self.intersect_ray_inv(ray.origin, ray.inv_dir(), t_min, t_max)
+    }
+
+    // Same as intersect_ray with the reciprocal of the direction precomputed, for
+    // testing one ray against many boxes.
+    pub fn intersect_ray_inv(&self, origin: Vec3, inv_dir: Vec3, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        let mut t0 = t_min;
+        let mut t1 = t_max;
+
+        for axis in 0..3 {
+            let mut near = (self.min[axis] - origin[axis]) * inv_dir[axis];
+            let mut far = (self.max[axis] - origin[axis]) * inv_dir[axis];
+            if near > far {
+                std::mem::swap(&mut near, &mut far);
+            }
+
+            // NaN (origin on a slab plane of a parallel ray) must not reject, so
+            // compare in the direction that lets it through.
+            if near > t0 {
+                t0 = near;
+            }
+            if far < t1 {
+                t1 = far;
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+
+        Some((t0, t1))
+    }
+}
+
+//===============================================================
+// Spheres.
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Vec3, radius: f32) -> BoundingSphere {
+        BoundingSphere { center, radius }
+    }
+
+    // Sphere centered at the average of the points, like D3DXComputeBoundingSphere.
+    pub fn from_points<V: Vertex>(points: &[V]) -> BoundingSphere {
+        if points.is_empty() {
+            return BoundingSphere::default();
+        }
+
+        let mut center = Vec3::ZERO;
+        for p in points {
+            center += p.pos();
+        }
+        center = center / points.len() as f32;
+
+        let radius_sq = points.iter().map(|p| (p.pos() - center).length_sq()).fold(0.0, f32::max);
+        BoundingSphere { center, radius: radius_sq.sqrt() }
+    }
+
+    pub fn from_aabb(b: &Aabb) -> BoundingSphere {
+        BoundingSphere { center: b.center(), radius: b.extent().length() }
+    }
+
+    pub fn to_aabb(&self) -> Aabb {
+        Aabb::from_center_extent(self.center, Vec3::splat(self.radius))
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        (p - self.center).length_sq() <= self.radius * self.radius
+    }
+
+    pub fn contains(&self, other: &BoundingSphere) -> bool {
+        (other.center - self.center).length() + other.radius <= self.radius
+    }
+
+    pub fn intersects(&self, other: &BoundingSphere) -> bool {
+        let r = self.radius + other.radius;
+        (other.center - self.center).length_sq() <= r * r
+    }
+
+    pub fn intersects_aabb(&self, b: &Aabb) -> bool {
+        b.intersects_sphere(self)
+    }
+
+    // Smallest sphere around both.
+    pub fn union(&self, other: &BoundingSphere) -> BoundingSphere {
+        let d = other.center - self.center;
+        let dist = d.length();
+
+        if dist + other.radius <= self.radius {
+            return *self;
+        }
+        if dist + self.radius <= other.radius {
+            return *other;
+        }
+
+        let radius = (dist + self.radius + other.radius) * 0.5;
+        let center = self.center + d * ((radius - self.radius) / dist);
+        BoundingSphere { center, radius }
+    }
+
+    // Sphere around the transformed sphere; the radius grows by the largest
+    // axis scale of 'm'.
+    pub fn transform(&self, m: &Mat4) -> BoundingSphere {
+        let scale = m.transform_normal(Vec3::X).length()
+            .max(m.transform_normal(Vec3::Y).length())
+            .max(m.transform_normal(Vec3::Z).length());
+        BoundingSphere { center: m.transform_coord(self.center), radius: self.radius * scale }
+    }
+
+    // Parameter range the ray spends inside the sphere, clipped to
+    // [t_min, t_max].  Replaces D3DXSphereBoundProbe.
+    pub fn intersect_ray(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        let m = ray.origin - self.center;
+        let a = ray.dir.length_sq();
+        let b = m.dot(ray.dir);
+        let c = m.length_sq() - self.radius * self.radius;
+
+        if a == 0.0 {
+            return if c <= 0.0 { Some((t_min, t_max)) } else { None };
+        }
+
+        let disc = b * b - a * c;
+        if disc < 0.0 {
+            return None;
+        }
+
+        let s = disc.sqrt();
+        let t0 = ((-b - s) / a).max(t_min);
+        let t1 = ((-b + s) / a).min(t_max);
+        if t0 > t1 {
+            None
+        } else {
+            Some((t0, t1))
+        }
+    }
+}
+
+//===============================================================
+// Oriented boxes.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Obb {
+    pub center: Vec3,
+    // Orthonormal.
+    pub axes: [Vec3; 3],
+    // Half the size along each axis.
+    pub extent: Vec3,
+}
+
+impl Default for Obb {
+    fn default() -> Self {
+        Obb { center: Vec3::ZERO, axes: [Vec3::X, Vec3::Y, Vec3::Z], extent: Vec3::ZERO }
+    }
+}
+
+impl Obb {
+    pub fn new(center: Vec3, axes: [Vec3; 3], extent: Vec3) -> Obb {
+        Obb { center, axes, extent }
+    }
+
+    // An axis-aligned box moved by 'm'.  'm' may rotate, translate and scale but
+    // not shear.
+    pub fn from_aabb(b: &Aabb, m: &Mat4) -> Obb {
+        let e = b.extent();
+        let mut axes = [Vec3::X, Vec3::Y, Vec3::Z];
+        let mut extent = [e.x, e.y, e.z];
+
+        for i in 0..3 {
+            let a = m.transform_normal(axes[i]);
+            let len = a.length();
+            axes[i] = a.normalize();
+            extent[i] *= len;
+        }
+
+        Obb { center: m.transform_coord(b.center()), axes, extent: Vec3::new(extent[0], extent[1], extent[2]) }
+    }
+
+    // Fits a box to the points along the principal axes of their covariance.
+    // Tighter than an Aabb for elongated, rotated point sets; not the optimal box.
+    pub fn from_points<V: Vertex>(points: &[V]) -> Obb {
+        if points.is_empty() {
+            return Obb::default();
+        }
+
+        let n = points.len() as f32;
+        let mut mean = Vec3::ZERO;
+        for p in points {
+            mean += p.pos();
+        }
+        mean = mean / n;
+
+        let mut cov = [[0.0f32; 3]; 3];
+        for p in points {
+            let d = p.pos() - mean;
+            for (i, row) in cov.iter_mut().enumerate() {
+                for (j, c) in row.iter_mut().enumerate() {
+                    *c += d[i] * d[j] / n;
+                }
+            }
+        }
+
+        let axes = symmetric_eigenvectors(cov);
+
+        let mut lo = Vec3::splat(f32::MAX);
+        let mut hi = Vec3::splat(f32::MIN);
+        for p in points {
+            let d = p.pos();
+            let local = Vec3::new(d.dot(axes[0]), d.dot(axes[1]), d.dot(axes[2]));
+            lo = lo.min(local);
+            hi = hi.max(local);
+        }
+
+        let c = (lo + hi) * 0.5;
+        Obb {
+            center: axes[0] * c.x + axes[1] * c.y + axes[2] * c.z,
+            axes,
+            extent: (hi - lo) * 0.5,
+        }
+    }
+
+    // Point in the box's frame, relative to its center.
+    pub fn to_local(&self, p: Vec3) -> Vec3 {
+        let d = p - self.center;
+        Vec3::new(d.dot(self.axes[0]), d.dot(self.axes[1]), d.dot(self.axes[2]))
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let mut res = [Vec3::ZERO; 8];
+        for (i, c) in res.iter_mut().enumerate() {
+            let sx = if i & 1 != 0 { 1.0 } else { -1.0 };
+            let sy = if i & 2 != 0 { 1.0 } else { -1.0 };
+            let sz = if i & 4 != 0 { 1.0 } else { -1.0 };
+            *c = self.center + self.axes[0] * (sx * self.extent.x) + self.axes[1] * (sy * self.extent.y) +
+                 self.axes[2] * (sz * self.extent.z);
+        }
+        res
+    }
+
+    pub fn to_aabb(&self) -> Aabb {
+        let e = Vec3::new(
+            self.axes[0].x.abs() * self.extent.x + self.axes[1].x.abs() * self.extent.y + self.axes[2].x.abs() * self.extent.z,
+            self.axes[0].y.abs() * self.extent.x + self.axes[1].y.abs() * self.extent.y + self.axes[2].y.abs() * self.extent.z,
+            self.axes[0].z.abs() * self.extent.x + self.axes[1].z.abs() * self.extent.y + self.axes[2].z.abs() * self.extent.z);
+        Aabb::from_center_extent(self.center, e)
+    }
+
+    pub fn closest_point(&self, p: Vec3) -> Vec3 {
+        let local = self.to_local(p);
+        let clamped = local.max(-self.extent).min(self.extent);
+        self.center + self.axes[0] * clamped.x + self.axes[1] * clamped.y + self.axes[2] * clamped.z
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        let local = self.to_local(p).abs();
+        local.x <= self.extent.x && local.y <= self.extent.y && local.z <= self.extent.z
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        (self.closest_point(sphere.center) - sphere.center).length_sq() <= sphere.radius * sphere.radius
+    }
+
+    // Separating axis test over the 15 candidate axes (Gottschalk et al.).
+    pub fn intersects(&self, other: &Obb) -> bool {
+        // Parallel edges give near-zero cross products; the epsilon keeps those
+        // axes from reporting a false separation.
+        const EPSILON: f32 = 1.0e-6;
+
+        let a = [self.extent.x, self.extent.y, self.extent.z];
+        let b = [other.extent.x, other.extent.y, other.extent.z];
+
+        // 'other' expressed in this box's frame.
+        let mut r = [[0.0f32; 3]; 3];
+        let mut abs_r = [[0.0f32; 3]; 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                r[i][j] = self.axes[i].dot(other.axes[j]);
+                abs_r[i][j] = r[i][j].abs() + EPSILON;
+            }
+        }
+
+        let d = other.center - self.center;
+        let t = [d.dot(self.axes[0]), d.dot(self.axes[1]), d.dot(self.axes[2])];
+
+        // This box's axes.
+        for i in 0..3 {
+            let rb = b[0] * abs_r[i][0] + b[1] * abs_r[i][1] + b[2] * abs_r[i][2];
+            if t[i].abs() > a[i] + rb {
+                return false;
+            }
+        }
+
+        // The other box's axes.
+        for j in 0..3 {
+            let ra = a[0] * abs_r[0][j] + a[1] * abs_r[1][j] + a[2] * abs_r[2][j];
+            let tj = t[0] * r[0][j] + t[1] * r[1][j] + t[2] * r[2][j];
+            if tj.abs() > ra + b[j] {
+                return false;
+            }
+        }
+
+        // Cross products of one axis from each box.
+        for i in 0..3 {
+            let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+            for j in 0..3 {
+                let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+                let ra = a[i1] * abs_r[i2][j] + a[i2] * abs_r[i1][j];
+                let rb = b[j1] * abs_r[i][j2] + b[j2] * abs_r[i][j1];
+                let tl = t[i2] * r[i1][j] - t[i1] * r[i2][j];
+                if tl.abs() > ra + rb {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    pub fn intersects_aabb(&self, b: &Aabb) -> bool {
+        self.intersects(&Obb::from_aabb(b, &Mat4::IDENTITY))
+    }
+
+    // Parameter range the ray spends inside the box, clipped to [t_min, t_max].
+    pub fn intersect_ray(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        let local = Ray::new(self.to_local(ray.origin),
+                             Vec3::new(ray.dir.dot(self.axes[0]), ray.dir.dot(self.axes[1]), ray.dir.dot(self.axes[2])));
+        Aabb::from_center_extent(Vec3::ZERO, self.extent).intersect_ray(&local, t_min, t_max)
+    }
+}
+
+// Eigenvectors of a symmetric 3x3 matrix by cyclic Jacobi rotations, largest
+// eigenvalue first.  The result is orthonormal and right-handed.
+fn symmetric_eigenvectors(m: [[f32; 3]; 3]) -> [Vec3; 3] {
+    let mut a = [[0.0f64; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            a[i][j] = m[i][j] as f64;
+        }
+    }
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0f64]];
+
+    for _sweep in 0..32 {
+        let off = a[0][1] * a[0][1] + a[0][2] * a[0][2] + a[1][2] * a[1][2];
+        if off < 1.0e-18 {
+            break;
+        }
+
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if a[p][q].abs() < 1.0e-18 {
+                continue;
+            }
+
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+
+            // a = J^T a J, v = v J.
+            for row in a.iter_mut().chain(v.iter_mut()) {
+                let (xp, xq) = (row[p], row[q]);
+                row[p] = c * xp - s * xq;
+                row[q] = s * xp + c * xq;
+            }
+            let (rp, rq) = (a[p], a[q]);
+            a[p] = std::array::from_fn(|k| c * rp[k] - s * rq[k]);
+            a[q] = std::array::from_fn(|k| s * rp[k] + c * rq[k]);
+        }
+    }
+
+    let mut order = [0, 1, 2];
+    order.sort_by(|x, y| a[*y][*y].partial_cmp(&a[*x][*x]).unwrap());
+
+    let column = |j: usize| Vec3::new(v[0][j] as f32, v[1][j] as f32, v[2][j] as f32).normalize();
+    let x = column(order[0]);
+    let y = column(order[1]);
+    [x, y, x.cross(y)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f32 = 1.0e-4;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() <= EPS, "{} != {}", a, b);
+    }
+
+    fn assert_near_vec(a: Vec3, b: Vec3) {
+        assert!((a - b).length() <= EPS, "{:?} != {:?}", a, b);
+    }
+
+    fn assert_range(r: Option<(f32, f32)>, t0: f32, t1: f32) {
+        let (a, b) = r.unwrap_or_else(|| panic!("expected ({}, {}), got a miss", t0, t1));
+        assert_near(a, t0);
+        assert_near(b, t1);
+    }
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0))
+    }
+
+    #[test]
+    fn aabb_empty() {
+        assert!(Aabb::EMPTY.is_empty());
+        assert_eq!(Aabb::default(), Aabb::EMPTY);
+        assert_eq!(Aabb::from_points::<Vec3>(&[]), Aabb::EMPTY);
+        assert_eq!(Aabb::EMPTY.surface_area(), 0.0);
+        assert_eq!(Aabb::EMPTY.transform(&Mat4::translation(1.0, 2.0, 3.0)), Aabb::EMPTY);
+
+        // Growing the empty box by a point gives that point, and merging it
+        // either way round leaves the other box unchanged.
+        let mut b = Aabb::EMPTY;
+        b.grow(Vec3::new(1.0, 2.0, 3.0));
+        assert!(!b.is_empty());
+        assert_eq!(b, Aabb::new(Vec3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 2.0, 3.0)));
+
+        let mut b = Aabb::EMPTY;
+        b.merge(&unit_box());
+        assert_eq!(b, unit_box());
+        let mut b = unit_box();
+        b.merge(&Aabb::EMPTY);
+        assert_eq!(b, unit_box());
+        assert_eq!(Aabb::EMPTY.union(&unit_box()), unit_box());
+    }
+
+    #[test]
+    fn aabb_grow_merge_contains() {
+        let mut b = unit_box();
+        b.grow(Vec3::new(3.0, 0.0, -2.0));
+        assert_eq!(b, Aabb::new(Vec3::new(-1.0, -1.0, -2.0), Vec3::new(3.0, 1.0, 1.0)));
+        assert_near_vec(b.center(), Vec3::new(1.0, 0.0, -0.5));
+        assert_near_vec(b.extent(), Vec3::new(2.0, 1.0, 1.5));
+        assert_near(b.surface_area(), 2.0 * (4.0 * 2.0 + 2.0 * 3.0 + 3.0 * 4.0));
+
+        let other = Aabb::new(Vec3::new(2.0, 2.0, 2.0), Vec3::new(4.0, 5.0, 6.0));
+        b.merge(&other);
+        assert_eq!(b, Aabb::new(Vec3::new(-1.0, -1.0, -2.0), Vec3::new(4.0, 5.0, 6.0)));
+        assert_eq!(unit_box().union(&other), Aabb::new(Vec3::splat(-1.0), Vec3::new(4.0, 5.0, 6.0)));
+
+        assert!(b.contains(&other));
+        assert!(b.contains(&unit_box()));
+        assert!(b.contains(&b));
+        assert!(!other.contains(&b));
+        assert!(unit_box().contains(&Aabb::EMPTY));
+        assert!(!unit_box().contains(&Aabb::new(Vec3::ZERO, Vec3::new(0.5, 0.5, 1.5))));
+
+        assert!(unit_box().contains_point(Vec3::new(1.0, -1.0, 0.0)));
+        assert!(!unit_box().contains_point(Vec3::new(1.0, -1.0, 1.01)));
+
+        for (i, c) in unit_box().corners().iter().enumerate() {
+            assert_eq!(c.x, if i & 1 != 0 { 1.0 } else { -1.0 });
+            assert_eq!(c.y, if i & 2 != 0 { 1.0 } else { -1.0 });
+            assert_eq!(c.z, if i & 4 != 0 { 1.0 } else { -1.0 });
+        }
+    }
+
+    #[test]
+    fn aabb_intersects() {
+        let a = unit_box();
+        assert!(a.intersects(&a));
+        assert!(a.intersects(&Aabb::new(Vec3::ZERO, Vec3::splat(5.0))));
+        // Touching faces count; a gap on any single axis does not.
+        assert!(a.intersects(&Aabb::new(Vec3::new(1.0, -1.0, -1.0), Vec3::new(2.0, 1.0, 1.0))));
+        assert!(!a.intersects(&Aabb::new(Vec3::new(1.01, -1.0, -1.0), Vec3::new(2.0, 1.0, 1.0))));
+        assert!(!a.intersects(&Aabb::new(Vec3::new(-1.0, -1.0, -3.0), Vec3::new(1.0, 1.0, -1.5))));
+        assert!(!a.intersects(&Aabb::EMPTY));
+    }
+
+    #[test]
+    fn aabb_transform() {
+        let b = Aabb::new(Vec3::ZERO, Vec3::new(2.0, 1.0, 1.0));
+        let moved = b.transform(&Mat4::translation(1.0, 2.0, 3.0));
+        assert_near_vec(moved.min, Vec3::new(1.0, 2.0, 3.0));
+        assert_near_vec(moved.max, Vec3::new(3.0, 3.0, 4.0));
+
+        // A quarter turn about y swaps the x and z extents.
+        let turned = b.transform(&Mat4::rotation_y(std::f32::consts::FRAC_PI_2));
+        assert_near_vec(turned.extent(), Vec3::new(0.5, 0.5, 1.0));
+    }
+
+    #[test]
+    fn ray_box() {
+        let b = unit_box();
+        let far = f32::MAX;
+
+        // Through the middle, and with a longer direction the distances shrink.
+        assert_range(b.intersect_ray(&Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X), 0.0, far), 4.0, 6.0);
+        assert_range(b.intersect_ray(&Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X * 2.0), 0.0, far), 2.0, 3.0);
+
+        // Diagonally, entering through one face and leaving through another.
+        let ray = Ray::new(Vec3::new(-3.0, -2.0, 0.5), Vec3::new(1.0, 1.0, 0.0));
+        assert_range(b.intersect_ray(&ray, 0.0, far), 2.0, 3.0);
+
+        // Starting inside, the range begins at t_min; with no lower limit it
+        // reaches back to where the line entered.
+        let ray = Ray::new(Vec3::new(0.5, 0.0, 0.0), Vec3::X);
+        assert_range(b.intersect_ray(&ray, 0.0, far), 0.0, 0.5);
+        assert_range(b.intersect_ray(&ray, -far, far), -1.5, 0.5);
+
+        // The far end is clipped to t_max, and a box beyond t_max is missed.
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X);
+        assert_range(b.intersect_ray(&ray, 0.0, 5.0), 4.0, 5.0);
+        assert_eq!(b.intersect_ray(&ray, 0.0, 3.0), None);
+
+        // Behind the origin.
+        assert_eq!(b.intersect_ray(&Ray::new(Vec3::new(5.0, 0.0, 0.0), Vec3::X), 0.0, far), None);
+
+        // Parallel to a slab: inside it, outside it and exactly on its plane.
+        assert_range(b.intersect_ray(&Ray::new(Vec3::new(-5.0, 0.5, 0.0), Vec3::X), 0.0, far), 4.0, 6.0);
+        assert_eq!(b.intersect_ray(&Ray::new(Vec3::new(-5.0, 2.0, 0.0), Vec3::X), 0.0, far), None);
+        assert_range(b.intersect_ray(&Ray::new(Vec3::new(-5.0, 1.0, 0.0), Vec3::X), 0.0, far), 4.0, 6.0);
+
+        // Passing over a corner.
+        let ray = Ray::new(Vec3::new(-5.0, 3.0, 0.0), Vec3::new(1.0, -0.3, 0.0));
+        assert_eq!(b.intersect_ray(&ray, 0.0, far), None);
+
+        let ray = Ray::new(Vec3::new(-5.0, 0.25, 0.0), Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(b.intersect_ray_inv(ray.origin, ray.inv_dir(), 0.0, far), b.intersect_ray(&ray, 0.0, far));
+    }
+
+    #[test]
+    fn ray_sphere() {
+        let s = BoundingSphere::new(Vec3::new(1.0, 0.0, 0.0), 2.0);
+        let far = f32::MAX;
+
+        assert_range(s.intersect_ray(&Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X), 0.0, far), 4.0, 8.0);
+        assert_range(s.intersect_ray(&Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X * 4.0), 0.0, far), 1.0, 2.0);
+
+        // Off center: the chord at distance 1 from the center is 2 * sqrt(3) long.
+        let ray = Ray::new(Vec3::new(-5.0, 1.0, 0.0), Vec3::X);
+        assert_range(s.intersect_ray(&ray, 0.0, far), 6.0 - 3.0f32.sqrt(), 6.0 + 3.0f32.sqrt());
+
+        // Starting inside.
+        let ray = Ray::new(Vec3::new(1.0, 0.0, 0.0), Vec3::Y);
+        assert_range(s.intersect_ray(&ray, 0.0, far), 0.0, 2.0);
+        assert_range(s.intersect_ray(&ray, -far, far), -2.0, 2.0);
+
+        // Grazing, missing, behind and clipped.
+        assert_range(s.intersect_ray(&Ray::new(Vec3::new(-5.0, 2.0, 0.0), Vec3::X), 0.0, far), 6.0, 6.0);
+        assert_eq!(s.intersect_ray(&Ray::new(Vec3::new(-5.0, 2.01, 0.0), Vec3::X), 0.0, far), None);
+        assert_eq!(s.intersect_ray(&Ray::new(Vec3::new(5.0, 0.0, 0.0), Vec3::X), 0.0, far), None);
+        assert_eq!(s.intersect_ray(&Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X), 0.0, 3.5), None);
+        assert_range(s.intersect_ray(&Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X), 5.0, 6.0), 5.0, 6.0);
+
+        // A zero direction hits only from inside, over the whole range.
+        assert_range(s.intersect_ray(&Ray::new(Vec3::ZERO, Vec3::ZERO), 0.0, 1.0), 0.0, 1.0);
+        assert_eq!(s.intersect_ray(&Ray::new(Vec3::new(4.0, 0.0, 0.0), Vec3::ZERO), 0.0, 1.0), None);
+    }
+
+    #[test]
+    fn ray_obb() {
+        // The unit box turned 45 degrees about y: its corner is at distance sqrt(2)
+        // along x.
+        let m = Mat4::rotation_y(std::f32::consts::FRAC_PI_4);
+        let b = Obb::from_aabb(&unit_box(), &m);
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X);
+        assert_range(b.intersect_ray(&ray, 0.0, f32::MAX), 5.0 - 2.0f32.sqrt(), 5.0 + 2.0f32.sqrt());
+        assert_eq!(b.intersect_ray(&Ray::new(Vec3::new(-5.0, 0.0, 1.5), Vec3::X), 0.0, f32::MAX), None);
+    }
+
+    #[test]
+    fn sphere_box() {
+        let b = unit_box();
+
+        // Beside a face the gap is measured straight across.
+        assert!(!b.intersects_sphere(&BoundingSphere::new(Vec3::new(2.5, 0.0, 0.0), 1.4)));
+        assert!(b.intersects_sphere(&BoundingSphere::new(Vec3::new(2.5, 0.0, 0.0), 1.5)));
+
+        // Off an edge it is the distance to the edge, sqrt(2), even though the
+        // sphere's own box overlaps.
+        let s = BoundingSphere::new(Vec3::new(2.0, 2.0, 0.0), 1.3);
+        assert!(s.to_aabb().intersects(&b));
+        assert!(!b.intersects_sphere(&s));
+        assert!(!s.intersects_aabb(&b));
+        let s = BoundingSphere::new(Vec3::new(2.0, 2.0, 0.0), 1.5);
+        assert!(b.intersects_sphere(&s));
+        assert!(s.intersects_aabb(&b));
+
+        // A sphere inside the box.
+        assert!(b.intersects_sphere(&BoundingSphere::new(Vec3::new(0.5, 0.0, 0.0), 0.1)));
+        assert_eq!(b.distance_sq(Vec3::new(0.5, 0.0, 0.0)), 0.0);
+        assert_near(b.distance_sq(Vec3::new(2.0, 3.0, 0.0)), 5.0);
+
+        // The same tests against an oriented box turned 45 degrees about z, whose
+        // corner now points along x.
+        let o = Obb::from_aabb(&b, &Mat4::rotation_z(std::f32::consts::FRAC_PI_4));
+        assert!(!o.intersects_sphere(&BoundingSphere::new(Vec3::new(2.5, 0.0, 0.0), 1.0)));
+        assert!(o.intersects_sphere(&BoundingSphere::new(Vec3::new(2.5, 0.0, 0.0), 1.1)));
+    }
+
+    #[test]
+    fn sphere_sphere() {
+        let a = BoundingSphere::new(Vec3::ZERO, 1.0);
+        assert!(a.intersects(&BoundingSphere::new(Vec3::new(3.0, 0.0, 0.0), 2.0)));
+        assert!(!a.intersects(&BoundingSphere::new(Vec3::new(3.0, 0.0, 0.0), 1.9)));
+        assert!(a.contains(&BoundingSphere::new(Vec3::new(0.5, 0.0, 0.0), 0.5)));
+        assert!(!a.contains(&BoundingSphere::new(Vec3::new(0.5, 0.0, 0.0), 0.6)));
+
+        let u = a.union(&BoundingSphere::new(Vec3::new(4.0, 0.0, 0.0), 1.0));
+        assert_near_vec(u.center, Vec3::new(2.0, 0.0, 0.0));
+        assert_near(u.radius, 3.0);
+        assert_eq!(a.union(&BoundingSphere::new(Vec3::new(0.2, 0.0, 0.0), 0.5)), a);
+
+        let t = a.transform(&(Mat4::scaling(1.0, 3.0, 2.0) * Mat4::translation(1.0, 0.0, 0.0)));
+        assert_near_vec(t.center, Vec3::new(1.0, 0.0, 0.0));
+        assert_near(t.radius, 3.0);
+    }
+
+    #[test]
+    fn obb_obb() {
+        let a = Obb::from_aabb(&unit_box(), &Mat4::IDENTITY);
+        assert!(a.intersects(&a));
+
+        // Separated along one of a's faces, and touching it.
+        let b = Obb::from_aabb(&unit_box(), &Mat4::translation(2.5, 0.0, 0.0));
+        assert!(!a.intersects(&b));
+        assert!(!b.intersects(&a));
+        let b = Obb::from_aabb(&unit_box(), &Mat4::translation(2.0, 0.0, 0.0));
+        assert!(a.intersects(&b));
+
+        // Turned 45 degrees about z, b's corner reaches sqrt(2) from its center:
+        // close enough at 2.3, not at 2.5.  Only b's face axes separate the second.
+        let turn = Mat4::rotation_z(std::f32::consts::FRAC_PI_4);
+        assert!(a.intersects(&Obb::from_aabb(&unit_box(), &(turn * Mat4::translation(2.3, 0.0, 0.0)))));
+        let b = Obb::from_aabb(&unit_box(), &(turn * Mat4::translation(2.5, 0.0, 0.0)));
+        assert!(!a.intersects(&b));
+        assert!(!b.intersects_aabb(&unit_box()));
+    }
+
+    #[test]
+    fn obb_obb_edge_axis() {
+        // Two thin rods, one along x and one along (0, 1, 1) turned about its own
+        // length so that none of its faces line up with the other's.  They pass
+        // each other along n = x cross (0, 1, 1), where each rod is
+        // 0.1 * sqrt(2) thick in radius, and every face axis sees them overlap, so
+        // only the edge-edge axis can tell them apart.
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+        let n = Vec3::new(0.0, -s, s);
+        let a = Obb::new(Vec3::ZERO, [Vec3::X, Vec3::Y, Vec3::Z], Vec3::new(5.0, 0.1, 0.1));
+        let rod = |d: f32| {
+            let axes = [Vec3::new(0.0, s, s), (Vec3::X + n) * s, (n - Vec3::X) * s];
+            Obb::new(n * d, axes, Vec3::new(5.0, 0.1, 0.1))
+        };
+
+        let radius = 0.2 * 2.0f32.sqrt();
+        let b = rod(radius + 0.05);
+        assert!(a.to_aabb().intersects(&b.to_aabb()));
+        let interval = |o: &Obb, axis: Vec3| {
+            let d = o.corners().map(|c| c.dot(axis));
+            (d.iter().copied().fold(f32::MAX, f32::min), d.iter().copied().fold(f32::MIN, f32::max))
+        };
+        for axis in a.axes.iter().chain(b.axes.iter()) {
+            let (lo_a, hi_a) = interval(&a, *axis);
+            let (lo_b, hi_b) = interval(&b, *axis);
+            assert!(lo_a <= hi_b && lo_b <= hi_a, "face axis {:?} separates", axis);
+        }
+        assert!(!a.intersects(&b));
+        assert!(!b.intersects(&a));
+
+        assert!(a.intersects(&rod(radius - 0.05)));
+        assert!(rod(-radius + 0.05).intersects(&a));
+        assert!(!rod(-radius - 0.05).intersects(&a));
+    }
+
+    #[test]
+    fn from_points() {
+        let points = [Vec3::new(1.0, 2.0, 3.0), Vec3::new(-1.0, 5.0, 0.0), Vec3::new(0.0, 0.0, 4.0)];
+        assert_eq!(Aabb::from_points(&points), Aabb::new(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 5.0, 4.0)));
+
+        // Centered on the average, reaching the farthest point.
+        let c = Vec3::new(1.0, 1.0, 1.0);
+        let points = [c + Vec3::X * 2.0, c - Vec3::X * 2.0, c + Vec3::Y, c - Vec3::Y, c + Vec3::Z, c - Vec3::Z];
+        let s = BoundingSphere::from_points(&points);
+        assert_near_vec(s.center, c);
+        assert_near(s.radius, 2.0);
+        assert_eq!(BoundingSphere::from_points::<Vec3>(&[]), BoundingSphere::default());
+
+        let s = BoundingSphere::from_aabb(&unit_box());
+        assert_near(s.radius, 3.0f32.sqrt());
+        assert!(unit_box().corners().iter().all(|p| s.contains_point(*p * 0.999)));
+
+        // The corners of a turned and moved box give back that box, largest axis
+        // first.  The axes may come back flipped.
+        let extent = Vec3::new(4.0, 1.0, 0.5);
+        let m = Mat4::rotation_y(0.5) * Mat4::translation(3.0, -2.0, 1.0);
+        let b = Obb::from_aabb(&Aabb::from_center_extent(Vec3::ZERO, extent), &m);
+        let fit = Obb::from_points(&b.corners());
+        assert_near_vec(fit.center, Vec3::new(3.0, -2.0, 1.0));
+        assert_near_vec(fit.extent, extent);
+        for i in 0..3 {
+            assert_near(fit.axes[i].dot(b.axes[i]).abs(), 1.0);
+        }
+        assert_near_vec(fit.axes[0].cross(fit.axes[1]), fit.axes[2]);
+        assert_eq!(Obb::from_points::<Vec3>(&[]), Obb::default());
+    }
+}
@@ -0,0 +1,337 @@
+// Bounding volume hierarchy over the faces of a mesh, for the ray queries
+// D3DXIntersect does by brute force.
+//
+// The tree only stores face numbers; queries and refits take the mesh it was
+// built from, so the vertices can be animated and the tree refit without a
+// rebuild as long as the indices stay the same.
+
+use crate::bounds::*;
+use crate::math::*;
+use crate::mesh::*;
+use crate::ray::*;
+
+#[derive(Copy, Clone, Debug)]
+pub struct BvhOptions {
+    // Nodes with this many faces or fewer are never split.
+    pub max_leaf_faces: usize,
+    // Buckets the centroids are sorted into when evaluating the surface area
+    // heuristic.
+    pub num_bins: usize,
+    // Cost of visiting a node relative to a ray-triangle test.
+    pub traversal_cost: f32,
+}
+
+impl Default for BvhOptions {
+    fn default() -> Self {
+        BvhOptions {
+            max_leaf_faces: 4,
+            num_bins: 16,
+            traversal_cost: 1.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    pub face: u32,
+    // Barycentrics, the hit point is p0 + u * (p1 - p0) + v * (p2 - p0).
+    pub u: f32,
+    pub v: f32,
+    // Ray parameter of the hit, see Ray::dir.
+    pub distance: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct BvhNode {
+    bounds: Aabb,
+    // Inner nodes: index of the left child, the right one follows it.
+    // Leaves: first entry in Bvh::faces.
+    first: u32,
+    // Number of faces for leaves, 0 for inner nodes.
+    count: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Bvh {
+    // Root first; children always come after their parent.
+    nodes: Vec<BvhNode>,
+    faces: Vec<u32>,
+}
+
+impl Bvh {
+    pub fn build<V: Vertex>(mesh: &Mesh<V>) -> Bvh {
+        Bvh::build_with_options(mesh, &BvhOptions::default())
+    }
+
+    // Top-down build with binned SAH splits.
+    pub fn build_with_options<V: Vertex>(mesh: &Mesh<V>, options: &BvhOptions) -> Bvh {
+        let num_faces = mesh.num_faces();
+        if num_faces == 0 {
+            return Bvh::default();
+        }
+
+        let face_bounds: Vec<Aabb> = (0..num_faces).map(|f| face_aabb(mesh, f)).collect();
+        let centroids: Vec<Vec3> = face_bounds.iter().map(|b| b.center()).collect();
+
+        let mut faces: Vec<u32> = (0..num_faces as u32).collect();
+        let mut nodes = vec![BvhNode { bounds: Aabb::EMPTY, first: 0, count: 0 }];
+
+        let mut stack = vec![(0usize, 0usize, num_faces)];
+        while let Some((node, start, end)) = stack.pop() {
+            let mut bounds = Aabb::EMPTY;
+            let mut centroid_bounds = Aabb::EMPTY;
+            for f in &faces[start..end] {
+                bounds.merge(&face_bounds[*f as usize]);
+                centroid_bounds.grow(centroids[*f as usize]);
+            }
+            nodes[node].bounds = bounds;
+
+            let split = if end - start > options.max_leaf_faces {
+                find_split(&mut faces[start..end], &face_bounds, &centroids, &bounds, &centroid_bounds, options)
+            } else {
+                None
+            };
+
+            match split {
+                Some(mid) => {
+                    let left = nodes.len();
+                    nodes.push(BvhNode { bounds: Aabb::EMPTY, first: 0, count: 0 });
+                    nodes.push(BvhNode { bounds: Aabb::EMPTY, first: 0, count: 0 });
+                    nodes[node].first = left as u32;
+                    nodes[node].count = 0;
+
+                    stack.push((left + 1, start + mid, end));
+                    stack.push((left, start, start + mid));
+                }
+                None => {
+                    nodes[node].first = start as u32;
+                    nodes[node].count = (end - start) as u32;
+                }
+            }
+        }
+
+        Bvh { nodes, faces }
+    }
+
+    // Recomputes every node's box after the vertices moved.  The tree keeps its
+    // structure, so queries stay correct but get slower as the motion departs
+    // from the pose the tree was built for.
+    pub fn refit<V: Vertex>(&mut self, mesh: &Mesh<V>) {
+        for i in (0..self.nodes.len()).rev() {
+            let node = self.nodes[i];
+            let first = node.first as usize;
+
+            let bounds = if node.count > 0 {
+                let mut bounds = Aabb::EMPTY;
+                for f in &self.faces[first..first + node.count as usize] {
+                    bounds.merge(&face_aabb(mesh, *f as usize));
+                }
+                bounds
+            } else {
+                self.nodes[first].bounds.union(&self.nodes[first + 1].bounds)
+            };
+
+            self.nodes[i].bounds = bounds;
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::EMPTY, |n| n.bounds)
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // Nearest hit in front of the ray origin.  Both sides of a face are hit, as
+    // with D3DXIntersect.
+    pub fn intersect<V: Vertex>(&self, mesh: &Mesh<V>, ray: &Ray) -> Option<RayHit> {
+        let mut hits = Vec::with_capacity(1);
+        self.traverse(mesh, ray, 0.0, f32::MAX, true, &mut hits);
+        hits.pop()
+    }
+
+    // Every hit in front of the ray origin, nearest first.
+    pub fn intersect_all<V: Vertex>(&self, mesh: &Mesh<V>, ray: &Ray) -> Vec<RayHit> {
+        let mut hits = Vec::new();
+        self.traverse(mesh, ray, 0.0, f32::MAX, false, &mut hits);
+        sort_hits(&mut hits);
+        hits
+    }
+
+    // Every hit between p0 and p1, nearest to p0 first.  Distances are measured
+    // from p0 in mesh units.
+    pub fn intersect_segment<V: Vertex>(&self, mesh: &Mesh<V>, p0: Vec3, p1: Vec3) -> Vec<RayHit> {
+        let length = (p1 - p0).length();
+        if length == 0.0 {
+            return Vec::new();
+        }
+
+        let ray = Ray::new(p0, (p1 - p0) / length);
+        let mut hits = Vec::new();
+        self.traverse(mesh, &ray, 0.0, length, false, &mut hits);
+        sort_hits(&mut hits);
+        hits
+    }
+
+    // Hits with distance in [t_min, t_max].  When 'closest' is set only the
+    // nearest is kept and children are visited near to far so the range shrinks
+    // as early as possible.
+    fn traverse<V: Vertex>(&self, mesh: &Mesh<V>, ray: &Ray, t_min: f32, t_max: f32, closest: bool,
+                           hits: &mut Vec<RayHit>) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let inv_dir = ray.inv_dir();
+        let mut t_max = t_max;
+
+        let root = match self.nodes[0].bounds.intersect_ray_inv(ray.origin, inv_dir, t_min, t_max) {
+            Some((t, _)) => t,
+            None => return,
+        };
+
+        let mut stack: Vec<(usize, f32)> = Vec::with_capacity(64);
+        stack.push((0, root));
+
+        while let Some((i, t_enter)) = stack.pop() {
+            if t_enter > t_max {
+                continue;
+            }
+
+            let node = &self.nodes[i];
+            let first = node.first as usize;
+
+            if node.count > 0 {
+                for f in &self.faces[first..first + node.count as usize] {
+                    let [p0, p1, p2] = mesh.face_positions(*f as usize);
+                    if let Some((t, u, v)) = intersect_triangle(ray, p0, p1, p2) {
+                        if t < t_min || t > t_max {
+                            continue;
+                        }
+
+                        if closest {
+                            t_max = t;
+                            hits.clear();
+                        }
+                        hits.push(RayHit { face: *f, u, v, distance: t });
+                    }
+                }
+                continue;
+            }
+
+            let left = self.nodes[first].bounds.intersect_ray_inv(ray.origin, inv_dir, t_min, t_max);
+            let right = self.nodes[first + 1].bounds.intersect_ray_inv(ray.origin, inv_dir, t_min, t_max);
+
+            match (left, right) {
+                (Some((tl, _)), Some((tr, _))) => {
+                    // Nearer child popped first.
+                    if tl <= tr {
+                        stack.push((first + 1, tr));
+                        stack.push((first, tl));
+                    } else {
+                        stack.push((first, tl));
+                        stack.push((first + 1, tr));
+                    }
+                }
+                (Some((tl, _)), None) => stack.push((first, tl)),
+                (None, Some((tr, _))) => stack.push((first + 1, tr)),
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+fn face_aabb<V: Vertex>(mesh: &Mesh<V>, f: usize) -> Aabb {
+    let [p0, p1, p2] = mesh.face_positions(f);
+    Aabb::new(p0.min(p1).min(p2), p0.max(p1).max(p2))
+}
+
+fn sort_hits(hits: &mut [RayHit]) {
+    hits.sort_by(|a, b| a.distance.total_cmp(&b.distance).then(a.face.cmp(&b.face)));
+}
+
+// Picks the cheapest binned split of 'faces' and partitions it in place.
+// Returns the size of the left part, or None when a leaf is cheaper.
+fn find_split(faces: &mut [u32], face_bounds: &[Aabb], centroids: &[Vec3], bounds: &Aabb,
+              centroid_bounds: &Aabb, options: &BvhOptions) -> Option<usize> {
+    let num_bins = options.num_bins.max(2);
+
+    let bin_of = |c: Vec3, axis: usize| -> usize {
+        let lo = centroid_bounds.min[axis];
+        let hi = centroid_bounds.max[axis];
+        let b = ((c[axis] - lo) / (hi - lo) * num_bins as f32) as usize;
+        b.min(num_bins - 1)
+    };
+
+    let mut best_cost = f32::MAX;
+    let mut best_axis = 0;
+    let mut best_bin = 0;
+
+    for axis in 0..3 {
+        if centroid_bounds.max[axis] - centroid_bounds.min[axis] <= 0.0 {
+            continue;
+        }
+
+        let mut bin_bounds = vec![Aabb::EMPTY; num_bins];
+        let mut bin_counts = vec![0usize; num_bins];
+        for f in faces.iter() {
+            let b = bin_of(centroids[*f as usize], axis);
+            bin_bounds[b].merge(&face_bounds[*f as usize]);
+            bin_counts[b] += 1;
+        }
+
+        // Area and count of everything right of each bin boundary.
+        let mut right_area = vec![0.0; num_bins];
+        let mut right_count = vec![0usize; num_bins];
+        let mut acc = Aabb::EMPTY;
+        let mut count = 0;
+        for b in (1..num_bins).rev() {
+            acc.merge(&bin_bounds[b]);
+            count += bin_counts[b];
+            right_area[b] = acc.surface_area();
+            right_count[b] = count;
+        }
+
+        let mut acc = Aabb::EMPTY;
+        let mut count = 0;
+        for b in 0..num_bins - 1 {
+            acc.merge(&bin_bounds[b]);
+            count += bin_counts[b];
+            if count == 0 || right_count[b + 1] == 0 {
+                continue;
+            }
+
+            let cost = acc.surface_area() * count as f32 + right_area[b + 1] * right_count[b + 1] as f32;
+            if cost < best_cost {
+                best_cost = cost;
+                best_axis = axis;
+                best_bin = b;
+            }
+        }
+    }
+
+    if best_cost == f32::MAX {
+        // Every centroid in the same place; nothing to split on.
+        return None;
+    }
+
+    let area = bounds.surface_area();
+    if area > 0.0 {
+        let split_cost = options.traversal_cost + best_cost / area;
+        if split_cost >= faces.len() as f32 {
+            return None;
+        }
+    }
+
+    // Partition in place, left part first.
+    let mut mid = 0;
+    for i in 0..faces.len() {
+        if bin_of(centroids[faces[i] as usize], best_axis) <= best_bin {
+            faces.swap(i, mid);
+            mid += 1;
+        }
+    }
+
+    Some(mid)
+}
@@ -0,0 +1,182 @@
+// View and projection state of a camera, the part of the demos' Camera that does
+// not depend on how it is moved.  Controllers (the controllers crate) move it.
+//
+// The orientation is kept as the world space right, up and look axes like the
+// demos do, so a camera can be turned about any axis without gimbal lock.
+
+use crate::bounds::*;
+use crate::frustum::*;
+use crate::math::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    // Relative to world space.
+    pos: Vec3,
+    right: Vec3,
+    up: Vec3,
+    look: Vec3,
+
+    fov_y: f32,
+    aspect: f32,
+    near_z: f32,
+    far_z: f32,
+
+    view: Mat4,
+    proj: Mat4,
+    view_proj: Mat4,
+
+    // World space.
+    frustum: Frustum,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::new()
+    }
+}
+
+impl Camera {
+    // At the origin looking down +z, with a 45 degree field of view.
+    pub fn new() -> Camera {
+        let mut camera = Camera {
+            pos: Vec3::ZERO,
+            right: Vec3::X,
+            up: Vec3::Y,
+            look: Vec3::Z,
+            fov_y: std::f32::consts::FRAC_PI_4,
+            aspect: 1.0,
+            near_z: 1.0,
+            far_z: 1000.0,
+            view: Mat4::IDENTITY,
+            proj: Mat4::IDENTITY,
+            view_proj: Mat4::IDENTITY,
+            frustum: Frustum::from_planes([Vec4::new(0.0, 0.0, 0.0, 0.0); 6]),
+        };
+        camera.proj = Mat4::perspective_fov_lh(camera.fov_y, camera.aspect, camera.near_z, camera.far_z);
+        camera.rebuild();
+        camera
+    }
+
+    pub fn get_pos(&self) -> Vec3 { self.pos }
+
+    pub fn get_right(&self) -> Vec3 { self.right }
+
+    pub fn get_up(&self) -> Vec3 { self.up }
+
+    pub fn get_look(&self) -> Vec3 { self.look }
+
+    // Rotation taking the x, y and z axes to right, up and look.
+    pub fn get_orientation(&self) -> Quat { Quat::from_axes(self.right, self.up, self.look) }
+
+    pub fn get_view(&self) -> &Mat4 { &self.view }
+
+    pub fn get_proj(&self) -> &Mat4 { &self.proj }
+
+    pub fn get_view_proj(&self) -> &Mat4 { &self.view_proj }
+
+    pub fn get_fov_y(&self) -> f32 { self.fov_y }
+
+    pub fn get_aspect(&self) -> f32 { self.aspect }
+
+    pub fn get_near_z(&self) -> f32 { self.near_z }
+
+    pub fn get_far_z(&self) -> f32 { self.far_z }
+
+    pub fn set_pos(&mut self, pos: Vec3) {
+        self.pos = pos;
+        self.rebuild();
+    }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        self.pos += offset;
+        self.rebuild();
+    }
+
+    pub fn look_at(&mut self, pos: Vec3, target: Vec3, up: Vec3) {
+        let look = (target - pos).normalize();
+        let right = up.cross(look).normalize();
+
+        self.pos = pos;
+        self.right = right;
+        self.up = look.cross(right);
+        self.look = look;
+        self.rebuild();
+    }
+
+    // Sets the axes from a look direction and an approximate up direction.
+    pub fn set_orientation(&mut self, look: Vec3, up: Vec3) {
+        let pos = self.pos;
+        self.look_at(pos, pos + look, up);
+    }
+
+    // Turns the camera's axes about a world space axis through its position.
+    pub fn rotate(&mut self, axis: Vec3, angle: f32) {
+        if angle == 0.0 {
+            return;
+        }
+
+        let r = Mat4::rotation_axis(axis, angle);
+        self.right = r.transform_normal(self.right);
+        self.up = r.transform_normal(self.up);
+        self.look = r.transform_normal(self.look);
+        self.rebuild();
+    }
+
+    pub fn set_lens(&mut self, fov_y: f32, aspect: f32, near_z: f32, far_z: f32) {
+        self.fov_y = fov_y;
+        self.aspect = aspect;
+        self.near_z = near_z;
+        self.far_z = far_z;
+
+        self.proj = Mat4::perspective_fov_lh(fov_y, aspect, near_z, far_z);
+        self.rebuild();
+    }
+
+    // Orthographic projection of a width x height view volume.  The field of
+    // view reads 0.
+    pub fn set_ortho(&mut self, width: f32, height: f32, near_z: f32, far_z: f32) {
+        self.fov_y = 0.0;
+        self.aspect = width / height;
+        self.near_z = near_z;
+        self.far_z = far_z;
+
+        self.proj = Mat4::ortho_lh(width, height, near_z, far_z);
+        self.rebuild();
+    }
+
+    // Conservative: boxes outside a plane are culled, boxes straddling several
+    // planes near a frustum corner may pass.  See get_frustum for the
+    // Inside/Intersecting tests.
+    pub fn is_visible(&self, bounding_box: &Aabb) -> bool {
+        self.frustum.is_visible(bounding_box)
+    }
+
+    pub fn get_frustum(&self) -> &Frustum {
+        &self.frustum
+    }
+
+    // World space, pointing inwards: near, far, left, right, top, bottom.
+    pub fn get_frustum_planes(&self) -> &[Vec4; 6] {
+        self.frustum.get_planes()
+    }
+
+    // Keeps the axes orthonormal, as repeated rotations let them drift, and
+    // recomputes the matrices and frustum.
+    fn rebuild(&mut self) {
+        self.look = self.look.normalize();
+        self.up = self.look.cross(self.right).normalize();
+        self.right = self.up.cross(self.look);
+
+        let p = self.pos;
+        self.view = Mat4 {
+            m: [
+                [self.right.x,       self.up.x,       self.look.x,       0.0],
+                [self.right.y,       self.up.y,       self.look.y,       0.0],
+                [self.right.z,       self.up.z,       self.look.z,       0.0],
+                [-p.dot(self.right), -p.dot(self.up), -p.dot(self.look), 1.0],
+            ]
+        };
+        self.view_proj = self.view * self.proj;
+        self.frustum = Frustum::from_matrix(&self.view_proj);
+    }
+}
@@ -0,0 +1,360 @@
+// Mesh cleaning and validation, the counterpart of D3DXCleanMesh and
+// D3DXValidMesh for meshes coming from any exporter or parser.
+//
+// Cleaning runs in a fixed order: drop faces with bad indices, weld, drop
+// degenerate and duplicate faces, check edges, split bowties and finally drop
+// the vertices no face uses any more.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::mesh::*;
+use crate::mesh_opt::*;
+
+#[derive(Copy, Clone, Debug)]
+pub struct CleanOptions {
+    // Merge vertices that agree within all three epsilons below.
+    pub weld: bool,
+    pub position_epsilon: f32,
+    // Largest per-component difference of normals and tex-coords.
+    pub normal_epsilon: f32,
+    pub tex_epsilon: f32,
+    // Faces with repeated vertices, or an area at or below this, are removed.
+    pub remove_degenerate: bool,
+    pub min_area: f32,
+    // Faces using the same three vertices in the same winding as an earlier
+    // face are removed.  Back-to-back faces (opposite windings) are kept.
+    pub remove_duplicate_faces: bool,
+    // Give every fan of faces around a bowtie vertex its own copy of it.
+    pub split_bowties: bool,
+}
+
+impl Default for CleanOptions {
+    fn default() -> Self {
+        CleanOptions {
+            weld: true,
+            position_epsilon: 1.0e-6,
+            normal_epsilon: 1.0e-3,
+            tex_epsilon: 1.0e-5,
+            remove_degenerate: true,
+            min_area: 0.0,
+            remove_duplicate_faces: true,
+            split_bowties: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CleanReport {
+    // Faces with an index past the end of the vertex array (always removed).
+    pub invalid_faces: usize,
+    pub welded_vertices: usize,
+    pub degenerate_faces: usize,
+    pub duplicate_faces: usize,
+    // Vertices no face uses, removed at the end.
+    pub unused_vertices: usize,
+    // Edges used by one face.
+    pub border_edges: usize,
+    // Edges used by more than two faces.
+    pub non_manifold_edges: usize,
+    // Edges between two faces that walk them in the same direction, i.e. one of
+    // the faces is flipped.
+    pub inconsistent_edges: usize,
+    // Vertices whose faces form more than one fan.
+    pub bowtie_vertices: usize,
+    // Vertex copies added by splitting bowties.
+    pub split_vertices: usize,
+    // new_faces[i] = old_faces[face_remap[i]].
+    pub face_remap: Vec<u32>,
+    // new_vertices[i] = old_vertices[vertex_remap[i]]; bowtie copies map to the
+    // vertex they copy.
+    pub vertex_remap: Vec<u32>,
+}
+
+impl CleanReport {
+    // Every edge has at most two faces and every vertex a single fan.
+    pub fn is_manifold(&self) -> bool {
+        self.non_manifold_edges == 0 && (self.bowtie_vertices == 0 || self.split_vertices > 0)
+    }
+
+    pub fn is_consistently_wound(&self) -> bool {
+        self.inconsistent_edges == 0
+    }
+
+    // No borders, so the mesh encloses a volume if it is also manifold.
+    pub fn is_closed(&self) -> bool {
+        self.border_edges == 0
+    }
+
+    // Nothing was found that cleaning fixes or that makes the mesh invalid.
+    // Borders are fine.
+    pub fn is_clean(&self) -> bool {
+        self.invalid_faces == 0 && self.welded_vertices == 0 && self.degenerate_faces == 0 &&
+        self.duplicate_faces == 0 && self.unused_vertices == 0 && self.non_manifold_edges == 0 &&
+        self.inconsistent_edges == 0 && self.bowtie_vertices == 0
+    }
+}
+
+impl fmt::Display for CleanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} invalid faces, {} welded vertices, {} degenerate faces, {} duplicate faces, \
+                   {} unused vertices, {} border edges, {} non-manifold edges, {} inconsistent edges, \
+                   {} bowtie vertices ({} copies added)",
+               self.invalid_faces, self.welded_vertices, self.degenerate_faces, self.duplicate_faces,
+               self.unused_vertices, self.border_edges, self.non_manifold_edges, self.inconsistent_edges,
+               self.bowtie_vertices, self.split_vertices)
+    }
+}
+
+// Reports what clean_mesh would find and fix, leaving the mesh alone.
+pub fn validate_mesh<V: Vertex>(mesh: &Mesh<V>, options: &CleanOptions) -> CleanReport {
+    let mut copy = mesh.clone();
+    clean_mesh(&mut copy, options)
+}
+
+pub fn clean_mesh<V: Vertex>(mesh: &mut Mesh<V>, options: &CleanOptions) -> CleanReport {
+    let mut report = CleanReport::default();
+
+    // Original vertex of every current vertex; bowtie copies are appended.
+    let mut vertex_origin: Vec<u32> = (0..mesh.vertices.len() as u32).collect();
+    let mut face_remap: Vec<u32> = (0..mesh.num_faces() as u32).collect();
+
+    //===========================================
+    // Faces with bad indices.
+
+    let num_vertices = mesh.vertices.len() as u32;
+    let valid: Vec<bool> = (0..mesh.num_faces())
+        .map(|f| mesh.face(f).iter().all(|i| *i < num_vertices))
+        .collect();
+    report.invalid_faces = valid.iter().filter(|v| !**v).count();
+    keep_faces(mesh, &mut face_remap, &valid);
+
+    //===========================================
+    // Welding.
+
+    if options.weld {
+        let point_reps = generate_point_reps(&mesh.vertices, options.position_epsilon);
+
+        let mut groups: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut weld_to: Vec<u32> = (0..mesh.vertices.len() as u32).collect();
+
+        for (i, rep) in point_reps.iter().enumerate() {
+            let group = groups.entry(*rep).or_default();
+            let v = &mesh.vertices[i];
+
+            let target = group.iter().find(|k| {
+                let u = &mesh.vertices[**k as usize];
+                close_attributes(u.normal().zip(v.normal()).map(|(a, b)| (a - b).abs().max_elem()),
+                                 options.normal_epsilon) &&
+                close_attributes(u.tex0().zip(v.tex0()).map(|(a, b)| (a.x - b.x).abs().max((a.y - b.y).abs())),
+                                 options.tex_epsilon)
+            });
+
+            match target {
+                Some(k) => {
+                    weld_to[i] = *k;
+                    report.welded_vertices += 1;
+                }
+                None => group.push(i as u32),
+            }
+        }
+
+        for i in &mut mesh.indices {
+            *i = weld_to[*i as usize];
+        }
+    }
+
+    // Everything below compares positions through point representatives, so
+    // vertices split only by normals or tex-coords still count as one point.
+    let mut point_reps = generate_point_reps(&mesh.vertices, options.position_epsilon);
+
+    //===========================================
+    // Degenerate and duplicate faces.
+
+    if options.remove_degenerate {
+        let keep: Vec<bool> = (0..mesh.num_faces())
+            .map(|f| {
+                let [a, b, c] = face_points(mesh, &point_reps, f);
+                a != b && b != c && c != a && mesh.face_normal(f).length() * 0.5 > options.min_area
+            })
+            .collect();
+        report.degenerate_faces = keep.iter().filter(|k| !**k).count();
+        keep_faces(mesh, &mut face_remap, &keep);
+    }
+
+    if options.remove_duplicate_faces {
+        let mut seen: HashSet<[u32; 3]> = HashSet::new();
+        let keep: Vec<bool> = (0..mesh.num_faces())
+            .map(|f| seen.insert(rotate_to_min(face_points(mesh, &point_reps, f))))
+            .collect();
+        report.duplicate_faces = keep.iter().filter(|k| !**k).count();
+        keep_faces(mesh, &mut face_remap, &keep);
+    }
+
+    //===========================================
+    // Edge checks.
+
+    // Undirected edge -> number of faces using it, and how many of them walk it
+    // from the lower to the higher point.
+    let mut edges: HashMap<(u32, u32), (u32, u32)> = HashMap::new();
+    for f in 0..mesh.num_faces() {
+        let p = face_points(mesh, &point_reps, f);
+        for e in 0..3 {
+            let (a, b) = (p[e], p[(e + 1) % 3]);
+            if a == b {
+                continue;
+            }
+            let entry = edges.entry((a.min(b), a.max(b))).or_insert((0, 0));
+            entry.0 += 1;
+            if a < b {
+                entry.1 += 1;
+            }
+        }
+    }
+
+    for (count, forward) in edges.values() {
+        match count {
+            1 => report.border_edges += 1,
+            2 => {
+                if *forward != 1 {
+                    report.inconsistent_edges += 1;
+                }
+            }
+            _ => report.non_manifold_edges += 1,
+        }
+    }
+
+    //===========================================
+    // Bowties.  The faces around a vertex are joined into fans through the
+    // edges they share; more than one fan makes the vertex non-manifold.
+
+    let corners = vertex_corners(mesh);
+
+    for v in 0..mesh.vertices.len() {
+        let around = &corners[v];
+        if around.len() < 2 {
+            continue;
+        }
+
+        // Union-find over the corners around v, joined by the far end of their edges.
+        let mut parent: Vec<usize> = (0..around.len()).collect();
+        let find = |parent: &mut Vec<usize>, mut i: usize| {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        };
+
+        let mut first_by_point: HashMap<u32, usize> = HashMap::new();
+        for (k, corner) in around.iter().enumerate() {
+            let f = *corner as usize / 3;
+            let c = *corner as usize % 3;
+            let p = face_points(mesh, &point_reps, f);
+
+            for other in [p[(c + 1) % 3], p[(c + 2) % 3]] {
+                match first_by_point.get(&other) {
+                    Some(j) => {
+                        let (ra, rb) = (find(&mut parent, k), find(&mut parent, *j));
+                        parent[ra] = rb;
+                    }
+                    None => {
+                        first_by_point.insert(other, k);
+                    }
+                }
+            }
+        }
+
+        let mut fans: Vec<usize> = Vec::new();
+        let mut fan_of: Vec<usize> = Vec::with_capacity(around.len());
+        for k in 0..around.len() {
+            let root = find(&mut parent, k);
+            let fan = match fans.iter().position(|r| *r == root) {
+                Some(i) => i,
+                None => {
+                    fans.push(root);
+                    fans.len() - 1
+                }
+            };
+            fan_of.push(fan);
+        }
+
+        if fans.len() < 2 {
+            continue;
+        }
+
+        report.bowtie_vertices += 1;
+
+        if options.split_bowties {
+            // The first fan keeps the vertex, the others get copies.
+            let first_copy = mesh.vertices.len() as u32;
+            for _ in 1..fans.len() {
+                mesh.vertices.push(mesh.vertices[v]);
+                vertex_origin.push(vertex_origin[v]);
+                point_reps.push(point_reps[v]);
+            }
+            report.split_vertices += fans.len() - 1;
+
+            for (k, corner) in around.iter().enumerate() {
+                if fan_of[k] > 0 {
+                    mesh.indices[*corner as usize] = first_copy + fan_of[k] as u32 - 1;
+                }
+            }
+        }
+    }
+
+    //===========================================
+    // Unused vertices.
+
+    let num_before = mesh.vertices.len();
+    let remap = compact(mesh);
+    report.unused_vertices = (num_before - mesh.vertices.len()).saturating_sub(report.welded_vertices);
+
+    report.vertex_remap = remap.iter().map(|i| vertex_origin[*i as usize]).collect();
+    report.face_remap = face_remap;
+    report
+}
+
+// Whether two optional attributes (compared by 'diff') are close enough to weld.
+// Vertex types without the attribute always are.
+fn close_attributes(diff: Option<f32>, epsilon: f32) -> bool {
+    match diff {
+        Some(d) => d <= epsilon,
+        None => true,
+    }
+}
+
+fn face_points<V>(mesh: &Mesh<V>, point_reps: &[u32], f: usize) -> [u32; 3] {
+    let i = &mesh.indices[f * 3..f * 3 + 3];
+    [point_reps[i[0] as usize], point_reps[i[1] as usize], point_reps[i[2] as usize]]
+}
+
+// Same face whichever vertex it starts at.
+fn rotate_to_min(p: [u32; 3]) -> [u32; 3] {
+    if p[0] <= p[1] && p[0] <= p[2] {
+        p
+    } else if p[1] <= p[2] {
+        [p[1], p[2], p[0]]
+    } else {
+        [p[2], p[0], p[1]]
+    }
+}
+
+// For every vertex, the index buffer positions (face * 3 + corner) that use it.
+fn vertex_corners<V>(mesh: &Mesh<V>) -> Vec<Vec<u32>> {
+    let mut corners = vec![Vec::new(); mesh.vertices.len()];
+    for (k, i) in mesh.indices.iter().enumerate() {
+        corners[*i as usize].push(k as u32);
+    }
+    corners
+}
+
+// Drops the faces whose 'keep' entry is false, tracking them in 'face_remap'.
+fn keep_faces<V>(mesh: &mut Mesh<V>, face_remap: &mut Vec<u32>, keep: &[bool]) {
+    if keep.iter().all(|k| *k) {
+        return;
+    }
+
+    let kept: Vec<u32> = (0..keep.len() as u32).filter(|f| keep[*f as usize]).collect();
+    apply_face_remap(mesh, &kept);
+    *face_remap = compose_remaps(face_remap, &kept);
+}
@@ -0,0 +1,232 @@
+// View frustum as six planes, with the culling tests of hierarchical scenes:
+// each test tells a volume that is completely inside from one that straddles
+// the boundary, so the children of a contained node need no tests at all.
+//
+// Two refinements from Assarsson and Moller, "Optimized View Frustum Culling
+// Algorithms for Bounding Boxes":
+//
+//   plane masks       a bit per plane still to test.  A node passes to its
+//                     children only the planes it straddles.
+//   plane coherency   the plane that culled an object last frame most likely
+//                     culls it again, so it is tested first.
+
+use crate::bounds::*;
+use crate::math::*;
+
+pub const NEAR_PLANE: usize = 0;
+pub const FAR_PLANE: usize = 1;
+pub const LEFT_PLANE: usize = 2;
+pub const RIGHT_PLANE: usize = 3;
+pub const TOP_PLANE: usize = 4;
+pub const BOTTOM_PLANE: usize = 5;
+
+// Mask with every plane to test.
+pub const ALL_PLANES: u8 = 0x3f;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Containment {
+    Outside,
+    Intersecting,
+    Inside,
+}
+
+// Volumes the frustum can test: a center and how far the volume reaches along
+// a plane normal.
+pub trait Cullable {
+    fn center(&self) -> Vec3;
+    // Half the volume's width along the unit vector n.
+    fn radius_along(&self, n: Vec3) -> f32;
+}
+
+impl Cullable for Vec3 {
+    fn center(&self) -> Vec3 { *self }
+
+    fn radius_along(&self, _n: Vec3) -> f32 { 0.0 }
+}
+
+impl Cullable for BoundingSphere {
+    fn center(&self) -> Vec3 { self.center }
+
+    fn radius_along(&self, _n: Vec3) -> f32 { self.radius }
+}
+
+impl Cullable for Aabb {
+    fn center(&self) -> Vec3 { Aabb::center(self) }
+
+    fn radius_along(&self, n: Vec3) -> f32 { self.extent().dot(n.abs()) }
+}
+
+impl Cullable for Obb {
+    fn center(&self) -> Vec3 { self.center }
+
+    fn radius_along(&self, n: Vec3) -> f32 {
+        self.extent.x * n.dot(self.axes[0]).abs() +
+        self.extent.y * n.dot(self.axes[1]).abs() +
+        self.extent.z * n.dot(self.axes[2]).abs()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum {
+    // Normalized and pointing inwards, in the order of the *_PLANE indices:
+    // a point p is inside plane i when dot(planes[i].xyz, p) + planes[i].w >= 0.
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    // The frustum of a view-projection matrix (or of a projection matrix alone,
+    // in view space), from the clip volume -w <= x, y <= w, 0 <= z <= w.
+    pub fn from_matrix(m: &Mat4) -> Frustum {
+        let (c0, c1, c2, c3) = (m.col(0), m.col(1), m.col(2), m.col(3));
+        Frustum::from_planes([c2, c3 - c2, c3 + c0, c3 - c0, c3 - c1, c3 + c1])
+    }
+
+    // Planes in the *_PLANE order pointing inwards, normalized here.
+    pub fn from_planes(planes: [Vec4; 6]) -> Frustum {
+        let mut res = Frustum { planes };
+        for p in res.planes.iter_mut() {
+            let length = p.xyz().length();
+            if length > 0.0 {
+                *p = *p * (1.0 / length);
+            }
+        }
+        res
+    }
+
+    pub fn get_planes(&self) -> &[Vec4; 6] {
+        &self.planes
+    }
+
+    pub fn get_plane(&self, i: usize) -> Vec4 {
+        self.planes[i]
+    }
+
+    // Signed distance of a point from plane i, positive inside.
+    pub fn distance(&self, i: usize, p: Vec3) -> f32 {
+        self.planes[i].xyz().dot(p) + self.planes[i].w
+    }
+
+    // Inside or Outside; points on a plane count as inside.
+    pub fn test_point(&self, p: Vec3) -> Containment {
+        self.test(&p)
+    }
+
+    pub fn test_sphere(&self, sphere: &BoundingSphere) -> Containment {
+        self.test(sphere)
+    }
+
+    pub fn test_aabb(&self, b: &Aabb) -> Containment {
+        self.test(b)
+    }
+
+    pub fn test_obb(&self, b: &Obb) -> Containment {
+        self.test(b)
+    }
+
+    // Exact for points and spheres against each plane, but conservative near
+    // the frustum's edges and corners: a box outside the frustum yet straddling
+    // two planes there is reported Intersecting.
+    pub fn test<V: Cullable>(&self, v: &V) -> Containment {
+        let mut last_plane = 0;
+        self.test_masked(v, ALL_PLANES, &mut last_plane).0
+    }
+
+    // Tests only the planes in 'mask', starting with 'last_plane'.  Returns the
+    // result and the mask of the planes the volume straddles, which is what its
+    // children need to test (none when it is Inside).  When the volume is
+    // Outside, 'last_plane' is set to the plane that culled it, for the next
+    // frame's test.
+    pub fn test_masked<V: Cullable>(&self, v: &V, mask: u8, last_plane: &mut usize) -> (Containment, u8) {
+        let center = v.center();
+        let first = (*last_plane).min(5);
+        let mut straddled = 0;
+
+        for i in std::iter::once(first).chain((0..6).filter(|&i| i != first)) {
+            let bit = 1 << i;
+            if mask & bit == 0 {
+                continue;
+            }
+
+            let n = self.planes[i].xyz();
+            let d = n.dot(center) + self.planes[i].w;
+            let r = v.radius_along(n);
+
+            if d + r < 0.0 {
+                *last_plane = i;
+                return (Containment::Outside, 0);
+            }
+            if d - r < 0.0 {
+                straddled |= bit;
+            }
+        }
+
+        if straddled == 0 {
+            (Containment::Inside, 0)
+        } else {
+            (Containment::Intersecting, straddled)
+        }
+    }
+
+    // True unless the volume is certainly outside.
+    pub fn is_visible<V: Cullable>(&self, v: &V) -> bool {
+        self.test(v) != Containment::Outside
+    }
+
+    // The eight corners, where three planes meet; bit 0 of the index picks the
+    // right plane (else left), bit 1 top (else bottom), bit 2 far (else near),
+    // like Aabb::corners.
+    pub fn corners(&self) -> [Vec3; 8] {
+        let mut res = [Vec3::ZERO; 8];
+        for (i, c) in res.iter_mut().enumerate() {
+            let x = if i & 1 != 0 { RIGHT_PLANE } else { LEFT_PLANE };
+            let y = if i & 2 != 0 { TOP_PLANE } else { BOTTOM_PLANE };
+            let z = if i & 4 != 0 { FAR_PLANE } else { NEAR_PLANE };
+            *c = intersect_planes(self.planes[x], self.planes[y], self.planes[z]);
+        }
+        res
+    }
+
+    // Sphere around the corners, centered on the line between the centers of
+    // the near and far faces where the nearest and farthest corners are equally
+    // far.  Tight for symmetric frusta; a shadow map fitted to it does not
+    // change size as the camera turns.
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        let corners = self.corners();
+        let face_center = |c: &[Vec3]| (c[0] + c[1] + c[2] + c[3]) * 0.25;
+        let near_center = face_center(&corners[0..4]);
+        let far_center = face_center(&corners[4..8]);
+
+        let near_radius_sq = corners[0..4].iter().map(|c| (*c - near_center).length_sq()).fold(0.0, f32::max);
+        let far_radius_sq = corners[4..8].iter().map(|c| (*c - far_center).length_sq()).fold(0.0, f32::max);
+
+        let axis = far_center - near_center;
+        let length = axis.length();
+        let center = if length > 0.0 {
+            let s = ((length * length + far_radius_sq - near_radius_sq) / (2.0 * length)).clamp(0.0, length);
+            near_center + axis * (s / length)
+        } else {
+            near_center
+        };
+
+        let radius_sq = corners.iter().map(|c| (*c - center).length_sq()).fold(0.0, f32::max);
+        BoundingSphere::new(center, radius_sq.sqrt())
+    }
+
+    // Box around the corners.
+    pub fn bounding_box(&self) -> Aabb {
+        let mut res = Aabb::EMPTY;
+        for c in self.corners() {
+            res.grow(c);
+        }
+        res
+    }
+}
+
+// The point where three planes meet.  The planes must not be parallel.
+fn intersect_planes(a: Vec4, b: Vec4, c: Vec4) -> Vec3 {
+    let (na, nb, nc) = (a.xyz(), b.xyz(), c.xyz());
+    let bc = nb.cross(nc);
+    let denom = na.dot(bc);
+
+    (bc * -a.w + nc.cross(na) * -b.w + na.cross(nb) * -c.w) / denom
+}
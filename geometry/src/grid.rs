@@ -0,0 +1,202 @@
+// Triangle grids on the xz-plane, the shared builder behind gen_tri_grid and
+// gen_tri_grid_32 and the starting point of terrain and water meshes.
+//
+// Vertices are laid out row by row from the -x/+z corner, so vertex (i, j) is at
+// index i * num_vert_cols + j; skirt vertices, if any, come after the grid.
+
+use std::fmt;
+use crate::math::*;
+use crate::mesh::*;
+
+// Index types a grid can be built with.
+pub trait GridIndex: Copy {
+    // Number of distinct vertices the type can address.
+    const MAX_VERTICES: usize;
+
+    fn from_usize(i: usize) -> Self;
+}
+
+impl GridIndex for u16 {
+    const MAX_VERTICES: usize = u16::MAX as usize + 1;
+
+    fn from_usize(i: usize) -> Self {
+        i as u16
+    }
+}
+
+impl GridIndex for u32 {
+    const MAX_VERTICES: usize = u32::MAX as usize;
+
+    fn from_usize(i: usize) -> Self {
+        i as u32
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GridError {
+    // A grid needs at least two rows and two columns of vertices.
+    TooSmall { num_vert_rows: u32, num_vert_cols: u32 },
+    // The index type cannot address every vertex.
+    IndexOverflow { num_vertices: usize, max_vertices: usize },
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridError::TooSmall { num_vert_rows, num_vert_cols } =>
+                write!(f, "a {}x{} grid has no cells", num_vert_rows, num_vert_cols),
+            GridError::IndexOverflow { num_vertices, max_vertices } =>
+                write!(f, "{} vertices do not fit an index type addressing {} vertices", num_vertices, max_vertices),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GridTexCoords {
+    // [0, 1] across the whole grid.
+    Normalized,
+    // The texture repeats every this many world units.
+    Tiled(f32),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GridDiagonals {
+    // Every cell split the same way, as gen_tri_grid does.
+    Uniform,
+    // Diagonals flip in a checkerboard pattern, which hides the directional
+    // artifacts of uniform splits on displaced grids (terrain, water).
+    Alternating,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct GridOptions {
+    // Where the center of the grid goes.
+    pub center: Vec3,
+    // Only written to vertex types that have tex-coords.
+    pub tex_coords: GridTexCoords,
+    pub diagonals: GridDiagonals,
+    // When positive, a strip of this depth hangs down from the border so cracks
+    // between neighbouring grids of different resolution are not see-through.
+    pub skirt_depth: f32,
+}
+
+impl Default for GridOptions {
+    fn default() -> Self {
+        GridOptions {
+            center: Vec3::ZERO,
+            tex_coords: GridTexCoords::Normalized,
+            diagonals: GridDiagonals::Uniform,
+            skirt_depth: 0.0,
+        }
+    }
+}
+
+// Builds a num_vert_rows x num_vert_cols grid with dx and dz spacing.  Vertex
+// types with normals get +y normals, ones with tex-coords get them according to
+// the options.
+pub fn build_tri_grid<V: Vertex, I: GridIndex>(num_vert_rows: u32, num_vert_cols: u32, dx: f32, dz: f32,
+                                               options: &GridOptions) -> Result<(Vec<V>, Vec<I>), GridError> {
+    if num_vert_rows < 2 || num_vert_cols < 2 {
+        return Err(GridError::TooSmall { num_vert_rows, num_vert_cols });
+    }
+
+    let rows = num_vert_rows as usize;
+    let cols = num_vert_cols as usize;
+
+    let num_grid_vertices = rows * cols;
+    let num_skirt_vertices = if options.skirt_depth > 0.0 { 2 * (rows + cols) - 4 } else { 0 };
+    let num_vertices = num_grid_vertices + num_skirt_vertices;
+
+    if num_vertices > I::MAX_VERTICES {
+        return Err(GridError::IndexOverflow { num_vertices, max_vertices: I::MAX_VERTICES });
+    }
+
+    let width = (cols - 1) as f32 * dx;
+    let depth = (rows - 1) as f32 * dz;
+
+    //===========================================
+    // Build vertices.
+
+    // Grid centered about the origin, rows going from +z to -z, then moved to
+    // 'center'.
+    let x_offset = -width * 0.5 + options.center.x;
+    let z_offset = depth * 0.5 + options.center.z;
+
+    let mut vertices: Vec<V> = Vec::with_capacity(num_vertices);
+    for i in 0..rows {
+        for j in 0..cols {
+            let mut v = V::default();
+            v.set_pos(Vec3::new(j as f32 * dx + x_offset, options.center.y, -(i as f32) * dz + z_offset));
+            v.set_normal(Vec3::Y);
+
+            let tex0 = match options.tex_coords {
+                GridTexCoords::Normalized => Vec2::new(j as f32 / (cols - 1) as f32, i as f32 / (rows - 1) as f32),
+                GridTexCoords::Tiled(size) => Vec2::new(j as f32 * dx / size, i as f32 * dz / size),
+            };
+            v.set_tex0(tex0);
+
+            vertices.push(v);
+        }
+    }
+
+    //===========================================
+    // Build indices.
+
+    let mut indices: Vec<I> = Vec::with_capacity((rows - 1) * (cols - 1) * 6 + num_skirt_vertices * 6);
+    let mut push = |tri: [usize; 3]| indices.extend(tri.iter().map(|i| I::from_usize(*i)));
+
+    for i in 0..rows - 1 {
+        for j in 0..cols - 1 {
+            let a = i * cols + j;
+            let b = a + 1;
+            let c = a + cols;
+            let d = c + 1;
+
+            if options.diagonals == GridDiagonals::Alternating && (i + j) % 2 == 1 {
+                push([a, b, d]);
+                push([a, d, c]);
+            } else {
+                push([a, b, c]);
+                push([c, b, d]);
+            }
+        }
+    }
+
+    //===========================================
+    // Skirts.  Walk the border clockwise (seen from above) and hang a quad below
+    // each border edge, wound to face outwards.
+
+    if num_skirt_vertices > 0 {
+        let mut border: Vec<usize> = Vec::with_capacity(num_skirt_vertices);
+        border.extend(0..cols);
+        border.extend((1..rows).map(|i| i * cols + cols - 1));
+        border.extend((0..cols - 1).rev().map(|j| (rows - 1) * cols + j));
+        border.extend((1..rows - 1).rev().map(|i| i * cols));
+
+        for b in &border {
+            let mut v = vertices[*b];
+            v.set_pos(v.pos() - Vec3::Y * options.skirt_depth);
+            vertices.push(v);
+        }
+
+        for k in 0..border.len() {
+            let next = (k + 1) % border.len();
+            let (p, q) = (border[k], border[next]);
+            let (p_low, q_low) = (num_grid_vertices + k, num_grid_vertices + next);
+
+            push([p, p_low, q]);
+            push([q, p_low, q_low]);
+        }
+    }
+
+    Ok((vertices, indices))
+}
+
+// Same as build_tri_grid, as a Mesh (32-bit indices, subset 0).
+pub fn build_tri_grid_mesh<V: Vertex>(num_vert_rows: u32, num_vert_cols: u32, dx: f32, dz: f32,
+                                      options: &GridOptions) -> Result<Mesh<V>, GridError> {
+    let (vertices, indices) = build_tri_grid::<V, u32>(num_vert_rows, num_vert_cols, dx, dz, options)?;
+    Ok(Mesh::new(vertices, indices))
+}
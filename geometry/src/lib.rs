@@ -0,0 +1,35 @@
+pub mod bounds;
+pub mod bvh;
+pub mod camera;
+pub mod clean;
+pub mod frustum;
+pub mod grid;
+pub mod lod;
+pub mod math;
+pub mod mesh;
+pub mod mesh_opt;
+pub mod picking;
+pub mod primitives;
+pub mod quadtree;
+pub mod ray;
+pub mod simplify;
+pub mod tangent_frame;
+pub mod uv_gen;
+
+pub use crate::bounds::*;
+pub use crate::bvh::*;
+pub use crate::camera::*;
+pub use crate::clean::*;
+pub use crate::frustum::*;
+pub use crate::grid::*;
+pub use crate::lod::*;
+pub use crate::math::*;
+pub use crate::mesh::*;
+pub use crate::mesh_opt::*;
+pub use crate::picking::*;
+pub use crate::primitives::*;
+pub use crate::quadtree::*;
+pub use crate::ray::*;
+pub use crate::simplify::*;
+pub use crate::tangent_frame::*;
+pub use crate::uv_gen::*;
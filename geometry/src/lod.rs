@@ -0,0 +1,107 @@
+// Discrete level-of-detail chains built with the edge-collapse simplifier, and
+// selection of a level from the size an error projects to on screen.
+
+use crate::math::*;
+use crate::mesh::*;
+use crate::mesh_opt::*;
+use crate::simplify::*;
+
+#[derive(Copy, Clone, Debug)]
+pub struct LodOptions {
+    // Levels including the full resolution one.
+    pub num_levels: usize,
+    // Fraction of faces kept from one level to the next.
+    pub reduction: f32,
+    // Levels are not made smaller than this.
+    pub min_faces: usize,
+    // Options passed to the simplifier; target_faces is set per level.
+    pub simplify: SimplifyOptions,
+}
+
+impl Default for LodOptions {
+    fn default() -> Self {
+        LodOptions {
+            num_levels: 4,
+            reduction: 0.5,
+            min_faces: 16,
+            simplify: SimplifyOptions::default(),
+        }
+    }
+}
+
+pub struct LodLevel<V> {
+    pub mesh: Mesh<V>,
+    // Object space error relative to level 0.
+    pub error: f32,
+}
+
+pub struct LodChain<V> {
+    // Finest first.  A level may stop short of its target when the simplifier runs
+    // out of valid collapses; generation stops there.
+    pub levels: Vec<LodLevel<V>>,
+}
+
+impl<V: Vertex> LodChain<V> {
+    // Each level is simplified from the previous one, so errors accumulate and
+    // never decrease along the chain.  Every level is optimized for the vertex cache.
+    pub fn generate(mesh: &Mesh<V>, options: &LodOptions) -> LodChain<V> {
+        let mut base = mesh.clone();
+        optimize_mesh(&mut base, &OptimizeOptions::default());
+
+        let mut levels = vec![LodLevel { mesh: base, error: 0.0 }];
+
+        while levels.len() < options.num_levels {
+            let prev = levels.last().unwrap();
+            let target = ((prev.mesh.num_faces() as f32 * options.reduction) as usize).max(options.min_faces);
+            if target >= prev.mesh.num_faces() {
+                break;
+            }
+
+            let mut simplify_options = options.simplify;
+            simplify_options.target_faces = target;
+
+            let mut res = simplify(&prev.mesh, &simplify_options);
+            if res.mesh.num_faces() >= prev.mesh.num_faces() {
+                break;
+            }
+
+            optimize_mesh(&mut res.mesh, &OptimizeOptions::default());
+            let error = prev.error + res.error;
+            levels.push(LodLevel { mesh: res.mesh, error });
+        }
+
+        LodChain { levels }
+    }
+
+    pub fn errors(&self) -> Vec<f32> {
+        self.levels.iter().map(|l| l.error).collect()
+    }
+
+    pub fn select(&self, distance: f32, scale: f32, proj: &Mat4, viewport_height: f32, max_pixel_error: f32) -> usize {
+        select_lod(&self.errors(), distance, scale, proj, viewport_height, max_pixel_error)
+    }
+}
+
+// Height in pixels an object space length 'size' covers at view space depth
+// 'distance'.  Works for both perspective and orthographic projections.
+pub fn projected_size(size: f32, distance: f32, proj: &Mat4, viewport_height: f32) -> f32 {
+    // proj.m[1][1] is cot(fov_y / 2) for a perspective projection and 2 / height
+    // for an orthographic one, which has no perspective divide (m[2][3] == 0).
+    let scale = proj.m[1][1] * viewport_height * 0.5;
+    if proj.m[2][3] == 0.0 {
+        size * scale
+    } else {
+        size * scale / distance.max(1.0e-6)
+    }
+}
+
+// Index of the coarsest level whose error projects to at most 'max_pixel_error'
+// pixels.  'errors' is increasing (see LodChain::errors); 'scale' is the object's
+// world scale.
+pub fn select_lod(errors: &[f32], distance: f32, scale: f32, proj: &Mat4, viewport_height: f32,
+                  max_pixel_error: f32) -> usize {
+    (0..errors.len())
+        .rev()
+        .find(|i| projected_size(errors[*i] * scale, distance, proj, viewport_height) <= max_pixel_error)
+        .unwrap_or(0)
+}
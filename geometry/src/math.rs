@@ -0,0 +1,739 @@
+// Plain Rust vector, matrix and quaternion types.
+//
+// These follow the D3DX conventions (row vectors, row-major matrices, left-handed
+// coordinate system) and have the same memory layout as D3DXVECTOR2, D3DXVECTOR3,
+// D3DXVECTOR4, D3DXMATRIX and D3DXQUATERNION, so data can be copied to and from
+// D3D buffers as-is.
+
+use std::ops::{Add, AddAssign, Div, Index, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Vec2 {
+    pub const ZERO: Vec2 = Vec2 { x: 0.0, y: 0.0 };
+
+    pub const fn new(x: f32, y: f32) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    pub fn dot(&self, other: Vec2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(*self).sqrt()
+    }
+
+    pub fn lerp(&self, other: Vec2, t: f32) -> Vec2 {
+        *self + (other - *self) * t
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2 { x: self.x + other.x, y: self.y + other.y }
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2 { x: self.x - other.x, y: self.y - other.y }
+    }
+}
+
+impl Mul<f32> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, s: f32) -> Vec2 {
+        Vec2 { x: self.x * s, y: self.y * s }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub const ZERO: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
+    pub const X: Vec3 = Vec3 { x: 1.0, y: 0.0, z: 0.0 };
+    pub const Y: Vec3 = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
+    pub const Z: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+
+    pub const fn new(x: f32, y: f32, z: f32) -> Vec3 {
+        Vec3 { x, y, z }
+    }
+
+    pub const fn splat(v: f32) -> Vec3 {
+        Vec3 { x: v, y: v, z: v }
+    }
+
+    pub fn dot(&self, other: Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
+    pub fn length_sq(&self) -> f32 {
+        self.dot(*self)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length_sq().sqrt()
+    }
+
+    // Like D3DXVec3Normalize, a zero vector stays zero.
+    pub fn normalize(&self) -> Vec3 {
+        let len = self.length();
+        if len > 0.0 {
+            *self / len
+        } else {
+            Vec3::ZERO
+        }
+    }
+
+    pub fn min(&self, other: Vec3) -> Vec3 {
+        Vec3 { x: self.x.min(other.x), y: self.y.min(other.y), z: self.z.min(other.z) }
+    }
+
+    pub fn max(&self, other: Vec3) -> Vec3 {
+        Vec3 { x: self.x.max(other.x), y: self.y.max(other.y), z: self.z.max(other.z) }
+    }
+
+    pub fn abs(&self) -> Vec3 {
+        Vec3 { x: self.x.abs(), y: self.y.abs(), z: self.z.abs() }
+    }
+
+    pub fn mul_elem(&self, other: Vec3) -> Vec3 {
+        Vec3 { x: self.x * other.x, y: self.y * other.y, z: self.z * other.z }
+    }
+
+    pub fn lerp(&self, other: Vec3, t: f32) -> Vec3 {
+        *self + (other - *self) * t
+    }
+
+    pub fn distance(&self, other: Vec3) -> f32 {
+        (*self - other).length()
+    }
+
+    pub fn min_elem(&self) -> f32 {
+        self.x.min(self.y).min(self.z)
+    }
+
+    pub fn max_elem(&self) -> f32 {
+        self.x.max(self.y).max(self.z)
+    }
+
+    // Some unit vector perpendicular to this one.
+    pub fn any_orthogonal(&self) -> Vec3 {
+        if self.x.abs() < 0.9 {
+            self.cross(Vec3::X).normalize()
+        } else {
+            self.cross(Vec3::Y).normalize()
+        }
+    }
+}
+
+impl Index<usize> for Vec3 {
+    type Output = f32;
+
+    fn index(&self, i: usize) -> &f32 {
+        match i {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 index out of range: {}", i),
+        }
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Vec3;
+
+    fn add(self, other: Vec3) -> Vec3 {
+        Vec3 { x: self.x + other.x, y: self.y + other.y, z: self.z + other.z }
+    }
+}
+
+impl AddAssign for Vec3 {
+    fn add_assign(&mut self, other: Vec3) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Vec3) -> Vec3 {
+        Vec3 { x: self.x - other.x, y: self.y - other.y, z: self.z - other.z }
+    }
+}
+
+impl SubAssign for Vec3 {
+    fn sub_assign(&mut self, other: Vec3) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<f32> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, s: f32) -> Vec3 {
+        Vec3 { x: self.x * s, y: self.y * s, z: self.z * s }
+    }
+}
+
+impl MulAssign<f32> for Vec3 {
+    fn mul_assign(&mut self, s: f32) {
+        *self = *self * s;
+    }
+}
+
+impl Div<f32> for Vec3 {
+    type Output = Vec3;
+
+    fn div(self, s: f32) -> Vec3 {
+        Vec3 { x: self.x / s, y: self.y / s, z: self.z / s }
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3 { x: -self.x, y: -self.y, z: -self.z }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Vec4 {
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Vec4 {
+        Vec4 { x, y, z, w }
+    }
+
+    pub fn from_vec3(v: Vec3, w: f32) -> Vec4 {
+        Vec4 { x: v.x, y: v.y, z: v.z, w }
+    }
+
+    pub fn xyz(&self) -> Vec3 {
+        Vec3 { x: self.x, y: self.y, z: self.z }
+    }
+
+    pub fn dot(&self, other: Vec4) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+}
+
+impl Add for Vec4 {
+    type Output = Vec4;
+
+    fn add(self, other: Vec4) -> Vec4 {
+        Vec4 { x: self.x + other.x, y: self.y + other.y, z: self.z + other.z, w: self.w + other.w }
+    }
+}
+
+impl Sub for Vec4 {
+    type Output = Vec4;
+
+    fn sub(self, other: Vec4) -> Vec4 {
+        Vec4 { x: self.x - other.x, y: self.y - other.y, z: self.z - other.z, w: self.w - other.w }
+    }
+}
+
+impl Mul<f32> for Vec4 {
+    type Output = Vec4;
+
+    fn mul(self, s: f32) -> Vec4 {
+        Vec4 { x: self.x * s, y: self.y * s, z: self.z * s, w: self.w * s }
+    }
+}
+
+// 4x4 matrix, m[row][col].  Vectors are treated as row vectors, so points are
+// transformed as p * M and transforms concatenate left to right.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct Mat4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::IDENTITY
+    }
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]
+    };
+
+    pub fn translation(x: f32, y: f32, z: f32) -> Mat4 {
+        let mut res = Mat4::IDENTITY;
+        res.m[3][0] = x;
+        res.m[3][1] = y;
+        res.m[3][2] = z;
+        res
+    }
+
+    pub fn scaling(sx: f32, sy: f32, sz: f32) -> Mat4 {
+        let mut res = Mat4::IDENTITY;
+        res.m[0][0] = sx;
+        res.m[1][1] = sy;
+        res.m[2][2] = sz;
+        res
+    }
+
+    pub fn rotation_x(angle: f32) -> Mat4 {
+        let (s, c) = angle.sin_cos();
+        let mut res = Mat4::IDENTITY;
+        res.m[1][1] = c;
+        res.m[1][2] = s;
+        res.m[2][1] = -s;
+        res.m[2][2] = c;
+        res
+    }
+
+    pub fn rotation_y(angle: f32) -> Mat4 {
+        let (s, c) = angle.sin_cos();
+        let mut res = Mat4::IDENTITY;
+        res.m[0][0] = c;
+        res.m[0][2] = -s;
+        res.m[2][0] = s;
+        res.m[2][2] = c;
+        res
+    }
+
+    pub fn rotation_z(angle: f32) -> Mat4 {
+        let (s, c) = angle.sin_cos();
+        let mut res = Mat4::IDENTITY;
+        res.m[0][0] = c;
+        res.m[0][1] = s;
+        res.m[1][0] = -s;
+        res.m[1][1] = c;
+        res
+    }
+
+    // Same as D3DXMatrixRotationAxis: rotation of 'angle' radians about 'axis'.
+    pub fn rotation_axis(axis: Vec3, angle: f32) -> Mat4 {
+        let a = axis.normalize();
+        let (s, c) = angle.sin_cos();
+        let t = 1.0 - c;
+
+        Mat4 {
+            m: [
+                [t * a.x * a.x + c,       t * a.x * a.y + s * a.z, t * a.x * a.z - s * a.y, 0.0],
+                [t * a.x * a.y - s * a.z, t * a.y * a.y + c,       t * a.y * a.z + s * a.x, 0.0],
+                [t * a.x * a.z + s * a.y, t * a.y * a.z - s * a.x, t * a.z * a.z + c,       0.0],
+                [0.0,                     0.0,                     0.0,                     1.0],
+            ]
+        }
+    }
+
+    // Same as D3DXMatrixRotationQuaternion.
+    pub fn rotation_quat(q: Quat) -> Mat4 {
+        let (right, up, look) = q.to_axes();
+        Mat4::from_axes(right, up, look, Vec3::ZERO)
+    }
+
+    // Builds a matrix whose rows are the given axes followed by a translation.
+    pub fn from_axes(right: Vec3, up: Vec3, look: Vec3, pos: Vec3) -> Mat4 {
+        Mat4 {
+            m: [
+                [right.x, right.y, right.z, 0.0],
+                [up.x,    up.y,    up.z,    0.0],
+                [look.x,  look.y,  look.z,  0.0],
+                [pos.x,   pos.y,   pos.z,   1.0],
+            ]
+        }
+    }
+
+    pub fn look_at_lh(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+        let z = (target - eye).normalize();
+        let x = up.cross(z).normalize();
+        let y = z.cross(x);
+
+        Mat4 {
+            m: [
+                [x.x,          y.x,          z.x,          0.0],
+                [x.y,          y.y,          z.y,          0.0],
+                [x.z,          y.z,          z.z,          0.0],
+                [-x.dot(eye),  -y.dot(eye),  -z.dot(eye),  1.0],
+            ]
+        }
+    }
+
+    pub fn perspective_fov_lh(fov_y: f32, aspect: f32, near_z: f32, far_z: f32) -> Mat4 {
+        let y_scale = 1.0 / (fov_y * 0.5).tan();
+        let x_scale = y_scale / aspect;
+        let q = far_z / (far_z - near_z);
+
+        Mat4 {
+            m: [
+                [x_scale, 0.0,     0.0,         0.0],
+                [0.0,     y_scale, 0.0,         0.0],
+                [0.0,     0.0,     q,           1.0],
+                [0.0,     0.0,     -q * near_z, 0.0],
+            ]
+        }
+    }
+
+    pub fn ortho_lh(width: f32, height: f32, near_z: f32, far_z: f32) -> Mat4 {
+        let q = 1.0 / (far_z - near_z);
+
+        Mat4 {
+            m: [
+                [2.0 / width, 0.0,          0.0,         0.0],
+                [0.0,         2.0 / height, 0.0,         0.0],
+                [0.0,         0.0,          q,           0.0],
+                [0.0,         0.0,          -q * near_z, 1.0],
+            ]
+        }
+    }
+
+    pub fn row(&self, i: usize) -> Vec4 {
+        Vec4 { x: self.m[i][0], y: self.m[i][1], z: self.m[i][2], w: self.m[i][3] }
+    }
+
+    pub fn col(&self, j: usize) -> Vec4 {
+        Vec4 { x: self.m[0][j], y: self.m[1][j], z: self.m[2][j], w: self.m[3][j] }
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut res = Mat4::IDENTITY;
+        for i in 0..4 {
+            for j in 0..4 {
+                res.m[i][j] = self.m[j][i];
+            }
+        }
+        res
+    }
+
+    // Like D3DXVec3TransformCoord: transforms (x, y, z, 1) and projects back to w = 1.
+    pub fn transform_coord(&self, v: Vec3) -> Vec3 {
+        let r = self.transform(Vec4::from_vec3(v, 1.0));
+        if r.w != 0.0 {
+            r.xyz() * (1.0 / r.w)
+        } else {
+            r.xyz()
+        }
+    }
+
+    // Like D3DXVec3TransformNormal: transforms (x, y, z, 0).
+    pub fn transform_normal(&self, v: Vec3) -> Vec3 {
+        Vec3 {
+            x: v.x * self.m[0][0] + v.y * self.m[1][0] + v.z * self.m[2][0],
+            y: v.x * self.m[0][1] + v.y * self.m[1][1] + v.z * self.m[2][1],
+            z: v.x * self.m[0][2] + v.y * self.m[1][2] + v.z * self.m[2][2],
+        }
+    }
+
+    pub fn transform(&self, v: Vec4) -> Vec4 {
+        Vec4 {
+            x: v.x * self.m[0][0] + v.y * self.m[1][0] + v.z * self.m[2][0] + v.w * self.m[3][0],
+            y: v.x * self.m[0][1] + v.y * self.m[1][1] + v.z * self.m[2][1] + v.w * self.m[3][1],
+            z: v.x * self.m[0][2] + v.y * self.m[1][2] + v.z * self.m[2][2] + v.w * self.m[3][2],
+            w: v.x * self.m[0][3] + v.y * self.m[1][3] + v.z * self.m[2][3] + v.w * self.m[3][3],
+        }
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let m = &self.m;
+
+        let s0 = m[0][0] * m[1][1] - m[1][0] * m[0][1];
+        let s1 = m[0][0] * m[1][2] - m[1][0] * m[0][2];
+        let s2 = m[0][0] * m[1][3] - m[1][0] * m[0][3];
+        let s3 = m[0][1] * m[1][2] - m[1][1] * m[0][2];
+        let s4 = m[0][1] * m[1][3] - m[1][1] * m[0][3];
+        let s5 = m[0][2] * m[1][3] - m[1][2] * m[0][3];
+
+        let c5 = m[2][2] * m[3][3] - m[3][2] * m[2][3];
+        let c4 = m[2][1] * m[3][3] - m[3][1] * m[2][3];
+        let c3 = m[2][1] * m[3][2] - m[3][1] * m[2][2];
+        let c2 = m[2][0] * m[3][3] - m[3][0] * m[2][3];
+        let c1 = m[2][0] * m[3][2] - m[3][0] * m[2][2];
+        let c0 = m[2][0] * m[3][1] - m[3][0] * m[2][1];
+
+        s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0
+    }
+
+    // General 4x4 inverse (cofactor expansion).  Returns None for singular matrices.
+    pub fn inverse(&self) -> Option<Mat4> {
+        let m = &self.m;
+
+        let s0 = m[0][0] * m[1][1] - m[1][0] * m[0][1];
+        let s1 = m[0][0] * m[1][2] - m[1][0] * m[0][2];
+        let s2 = m[0][0] * m[1][3] - m[1][0] * m[0][3];
+        let s3 = m[0][1] * m[1][2] - m[1][1] * m[0][2];
+        let s4 = m[0][1] * m[1][3] - m[1][1] * m[0][3];
+        let s5 = m[0][2] * m[1][3] - m[1][2] * m[0][3];
+
+        let c5 = m[2][2] * m[3][3] - m[3][2] * m[2][3];
+        let c4 = m[2][1] * m[3][3] - m[3][1] * m[2][3];
+        let c3 = m[2][1] * m[3][2] - m[3][1] * m[2][2];
+        let c2 = m[2][0] * m[3][3] - m[3][0] * m[2][3];
+        let c1 = m[2][0] * m[3][2] - m[3][0] * m[2][2];
+        let c0 = m[2][0] * m[3][1] - m[3][0] * m[2][1];
+
+        let det = s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0;
+        if det.abs() < f32::EPSILON * f32::EPSILON {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+
+        let mut res = Mat4::IDENTITY;
+
+        res.m[0][0] = ( m[1][1] * c5 - m[1][2] * c4 + m[1][3] * c3) * inv_det;
+        res.m[0][1] = (-m[0][1] * c5 + m[0][2] * c4 - m[0][3] * c3) * inv_det;
+        res.m[0][2] = ( m[3][1] * s5 - m[3][2] * s4 + m[3][3] * s3) * inv_det;
+        res.m[0][3] = (-m[2][1] * s5 + m[2][2] * s4 - m[2][3] * s3) * inv_det;
+
+        res.m[1][0] = (-m[1][0] * c5 + m[1][2] * c2 - m[1][3] * c1) * inv_det;
+        res.m[1][1] = ( m[0][0] * c5 - m[0][2] * c2 + m[0][3] * c1) * inv_det;
+        res.m[1][2] = (-m[3][0] * s5 + m[3][2] * s2 - m[3][3] * s1) * inv_det;
+        res.m[1][3] = ( m[2][0] * s5 - m[2][2] * s2 + m[2][3] * s1) * inv_det;
+
+        res.m[2][0] = ( m[1][0] * c4 - m[1][1] * c2 + m[1][3] * c0) * inv_det;
+        res.m[2][1] = (-m[0][0] * c4 + m[0][1] * c2 - m[0][3] * c0) * inv_det;
+        res.m[2][2] = ( m[3][0] * s4 - m[3][1] * s2 + m[3][3] * s0) * inv_det;
+        res.m[2][3] = (-m[2][0] * s4 + m[2][1] * s2 - m[2][3] * s0) * inv_det;
+
+        res.m[3][0] = (-m[1][0] * c3 + m[1][1] * c1 - m[1][2] * c0) * inv_det;
+        res.m[3][1] = ( m[0][0] * c3 - m[0][1] * c1 + m[0][2] * c0) * inv_det;
+        res.m[3][2] = (-m[3][0] * s3 + m[3][1] * s1 - m[3][2] * s0) * inv_det;
+        res.m[3][3] = ( m[2][0] * s3 - m[2][1] * s1 + m[2][2] * s0) * inv_det;
+
+        Some(res)
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut res = Mat4 { m: [[0.0; 4]; 4] };
+        for i in 0..4 {
+            for j in 0..4 {
+                res.m[i][j] = self.m[i][0] * other.m[0][j] +
+                              self.m[i][1] * other.m[1][j] +
+                              self.m[i][2] * other.m[2][j] +
+                              self.m[i][3] * other.m[3][j];
+            }
+        }
+        res
+    }
+}
+
+// Unit quaternion for rotations, with the same memory layout as D3DXQUATERNION.
+// Like the matrices, products concatenate left to right: a * b rotates by a,
+// then by b (D3DXQuaternionMultiply order).
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Quat::IDENTITY
+    }
+}
+
+impl Quat {
+    pub const IDENTITY: Quat = Quat { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Quat {
+        Quat { x, y, z, w }
+    }
+
+    // Rotation of 'angle' radians about 'axis', like Mat4::rotation_axis.
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+        let (s, c) = (angle * 0.5).sin_cos();
+        let a = axis.normalize() * s;
+        Quat { x: a.x, y: a.y, z: a.z, w: c }
+    }
+
+    // Rotation taking the x, y and z axes to 'right', 'up' and 'look', which
+    // must be orthonormal, e.g. a camera's axes.
+    pub fn from_axes(right: Vec3, up: Vec3, look: Vec3) -> Quat {
+        // r[i][j] is component i of axis j.
+        let r = [
+            [right.x, up.x, look.x],
+            [right.y, up.y, look.y],
+            [right.z, up.z, look.z],
+        ];
+
+        let trace = r[0][0] + r[1][1] + r[2][2];
+        let q = if trace > 0.0 {
+            let s = 0.5 / (trace + 1.0).sqrt();
+            Quat::new((r[2][1] - r[1][2]) * s, (r[0][2] - r[2][0]) * s, (r[1][0] - r[0][1]) * s, 0.25 / s)
+        } else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+            let s = 2.0 * (1.0 + r[0][0] - r[1][1] - r[2][2]).sqrt();
+            Quat::new(0.25 * s, (r[0][1] + r[1][0]) / s, (r[0][2] + r[2][0]) / s, (r[2][1] - r[1][2]) / s)
+        } else if r[1][1] > r[2][2] {
+            let s = 2.0 * (1.0 + r[1][1] - r[0][0] - r[2][2]).sqrt();
+            Quat::new((r[0][1] + r[1][0]) / s, 0.25 * s, (r[1][2] + r[2][1]) / s, (r[0][2] - r[2][0]) / s)
+        } else {
+            let s = 2.0 * (1.0 + r[2][2] - r[0][0] - r[1][1]).sqrt();
+            Quat::new((r[0][2] + r[2][0]) / s, (r[1][2] + r[2][1]) / s, 0.25 * s, (r[1][0] - r[0][1]) / s)
+        };
+        q.normalize()
+    }
+
+    pub fn dot(&self, other: Quat) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(*self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Quat {
+        let len = self.length();
+        if len > 0.0 {
+            self.scale(1.0 / len)
+        } else {
+            Quat::IDENTITY
+        }
+    }
+
+    // The inverse rotation of a unit quaternion.
+    pub fn conjugate(&self) -> Quat {
+        Quat { x: -self.x, y: -self.y, z: -self.z, w: self.w }
+    }
+
+    pub fn rotate(&self, v: Vec3) -> Vec3 {
+        // v + 2 q.xyz x (q.xyz x v + w v)
+        let u = Vec3::new(self.x, self.y, self.z);
+        let t = u.cross(v) * 2.0;
+        v + t * self.w + u.cross(t)
+    }
+
+    // The rotated x, y and z axes: right, up and look for a camera.
+    pub fn to_axes(&self) -> (Vec3, Vec3, Vec3) {
+        (self.rotate(Vec3::X), self.rotate(Vec3::Y), self.rotate(Vec3::Z))
+    }
+
+    // Spherical linear interpolation along the shorter arc (D3DXQuaternionSlerp).
+    pub fn slerp(&self, other: Quat, t: f32) -> Quat {
+        let other = if self.dot(other) < 0.0 { other.scale(-1.0) } else { other };
+        self.slerp_no_invert(other, t)
+    }
+
+    // Spherical quadrangle interpolation from q1 to q2 with the control points
+    // from squad_control (D3DXQuaternionSquad): a C1 curve through a sequence of
+    // rotations, as a Catmull-Rom spline is through points.
+    pub fn squad(q1: Quat, a: Quat, b: Quat, q2: Quat, t: f32) -> Quat {
+        let c = q1.slerp_no_invert(q2, t);
+        let d = a.slerp_no_invert(b, t);
+        c.slerp_no_invert(d, 2.0 * t * (1.0 - t))
+    }
+
+    // Control point of q between its neighbours for squad.  The neighbours have
+    // to be on q's side of the 4D sphere (dot >= 0), see align_to.
+    pub fn squad_control(prev: Quat, q: Quat, next: Quat) -> Quat {
+        let inv = q.conjugate();
+        let a = hamilton(inv, next).ln();
+        let b = hamilton(inv, prev).ln();
+        let sum = Quat::new(a.x + b.x, a.y + b.y, a.z + b.z, 0.0);
+        hamilton(q, sum.scale(-0.25).exp())
+    }
+
+    // This quaternion or its negation, whichever is closer to 'other'.  Both
+    // are the same rotation.
+    pub fn align_to(&self, other: Quat) -> Quat {
+        if self.dot(other) < 0.0 { self.scale(-1.0) } else { *self }
+    }
+
+    // Logarithm of a unit quaternion, a pure quaternion (D3DXQuaternionLn).
+    pub fn ln(&self) -> Quat {
+        let w = self.w.clamp(-1.0, 1.0);
+        let theta = w.acos();
+        let s = theta.sin();
+        let k = if s.abs() > 1e-6 { theta / s } else { 1.0 };
+        Quat::new(self.x * k, self.y * k, self.z * k, 0.0)
+    }
+
+    // Exponential of a pure quaternion, a unit quaternion (D3DXQuaternionExp).
+    pub fn exp(&self) -> Quat {
+        let theta = (self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        let (s, c) = theta.sin_cos();
+        let k = if theta > 1e-6 { s / theta } else { 1.0 };
+        Quat::new(self.x * k, self.y * k, self.z * k, c)
+    }
+
+    fn scale(&self, s: f32) -> Quat {
+        Quat { x: self.x * s, y: self.y * s, z: self.z * s, w: self.w * s }
+    }
+
+    fn slerp_no_invert(&self, other: Quat, t: f32) -> Quat {
+        let cos = self.dot(other).clamp(-1.0, 1.0);
+        let (k0, k1) = if cos.abs() < 0.9999 {
+            let theta = cos.acos();
+            let s = theta.sin();
+            (((1.0 - t) * theta).sin() / s, (t * theta).sin() / s)
+        } else {
+            // Nearly the same rotation: lerp, which also avoids dividing by ~0.
+            (1.0 - t, t)
+        };
+
+        Quat {
+            x: self.x * k0 + other.x * k1,
+            y: self.y * k0 + other.y * k1,
+            z: self.z * k0 + other.z * k1,
+            w: self.w * k0 + other.w * k1,
+        }.normalize()
+    }
+}
+
+impl Mul for Quat {
+    type Output = Quat;
+
+    fn mul(self, other: Quat) -> Quat {
+        hamilton(other, self)
+    }
+}
+
+// The Hamilton product a b, which rotates by b first.
+fn hamilton(a: Quat, b: Quat) -> Quat {
+    Quat {
+        x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+        y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+        z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+    }
+}
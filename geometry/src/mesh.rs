@@ -0,0 +1,255 @@
+// CPU-side triangle mesh: a vertex array, a triangle list index array and one
+// attribute (subset) id per face, mirroring what an ID3DXMesh keeps in its vertex,
+// index and attribute buffers.
+
+use crate::math::*;
+
+// Vertex types understood by the mesh routines.  Layouts match the ones declared
+// in common::vertex so they can be copied straight into D3D vertex buffers.
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct VertexPN {
+    pub pos: Vec3,
+    pub normal: Vec3,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct VertexPNT {
+    pub pos: Vec3,
+    pub normal: Vec3,
+    pub tex0: Vec2,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct NMapVertex {
+    pub pos: Vec3,
+    pub tangent: Vec3,
+    pub binormal: Vec3,
+    pub normal: Vec3,
+    pub tex0: Vec2,
+}
+
+// Access to the vertex components the mesh algorithms care about.  Components a
+// vertex type does not have read as None and writes to them are ignored.
+pub trait Vertex: Copy + Default {
+    fn pos(&self) -> Vec3;
+    fn set_pos(&mut self, pos: Vec3);
+
+    fn normal(&self) -> Option<Vec3> {
+        None
+    }
+
+    fn set_normal(&mut self, _normal: Vec3) {}
+
+    fn tex0(&self) -> Option<Vec2> {
+        None
+    }
+
+    fn set_tex0(&mut self, _tex0: Vec2) {}
+
+    // Interpolates every known component; normals are renormalized.
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mut res = *self;
+        res.set_pos(self.pos().lerp(other.pos(), t));
+
+        if let (Some(n0), Some(n1)) = (self.normal(), other.normal()) {
+            res.set_normal(n0.lerp(n1, t).normalize());
+        }
+
+        if let (Some(t0), Some(t1)) = (self.tex0(), other.tex0()) {
+            res.set_tex0(t0.lerp(t1, t));
+        }
+
+        res
+    }
+}
+
+impl Vertex for Vec3 {
+    fn pos(&self) -> Vec3 {
+        *self
+    }
+
+    fn set_pos(&mut self, pos: Vec3) {
+        *self = pos;
+    }
+}
+
+impl Vertex for VertexPN {
+    fn pos(&self) -> Vec3 {
+        self.pos
+    }
+
+    fn set_pos(&mut self, pos: Vec3) {
+        self.pos = pos;
+    }
+
+    fn normal(&self) -> Option<Vec3> {
+        Some(self.normal)
+    }
+
+    fn set_normal(&mut self, normal: Vec3) {
+        self.normal = normal;
+    }
+}
+
+impl Vertex for VertexPNT {
+    fn pos(&self) -> Vec3 {
+        self.pos
+    }
+
+    fn set_pos(&mut self, pos: Vec3) {
+        self.pos = pos;
+    }
+
+    fn normal(&self) -> Option<Vec3> {
+        Some(self.normal)
+    }
+
+    fn set_normal(&mut self, normal: Vec3) {
+        self.normal = normal;
+    }
+
+    fn tex0(&self) -> Option<Vec2> {
+        Some(self.tex0)
+    }
+
+    fn set_tex0(&mut self, tex0: Vec2) {
+        self.tex0 = tex0;
+    }
+}
+
+impl Vertex for NMapVertex {
+    fn pos(&self) -> Vec3 {
+        self.pos
+    }
+
+    fn set_pos(&mut self, pos: Vec3) {
+        self.pos = pos;
+    }
+
+    fn normal(&self) -> Option<Vec3> {
+        Some(self.normal)
+    }
+
+    fn set_normal(&mut self, normal: Vec3) {
+        self.normal = normal;
+    }
+
+    fn tex0(&self) -> Option<Vec2> {
+        Some(self.tex0)
+    }
+
+    fn set_tex0(&mut self, tex0: Vec2) {
+        self.tex0 = tex0;
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Mesh<V> {
+    pub vertices: Vec<V>,
+    // Triangle list, three indices per face.
+    pub indices: Vec<u32>,
+    // Subset id of each face.
+    pub attributes: Vec<u32>,
+}
+
+impl<V: Vertex> Mesh<V> {
+    // Builds a mesh with every face in subset 0.
+    pub fn new(vertices: Vec<V>, indices: Vec<u32>) -> Mesh<V> {
+        let attributes = vec![0; indices.len() / 3];
+        Mesh { vertices, indices, attributes }
+    }
+
+    pub fn with_attributes(vertices: Vec<V>, indices: Vec<u32>, attributes: Vec<u32>) -> Mesh<V> {
+        assert_eq!(indices.len() / 3, attributes.len(), "one attribute per face expected");
+        Mesh { vertices, indices, attributes }
+    }
+
+    pub fn num_vertices(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn face(&self, f: usize) -> [u32; 3] {
+        [self.indices[f * 3], self.indices[f * 3 + 1], self.indices[f * 3 + 2]]
+    }
+
+    pub fn face_positions(&self, f: usize) -> [Vec3; 3] {
+        let [i0, i1, i2] = self.face(f);
+        [self.vertices[i0 as usize].pos(),
+         self.vertices[i1 as usize].pos(),
+         self.vertices[i2 as usize].pos()]
+    }
+
+    // Unnormalized face normal; its length is twice the face area.
+    pub fn face_normal(&self, f: usize) -> Vec3 {
+        let [p0, p1, p2] = self.face_positions(f);
+        (p1 - p0).cross(p2 - p0)
+    }
+
+    // Number of subsets, i.e. one past the largest attribute id.
+    pub fn num_subsets(&self) -> u32 {
+        self.attributes.iter().max().map_or(0, |a| a + 1)
+    }
+
+    // Appends another mesh, offsetting its indices.
+    pub fn append(&mut self, other: &Mesh<V>) {
+        let base = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices.extend(other.indices.iter().map(|i| i + base));
+        self.attributes.extend_from_slice(&other.attributes);
+    }
+
+    // Applies a transform to every position, and to normals through the
+    // inverse-transpose so non-uniform scales keep them perpendicular.
+    pub fn transform(&mut self, m: &Mat4) {
+        let normal_m = m.inverse().unwrap_or(Mat4::IDENTITY).transpose();
+        for v in &mut self.vertices {
+            v.set_pos(m.transform_coord(v.pos()));
+            if let Some(n) = v.normal() {
+                v.set_normal(normal_m.transform_normal(n).normalize());
+            }
+        }
+    }
+
+    // Recomputes smooth normals over shared vertices.
+    pub fn compute_normals(&mut self) {
+        let normals = vertex_normals(&self.vertices, &self.indices);
+        for (v, n) in self.vertices.iter_mut().zip(normals) {
+            v.set_normal(n);
+        }
+    }
+}
+
+// Angle-weighted vertex normals, the same weighting D3DXComputeNormals uses.
+pub fn vertex_normals<V: Vertex>(vertices: &[V], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; vertices.len()];
+
+    for tri in indices.chunks_exact(3) {
+        let p = [vertices[tri[0] as usize].pos(),
+                 vertices[tri[1] as usize].pos(),
+                 vertices[tri[2] as usize].pos()];
+
+        let n = (p[1] - p[0]).cross(p[2] - p[0]).normalize();
+
+        for k in 0..3 {
+            let angle = corner_angle(p[k], p[(k + 1) % 3], p[(k + 2) % 3]);
+            normals[tri[k] as usize] += n * angle;
+        }
+    }
+
+    normals.iter().map(|n| n.normalize()).collect()
+}
+
+// Interior angle of a triangle at corner 'p' with neighbours 'a' and 'b'.
+pub fn corner_angle(p: Vec3, a: Vec3, b: Vec3) -> f32 {
+    let e0 = (a - p).normalize();
+    let e1 = (b - p).normalize();
+    e0.dot(e1).clamp(-1.0, 1.0).acos()
+}
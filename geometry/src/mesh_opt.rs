@@ -0,0 +1,611 @@
+// Adjacency generation and mesh optimization, the CPU-side counterparts of
+// ID3DXBaseMesh::GenerateAdjacency and ID3DXMesh::Optimize with the
+// D3DXMESHOPT_COMPACT, D3DXMESHOPT_ATTRSORT and D3DXMESHOPT_VERTEXCACHE flags.
+//
+// Remaps produced here always map a new index to the old index it came from,
+// i.e. new_vertices[i] = old_vertices[vertex_remap[i]].
+
+use std::collections::HashMap;
+use std::fmt;
+use crate::mesh::*;
+
+// Marks a face edge with no neighbour, same value D3DX uses.
+pub const NO_ADJACENCY: u32 = u32::MAX;
+
+// Cache size used for the statistics when none is given.  Matches the cache
+// size D3DX assumes for D3DXMESHOPT_VERTEXCACHE.
+pub const DEFAULT_CACHE_SIZE: usize = 16;
+
+//===============================================================
+// Point representatives and adjacency.
+
+// For every vertex, the index of the lowest numbered vertex it is joined to by
+// a chain of vertices each within 'epsilon' of the next (itself when there is
+// none).  Vertices that are split only because of differing normals or
+// tex-coords end up sharing a representative.
+pub fn generate_point_reps<V: Vertex>(vertices: &[V], epsilon: f32) -> Vec<u32> {
+    // Union-find: every set's root is its lowest numbered vertex.
+    let mut point_reps: Vec<u32> = (0..vertices.len() as u32).collect();
+
+    // Sweep along x so only nearby candidates are compared.
+    let mut order: Vec<u32> = (0..vertices.len() as u32).collect();
+    order.sort_by(|a, b| {
+        let pa = vertices[*a as usize].pos();
+        let pb = vertices[*b as usize].pos();
+        pa.x.total_cmp(&pb.x).then(a.cmp(b))
+    });
+
+    let eps_sq = epsilon * epsilon;
+
+    for i in 0..order.len() {
+        let a = order[i];
+        let pa = vertices[a as usize].pos();
+
+        for &b in &order[i + 1..] {
+            let pb = vertices[b as usize].pos();
+            if pb.x - pa.x > epsilon {
+                break;
+            }
+
+            if (pb - pa).length_sq() <= eps_sq {
+                let root_a = find_rep(&mut point_reps, a);
+                let root_b = find_rep(&mut point_reps, b);
+                let (lo, hi) = if root_a < root_b { (root_a, root_b) } else { (root_b, root_a) };
+                point_reps[hi as usize] = lo;
+            }
+        }
+    }
+
+    // Point every entry straight at its root.
+    for i in 0..point_reps.len() as u32 {
+        find_rep(&mut point_reps, i);
+    }
+
+    point_reps
+}
+
+// Root of the vertex's set, pointing every vertex on the way at it.
+fn find_rep(point_reps: &mut [u32], vertex: u32) -> u32 {
+    let mut root = vertex;
+    while point_reps[root as usize] != root {
+        root = point_reps[root as usize];
+    }
+
+    let mut v = vertex;
+    while point_reps[v as usize] != root {
+        let next = point_reps[v as usize];
+        point_reps[v as usize] = root;
+        v = next;
+    }
+    root
+}
+
+// Three entries per face: the face sharing edge (v0, v1), (v1, v2) and (v2, v0),
+// or NO_ADJACENCY.  Vertices within 'epsilon' are treated as the same point.
+// Edges shared by more than two faces get paired first come, first served.
+pub fn generate_adjacency<V: Vertex>(mesh: &Mesh<V>, epsilon: f32) -> Vec<u32> {
+    let point_reps = generate_point_reps(&mesh.vertices, epsilon);
+    adjacency_from_point_reps(&mesh.indices, &point_reps)
+}
+
+pub fn adjacency_from_point_reps(indices: &[u32], point_reps: &[u32]) -> Vec<u32> {
+    let num_faces = indices.len() / 3;
+    let mut adjacency = vec![NO_ADJACENCY; num_faces * 3];
+
+    // Directed edge (from, to) -> face edges (face * 3 + edge) still unpaired.
+    let mut open_edges: HashMap<(u32, u32), Vec<u32>> = HashMap::new();
+
+    for f in 0..num_faces {
+        for e in 0..3 {
+            let a = point_reps[indices[f * 3 + e] as usize];
+            let b = point_reps[indices[f * 3 + (e + 1) % 3] as usize];
+            if a == b {
+                continue; // Degenerate edge.
+            }
+
+            // A consistently wound neighbour walks the edge the other way.
+            let twin = open_edges.get_mut(&(b, a)).and_then(|list| {
+                let pos = list.iter().position(|fe| (*fe / 3) as usize != f)?;
+                Some(list.swap_remove(pos))
+            });
+
+            match twin {
+                Some(fe) => {
+                    adjacency[f * 3 + e] = fe / 3;
+                    adjacency[fe as usize] = f as u32;
+                }
+                None => {
+                    open_edges.entry((a, b)).or_default().push((f * 3 + e) as u32);
+                }
+            }
+        }
+    }
+
+    adjacency
+}
+
+//===============================================================
+// Statistics.
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MeshStats {
+    pub num_vertices: usize,
+    pub num_faces: usize,
+    pub num_subsets: usize,
+    pub unused_vertices: usize,
+    // Vertices transformed per triangle with a FIFO post-transform cache.  3.0 is
+    // the worst case, around 0.5-0.7 is typical for well ordered meshes.
+    pub acmr: f32,
+    // Vertices transformed per referenced vertex.  1.0 is optimal.
+    pub atvr: f32,
+    // Number of vertex index jumps over 'cache_size' vertices, a rough measure of
+    // how scattered vertex fetches are.
+    pub fetch_jumps: usize,
+}
+
+pub fn mesh_stats<V>(mesh: &Mesh<V>, cache_size: usize) -> MeshStats {
+    let num_vertices = mesh.vertices.len();
+    let num_faces = mesh.indices.len() / 3;
+
+    let mut used = vec![false; num_vertices];
+    for i in &mesh.indices {
+        used[*i as usize] = true;
+    }
+    let num_used = used.iter().filter(|u| **u).count();
+
+    let mut subsets: Vec<u32> = mesh.attributes.clone();
+    subsets.sort_unstable();
+    subsets.dedup();
+
+    let misses = simulate_fifo_cache(&mesh.indices, num_vertices, cache_size);
+
+    let mut fetch_jumps = 0;
+    for pair in mesh.indices.windows(2) {
+        if (pair[1] as i64 - pair[0] as i64).unsigned_abs() > cache_size as u64 {
+            fetch_jumps += 1;
+        }
+    }
+
+    MeshStats {
+        num_vertices,
+        num_faces,
+        num_subsets: subsets.len(),
+        unused_vertices: num_vertices - num_used,
+        acmr: if num_faces > 0 { misses as f32 / num_faces as f32 } else { 0.0 },
+        atvr: if num_used > 0 { misses as f32 / num_used as f32 } else { 0.0 },
+        fetch_jumps,
+    }
+}
+
+// Number of cache misses a FIFO post-transform cache of 'cache_size' entries
+// has when drawing the indices in order.
+pub fn simulate_fifo_cache(indices: &[u32], num_vertices: usize, cache_size: usize) -> usize {
+    // Time stamp of when each vertex entered the cache.
+    let mut entered: Vec<usize> = vec![usize::MAX; num_vertices];
+    let mut time = 0usize;
+    let mut misses = 0;
+
+    for i in indices {
+        let i = *i as usize;
+        let in_cache = entered[i] != usize::MAX && time - entered[i] < cache_size;
+        if !in_cache {
+            entered[i] = time;
+            time += 1;
+            misses += 1;
+        }
+    }
+
+    misses
+}
+
+impl fmt::Display for MeshStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} verts ({} unused), {} faces, {} subsets, ACMR {:.3}, ATVR {:.3}, {} fetch jumps",
+               self.num_vertices, self.unused_vertices, self.num_faces, self.num_subsets,
+               self.acmr, self.atvr, self.fetch_jumps)
+    }
+}
+
+//===============================================================
+// Attribute sort.
+
+// A contiguous run of faces using the same attribute, like D3DXATTRIBUTERANGE.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct AttributeRange {
+    pub attrib_id: u32,
+    pub face_start: u32,
+    pub face_count: u32,
+    pub vertex_start: u32,
+    pub vertex_count: u32,
+}
+
+// Reorders faces so each subset is contiguous, keeping the relative order of
+// faces inside a subset.  Returns the face remap.
+pub fn attribute_sort<V>(mesh: &mut Mesh<V>) -> Vec<u32> {
+    let mut face_remap: Vec<u32> = (0..mesh.attributes.len() as u32).collect();
+    face_remap.sort_by_key(|f| mesh.attributes[*f as usize]);
+    apply_face_remap(mesh, &face_remap);
+    face_remap
+}
+
+// Attribute table of a mesh whose faces are sorted by attribute.
+pub fn attribute_table<V>(mesh: &Mesh<V>) -> Vec<AttributeRange> {
+    let mut table: Vec<AttributeRange> = Vec::new();
+
+    for (f, attrib_id) in mesh.attributes.iter().enumerate() {
+        let face = &mesh.indices[f * 3..f * 3 + 3];
+        let lo = *face.iter().min().unwrap();
+        let hi = *face.iter().max().unwrap();
+
+        match table.last_mut() {
+            Some(range) if range.attrib_id == *attrib_id => {
+                let end = (range.vertex_start + range.vertex_count).max(hi + 1);
+                range.vertex_start = range.vertex_start.min(lo);
+                range.vertex_count = end - range.vertex_start;
+                range.face_count += 1;
+            }
+            _ => table.push(AttributeRange {
+                attrib_id: *attrib_id,
+                face_start: f as u32,
+                face_count: 1,
+                vertex_start: lo,
+                vertex_count: hi + 1 - lo,
+            }),
+        }
+    }
+
+    table
+}
+
+//===============================================================
+// Compaction and vertex fetch order.
+
+// Removes vertices no face references.  Returns the vertex remap.
+pub fn compact<V: Copy>(mesh: &mut Mesh<V>) -> Vec<u32> {
+    let mut used = vec![false; mesh.vertices.len()];
+    for i in &mesh.indices {
+        used[*i as usize] = true;
+    }
+
+    let vertex_remap: Vec<u32> = (0..mesh.vertices.len() as u32).filter(|i| used[*i as usize]).collect();
+    apply_vertex_remap(mesh, &vertex_remap);
+    vertex_remap
+}
+
+// Reorders vertices in the order faces first reference them, so vertex fetches
+// walk memory mostly forward.  Unreferenced vertices are moved to the end.
+pub fn optimize_vertex_fetch<V: Copy>(mesh: &mut Mesh<V>) -> Vec<u32> {
+    let mut seen = vec![false; mesh.vertices.len()];
+    let mut vertex_remap: Vec<u32> = Vec::with_capacity(mesh.vertices.len());
+
+    for i in &mesh.indices {
+        if !seen[*i as usize] {
+            seen[*i as usize] = true;
+            vertex_remap.push(*i);
+        }
+    }
+
+    for (i, seen) in seen.iter().enumerate() {
+        if !seen {
+            vertex_remap.push(i as u32);
+        }
+    }
+
+    apply_vertex_remap(mesh, &vertex_remap);
+    vertex_remap
+}
+
+//===============================================================
+// Vertex cache optimization (Tom Forsyth, "Linear-Speed Vertex Cache
+// Optimisation").  Faces are only reordered within runs of equal attribute so
+// an attribute sort is preserved.
+
+const FORSYTH_CACHE_SIZE: usize = 32;
+const CACHE_DECAY_POWER: f32 = 1.5;
+const LAST_TRI_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+fn forsyth_vertex_score(cache_pos: Option<usize>, remaining_tris: u32) -> f32 {
+    if remaining_tris == 0 {
+        // No triangle needs this vertex.
+        return -1.0;
+    }
+
+    let mut score = match cache_pos {
+        None => 0.0,
+        // The vertices of the last triangle get a fixed score so the next triangle
+        // does not reuse them in a way that skips the others.
+        Some(p) if p < 3 => LAST_TRI_SCORE,
+        Some(p) => {
+            let scaler = 1.0 / (FORSYTH_CACHE_SIZE - 3) as f32;
+            (1.0 - (p - 3) as f32 * scaler).powf(CACHE_DECAY_POWER)
+        }
+    };
+
+    // Favour vertices with few triangles left so lone triangles are not stranded.
+    score += VALENCE_BOOST_SCALE * (remaining_tris as f32).powf(-VALENCE_BOOST_POWER);
+    score
+}
+
+// Returns the face remap.
+pub fn optimize_vertex_cache<V>(mesh: &mut Mesh<V>) -> Vec<u32> {
+    let num_faces = mesh.indices.len() / 3;
+    let mut face_remap: Vec<u32> = Vec::with_capacity(num_faces);
+
+    let mut start = 0;
+    while start < num_faces {
+        let mut end = start + 1;
+        while end < num_faces && mesh.attributes[end] == mesh.attributes[start] {
+            end += 1;
+        }
+
+        face_remap.extend(forsyth_order(&mesh.indices, mesh.vertices.len(), start, end));
+        start = end;
+    }
+
+    apply_face_remap(mesh, &face_remap);
+    face_remap
+}
+
+// Optimized order of faces [first, last).
+fn forsyth_order(indices: &[u32], num_vertices: usize, first: usize, last: usize) -> Vec<u32> {
+    let num_faces = last - first;
+
+    // Per vertex: faces still to be drawn that use it.
+    let mut vertex_faces: Vec<Vec<u32>> = vec![Vec::new(); num_vertices];
+    for f in first..last {
+        for k in 0..3 {
+            vertex_faces[indices[f * 3 + k] as usize].push(f as u32);
+        }
+    }
+
+    let mut remaining: Vec<u32> = vertex_faces.iter().map(|faces| faces.len() as u32).collect();
+    let mut cache_pos: Vec<Option<usize>> = vec![None; num_vertices];
+    let mut vertex_score: Vec<f32> = (0..num_vertices).map(|v| forsyth_vertex_score(None, remaining[v])).collect();
+
+    let face_score = |f: usize, vertex_score: &[f32]| -> f32 {
+        (0..3).map(|k| vertex_score[indices[f * 3 + k] as usize]).sum()
+    };
+
+    let mut tri_score: Vec<f32> = (first..last).map(|f| face_score(f, &vertex_score)).collect();
+    let mut added = vec![false; num_faces];
+
+    let mut cache: Vec<u32> = Vec::with_capacity(FORSYTH_CACHE_SIZE + 3);
+    let mut order: Vec<u32> = Vec::with_capacity(num_faces);
+
+    let mut best: Option<usize> = None;
+    let mut scan_from = 0;
+
+    while order.len() < num_faces {
+        // When no candidate came out of the cache, fall back to the best
+        // remaining face overall.
+        let f = match best {
+            Some(f) => f,
+            None => {
+                while added[scan_from] {
+                    scan_from += 1;
+                }
+                let mut f = scan_from;
+                for g in scan_from..num_faces {
+                    if !added[g] && tri_score[g] > tri_score[f] {
+                        f = g;
+                    }
+                }
+                f
+            }
+        };
+
+        added[f] = true;
+        order.push((first + f) as u32);
+
+        // Move the face's vertices to the front of the LRU cache.
+        let tri = &indices[(first + f) * 3..(first + f) * 3 + 3];
+        for v in tri {
+            remaining[*v as usize] -= 1;
+            cache.retain(|c| c != v);
+        }
+        for v in tri.iter().rev() {
+            cache.insert(0, *v);
+        }
+
+        // Vertices pushed out of the cache lose their cache score.
+        let evicted: Vec<u32> = if cache.len() > FORSYTH_CACHE_SIZE {
+            cache.split_off(FORSYTH_CACHE_SIZE)
+        } else {
+            Vec::new()
+        };
+
+        for v in &evicted {
+            cache_pos[*v as usize] = None;
+        }
+        for (p, v) in cache.iter().enumerate() {
+            cache_pos[*v as usize] = Some(p);
+        }
+
+        // Rescore the touched vertices and their faces, tracking the best one.
+        best = None;
+        let mut best_score = -1.0;
+
+        for v in cache.iter().chain(evicted.iter()) {
+            let v = *v as usize;
+            vertex_score[v] = forsyth_vertex_score(cache_pos[v], remaining[v]);
+        }
+
+        for v in cache.iter().chain(evicted.iter()) {
+            for g in &vertex_faces[*v as usize] {
+                let g = *g as usize - first;
+                if added[g] {
+                    continue;
+                }
+
+                tri_score[g] = face_score(first + g, &vertex_score);
+                if tri_score[g] > best_score {
+                    best_score = tri_score[g];
+                    best = Some(g);
+                }
+            }
+        }
+    }
+
+    order
+}
+
+//===============================================================
+// Full optimization pass with before/after statistics for each step.
+
+#[derive(Copy, Clone, Debug)]
+pub struct OptimizeOptions {
+    pub compact: bool,
+    pub attribute_sort: bool,
+    pub vertex_cache: bool,
+    pub vertex_fetch: bool,
+    // Cache size used to report ACMR/ATVR.
+    pub cache_size: usize,
+}
+
+impl Default for OptimizeOptions {
+    // Same steps as D3DXMESHOPT_COMPACT | D3DXMESHOPT_ATTRSORT | D3DXMESHOPT_VERTEXCACHE,
+    // plus vertex fetch reordering.
+    fn default() -> Self {
+        OptimizeOptions {
+            compact: true,
+            attribute_sort: true,
+            vertex_cache: true,
+            vertex_fetch: true,
+            cache_size: DEFAULT_CACHE_SIZE,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OptimizeStep {
+    Compact,
+    AttributeSort,
+    VertexCache,
+    VertexFetch,
+}
+
+#[derive(Clone, Debug)]
+pub struct OptimizeReport {
+    pub steps: Vec<(OptimizeStep, MeshStats, MeshStats)>,
+    pub face_remap: Vec<u32>,
+    pub vertex_remap: Vec<u32>,
+    pub attribute_table: Vec<AttributeRange>,
+}
+
+impl fmt::Display for OptimizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (step, before, after) in &self.steps {
+            writeln!(f, "{:?}:", step)?;
+            writeln!(f, "  before: {}", before)?;
+            writeln!(f, "  after:  {}", after)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn optimize_mesh<V: Copy>(mesh: &mut Mesh<V>, options: &OptimizeOptions) -> OptimizeReport {
+    let mut report = OptimizeReport {
+        steps: Vec::new(),
+        face_remap: (0..(mesh.indices.len() / 3) as u32).collect(),
+        vertex_remap: (0..mesh.vertices.len() as u32).collect(),
+        attribute_table: Vec::new(),
+    };
+
+    let run = |step: OptimizeStep, mesh: &mut Mesh<V>, report: &mut OptimizeReport| {
+        let before = mesh_stats(mesh, options.cache_size);
+
+        match step {
+            OptimizeStep::Compact => {
+                let remap = compact(mesh);
+                report.vertex_remap = compose_remaps(&report.vertex_remap, &remap);
+            }
+            OptimizeStep::AttributeSort => {
+                let remap = attribute_sort(mesh);
+                report.face_remap = compose_remaps(&report.face_remap, &remap);
+            }
+            OptimizeStep::VertexCache => {
+                let remap = optimize_vertex_cache(mesh);
+                report.face_remap = compose_remaps(&report.face_remap, &remap);
+            }
+            OptimizeStep::VertexFetch => {
+                let remap = optimize_vertex_fetch(mesh);
+                report.vertex_remap = compose_remaps(&report.vertex_remap, &remap);
+            }
+        }
+
+        let after = mesh_stats(mesh, options.cache_size);
+        report.steps.push((step, before, after));
+    };
+
+    if options.compact {
+        run(OptimizeStep::Compact, mesh, &mut report);
+    }
+    if options.attribute_sort {
+        run(OptimizeStep::AttributeSort, mesh, &mut report);
+    }
+    if options.vertex_cache {
+        run(OptimizeStep::VertexCache, mesh, &mut report);
+    }
+    if options.vertex_fetch {
+        run(OptimizeStep::VertexFetch, mesh, &mut report);
+    }
+
+    report.attribute_table = attribute_table(mesh);
+    report
+}
+
+//===============================================================
+// Remap helpers.
+
+// new_faces[i] = old_faces[face_remap[i]].
+pub fn apply_face_remap<V>(mesh: &mut Mesh<V>, face_remap: &[u32]) {
+    let mut indices = Vec::with_capacity(face_remap.len() * 3);
+    let mut attributes = Vec::with_capacity(face_remap.len());
+
+    for f in face_remap {
+        let f = *f as usize;
+        indices.extend_from_slice(&mesh.indices[f * 3..f * 3 + 3]);
+        attributes.push(mesh.attributes[f]);
+    }
+
+    mesh.indices = indices;
+    mesh.attributes = attributes;
+}
+
+// new_vertices[i] = old_vertices[vertex_remap[i]].  Indices to vertices that are
+// not in the remap are left dangling, so only drop vertices no face uses.
+pub fn apply_vertex_remap<V: Copy>(mesh: &mut Mesh<V>, vertex_remap: &[u32]) {
+    let mut old_to_new = vec![u32::MAX; mesh.vertices.len()];
+    for (new, old) in vertex_remap.iter().enumerate() {
+        old_to_new[*old as usize] = new as u32;
+    }
+
+    mesh.vertices = vertex_remap.iter().map(|old| mesh.vertices[*old as usize]).collect();
+    for i in &mut mesh.indices {
+        *i = old_to_new[*i as usize];
+    }
+}
+
+// Combines two new-to-old remaps applied one after the other.
+pub fn compose_remaps(first: &[u32], second: &[u32]) -> Vec<u32> {
+    second.iter().map(|i| first[*i as usize]).collect()
+}
+
+// Adjacency of a mesh after its faces were reordered by 'face_remap'.
+pub fn remap_adjacency(adjacency: &[u32], face_remap: &[u32]) -> Vec<u32> {
+    let mut old_to_new = vec![NO_ADJACENCY; adjacency.len() / 3];
+    for (new, old) in face_remap.iter().enumerate() {
+        old_to_new[*old as usize] = new as u32;
+    }
+
+    let mut res = Vec::with_capacity(face_remap.len() * 3);
+    for old in face_remap {
+        for e in 0..3 {
+            let n = adjacency[*old as usize * 3 + e];
+            res.push(if n == NO_ADJACENCY { NO_ADJACENCY } else { old_to_new[n as usize] });
+        }
+    }
+    res
+}
@@ -0,0 +1,372 @@
+// Screen to world picking: rays and rectangle selection frustums from viewport
+// coordinates, and queries for the scene objects they pick.
+//
+// Everything goes through the inverse of the view-projection matrix, so any
+// viewport and any projection, perspective or orthographic, works.  Queries
+// test each object's bounding volume first and only then its triangles, if it
+// has any.
+
+use crate::bounds::*;
+use crate::bvh::*;
+use crate::camera::*;
+use crate::frustum::*;
+use crate::math::*;
+use crate::mesh::*;
+use crate::ray::*;
+
+//===============================================================
+// Viewports.
+
+// Area of the render target the projection maps to, like a D3DVIEWPORT9: pixel
+// coordinates grow right and down from the target's top left corner, and depth
+// goes from min_z at the near plane to max_z at the far plane.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub min_z: f32,
+    pub max_z: f32,
+}
+
+impl Viewport {
+    // With the full depth range.
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Viewport {
+        Viewport { x, y, width, height, min_z: 0.0, max_z: 1.0 }
+    }
+
+    // From viewport coordinates (pixels and depth) to normalized device
+    // coordinates, x and y in [-1, 1] with y up and z in [0, 1].
+    pub fn to_ndc(&self, p: Vec3) -> Vec3 {
+        Vec3::new(
+             2.0 * (p.x - self.x) / self.width - 1.0,
+            -2.0 * (p.y - self.y) / self.height + 1.0,
+            (p.z - self.min_z) / (self.max_z - self.min_z))
+    }
+
+    pub fn from_ndc(&self, p: Vec3) -> Vec3 {
+        Vec3::new(
+            self.x + (p.x + 1.0) * 0.5 * self.width,
+            self.y + (1.0 - p.y) * 0.5 * self.height,
+            self.min_z + p.z * (self.max_z - self.min_z))
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+//===============================================================
+// Rays and selection frustums.
+
+// Maps between world space and the viewport of one view.  Build one per frame,
+// as the camera moves.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Picker {
+    viewport: Viewport,
+    view_proj: Mat4,
+    inv_view_proj: Mat4,
+}
+
+impl Picker {
+    // None if the view-projection matrix cannot be inverted.
+    pub fn new(viewport: Viewport, view: &Mat4, proj: &Mat4) -> Option<Picker> {
+        let view_proj = *view * *proj;
+        let inv_view_proj = view_proj.inverse()?;
+        Some(Picker { viewport, view_proj, inv_view_proj })
+    }
+
+    pub fn from_camera(camera: &Camera, viewport: Viewport) -> Option<Picker> {
+        Picker::new(viewport, camera.get_view(), camera.get_proj())
+    }
+
+    pub fn get_viewport(&self) -> &Viewport {
+        &self.viewport
+    }
+
+    pub fn get_view_proj(&self) -> &Mat4 {
+        &self.view_proj
+    }
+
+    // World space point to viewport coordinates.  Replaces D3DXVec3Project.
+    pub fn project(&self, p: Vec3) -> Vec3 {
+        self.viewport.from_ndc(self.view_proj.transform_coord(p))
+    }
+
+    // Viewport coordinates to the world space point there.  Replaces
+    // D3DXVec3Unproject.
+    pub fn unproject(&self, p: Vec3) -> Vec3 {
+        self.inv_view_proj.transform_coord(self.viewport.to_ndc(p))
+    }
+
+    // Ray through the pixel point (x, y), from the near plane towards the far
+    // one with a unit direction, so hit distances are world distances from the
+    // near plane.  For orthographic views the rays are parallel.
+    pub fn get_ray(&self, x: f32, y: f32) -> Ray {
+        let near = self.unproject(Vec3::new(x, y, self.viewport.min_z));
+        let far = self.unproject(Vec3::new(x, y, self.viewport.max_z));
+        Ray::new(near, (far - near).normalize())
+    }
+
+    // Frustum of the rectangle between the pixel points (x0, y0) and (x1, y1),
+    // in any order, between the near and far planes.  Rectangles less than a
+    // pixel across are widened to a pixel, so a click selects what is under it.
+    pub fn get_selection_frustum(&self, x0: f32, y0: f32, x1: f32, y1: f32) -> Frustum {
+        let (x0, x1) = widen(x0.min(x1), x0.max(x1));
+        let (y0, y1) = widen(y0.min(y1), y0.max(y1));
+
+        let p0 = self.viewport.to_ndc(Vec3::new(x0, y1, 0.0));
+        let p1 = self.viewport.to_ndc(Vec3::new(x1, y0, 0.0));
+
+        // Scales and moves the rectangle onto the whole clip volume, like
+        // gluPickMatrix, so the usual frustum extraction gives its planes.
+        let sx = 2.0 / (p1.x - p0.x);
+        let sy = 2.0 / (p1.y - p0.y);
+        let mut pick = Mat4::IDENTITY;
+        pick.m[0][0] = sx;
+        pick.m[1][1] = sy;
+        pick.m[3][0] = -sx * 0.5 * (p0.x + p1.x);
+        pick.m[3][1] = -sy * 0.5 * (p0.y + p1.y);
+
+        Frustum::from_matrix(&(self.view_proj * pick))
+    }
+}
+
+fn widen(min: f32, max: f32) -> (f32, f32) {
+    if max - min < 1.0 {
+        let center = 0.5 * (min + max);
+        (center - 0.5, center + 0.5)
+    } else {
+        (min, max)
+    }
+}
+
+//===============================================================
+// Queries.
+
+// World space bounds of a pickable object.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BoundingVolume {
+    Sphere(BoundingSphere),
+    Aabb(Aabb),
+    Obb(Obb),
+}
+
+impl BoundingVolume {
+    // Parameter range the ray spends inside the volume, clipped to
+    // [t_min, t_max].
+    pub fn intersect_ray(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        match self {
+            BoundingVolume::Sphere(s) => s.intersect_ray(ray, t_min, t_max),
+            BoundingVolume::Aabb(b) => b.intersect_ray(ray, t_min, t_max),
+            BoundingVolume::Obb(b) => b.intersect_ray(ray, t_min, t_max),
+        }
+    }
+
+    fn as_cullable(&self) -> &dyn Cullable {
+        match self {
+            BoundingVolume::Sphere(s) => s,
+            BoundingVolume::Aabb(b) => b,
+            BoundingVolume::Obb(b) => b,
+        }
+    }
+}
+
+impl Cullable for BoundingVolume {
+    fn center(&self) -> Vec3 {
+        self.as_cullable().center()
+    }
+
+    fn radius_along(&self, n: Vec3) -> f32 {
+        self.as_cullable().radius_along(n)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PickHit {
+    // Ray parameter of the hit, see Ray::dir.
+    pub distance: f32,
+    // The triangle hit, None for objects picked by their bounds alone.
+    pub triangle: Option<RayHit>,
+}
+
+// Scene objects the queries can pick.
+pub trait Pickable {
+    fn get_bounds(&self) -> BoundingVolume;
+
+    // Exact test of a world space ray that hit the bounds, entering them at
+    // 'bounds_distance'.  The default picks by the bounds alone; objects with
+    // triangles test those, like PickableMesh.
+    fn intersect_ray(&self, _ray: &Ray, bounds_distance: f32) -> Option<PickHit> {
+        Some(PickHit { distance: bounds_distance, triangle: None })
+    }
+
+    // Exact test of a selection frustum that the bounds straddle.  The default
+    // selects by the bounds alone.
+    fn intersects_frustum(&self, _frustum: &Frustum) -> bool {
+        true
+    }
+}
+
+impl<T: Pickable + ?Sized> Pickable for &T {
+    fn get_bounds(&self) -> BoundingVolume {
+        (**self).get_bounds()
+    }
+
+    fn intersect_ray(&self, ray: &Ray, bounds_distance: f32) -> Option<PickHit> {
+        (**self).intersect_ray(ray, bounds_distance)
+    }
+
+    fn intersects_frustum(&self, frustum: &Frustum) -> bool {
+        (**self).intersects_frustum(frustum)
+    }
+}
+
+impl Pickable for BoundingVolume {
+    fn get_bounds(&self) -> BoundingVolume { *self }
+}
+
+impl Pickable for BoundingSphere {
+    fn get_bounds(&self) -> BoundingVolume { BoundingVolume::Sphere(*self) }
+}
+
+impl Pickable for Aabb {
+    fn get_bounds(&self) -> BoundingVolume { BoundingVolume::Aabb(*self) }
+}
+
+impl Pickable for Obb {
+    fn get_bounds(&self) -> BoundingVolume { BoundingVolume::Obb(*self) }
+}
+
+// Nearest object the ray hits in front of its origin, as its index in
+// 'objects' and the hit.  Objects are tested in the order the ray enters their
+// bounds, and the test stops at the first bounds beyond the nearest hit.
+pub fn pick_nearest<P: Pickable>(objects: &[P], ray: &Ray) -> Option<(usize, PickHit)> {
+    let mut candidates = bounds_hits(objects, ray);
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let mut nearest: Option<(usize, PickHit)> = None;
+    for (i, t) in candidates {
+        if nearest.is_some_and(|(_, hit)| t > hit.distance) {
+            break;
+        }
+        if let Some(hit) = objects[i].intersect_ray(ray, t) {
+            if hit.distance >= 0.0 && nearest.is_none_or(|(_, n)| hit.distance < n.distance) {
+                nearest = Some((i, hit));
+            }
+        }
+    }
+    nearest
+}
+
+// Every object the ray hits in front of its origin, nearest first.
+pub fn pick_all<P: Pickable>(objects: &[P], ray: &Ray) -> Vec<(usize, PickHit)> {
+    let mut hits: Vec<(usize, PickHit)> = bounds_hits(objects, ray).into_iter()
+        .filter_map(|(i, t)| objects[i].intersect_ray(ray, t).map(|hit| (i, hit)))
+        .filter(|(_, hit)| hit.distance >= 0.0)
+        .collect();
+    hits.sort_by(|a, b| a.1.distance.total_cmp(&b.1.distance));
+    hits
+}
+
+// Indices of the objects at least partly inside a selection frustum, in the
+// order of 'objects'.  Objects whose bounds are inside are selected without
+// testing their triangles.
+pub fn pick_in_frustum<P: Pickable>(objects: &[P], frustum: &Frustum) -> Vec<usize> {
+    objects.iter().enumerate()
+        .filter(|(_, o)| match frustum.test(&o.get_bounds()) {
+            Containment::Outside => false,
+            Containment::Inside => true,
+            Containment::Intersecting => o.intersects_frustum(frustum),
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// Objects whose bounds the ray hits in front of its origin, with the distance
+// it enters them at.
+fn bounds_hits<P: Pickable>(objects: &[P], ray: &Ray) -> Vec<(usize, f32)> {
+    objects.iter().enumerate()
+        .filter_map(|(i, o)| o.get_bounds().intersect_ray(ray, 0.0, f32::MAX).map(|(t, _)| (i, t)))
+        .collect()
+}
+
+//===============================================================
+// Meshes.
+
+// A mesh placed in the world, picked by its triangles through its Bvh.
+#[derive(Copy, Clone, Debug)]
+pub struct PickableMesh<'a, V: Vertex> {
+    mesh: &'a Mesh<V>,
+    bvh: &'a Bvh,
+    world: Mat4,
+    inv_world: Mat4,
+    bounds: Obb,
+}
+
+impl<'a, V: Vertex> PickableMesh<'a, V> {
+    // 'bvh' must have been built from 'mesh'.  'world' may rotate, translate
+    // and scale but not shear.  None if 'world' cannot be inverted.
+    pub fn new(mesh: &'a Mesh<V>, bvh: &'a Bvh, world: Mat4) -> Option<PickableMesh<'a, V>> {
+        let inv_world = world.inverse()?;
+        let bounds = Obb::from_aabb(&bvh.bounds(), &world);
+        Some(PickableMesh { mesh, bvh, world, inv_world, bounds })
+    }
+
+    pub fn get_mesh(&self) -> &'a Mesh<V> {
+        self.mesh
+    }
+
+    pub fn get_world(&self) -> &Mat4 {
+        &self.world
+    }
+}
+
+impl<'a, V: Vertex> Pickable for PickableMesh<'a, V> {
+    fn get_bounds(&self) -> BoundingVolume {
+        BoundingVolume::Obb(self.bounds)
+    }
+
+    // The ray is moved to object space, which keeps its parameter, so the
+    // distance is still in units of the world space direction.
+    fn intersect_ray(&self, ray: &Ray, _bounds_distance: f32) -> Option<PickHit> {
+        self.bvh.intersect(self.mesh, &ray.transform(&self.inv_world))
+            .map(|hit| PickHit { distance: hit.distance, triangle: Some(hit) })
+    }
+
+    fn intersects_frustum(&self, frustum: &Frustum) -> bool {
+        (0..self.mesh.num_faces()).any(|f| {
+            let p = self.mesh.face_positions(f).map(|p| self.world.transform_coord(p));
+            triangle_intersects_frustum(frustum, &p)
+        })
+    }
+}
+
+// Exact: clips the triangle by each plane in turn and checks that something is
+// left.
+fn triangle_intersects_frustum(frustum: &Frustum, triangle: &[Vec3; 3]) -> bool {
+    let mut poly: Vec<Vec3> = triangle.to_vec();
+    let mut clipped = Vec::with_capacity(9);
+
+    for i in 0..6 {
+        clipped.clear();
+        for (j, &a) in poly.iter().enumerate() {
+            let b = poly[(j + 1) % poly.len()];
+            let da = frustum.distance(i, a);
+            let db = frustum.distance(i, b);
+
+            if da >= 0.0 {
+                clipped.push(a);
+            }
+            if (da >= 0.0) != (db >= 0.0) {
+                clipped.push(a.lerp(b, da / (da - db)));
+            }
+        }
+        if clipped.is_empty() {
+            return false;
+        }
+        std::mem::swap(&mut poly, &mut clipped);
+    }
+    true
+}
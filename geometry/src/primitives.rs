@@ -0,0 +1,507 @@
+// Procedural primitives with normals and texture coordinates, replacing
+// D3DXCreateBox/Sphere/Cylinder/Torus/Teapot (which only output positions and
+// normals, hence gen_spherical_tex_coords and gen_cyl_tex_coords).
+//
+// All shapes are centered at the origin with y as the up axis; use Mesh::transform
+// to place them differently.  Faces are wound clockwise when seen from outside,
+// as Direct3D expects with the default cull mode.  Vertices along texture seams
+// are duplicated so each side gets its own tex-coords.
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use crate::math::*;
+use crate::mesh::*;
+use crate::mesh_opt::*;
+use crate::tangent_frame::*;
+
+// Adds tangents and binormals for normal mapping.
+pub fn with_tangents(mesh: &Mesh<VertexPNT>) -> Mesh<NMapVertex> {
+    compute_tangent_frame(mesh, &TangentFrameOptions::default()).mesh
+}
+
+// Axis aligned box.  Each side is a separate quad with tex-coords covering the
+// whole texture, same as D3DXCreateBox plus the tex-coords.
+pub fn create_box(width: f32, height: f32, depth: f32) -> Mesh<VertexPNT> {
+    let half = Vec3::new(width, height, depth) * 0.5;
+
+    // Outward normal, then the directions u and v grow in as seen from outside
+    // (u to the right, v down).
+    let sides = [
+        (-Vec3::Z, Vec3::X, -Vec3::Y),
+        (Vec3::Z, -Vec3::X, -Vec3::Y),
+        (Vec3::X, Vec3::Z, -Vec3::Y),
+        (-Vec3::X, -Vec3::Z, -Vec3::Y),
+        (Vec3::Y, Vec3::X, -Vec3::Z),
+        (-Vec3::Y, Vec3::X, Vec3::Z),
+    ];
+
+    let mut mesh = Mesh::default();
+    for (n, u_dir, v_dir) in sides {
+        let side = parametric_surface(1, 1, |u, v| {
+            let p = n + u_dir * (u * 2.0 - 1.0) + v_dir * (v * 2.0 - 1.0);
+            VertexPNT { pos: p.mul_elem(half), normal: n, tex0: Vec2::new(u, v) }
+        });
+        mesh.append(&side);
+    }
+    mesh
+}
+
+// Latitude/longitude sphere.  u follows the longitude and v goes from the north
+// (+y) to the south pole, the mapping gen_spherical_tex_coords computes.
+pub fn create_sphere(radius: f32, slices: u32, stacks: u32) -> Mesh<VertexPNT> {
+    parametric_surface(stacks, slices, |u, v| {
+        let (cos_theta, sin_theta) = unit_circle(u);
+        let (cos_phi, sin_phi) = half_circle(v);
+        let n = Vec3::new(sin_phi * cos_theta, cos_phi, sin_phi * sin_theta);
+
+        // The pole is shared by a whole row of triangles; give each its own copy
+        // with u at the middle of the triangle instead of at one corner.
+        let u = if v == 0.0 {
+            u - 0.5 / slices as f32
+        } else if v == 1.0 {
+            u + 0.5 / slices as f32
+        } else {
+            u
+        };
+
+        VertexPNT { pos: n * radius, normal: n, tex0: Vec2::new(u, v) }
+    })
+}
+
+// Sphere made by subdividing an icosahedron, which spreads the triangles evenly
+// instead of bunching them at the poles.  Tex-coords use the same mapping as
+// create_sphere.
+pub fn create_icosphere(radius: f32, subdivisions: u32) -> Mesh<VertexPNT> {
+    let t = (1.0 + 5.0f32.sqrt()) * 0.5;
+
+    let mut points: Vec<Vec3> = [
+        (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+        (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+        (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+    ].iter().map(|(x, y, z)| Vec3::new(*x, *y, *z).normalize()).collect();
+
+    let mut faces: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, points: &mut Vec<Vec3>| -> u32 {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push(((points[a as usize] + points[b as usize]) * 0.5).normalize());
+                points.len() as u32 - 1
+            })
+        };
+
+        let mut next = Vec::with_capacity(faces.len() * 4);
+        for [a, b, c] in faces {
+            let ab = midpoint(a, b, &mut points);
+            let bc = midpoint(b, c, &mut points);
+            let ca = midpoint(c, a, &mut points);
+            next.extend_from_slice(&[[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+        }
+        faces = next;
+    }
+
+    //===============================================================
+    // Tex-coords.  Faces straddling the u = 0/1 seam get copies of their low-u
+    // corners shifted by one; pole vertices get a copy per face.
+
+    let mut vertices: Vec<VertexPNT> = Vec::new();
+    let mut indices: Vec<u32> = Vec::with_capacity(faces.len() * 3);
+    let mut copies: HashMap<(u32, u32), u32> = HashMap::new();
+
+    for face in faces {
+        let mut u = face.map(|i| {
+            let p = points[i as usize];
+            let u = p.z.atan2(p.x) / (2.0 * PI);
+            if u < 0.0 { u + 1.0 } else { u }
+        });
+
+        let max_u = u.iter().cloned().fold(f32::MIN, f32::max);
+        let min_u = u.iter().cloned().fold(f32::MAX, f32::min);
+        if max_u - min_u > 0.5 {
+            for u in &mut u {
+                if *u < 0.5 {
+                    *u += 1.0;
+                }
+            }
+        }
+
+        let is_pole = face.map(|i| {
+            let p = points[i as usize];
+            p.x * p.x + p.z * p.z < 1.0e-12
+        });
+        for k in 0..3 {
+            if is_pole[k] {
+                u[k] = (u[(k + 1) % 3] + u[(k + 2) % 3]) * 0.5;
+            }
+        }
+
+        let mut tri = [0u32; 3];
+        for k in 0..3 {
+            let i = face[k];
+            tri[k] = *copies.entry((i, u[k].to_bits())).or_insert_with(|| {
+                let n = points[i as usize];
+                vertices.push(VertexPNT {
+                    pos: n * radius,
+                    normal: n,
+                    tex0: Vec2::new(u[k], n.y.clamp(-1.0, 1.0).acos() / PI),
+                });
+                vertices.len() as u32 - 1
+            });
+        }
+
+        indices.extend_from_slice(&orient_triangle(&vertices, tri));
+    }
+
+    Mesh::new(vertices, indices)
+}
+
+// Cylinder along y from -length/2 (radius1) to +length/2 (radius2), like
+// D3DXCreateCylinder but along y.  A zero radius makes a cone.  The side maps
+// u around the axis and v from top to bottom, as gen_cyl_tex_coords does; the
+// caps are mapped planar.
+pub fn create_cylinder(radius1: f32, radius2: f32, length: f32, slices: u32, stacks: u32,
+                       caps: bool) -> Mesh<VertexPNT> {
+    let half = length * 0.5;
+
+    // The side normal leans towards the thin end.
+    let slope = (radius1 - radius2) / length;
+
+    let mut mesh = parametric_surface(stacks, slices, |u, v| {
+        let (cos_theta, sin_theta) = unit_circle(u);
+        let r = radius2 + (radius1 - radius2) * v;
+
+        // A pointed end is shared by a row of triangles, see create_sphere.
+        let u = if v == 0.0 && radius2 == 0.0 {
+            u - 0.5 / slices as f32
+        } else if v == 1.0 && radius1 == 0.0 {
+            u + 0.5 / slices as f32
+        } else {
+            u
+        };
+
+        VertexPNT {
+            pos: Vec3::new(r * cos_theta, half - length * v, r * sin_theta),
+            normal: Vec3::new(cos_theta, slope, sin_theta).normalize(),
+            tex0: Vec2::new(u, v),
+        }
+    });
+
+    if caps {
+        if radius2 > 0.0 {
+            mesh.append(&disk(radius2, slices, half, Vec3::Y));
+        }
+        if radius1 > 0.0 {
+            mesh.append(&disk(radius1, slices, -half, -Vec3::Y));
+        }
+    }
+
+    mesh
+}
+
+// Cone along y with its base (capped) at -length/2 and the tip at +length/2.
+pub fn create_cone(radius: f32, length: f32, slices: u32, stacks: u32) -> Mesh<VertexPNT> {
+    create_cylinder(radius, 0.0, length, slices, stacks, true)
+}
+
+// Torus lying in the xz-plane.  u goes around the ring and v around the tube,
+// downwards on the outside like v on the sphere.
+pub fn create_torus(tube_radius: f32, ring_radius: f32, sides: u32, rings: u32) -> Mesh<VertexPNT> {
+    parametric_surface(sides, rings, |u, v| {
+        let (cos_u, sin_u) = unit_circle(u);
+        let (cos_v, sin_v) = unit_circle(v);
+
+        let n = Vec3::new(cos_v * cos_u, -sin_v, cos_v * sin_u);
+        let center = Vec3::new(ring_radius * cos_u, 0.0, ring_radius * sin_u);
+
+        VertexPNT { pos: center + n * tube_radius, normal: n, tex0: Vec2::new(u, v) }
+    })
+}
+
+// Flat grid on the xz-plane facing +y, with tex-coords covering the whole texture.
+pub fn create_plane(width: f32, depth: f32, rows: u32, cols: u32) -> Mesh<VertexPNT> {
+    parametric_surface(rows, cols, |u, v| VertexPNT {
+        pos: Vec3::new((u - 0.5) * width, 0.0, (0.5 - v) * depth),
+        normal: Vec3::Y,
+        tex0: Vec2::new(u, v),
+    })
+}
+
+// Disk on the xz-plane facing +y.
+pub fn create_disk(radius: f32, slices: u32) -> Mesh<VertexPNT> {
+    disk(radius, slices, 0.0, Vec3::Y)
+}
+
+//===============================================================
+// Utah teapot.
+
+// Control point indices of the ten distinct patches; the others are mirror
+// images of these (Newell's data as distributed with GLUT).
+const TEAPOT_PATCHES: [[u16; 16]; 10] = [
+    // rim
+    [102, 103, 104, 105, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    // body
+    [12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27],
+    [24, 25, 26, 27, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40],
+    // lid
+    [96, 96, 96, 96, 97, 98, 99, 100, 101, 101, 101, 101, 0, 1, 2, 3],
+    [0, 1, 2, 3, 106, 107, 108, 109, 110, 111, 112, 113, 114, 115, 116, 117],
+    // bottom
+    [118, 118, 118, 118, 124, 122, 119, 121, 123, 126, 125, 120, 40, 39, 38, 37],
+    // handle
+    [41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56],
+    [53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 28, 65, 66, 67],
+    // spout
+    [68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83],
+    [80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95],
+];
+
+// Patches before this one are rotationally symmetric and get four copies, the
+// handle and spout only get mirrored across the xz-plane.
+const TEAPOT_NUM_BODY_PATCHES: usize = 6;
+
+// z-up, as in the original data.
+const TEAPOT_POINTS: [[f32; 3]; 127] = [
+    [0.2, 0.0, 2.7], [0.2, -0.112, 2.7], [0.112, -0.2, 2.7], [0.0, -0.2, 2.7],
+    [1.3375, 0.0, 2.53125], [1.3375, -0.749, 2.53125], [0.749, -1.3375, 2.53125], [0.0, -1.3375, 2.53125],
+    [1.4375, 0.0, 2.53125], [1.4375, -0.805, 2.53125], [0.805, -1.4375, 2.53125], [0.0, -1.4375, 2.53125],
+    [1.5, 0.0, 2.4], [1.5, -0.84, 2.4], [0.84, -1.5, 2.4], [0.0, -1.5, 2.4],
+    [1.75, 0.0, 1.875], [1.75, -0.98, 1.875], [0.98, -1.75, 1.875], [0.0, -1.75, 1.875],
+    [2.0, 0.0, 1.35], [2.0, -1.12, 1.35], [1.12, -2.0, 1.35], [0.0, -2.0, 1.35],
+    [2.0, 0.0, 0.9], [2.0, -1.12, 0.9], [1.12, -2.0, 0.9], [0.0, -2.0, 0.9],
+    [-2.0, 0.0, 0.9], [2.0, 0.0, 0.45], [2.0, -1.12, 0.45], [1.12, -2.0, 0.45],
+    [0.0, -2.0, 0.45], [1.5, 0.0, 0.225], [1.5, -0.84, 0.225], [0.84, -1.5, 0.225],
+    [0.0, -1.5, 0.225], [1.5, 0.0, 0.15], [1.5, -0.84, 0.15], [0.84, -1.5, 0.15],
+    [0.0, -1.5, 0.15], [-1.6, 0.0, 2.025], [-1.6, -0.3, 2.025], [-1.5, -0.3, 2.25],
+    [-1.5, 0.0, 2.25], [-2.3, 0.0, 2.025], [-2.3, -0.3, 2.025], [-2.5, -0.3, 2.25],
+    [-2.5, 0.0, 2.25], [-2.7, 0.0, 2.025], [-2.7, -0.3, 2.025], [-3.0, -0.3, 2.25],
+    [-3.0, 0.0, 2.25], [-2.7, 0.0, 1.8], [-2.7, -0.3, 1.8], [-3.0, -0.3, 1.8],
+    [-3.0, 0.0, 1.8], [-2.7, 0.0, 1.575], [-2.7, -0.3, 1.575], [-3.0, -0.3, 1.35],
+    [-3.0, 0.0, 1.35], [-2.5, 0.0, 1.125], [-2.5, -0.3, 1.125], [-2.65, -0.3, 0.9375],
+    [-2.65, 0.0, 0.9375], [-2.0, -0.3, 0.9], [-1.9, -0.3, 0.6], [-1.9, 0.0, 0.6],
+    [1.7, 0.0, 1.425], [1.7, -0.66, 1.425], [1.7, -0.66, 0.6], [1.7, 0.0, 0.6],
+    [2.6, 0.0, 1.425], [2.6, -0.66, 1.425], [3.1, -0.66, 0.825], [3.1, 0.0, 0.825],
+    [2.3, 0.0, 2.1], [2.3, -0.25, 2.1], [2.4, -0.25, 2.025], [2.4, 0.0, 2.025],
+    [2.7, 0.0, 2.4], [2.7, -0.25, 2.4], [3.3, -0.25, 2.4], [3.3, 0.0, 2.4],
+    [2.8, 0.0, 2.475], [2.8, -0.25, 2.475], [3.525, -0.25, 2.49375], [3.525, 0.0, 2.49375],
+    [2.9, 0.0, 2.475], [2.9, -0.15, 2.475], [3.45, -0.15, 2.5125], [3.45, 0.0, 2.5125],
+    [2.8, 0.0, 2.4], [2.8, -0.15, 2.4], [3.2, -0.15, 2.4], [3.2, 0.0, 2.4],
+    [0.0, 0.0, 3.15], [0.8, 0.0, 3.15], [0.8, -0.45, 3.15], [0.45, -0.8, 3.15],
+    [0.0, -0.8, 3.15], [0.0, 0.0, 2.85], [1.4, 0.0, 2.4], [1.4, -0.784, 2.4],
+    [0.784, -1.4, 2.4], [0.0, -1.4, 2.4], [0.4, 0.0, 2.55], [0.4, -0.224, 2.55],
+    [0.224, -0.4, 2.55], [0.0, -0.4, 2.55], [1.3, 0.0, 2.55], [1.3, -0.728, 2.55],
+    [0.728, -1.3, 2.55], [0.0, -1.3, 2.55], [1.3, 0.0, 2.4], [1.3, -0.728, 2.4],
+    [0.728, -1.3, 2.4], [0.0, -1.3, 2.4], [0.0, 0.0, 0.0], [1.425, -0.798, 0.0],
+    [1.5, 0.0, 0.075], [1.425, 0.0, 0.0], [0.798, -1.425, 0.0], [0.0, -1.5, 0.075],
+    [0.0, -1.425, 0.0], [1.5, -0.84, 0.075], [0.84, -1.5, 0.075],
+];
+
+// The Utah teapot, about 'size' units high, spout pointing to +x.  Every Bezier
+// patch becomes a tessellation x tessellation grid with the patch parameters as
+// tex-coords.
+pub fn create_teapot(size: f32, tessellation: u32) -> Mesh<VertexPNT> {
+    let n = tessellation.max(1);
+    let scale = size / 3.15;
+
+    // Data is z-up with the bottom at z = 0; turn it y-up and center it.
+    let to_local = |p: [f32; 3], sx: f32, sy: f32| {
+        Vec3::new(p[0] * sx, p[2] - 1.575, -p[1] * sy) * scale
+    };
+
+    let mut vertices: Vec<VertexPNT> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for (i, patch) in TEAPOT_PATCHES.iter().enumerate() {
+        // Mirrored copies also reverse the column order so the winding stays the same.
+        let copies: &[(f32, f32, bool)] = if i < TEAPOT_NUM_BODY_PATCHES {
+            &[(1.0, 1.0, false), (1.0, -1.0, true), (-1.0, 1.0, true), (-1.0, -1.0, false)]
+        } else {
+            &[(1.0, 1.0, false), (1.0, -1.0, true)]
+        };
+
+        for (sx, sy, reverse) in copies {
+            let mut cp = [Vec3::ZERO; 16];
+            for r in 0..4 {
+                for c in 0..4 {
+                    let src = if *reverse { 3 - c } else { c };
+                    cp[r * 4 + c] = to_local(TEAPOT_POINTS[patch[r * 4 + src] as usize], *sx, *sy);
+                }
+            }
+
+            let base = vertices.len() as u32;
+            for r in 0..=n {
+                for c in 0..=n {
+                    let u = c as f32 / n as f32;
+                    let v = r as f32 / n as f32;
+                    vertices.push(VertexPNT {
+                        pos: eval_bezier_patch(&cp, u, v),
+                        normal: Vec3::ZERO,
+                        tex0: Vec2::new(u, v),
+                    });
+                }
+            }
+            push_grid_indices(&mut indices, base, n, n);
+        }
+    }
+
+    //===============================================================
+    // Neighbouring patches share edges but not vertices (tex-coords differ), so
+    // weld by position to drop the collapsed triangles at the lid knob and bottom
+    // center and to get normals that are smooth across patches.
+
+    let point_reps = generate_point_reps(&vertices, size * 1.0e-5);
+
+    let mut welded: Vec<u32> = Vec::with_capacity(indices.len());
+    let mut kept: Vec<u32> = Vec::with_capacity(indices.len());
+    for tri in indices.chunks_exact(3) {
+        let w = [0, 1, 2].map(|k| point_reps[tri[k] as usize]);
+        if w[0] != w[1] && w[1] != w[2] && w[2] != w[0] {
+            welded.extend_from_slice(&w);
+            kept.extend_from_slice(tri);
+        }
+    }
+
+    // The data is wound consistently; check once which way.
+    let center = vertices.iter().fold(Vec3::ZERO, |acc, v| acc + v.pos) / vertices.len() as f32;
+    let mut flux = 0.0;
+    for tri in welded.chunks_exact(3) {
+        let p = [0, 1, 2].map(|k| vertices[tri[k] as usize].pos);
+        flux += (p[1] - p[0]).cross(p[2] - p[0]).dot((p[0] + p[1] + p[2]) / 3.0 - center);
+    }
+    if flux < 0.0 {
+        for tri in kept.chunks_exact_mut(3) {
+            tri.swap(1, 2);
+        }
+        for tri in welded.chunks_exact_mut(3) {
+            tri.swap(1, 2);
+        }
+    }
+
+    let normals = vertex_normals(&vertices, &welded);
+    for (i, v) in vertices.iter_mut().enumerate() {
+        v.normal = normals[point_reps[i] as usize];
+    }
+
+    let mut mesh = Mesh::new(vertices, kept);
+    compact(&mut mesh);
+    mesh
+}
+
+fn bernstein(t: f32) -> [f32; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t]
+}
+
+// Bicubic Bezier patch; rows of control points follow v, columns follow u.
+fn eval_bezier_patch(cp: &[Vec3; 16], u: f32, v: f32) -> Vec3 {
+    let bu = bernstein(u);
+    let bv = bernstein(v);
+
+    let mut p = Vec3::ZERO;
+    for r in 0..4 {
+        for c in 0..4 {
+            p += cp[r * 4 + c] * (bv[r] * bu[c]);
+        }
+    }
+    p
+}
+
+//===============================================================
+// Helpers.
+
+// cos and sin of a full turn times t, exact at the ends so seam vertices match.
+fn unit_circle(t: f32) -> (f32, f32) {
+    if t <= 0.0 || t >= 1.0 {
+        (1.0, 0.0)
+    } else {
+        let a = 2.0 * PI * t;
+        (a.cos(), a.sin())
+    }
+}
+
+// cos and sin of half a turn times t, exact at the ends so poles collapse.
+fn half_circle(t: f32) -> (f32, f32) {
+    if t <= 0.0 {
+        (1.0, 0.0)
+    } else if t >= 1.0 {
+        (-1.0, 0.0)
+    } else {
+        let a = PI * t;
+        (a.cos(), a.sin())
+    }
+}
+
+// Two triangles per cell of a (rows + 1) x (cols + 1) vertex grid, split the same
+// way gen_tri_grid does.
+fn push_grid_indices(indices: &mut Vec<u32>, base: u32, rows: u32, cols: u32) {
+    let stride = cols + 1;
+    for i in 0..rows {
+        for j in 0..cols {
+            let a = base + i * stride + j;
+            let b = a + 1;
+            let c = a + stride;
+            let d = c + 1;
+            indices.extend_from_slice(&[a, b, c, c, b, d]);
+        }
+    }
+}
+
+// Samples 'eval' on a (rows + 1) x (cols + 1) grid over [0, 1]^2.  Triangles
+// collapsed to a line (at poles and tips) are dropped and the rest are wound so
+// they face the way their vertex normals point.
+fn parametric_surface(rows: u32, cols: u32, eval: impl Fn(f32, f32) -> VertexPNT) -> Mesh<VertexPNT> {
+    let mut vertices = Vec::with_capacity(((rows + 1) * (cols + 1)) as usize);
+    for i in 0..=rows {
+        for j in 0..=cols {
+            vertices.push(eval(j as f32 / cols as f32, i as f32 / rows as f32));
+        }
+    }
+
+    let mut grid = Vec::new();
+    push_grid_indices(&mut grid, 0, rows, cols);
+
+    let mut indices = Vec::with_capacity(grid.len());
+    for tri in grid.chunks_exact(3) {
+        let p = [0, 1, 2].map(|k| vertices[tri[k] as usize].pos);
+        if p[0] == p[1] || p[1] == p[2] || p[2] == p[0] {
+            continue;
+        }
+        indices.extend_from_slice(&orient_triangle(&vertices, [tri[0], tri[1], tri[2]]));
+    }
+
+    let mut mesh = Mesh::new(vertices, indices);
+    compact(&mut mesh);
+    mesh
+}
+
+// Flips the triangle if its face normal disagrees with its vertex normals.
+fn orient_triangle(vertices: &[VertexPNT], tri: [u32; 3]) -> [u32; 3] {
+    let [a, b, c] = tri.map(|i| &vertices[i as usize]);
+    let n = (b.pos - a.pos).cross(c.pos - a.pos);
+    if n.dot(a.normal + b.normal + c.normal) < 0.0 {
+        [tri[0], tri[2], tri[1]]
+    } else {
+        tri
+    }
+}
+
+// Disk at height y facing 'normal' (+y or -y), mapped planar as seen from the
+// side it faces.
+fn disk(radius: f32, slices: u32, y: f32, normal: Vec3) -> Mesh<VertexPNT> {
+    parametric_surface(1, slices, |u, v| {
+        let (cos_theta, sin_theta) = unit_circle(u);
+        let x = v * cos_theta;
+        let z = v * sin_theta;
+
+        VertexPNT {
+            pos: Vec3::new(x * radius, y, z * radius),
+            normal,
+            tex0: Vec2::new(0.5 + x * 0.5, 0.5 - z * 0.5 * normal.y),
+        }
+    })
+}
@@ -0,0 +1,260 @@
+// Quadtree over the xz-plane for culling static scenery (terrain sub-grids,
+// buildings, trees) against a view frustum, in place of testing every object.
+//
+// Items are world boxes known by their index in the slice the tree is built
+// from.  Each node splits its region into four quadrants and each item goes to
+// the quadrant holding its center, so items never straddle nodes; node boxes
+// are fitted to the items below them instead, and can overlap like a loose
+// quadtree's.  The items below a node are contiguous, so a node inside the
+// frustum hands over all of them without further tests.
+//
+// Traversal passes plane masks down (see Frustum::test_masked): a node tests
+// only the planes its parent straddled.
+
+use crate::bounds::*;
+use crate::frustum::*;
+use crate::math::*;
+
+#[derive(Copy, Clone, Debug)]
+pub struct QuadTreeOptions {
+    // Nodes with this many items or fewer are never split.
+    pub max_leaf_items: usize,
+    // Nodes this deep are never split either (the root is at depth 0), which
+    // stops items with the same center from being split forever.
+    pub max_depth: usize,
+}
+
+impl Default for QuadTreeOptions {
+    fn default() -> Self {
+        QuadTreeOptions {
+            max_leaf_items: 4,
+            max_depth: 8,
+        }
+    }
+}
+
+// Work done by a cull.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CullStats {
+    // Nodes whose boxes were tested, and of those, the ones found completely
+    // outside or inside the frustum.
+    pub num_nodes_visited: u32,
+    pub num_nodes_culled: u32,
+    pub num_nodes_inside: u32,
+    // Box tests of nodes and items.  Testing every item would take one per item.
+    pub num_tests: u32,
+    pub num_visible: u32,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct QuadTreeNode {
+    bounds: Aabb,
+    parent: u32,
+    // Index of the first child, the others follow it.  0 children for leaves.
+    first_child: u32,
+    num_children: u32,
+    // The items of the whole subtree are QuadTree::items[first..first + count].
+    first: u32,
+    count: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct QuadTree {
+    // Root first; siblings are next to each other.
+    nodes: Vec<QuadTreeNode>,
+    items: Vec<u32>,
+    // By item.
+    item_bounds: Vec<Aabb>,
+    item_leaves: Vec<u32>,
+}
+
+const NO_PARENT: u32 = u32::MAX;
+
+impl QuadTree {
+    pub fn build(item_bounds: &[Aabb]) -> QuadTree {
+        QuadTree::build_with_options(item_bounds, &QuadTreeOptions::default())
+    }
+
+    pub fn build_with_options(item_bounds: &[Aabb], options: &QuadTreeOptions) -> QuadTree {
+        if item_bounds.is_empty() {
+            return QuadTree::default();
+        }
+
+        let mut region = Aabb::EMPTY;
+        for b in item_bounds {
+            region.grow(b.center());
+        }
+
+        let mut tree = QuadTree {
+            nodes: vec![QuadTreeNode {
+                bounds: Aabb::EMPTY,
+                parent: NO_PARENT,
+                first_child: 0,
+                num_children: 0,
+                first: 0,
+                count: item_bounds.len() as u32,
+            }],
+            items: (0..item_bounds.len() as u32).collect(),
+            item_bounds: item_bounds.to_vec(),
+            item_leaves: vec![0; item_bounds.len()],
+        };
+        tree.split(0, Vec2::new(region.min.x, region.min.z), Vec2::new(region.max.x, region.max.z), 0, options);
+        tree
+    }
+
+    // Box around all the items.
+    pub fn bounds(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::EMPTY, |n| n.bounds)
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn num_items(&self) -> usize {
+        self.item_bounds.len()
+    }
+
+    pub fn item_bounds(&self, item: usize) -> &Aabb {
+        &self.item_bounds[item]
+    }
+
+    // Changes an item's box and refits the nodes above it.  The item stays in
+    // its leaf, so this is for boxes that change a little, like those of
+    // sculpted terrain sub-grids; rebuild the tree for items that move.
+    pub fn set_item_bounds(&mut self, item: usize, bounds: Aabb) {
+        self.item_bounds[item] = bounds;
+
+        let mut node = self.item_leaves[item];
+        while node != NO_PARENT {
+            self.fit(node as usize);
+            node = self.nodes[node as usize].parent;
+        }
+    }
+
+    // Fills 'visible' with the items not certainly outside the frustum, nearest
+    // to 'eye' first (by their boxes' centers).
+    pub fn cull(&self, frustum: &Frustum, eye: Vec3, visible: &mut Vec<usize>) -> CullStats {
+        visible.clear();
+        let mut stats = CullStats::default();
+        if self.nodes.is_empty() {
+            return stats;
+        }
+
+        // Neighbouring nodes are usually culled by the same plane.
+        let mut last_plane = 0;
+        let mut stack: Vec<(usize, u8)> = Vec::with_capacity(64);
+        stack.push((0, ALL_PLANES));
+
+        while let Some((i, mask)) = stack.pop() {
+            let node = self.nodes[i];
+            stats.num_nodes_visited += 1;
+            stats.num_tests += 1;
+
+            let items = &self.items[node.first as usize..(node.first + node.count) as usize];
+            match frustum.test_masked(&node.bounds, mask, &mut last_plane) {
+                (Containment::Outside, _) => stats.num_nodes_culled += 1,
+                (Containment::Inside, _) => {
+                    stats.num_nodes_inside += 1;
+                    visible.extend(items.iter().map(|item| *item as usize));
+                }
+                (Containment::Intersecting, straddled) => {
+                    if node.num_children == 0 {
+                        for item in items {
+                            stats.num_tests += 1;
+                            let (c, _) = frustum.test_masked(&self.item_bounds[*item as usize], straddled,
+                                                             &mut last_plane);
+                            if c != Containment::Outside {
+                                visible.push(*item as usize);
+                            }
+                        }
+                    } else {
+                        for child in node.first_child..node.first_child + node.num_children {
+                            stack.push((child as usize, straddled));
+                        }
+                    }
+                }
+            }
+        }
+
+        visible.sort_by(|a, b| {
+            let da = (self.item_bounds[*a].center() - eye).length_sq();
+            let db = (self.item_bounds[*b].center() - eye).length_sq();
+            da.total_cmp(&db)
+        });
+        stats.num_visible = visible.len() as u32;
+        stats
+    }
+
+    // Splits the node's region (min to max in x and z) into quadrants and
+    // recurses, then fits the node's box.
+    fn split(&mut self, i: usize, min: Vec2, max: Vec2, depth: usize, options: &QuadTreeOptions) {
+        let node = self.nodes[i];
+        let (first, count) = (node.first as usize, node.count as usize);
+
+        if count > options.max_leaf_items && depth < options.max_depth {
+            // Sorting by quadrant makes each quadrant's items contiguous.
+            let mid = (min + max) * 0.5;
+            let item_bounds = &self.item_bounds;
+            let quadrant = |item: &u32| {
+                let c = item_bounds[*item as usize].center();
+                (c.x >= mid.x) as usize | ((c.z >= mid.y) as usize) << 1
+            };
+            self.items[first..first + count].sort_by_key(quadrant);
+
+            let mut quadrant_counts = [0; 4];
+            for item in &self.items[first..first + count] {
+                quadrant_counts[quadrant(item)] += 1;
+            }
+
+            let first_child = self.nodes.len();
+            let mut children = Vec::with_capacity(4);
+            let mut start = first;
+            for (q, n) in quadrant_counts.iter().enumerate() {
+                if *n == 0 {
+                    continue;
+                }
+                let child_min = Vec2::new(if q & 1 != 0 { mid.x } else { min.x }, if q & 2 != 0 { mid.y } else { min.y });
+                let child_max = Vec2::new(if q & 1 != 0 { max.x } else { mid.x }, if q & 2 != 0 { max.y } else { mid.y });
+                self.nodes.push(QuadTreeNode {
+                    bounds: Aabb::EMPTY,
+                    parent: i as u32,
+                    first_child: 0,
+                    num_children: 0,
+                    first: start as u32,
+                    count: *n as u32,
+                });
+                children.push((child_min, child_max));
+                start += n;
+            }
+
+            self.nodes[i].first_child = first_child as u32;
+            self.nodes[i].num_children = children.len() as u32;
+            for (c, (child_min, child_max)) in children.into_iter().enumerate() {
+                self.split(first_child + c, child_min, child_max, depth + 1, options);
+            }
+        } else {
+            for item in &self.items[first..first + count] {
+                self.item_leaves[*item as usize] = i as u32;
+            }
+        }
+
+        self.fit(i);
+    }
+
+    // Fits the node's box to its children's, or to its items' for leaves.
+    fn fit(&mut self, i: usize) {
+        let node = self.nodes[i];
+        let mut bounds = Aabb::EMPTY;
+        if node.num_children == 0 {
+            for item in &self.items[node.first as usize..(node.first + node.count) as usize] {
+                bounds.merge(&self.item_bounds[*item as usize]);
+            }
+        } else {
+            for child in node.first_child..node.first_child + node.num_children {
+                bounds.merge(&self.nodes[child as usize].bounds);
+            }
+        }
+        self.nodes[i].bounds = bounds;
+    }
+}
@@ -0,0 +1,59 @@
+// Rays and ray-triangle intersection.
+
+use crate::math::*;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    // Need not be normalized; hit distances are measured in units of its length.
+    pub dir: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, dir: Vec3) -> Ray {
+        Ray { origin, dir }
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.dir * t
+    }
+
+    pub fn inv_dir(&self) -> Vec3 {
+        Vec3::new(1.0 / self.dir.x, 1.0 / self.dir.y, 1.0 / self.dir.z)
+    }
+
+    // Moves the ray to another space, e.g. world to object space with the inverse
+    // world matrix.
+    pub fn transform(&self, m: &Mat4) -> Ray {
+        Ray { origin: m.transform_coord(self.origin), dir: m.transform_normal(self.dir) }
+    }
+}
+
+// Two-sided Moller-Trumbore test.  Returns (t, u, v) with the hit point at
+// p0 + u * (p1 - p0) + v * (p2 - p0), the barycentrics D3DXIntersect reports.
+pub fn intersect_triangle(ray: &Ray, p0: Vec3, p1: Vec3, p2: Vec3) -> Option<(f32, f32, f32)> {
+    let e1 = p1 - p0;
+    let e2 = p2 - p0;
+
+    let p = ray.dir.cross(e2);
+    let det = e1.dot(p);
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let s = ray.origin - p0;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(e1);
+    let v = ray.dir.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = e2.dot(q) * inv_det;
+    Some((t, u, v))
+}
@@ -0,0 +1,472 @@
+// Edge-collapse simplification driven by the quadric error metric (Garland &
+// Heckbert, "Surface Simplification Using Quadric Error Metrics").
+//
+// Collapses are half-edge collapses: vertex P moves onto its neighbour Q, so no
+// new vertices are created and attributes (normals, tex-coords) never have to be
+// interpolated.  Vertices are grouped by position so vertices split along a uv or
+// normal seam move together.  Edges on an open border, a seam or between two
+// materials are feature edges; a vertex on such an edge may only slide along it,
+// which keeps seams watertight and subset outlines intact.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use crate::math::*;
+use crate::mesh::*;
+use crate::mesh_opt::*;
+
+#[derive(Copy, Clone, Debug)]
+pub struct SimplifyOptions {
+    // Stop once the mesh has this many faces or fewer.
+    pub target_faces: usize,
+    // Stop before a collapse would move the surface further than this (object
+    // space units, root mean square distance to the original planes).
+    pub max_error: f32,
+    // Never move vertices on an open border, e.g. the edges of terrain chunks that
+    // have to line up with their neighbours.
+    pub lock_borders: bool,
+    // Weight of the extra planes keeping feature edges in place, relative to the
+    // surface planes.
+    pub feature_weight: f32,
+    // Vertices closer than this are treated as the same point, so seams whose
+    // duplicated vertices differ by rounding noise stay closed.
+    pub weld_epsilon: f32,
+}
+
+impl Default for SimplifyOptions {
+    fn default() -> Self {
+        SimplifyOptions {
+            target_faces: 0,
+            max_error: f32::MAX,
+            lock_borders: false,
+            feature_weight: 10.0,
+            weld_epsilon: 1.0e-6,
+        }
+    }
+}
+
+pub struct SimplifyResult<V> {
+    pub mesh: Mesh<V>,
+    // Largest error of the collapses performed, same units as max_error.
+    pub error: f32,
+}
+
+pub fn simplify<V: Vertex>(mesh: &Mesh<V>, options: &SimplifyOptions) -> SimplifyResult<V> {
+    let mut s = Simplifier::new(mesh, options);
+    s.run();
+
+    let mut indices = Vec::with_capacity(s.live_faces * 3);
+    let mut attributes = Vec::with_capacity(s.live_faces);
+    for f in 0..mesh.num_faces() {
+        if s.face_alive[f] {
+            indices.extend_from_slice(&s.indices[f * 3..f * 3 + 3]);
+            attributes.push(mesh.attributes[f]);
+        }
+    }
+
+    let mut out = Mesh::with_attributes(mesh.vertices.clone(), indices, attributes);
+    compact(&mut out);
+
+    SimplifyResult { mesh: out, error: s.error as f32 }
+}
+
+//===============================================================
+// Quadrics.
+
+// Symmetric 4x4 matrix stored as its upper triangle, plus the summed weights so
+// the error can be reported as a mean distance.
+#[derive(Copy, Clone, Debug, Default)]
+struct Quadric {
+    a: [f64; 10],
+    weight: f64,
+}
+
+impl Quadric {
+    // Squared distance to the plane n.p + d = 0, times 'weight'.
+    fn from_plane(n: Vec3, d: f32, weight: f32) -> Quadric {
+        let (a, b, c, d, w) = (n.x as f64, n.y as f64, n.z as f64, d as f64, weight as f64);
+        Quadric {
+            a: [a * a * w, a * b * w, a * c * w, a * d * w,
+                b * b * w, b * c * w, b * d * w,
+                c * c * w, c * d * w,
+                d * d * w],
+            weight: w,
+        }
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for i in 0..10 {
+            self.a[i] += other.a[i];
+        }
+        self.weight += other.weight;
+    }
+
+    fn sum(&self, other: &Quadric) -> Quadric {
+        let mut res = *self;
+        res.add(other);
+        res
+    }
+
+    fn error(&self, p: Vec3) -> f64 {
+        let (x, y, z) = (p.x as f64, p.y as f64, p.z as f64);
+        let q = &self.a;
+        let e = q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+              + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+              + q[7] * z * z + 2.0 * q[8] * z
+              + q[9];
+        e.max(0.0)
+    }
+
+    // Root mean square distance, comparable across meshes of different sizes.
+    fn distance(&self, p: Vec3) -> f64 {
+        if self.weight > 0.0 { (self.error(p) / self.weight).sqrt() } else { 0.0 }
+    }
+}
+
+//===============================================================
+// Collapse queue.
+
+struct Candidate {
+    cost: f64,
+    from: u32,
+    to: u32,
+    from_version: u32,
+    to_version: u32,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // Reversed so the BinaryHeap pops the cheapest collapse first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+//===============================================================
+// Simplifier state.  "Groups" are sets of vertices sharing a position; all
+// connectivity decisions are made on groups, the index buffer holds vertices.
+
+struct Simplifier<'a, V> {
+    options: &'a SimplifyOptions,
+    attributes: &'a [u32],
+    indices: Vec<u32>,
+    face_alive: Vec<bool>,
+    live_faces: usize,
+
+    group_of: Vec<u32>,
+    group_pos: Vec<Vec3>,
+    group_faces: Vec<Vec<u32>>,
+    group_alive: Vec<bool>,
+    group_version: Vec<u32>,
+    group_locked: Vec<bool>,
+    quadrics: Vec<Quadric>,
+
+    heap: BinaryHeap<Candidate>,
+    error: f64,
+
+    _vertex: std::marker::PhantomData<V>,
+}
+
+impl<'a, V: Vertex> Simplifier<'a, V> {
+    fn new(mesh: &'a Mesh<V>, options: &'a SimplifyOptions) -> Simplifier<'a, V> {
+        let num_faces = mesh.num_faces();
+
+        let point_reps = generate_point_reps(&mesh.vertices, options.weld_epsilon);
+        let mut group_of = vec![0u32; mesh.num_vertices()];
+        let mut group_pos = Vec::new();
+        for v in 0..mesh.num_vertices() {
+            let rep = point_reps[v] as usize;
+            if rep == v {
+                group_of[v] = group_pos.len() as u32;
+                group_pos.push(mesh.vertices[v].pos());
+            } else {
+                group_of[v] = group_of[rep];
+            }
+        }
+
+        let num_groups = group_pos.len();
+        let mut group_faces = vec![Vec::new(); num_groups];
+        let mut face_alive = vec![true; num_faces];
+        let mut live_faces = 0;
+
+        for f in 0..num_faces {
+            let g = [0, 1, 2].map(|k| group_of[mesh.indices[f * 3 + k] as usize]);
+            if g[0] == g[1] || g[1] == g[2] || g[2] == g[0] {
+                face_alive[f] = false;
+                continue;
+            }
+            for g in g {
+                group_faces[g as usize].push(f as u32);
+            }
+            live_faces += 1;
+        }
+
+        let mut s = Simplifier {
+            options,
+            attributes: &mesh.attributes,
+            indices: mesh.indices.clone(),
+            face_alive,
+            live_faces,
+            group_of,
+            group_pos,
+            group_faces,
+            group_alive: vec![true; num_groups],
+            group_version: vec![0; num_groups],
+            group_locked: vec![false; num_groups],
+            quadrics: vec![Quadric::default(); num_groups],
+            heap: BinaryHeap::new(),
+            error: 0.0,
+            _vertex: std::marker::PhantomData,
+        };
+
+        s.init_quadrics();
+        s
+    }
+
+    fn init_quadrics(&mut self) {
+        for f in 0..self.face_alive.len() {
+            if !self.face_alive[f] {
+                continue;
+            }
+
+            let g = self.face_groups(f);
+            let p = g.map(|g| self.group_pos[g as usize]);
+            let n = (p[1] - p[0]).cross(p[2] - p[0]);
+            let area = n.length() * 0.5;
+            let n = n.normalize();
+
+            let q = Quadric::from_plane(n, -n.dot(p[0]), area);
+            for g in g {
+                self.quadrics[g as usize].add(&q);
+            }
+
+            // Planes through feature edges, perpendicular to the face, keep the
+            // edges from drifting sideways.
+            for k in 0..3 {
+                let (a, b) = (g[k], g[(k + 1) % 3]);
+                if !self.is_feature_edge(a, b) {
+                    continue;
+                }
+
+                let e = p[(k + 1) % 3] - p[k];
+                let side = e.cross(n).normalize();
+                let q = Quadric::from_plane(side, -side.dot(p[k]), e.length_sq() * self.options.feature_weight);
+                self.quadrics[a as usize].add(&q);
+                self.quadrics[b as usize].add(&q);
+
+                if self.options.lock_borders && self.faces_on_edge(a, b).len() == 1 {
+                    self.group_locked[a as usize] = true;
+                    self.group_locked[b as usize] = true;
+                }
+            }
+        }
+    }
+
+    fn run(&mut self) {
+        for g in 0..self.group_pos.len() as u32 {
+            self.push_edges(g);
+        }
+
+        while self.live_faces > self.options.target_faces {
+            let c = match self.heap.pop() {
+                Some(c) => c,
+                None => break,
+            };
+
+            let (p, q) = (c.from as usize, c.to as usize);
+            if !self.group_alive[p] || !self.group_alive[q] ||
+                self.group_version[p] != c.from_version || self.group_version[q] != c.to_version {
+                continue; // Stale entry.
+            }
+
+            let distance = self.quadrics[p].sum(&self.quadrics[q]).distance(self.group_pos[q]);
+            if distance > self.options.max_error as f64 {
+                break;
+            }
+
+            if let Some(wedge_map) = self.check_collapse(c.from, c.to) {
+                self.collapse(c.from, c.to, &wedge_map);
+                self.error = self.error.max(distance);
+            }
+        }
+    }
+
+    //===============================================================
+    // Connectivity queries.
+
+    fn face_groups(&self, f: usize) -> [u32; 3] {
+        [0, 1, 2].map(|k| self.group_of[self.indices[f * 3 + k] as usize])
+    }
+
+    fn live_faces_of(&self, g: u32) -> impl Iterator<Item = usize> + '_ {
+        self.group_faces[g as usize].iter().map(|f| *f as usize).filter(move |f| self.face_alive[*f])
+    }
+
+    fn neighbours(&self, g: u32) -> Vec<u32> {
+        let mut res: Vec<u32> = self.live_faces_of(g)
+            .flat_map(|f| self.face_groups(f))
+            .filter(|n| *n != g)
+            .collect();
+        res.sort_unstable();
+        res.dedup();
+        res
+    }
+
+    fn faces_on_edge(&self, a: u32, b: u32) -> Vec<usize> {
+        self.live_faces_of(a).filter(|f| self.face_groups(*f).contains(&b)).collect()
+    }
+
+    // Vertex used by face 'f' at group 'g'.
+    fn wedge(&self, f: usize, g: u32) -> u32 {
+        let k = self.face_groups(f).iter().position(|x| *x == g).unwrap();
+        self.indices[f * 3 + k]
+    }
+
+    // Open border, non-manifold edge, uv/normal seam or material boundary.
+    fn is_feature_edge(&self, a: u32, b: u32) -> bool {
+        let faces = self.faces_on_edge(a, b);
+        if faces.len() != 2 {
+            return true;
+        }
+
+        let (f0, f1) = (faces[0], faces[1]);
+        self.attributes[f0] != self.attributes[f1] ||
+            self.wedge(f0, a) != self.wedge(f1, a) ||
+            self.wedge(f0, b) != self.wedge(f1, b)
+    }
+
+    //===============================================================
+    // Collapses.
+
+    fn push_edges(&mut self, g: u32) {
+        for n in self.neighbours(g) {
+            for (from, to) in [(g, n), (n, g)] {
+                if self.group_locked[from as usize] {
+                    continue;
+                }
+
+                let q = self.quadrics[from as usize].sum(&self.quadrics[to as usize]);
+                self.heap.push(Candidate {
+                    cost: q.error(self.group_pos[to as usize]),
+                    from,
+                    to,
+                    from_version: self.group_version[from as usize],
+                    to_version: self.group_version[to as usize],
+                });
+            }
+        }
+    }
+
+    // Checks whether P can move onto Q.  On success returns, for each vertex of P,
+    // the vertex of Q it merges into.
+    fn check_collapse(&self, p: u32, q: u32) -> Option<Vec<(u32, u32)>> {
+        let shared = self.faces_on_edge(p, q);
+        if shared.is_empty() {
+            return None;
+        }
+
+        // Vertices on feature edges may only slide along them.  Corners (one or more
+        // than two feature edges) stay put.
+        let p_neighbours = self.neighbours(p);
+        let features: Vec<u32> = p_neighbours.iter().copied().filter(|n| self.is_feature_edge(p, *n)).collect();
+        match features.len() {
+            0 => {}
+            2 if features.contains(&q) => {}
+            _ => return None,
+        }
+
+        // Link condition: the only neighbours P and Q share are the opposite
+        // corners of the faces on the edge, otherwise the collapse pinches the
+        // surface into a non-manifold shape.
+        let q_neighbours = self.neighbours(q);
+        let common = p_neighbours.iter().filter(|n| q_neighbours.contains(n)).count();
+        let mut opposite: Vec<u32> = shared.iter()
+            .flat_map(|f| self.face_groups(*f))
+            .filter(|g| *g != p && *g != q)
+            .collect();
+        opposite.sort_unstable();
+        opposite.dedup();
+        if common != opposite.len() {
+            return None;
+        }
+
+        // Each vertex of P has to find its counterpart of Q on a shared face.
+        let mut wedge_map: Vec<(u32, u32)> = Vec::new();
+        for f in &shared {
+            let u = self.wedge(*f, p);
+            if !wedge_map.iter().any(|(w, _)| *w == u) {
+                wedge_map.push((u, self.wedge(*f, q)));
+            }
+        }
+
+        let target = self.group_pos[q as usize];
+        for f in self.live_faces_of(p) {
+            if shared.contains(&f) {
+                continue;
+            }
+
+            let u = self.wedge(f, p);
+            if !wedge_map.iter().any(|(w, _)| *w == u) {
+                return None;
+            }
+
+            // The face must not flip or collapse to a sliver.
+            let g = self.face_groups(f);
+            let before = g.map(|g| self.group_pos[g as usize]);
+            let after = g.map(|g| if g == p { target } else { self.group_pos[g as usize] });
+
+            let n0 = (before[1] - before[0]).cross(before[2] - before[0]);
+            let n1 = (after[1] - after[0]).cross(after[2] - after[0]);
+            if n1.length_sq() <= n0.length_sq() * 1.0e-6 || n0.normalize().dot(n1.normalize()) < 0.2 {
+                return None;
+            }
+        }
+
+        Some(wedge_map)
+    }
+
+    fn collapse(&mut self, p: u32, q: u32, wedge_map: &[(u32, u32)]) {
+        let faces: Vec<usize> = self.live_faces_of(p).collect();
+
+        for f in faces {
+            if self.face_groups(f).contains(&q) {
+                self.face_alive[f] = false;
+                self.live_faces -= 1;
+                continue;
+            }
+
+            for k in 0..3 {
+                let i = self.indices[f * 3 + k];
+                if let Some((_, v)) = wedge_map.iter().find(|(u, _)| *u == i) {
+                    self.indices[f * 3 + k] = *v;
+                }
+            }
+            self.group_faces[q as usize].push(f as u32);
+        }
+
+        let qp = self.quadrics[p as usize];
+        self.quadrics[q as usize].add(&qp);
+
+        self.group_alive[p as usize] = false;
+        self.group_faces[p as usize].clear();
+        self.group_version[p as usize] += 1;
+        self.group_version[q as usize] += 1;
+
+        let live: Vec<u32> = self.live_faces_of(q).map(|f| f as u32).collect();
+        self.group_faces[q as usize] = live;
+
+        self.push_edges(q);
+    }
+}
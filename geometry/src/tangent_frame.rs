@@ -0,0 +1,203 @@
+// Per-vertex tangent frames for normal mapping, a native replacement for
+// D3DXComputeTangentFrameEx.
+//
+// Uses Lengyel's method: each face contributes the derivatives of position with
+// respect to u (tangent) and v (binormal), weighted by the corner angle, and the
+// sums are Gram-Schmidt orthonormalized against the vertex normal.  Faces whose
+// texture mapping is mirrored have the opposite handedness; vertices shared by
+// faces of both handedness are split so each copy gets a consistent frame.
+
+use crate::math::*;
+use crate::mesh::*;
+
+#[derive(Copy, Clone, Debug)]
+pub struct TangentFrameOptions {
+    // Duplicate vertices shared by mirrored and non-mirrored faces.  When false,
+    // such vertices take the handedness of the majority of their faces.
+    pub split_mirrored: bool,
+    // Recompute the normals from the faces instead of using the input normals.
+    pub recompute_normals: bool,
+    // Faces whose uv area (absolute determinant) is below this value do not
+    // contribute a tangent direction.
+    pub degenerate_uv_threshold: f32,
+    // Tangents shorter than this after accumulation are considered singular and
+    // replaced by an arbitrary direction perpendicular to the normal.
+    pub singular_point_threshold: f32,
+}
+
+impl Default for TangentFrameOptions {
+    fn default() -> Self {
+        TangentFrameOptions {
+            split_mirrored: true,
+            recompute_normals: false,
+            degenerate_uv_threshold: 1.0e-12,
+            singular_point_threshold: 1.0e-6,
+        }
+    }
+}
+
+pub struct TangentFrameResult {
+    pub mesh: Mesh<NMapVertex>,
+    // +1 or -1 per output vertex: sign of dot(cross(normal, tangent), binormal)
+    // before orthonormalization, i.e. whether the uv mapping is mirrored.
+    pub handedness: Vec<f32>,
+    // Index of the input vertex each output vertex was created from.
+    pub vertex_remap: Vec<u32>,
+}
+
+pub fn compute_tangent_frame<V: Vertex>(mesh: &Mesh<V>, options: &TangentFrameOptions) -> TangentFrameResult {
+    let num_faces = mesh.num_faces();
+
+    //===============================================================
+    // Per-face derivatives dP/du and dP/dv and the face handedness.
+
+    let mut face_tangents = vec![Vec3::ZERO; num_faces];
+    let mut face_binormals = vec![Vec3::ZERO; num_faces];
+    let mut face_signs = vec![1.0f32; num_faces];
+
+    for f in 0..num_faces {
+        let [i0, i1, i2] = mesh.face(f);
+        let v0 = &mesh.vertices[i0 as usize];
+        let v1 = &mesh.vertices[i1 as usize];
+        let v2 = &mesh.vertices[i2 as usize];
+
+        let t0 = v0.tex0().unwrap_or(Vec2::ZERO);
+        let t1 = v1.tex0().unwrap_or(Vec2::ZERO);
+        let t2 = v2.tex0().unwrap_or(Vec2::ZERO);
+
+        let e1 = v1.pos() - v0.pos();
+        let e2 = v2.pos() - v0.pos();
+
+        let du1 = t1.x - t0.x;
+        let dv1 = t1.y - t0.y;
+        let du2 = t2.x - t0.x;
+        let dv2 = t2.y - t0.y;
+
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() <= options.degenerate_uv_threshold {
+            continue;
+        }
+
+        let r = 1.0 / det;
+        let sdir = (e1 * dv2 - e2 * dv1) * r;
+        let tdir = (e2 * du1 - e1 * du2) * r;
+
+        face_tangents[f] = sdir;
+        face_binormals[f] = tdir;
+
+        let n = e1.cross(e2);
+        face_signs[f] = if n.cross(sdir).dot(tdir) < 0.0 { -1.0 } else { 1.0 };
+    }
+
+    //===============================================================
+    // Decide which output vertex each face corner uses.  A vertex keeps its
+    // index for faces of the handedness most of its faces have; faces of the
+    // other handedness get a duplicate when splitting is enabled.
+
+    let mut sign_votes = vec![0.0f32; mesh.num_vertices()];
+    for (f, sign) in face_signs.iter().enumerate() {
+        for i in mesh.face(f) {
+            sign_votes[i as usize] += sign;
+        }
+    }
+
+    let mut vertex_remap: Vec<u32> = (0..mesh.num_vertices() as u32).collect();
+    let mut handedness: Vec<f32> = sign_votes.iter().map(|s| if *s < 0.0 { -1.0 } else { 1.0 }).collect();
+    let mut mirrored_copy: Vec<Option<u32>> = vec![None; mesh.num_vertices()];
+    let mut indices = mesh.indices.clone();
+
+    if options.split_mirrored {
+        for f in 0..num_faces {
+            for k in 0..3 {
+                let i = mesh.indices[f * 3 + k] as usize;
+                if face_signs[f] == handedness[i] {
+                    continue;
+                }
+
+                let copy = match mirrored_copy[i] {
+                    Some(copy) => copy,
+                    None => {
+                        let copy = vertex_remap.len() as u32;
+                        vertex_remap.push(i as u32);
+                        handedness.push(face_signs[f]);
+                        mirrored_copy[i] = Some(copy);
+                        copy
+                    }
+                };
+
+                indices[f * 3 + k] = copy;
+            }
+        }
+    }
+
+    //===============================================================
+    // Accumulate angle-weighted face vectors onto the output vertices.
+
+    let num_out = vertex_remap.len();
+
+    let mut tangents = vec![Vec3::ZERO; num_out];
+    let mut binormals = vec![Vec3::ZERO; num_out];
+    let mut normals = vec![Vec3::ZERO; num_out];
+
+    for f in 0..num_faces {
+        let p = mesh.face_positions(f);
+        let n = (p[1] - p[0]).cross(p[2] - p[0]).normalize();
+
+        for k in 0..3 {
+            let out = indices[f * 3 + k] as usize;
+            let angle = corner_angle(p[k], p[(k + 1) % 3], p[(k + 2) % 3]);
+
+            tangents[out] += face_tangents[f].normalize() * angle;
+            binormals[out] += face_binormals[f].normalize() * angle;
+            normals[out] += n * angle;
+        }
+    }
+
+    //===============================================================
+    // Orthonormalize.
+
+    let mut vertices = Vec::with_capacity(num_out);
+
+    for out in 0..num_out {
+        let src = &mesh.vertices[vertex_remap[out] as usize];
+
+        let mut n = if options.recompute_normals {
+            normals[out].normalize()
+        } else {
+            src.normal().unwrap_or(normals[out]).normalize()
+        };
+        if n.length_sq() == 0.0 {
+            n = normals[out].normalize();
+        }
+
+        // Gram-Schmidt: remove the normal component from the tangent.
+        let mut t = tangents[out] - n * n.dot(tangents[out]);
+        if t.length() < options.singular_point_threshold {
+            // No usable uv derivative, try the binormal before giving up.
+            let b = binormals[out] - n * n.dot(binormals[out]);
+            t = if b.length() >= options.singular_point_threshold {
+                b.cross(n) * handedness[out]
+            } else {
+                n.any_orthogonal()
+            };
+        }
+        let t = t.normalize();
+
+        // The binormal follows from the other two axes and the handedness.
+        let b = n.cross(t) * handedness[out];
+
+        vertices.push(NMapVertex {
+            pos: src.pos(),
+            tangent: t,
+            binormal: b,
+            normal: n,
+            tex0: src.tex0().unwrap_or(Vec2::ZERO),
+        });
+    }
+
+    TangentFrameResult {
+        mesh: Mesh::with_attributes(vertices, indices, mesh.attributes.clone()),
+        handedness,
+        vertex_remap,
+    }
+}
@@ -0,0 +1,218 @@
+// Texture coordinate generation by projection, for meshes that come without
+// tex-coords (D3DXCreate* shapes, scanned or procedural meshes).
+//
+// Positions are first moved into projector space by a transform, so the
+// projections below can be scaled, rotated and offset freely.  The wrapping
+// projections duplicate vertices on the seam where u goes from 1 back to 0, and
+// at the poles where u is undefined, so no face stretches across the texture.
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use crate::math::*;
+use crate::mesh::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UvProjection {
+    // Along a direction onto the plane perpendicular to it, one texture repeat per
+    // projector space unit.
+    Planar(Vec3),
+    // Every face is projected along the axis its normal is closest to, also known
+    // as box or triplanar mapping.  Same scale as Planar.
+    Box,
+    // Longitude and latitude about the origin: u = atan2(z, x) / 2pi and v from 0
+    // at +y to 1 at -y, as gen_spherical_tex_coords computes.
+    Spherical,
+    // Around an axis through the origin.  u is the angle about the axis and v goes
+    // from 0 at the top of the mesh to 1 at the bottom, as gen_cyl_tex_coords
+    // computes.
+    Cylindrical(Vec3),
+}
+
+// Overwrites the tex-coords of every vertex.  Returns the number of vertices
+// added to the mesh to cut seams.
+pub fn generate_tex_coords<V: Vertex>(mesh: &mut Mesh<V>, projection: UvProjection, transform: &Mat4) -> usize {
+    let num_vertices = mesh.vertices.len();
+    let positions: Vec<Vec3> = mesh.vertices.iter().map(|v| transform.transform_coord(v.pos())).collect();
+
+    match projection {
+        UvProjection::Planar(dir) => {
+            let (right, up) = planar_basis(dir);
+            for (v, p) in mesh.vertices.iter_mut().zip(&positions) {
+                v.set_tex0(Vec2::new(p.dot(right), -p.dot(up)));
+            }
+        }
+        UvProjection::Box => box_tex_coords(mesh, &positions),
+        UvProjection::Spherical => {
+            let coords: Vec<WrappedCoord> = positions.iter()
+                .map(|p| {
+                    let r = p.length();
+                    let phi = if r > 0.0 { (p.y / r).clamp(-1.0, 1.0).acos() } else { 0.0 };
+                    WrappedCoord {
+                        u: wrap_angle(p.z.atan2(p.x)),
+                        v: phi / PI,
+                        pole: p.x * p.x + p.z * p.z <= (r * 1.0e-5) * (r * 1.0e-5),
+                    }
+                })
+                .collect();
+            cut_seams(mesh, &coords);
+        }
+        UvProjection::Cylindrical(axis) => {
+            let axis = axis.normalize();
+            let e1 = cylinder_reference(axis);
+            let e2 = e1.cross(axis);
+
+            let heights: Vec<f32> = positions.iter().map(|p| p.dot(axis)).collect();
+            let top = heights.iter().cloned().fold(f32::MIN, f32::max);
+            let bottom = heights.iter().cloned().fold(f32::MAX, f32::min);
+            let height = if top > bottom { top - bottom } else { 1.0 };
+
+            let max_radius = positions.iter().map(|p| (*p - axis * p.dot(axis)).length()).fold(0.0, f32::max);
+
+            let coords: Vec<WrappedCoord> = positions.iter().zip(&heights)
+                .map(|(p, h)| {
+                    let (x, y) = (p.dot(e1), p.dot(e2));
+                    WrappedCoord {
+                        u: wrap_angle(y.atan2(x)),
+                        v: (top - h) / height,
+                        pole: (x * x + y * y).sqrt() <= max_radius * 1.0e-5,
+                    }
+                })
+                .collect();
+            cut_seams(mesh, &coords);
+        }
+    }
+
+    mesh.vertices.len() - num_vertices
+}
+
+// Right and up directions of a projector looking along 'look', chosen so the
+// texture is not mirrored when seen from where the projector is.
+fn planar_basis(look: Vec3) -> (Vec3, Vec3) {
+    let look = look.normalize();
+    let up = if look.y.abs() > 0.99 { Vec3::Z } else { Vec3::Y };
+    let right = up.cross(look).normalize();
+    (right, look.cross(right))
+}
+
+// Direction around the axis u starts from: x when possible, so the y axis gives
+// the same u as gen_cyl_tex_coords.
+fn cylinder_reference(axis: Vec3) -> Vec3 {
+    let reference = if axis.x.abs() < 0.9 { Vec3::X } else { Vec3::Z };
+    (reference - axis * reference.dot(axis)).normalize()
+}
+
+// Angle in (-pi, pi] to [0, 1).
+fn wrap_angle(theta: f32) -> f32 {
+    let u = theta / (2.0 * PI);
+    if u < 0.0 { u + 1.0 } else { u }
+}
+
+fn box_tex_coords<V: Vertex>(mesh: &mut Mesh<V>, positions: &[Vec3]) {
+    // Projector per face direction: -x, +x, -y, +y, -z, +z.  Each looks back at
+    // faces pointing its way.
+    let axes = [Vec3::X, Vec3::Y, Vec3::Z];
+    let bases: Vec<(Vec3, Vec3)> = (0..6)
+        .map(|d| {
+            let sign = if d % 2 == 1 { 1.0 } else { -1.0 };
+            planar_basis(-axes[d / 2] * sign)
+        })
+        .collect();
+
+    // Direction each vertex got its tex-coords for, and copies made for others.
+    let mut vertex_dir: Vec<Option<usize>> = vec![None; mesh.vertices.len()];
+    let mut copies: HashMap<(u32, usize), u32> = HashMap::new();
+
+    for f in 0..mesh.num_faces() {
+        let idx = mesh.face(f);
+        let [p0, p1, p2] = [positions[idx[0] as usize], positions[idx[1] as usize], positions[idx[2] as usize]];
+        let n = (p1 - p0).cross(p2 - p0);
+
+        let a = n.abs();
+        let axis = if a.x >= a.y && a.x >= a.z { 0 } else if a.y >= a.z { 1 } else { 2 };
+        let d = axis * 2 + if n[axis] >= 0.0 { 1 } else { 0 };
+        let (right, up) = bases[d];
+
+        for (k, i) in idx.iter().enumerate() {
+            let p = positions[*i as usize];
+            let tex0 = Vec2::new(p.dot(right), -p.dot(up));
+
+            match vertex_dir[*i as usize] {
+                None => {
+                    vertex_dir[*i as usize] = Some(d);
+                    mesh.vertices[*i as usize].set_tex0(tex0);
+                }
+                Some(first) if first == d => {}
+                Some(_) => {
+                    let vertices = &mut mesh.vertices;
+                    let copy = *copies.entry((*i, d)).or_insert_with(|| {
+                        let mut v = vertices[*i as usize];
+                        v.set_tex0(tex0);
+                        vertices.push(v);
+                        (vertices.len() - 1) as u32
+                    });
+                    mesh.indices[f * 3 + k] = copy;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct WrappedCoord {
+    u: f32,
+    v: f32,
+    // On the axis, where u is meaningless.
+    pole: bool,
+}
+
+// Writes the coordinates, then gives faces straddling the seam copies of their
+// low-u vertices with u + 1, and faces touching a pole their own copy of the
+// pole vertex with u in the middle of their other corners.
+fn cut_seams<V: Vertex>(mesh: &mut Mesh<V>, coords: &[WrappedCoord]) {
+    for (v, c) in mesh.vertices.iter_mut().zip(coords) {
+        v.set_tex0(Vec2::new(c.u, c.v));
+    }
+
+    let mut shifted: HashMap<u32, u32> = HashMap::new();
+
+    for f in 0..mesh.num_faces() {
+        let idx = mesh.face(f);
+        let corners: Vec<usize> = (0..3).filter(|k| !coords[idx[*k] as usize].pole).collect();
+        if corners.is_empty() {
+            continue;
+        }
+
+        let lo = corners.iter().map(|k| coords[idx[*k] as usize].u).fold(f32::MAX, f32::min);
+        let hi = corners.iter().map(|k| coords[idx[*k] as usize].u).fold(f32::MIN, f32::max);
+        let straddles = hi - lo > 0.5;
+
+        let mut u_sum = 0.0;
+        for k in &corners {
+            let i = idx[*k];
+            let c = coords[i as usize];
+
+            if straddles && c.u < 0.5 {
+                let vertices = &mut mesh.vertices;
+                let copy = *shifted.entry(i).or_insert_with(|| {
+                    let mut v = vertices[i as usize];
+                    v.set_tex0(Vec2::new(c.u + 1.0, c.v));
+                    vertices.push(v);
+                    (vertices.len() - 1) as u32
+                });
+                mesh.indices[f * 3 + k] = copy;
+                u_sum += c.u + 1.0;
+            } else {
+                u_sum += c.u;
+            }
+        }
+
+        let u_mid = u_sum / corners.len() as f32;
+        for k in (0..3).filter(|k| !corners.contains(k)) {
+            let i = idx[k];
+            let mut v = mesh.vertices[i as usize];
+            v.set_tex0(Vec2::new(u_mid, coords[i as usize].v));
+            mesh.vertices.push(v);
+            mesh.indices[f * 3 + k] = (mesh.vertices.len() - 1) as u32;
+        }
+    }
+}
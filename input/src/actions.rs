@@ -0,0 +1,208 @@
+// Named actions bound to keys, mouse buttons and mouse axes, so the demos ask
+// for "MoveForward" rather than DIK_W and the bindings can be changed in a file.
+//
+// A binding file has one action per line:
+//
+//     # Comment
+//     MoveForward = +W, -S
+//     Pitch = -MouseY * 0.5
+//     Fire = Space, Mouse1
+//
+// Each binding may have a sign and a scale.  Actions left empty are unbound.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::keys::*;
+use crate::state::*;
+
+pub const MOVE_FORWARD: &str = "MoveForward";
+pub const STRAFE: &str = "Strafe";
+pub const MOVE_UP: &str = "MoveUp";
+pub const PITCH: &str = "Pitch";
+pub const YAW: &str = "Yaw";
+pub const ROLL: &str = "Roll";
+pub const ZOOM: &str = "Zoom";
+pub const JUMP: &str = "Jump";
+pub const FIRE: &str = "Fire";
+pub const PICK: &str = "Pick";
+
+// What ActionMap::standard() holds: the camera controllers and the pickers.
+pub const DEFAULT_BINDINGS: &str = "\
+MoveForward = +W, -S
+Strafe = +D, -A
+MoveUp = +E, -Q
+Pitch = MouseY
+Yaw = MouseX
+Roll = +C, -Z
+Zoom = MouseWheel
+Jump = Space
+Fire = LControl
+Pick = Mouse0
+";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BindingError {
+    // 1-based.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for BindingError {}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ActionMap {
+    // In the order they were bound, so saving keeps the file's order.
+    actions: Vec<(String, Vec<(Binding, f32)>)>,
+}
+
+impl ActionMap {
+    pub fn new() -> ActionMap {
+        ActionMap::default()
+    }
+
+    // The standard actions with DEFAULT_BINDINGS.
+    pub fn standard() -> ActionMap {
+        ActionMap::parse(DEFAULT_BINDINGS).unwrap()
+    }
+
+    pub fn parse(text: &str) -> Result<ActionMap, BindingError> {
+        let mut map = ActionMap::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: String| BindingError { line: i + 1, message };
+
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (name, bindings) = line.split_once('=').ok_or_else(|| error(format!("expected 'Action = bindings', got '{}'", line)))?;
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(error(format!("bad action name '{}'", name)));
+            }
+            if map.find(name).is_some() {
+                return Err(error(format!("action '{}' bound twice", name)));
+            }
+
+            let mut list = Vec::new();
+            for token in bindings.split(',').map(|t| t.trim()).filter(|t| !t.is_empty()) {
+                list.push(parse_binding(token).ok_or_else(|| error(format!("bad binding '{}'", token)))?);
+            }
+            map.actions.push((name.to_string(), list));
+        }
+
+        Ok(map)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ActionMap> {
+        let text = fs::read_to_string(path)?;
+        ActionMap::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    // Takes every action of 'other', replacing the bindings of actions both have.
+    // Used to apply a binding file on top of the defaults.
+    pub fn extend(&mut self, other: &ActionMap) {
+        for (name, bindings) in &other.actions {
+            match self.find(name) {
+                Some(i) => self.actions[i].1 = bindings.clone(),
+                None => self.actions.push((name.clone(), bindings.clone())),
+            }
+        }
+    }
+
+    // Adds a binding to an action, creating the action if needed.
+    pub fn bind(&mut self, action: &str, binding: Binding, scale: f32) {
+        match self.find(action) {
+            Some(i) => self.actions[i].1.push((binding, scale)),
+            None => self.actions.push((action.to_string(), vec![(binding, scale)])),
+        }
+    }
+
+    // Removes every binding of an action.  The action stays known.
+    pub fn unbind(&mut self, action: &str) {
+        if let Some(i) = self.find(action) {
+            self.actions[i].1.clear();
+        }
+    }
+
+    pub fn get_bindings(&self, action: &str) -> &[(Binding, f32)] {
+        self.find(action).map_or(&[], |i| &self.actions[i].1)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.iter().map(|(name, _)| name.as_str())
+    }
+
+    // True when any binding of the action is active: a key or button held, or
+    // a mouse axis moving.
+    pub fn down(&self, input: &dyn InputState, action: &str) -> bool {
+        self.get_bindings(action).iter().any(|(binding, _)| binding.value(input) != 0.0)
+    }
+
+    // Sum of the action's bindings times their scales.  Keys and buttons add up
+    // to at most 1 either way, so W and Up bound together do not double the
+    // speed; mouse axes are added on top unclamped.
+    pub fn axis(&self, input: &dyn InputState, action: &str) -> f32 {
+        let mut digital = 0.0;
+        let mut analog = 0.0;
+
+        for (binding, scale) in self.get_bindings(action) {
+            if binding.is_analog() {
+                analog += binding.value(input) * scale;
+            } else {
+                digital += binding.value(input) * scale;
+            }
+        }
+
+        f32::clamp(digital, -1.0, 1.0) + analog
+    }
+
+    fn find(&self, action: &str) -> Option<usize> {
+        self.actions.iter().position(|(name, _)| name == action)
+    }
+}
+
+impl fmt::Display for ActionMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, bindings) in &self.actions {
+            write!(f, "{} =", name)?;
+            for (i, (binding, scale)) in bindings.iter().enumerate() {
+                let sign = if *scale < 0.0 { "-" } else { "" };
+                write!(f, "{} {}{}", if i == 0 { "" } else { "," }, sign, binding)?;
+                if scale.abs() != 1.0 {
+                    write!(f, " * {}", scale.abs())?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// [+|-] name [* scale]
+fn parse_binding(token: &str) -> Option<(Binding, f32)> {
+    let (sign, rest) = match token.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, token.strip_prefix('+').unwrap_or(token)),
+    };
+
+    let (name, scale) = match rest.split_once('*') {
+        Some((name, scale)) => (name, scale.trim().parse::<f32>().ok().filter(|s| s.is_finite())?),
+        None => (rest, 1.0),
+    };
+
+    Binding::from_name(name.trim()).map(|binding| (binding, sign * scale))
+}
@@ -0,0 +1,117 @@
+// Names of keys and mouse controls as written in binding files.
+
+use std::fmt;
+use crate::state::*;
+
+// DirectInput scan codes (the DIK_* values) by name.
+pub const KEY_NAMES: &[(&str, usize)] = &[
+    ("Escape", 0x01),
+    ("1", 0x02), ("2", 0x03), ("3", 0x04), ("4", 0x05), ("5", 0x06),
+    ("6", 0x07), ("7", 0x08), ("8", 0x09), ("9", 0x0A), ("0", 0x0B),
+    ("Minus", 0x0C), ("Equals", 0x0D), ("Backspace", 0x0E), ("Tab", 0x0F),
+    ("Q", 0x10), ("W", 0x11), ("E", 0x12), ("R", 0x13), ("T", 0x14),
+    ("Y", 0x15), ("U", 0x16), ("I", 0x17), ("O", 0x18), ("P", 0x19),
+    ("LBracket", 0x1A), ("RBracket", 0x1B), ("Return", 0x1C), ("LControl", 0x1D),
+    ("A", 0x1E), ("S", 0x1F), ("D", 0x20), ("F", 0x21), ("G", 0x22),
+    ("H", 0x23), ("J", 0x24), ("K", 0x25), ("L", 0x26),
+    ("Semicolon", 0x27), ("Apostrophe", 0x28), ("Grave", 0x29), ("LShift", 0x2A), ("Backslash", 0x2B),
+    ("Z", 0x2C), ("X", 0x2D), ("C", 0x2E), ("V", 0x2F), ("B", 0x30),
+    ("N", 0x31), ("M", 0x32),
+    ("Comma", 0x33), ("Period", 0x34), ("Slash", 0x35), ("RShift", 0x36), ("Multiply", 0x37),
+    ("LAlt", 0x38), ("Space", 0x39), ("CapsLock", 0x3A),
+    ("F1", 0x3B), ("F2", 0x3C), ("F3", 0x3D), ("F4", 0x3E), ("F5", 0x3F),
+    ("F6", 0x40), ("F7", 0x41), ("F8", 0x42), ("F9", 0x43), ("F10", 0x44),
+    ("NumLock", 0x45), ("ScrollLock", 0x46),
+    ("Numpad7", 0x47), ("Numpad8", 0x48), ("Numpad9", 0x49), ("Subtract", 0x4A),
+    ("Numpad4", 0x4B), ("Numpad5", 0x4C), ("Numpad6", 0x4D), ("Add", 0x4E),
+    ("Numpad1", 0x4F), ("Numpad2", 0x50), ("Numpad3", 0x51), ("Numpad0", 0x52), ("Decimal", 0x53),
+    ("F11", 0x57), ("F12", 0x58),
+    ("NumpadEnter", 0x9C), ("RControl", 0x9D), ("Divide", 0xB5), ("RAlt", 0xB8),
+    ("Home", 0xC7), ("Up", 0xC8), ("PageUp", 0xC9), ("Left", 0xCB), ("Right", 0xCD),
+    ("End", 0xCF), ("Down", 0xD0), ("PageDown", 0xD1), ("Insert", 0xD2), ("Delete", 0xD3),
+];
+
+// Case-insensitive.
+pub fn key_from_name(name: &str) -> Option<usize> {
+    KEY_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, key)| *key)
+}
+
+pub fn key_name(key: usize) -> Option<&'static str> {
+    KEY_NAMES.iter().find(|(_, k)| *k == key).map(|(name, _)| *name)
+}
+
+// Something an action can be bound to.  Keys and buttons are 0 or 1, the mouse
+// axes give their delta for the frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(usize),
+    MouseButton(u32),
+    MouseX,
+    MouseY,
+    MouseWheel,
+}
+
+impl Binding {
+    // Key names from KEY_NAMES, "Mouse0" to "Mouse7", "MouseX", "MouseY" and
+    // "MouseWheel", or "Key" followed by a scan code in hex for keys without a
+    // name.  Case-insensitive.
+    pub fn from_name(name: &str) -> Option<Binding> {
+        let lower = name.to_ascii_lowercase();
+
+        match lower.as_str() {
+            "mousex" => return Some(Binding::MouseX),
+            "mousey" => return Some(Binding::MouseY),
+            "mousewheel" => return Some(Binding::MouseWheel),
+            _ => {}
+        }
+
+        if let Some(button) = lower.strip_prefix("mouse") {
+            return match button.parse::<u32>() {
+                Ok(b) if (b as usize) < NUM_MOUSE_BUTTONS => Some(Binding::MouseButton(b)),
+                _ => None,
+            };
+        }
+
+        if let Some(key) = key_from_name(name) {
+            return Some(Binding::Key(key));
+        }
+
+        lower.strip_prefix("key")
+            .and_then(|code| usize::from_str_radix(code.trim_start_matches("0x"), 16).ok())
+            .filter(|key| *key < NUM_KEYS)
+            .map(Binding::Key)
+    }
+
+    // Digital bindings are held or not; analog ones (the mouse axes) have a value
+    // every frame.
+    pub fn is_analog(&self) -> bool {
+        matches!(self, Binding::MouseX | Binding::MouseY | Binding::MouseWheel)
+    }
+
+    pub fn value(&self, input: &dyn InputState) -> f32 {
+        let down = |d: bool| if d { 1.0 } else { 0.0 };
+
+        match *self {
+            Binding::Key(key) => down(input.key_down(key)),
+            Binding::MouseButton(button) => down(input.mouse_button_down(button)),
+            Binding::MouseX => input.mouse_dx(),
+            Binding::MouseY => input.mouse_dy(),
+            Binding::MouseWheel => input.mouse_dz(),
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Binding::Key(key) => match key_name(key) {
+                Some(name) => write!(f, "{}", name),
+                None => write!(f, "Key{:02X}", key),
+            },
+            Binding::MouseButton(button) => write!(f, "Mouse{}", button),
+            Binding::MouseX => write!(f, "MouseX"),
+            Binding::MouseY => write!(f, "MouseY"),
+            Binding::MouseWheel => write!(f, "MouseWheel"),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod actions;
+pub mod keys;
+pub mod record;
+pub mod state;
+
+pub use crate::actions::*;
+pub use crate::keys::*;
+pub use crate::record::*;
+pub use crate::state::*;
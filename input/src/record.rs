@@ -0,0 +1,337 @@
+// Recording of the input of every frame together with its dt, to replay a
+// session exactly: with the same frame times and input, the demos compute the
+// same camera paths and particle bursts.
+//
+// File layout, little-endian:
+//
+//     header: "LUNAINPT", version u16, seed u64, frame count u32
+//     frame:  dt f32, flags u8, then what the flags say changed since the
+//             previous frame (which starts all released):
+//             MOUSE_MOVED    dx, dy, dz f32
+//             BUTTONS        button mask u8
+//             KEYS           count u16, then the scan codes that toggled as u8
+//
+// An idle frame is 5 bytes.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use crate::state::*;
+
+const MAGIC: &[u8; 8] = b"LUNAINPT";
+const VERSION: u16 = 1;
+
+const MOUSE_MOVED: u8 = 0x01;
+const BUTTONS: u8 = 0x02;
+const KEYS: u8 = 0x04;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct InputFrame {
+    pub dt: f32,
+    pub input: MemoryInput,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputRecording {
+    // For the random number generators of the session, so random effects
+    // replay too.
+    pub seed: u64,
+    frames: Vec<InputFrame>,
+}
+
+impl InputRecording {
+    pub fn new(seed: u64) -> InputRecording {
+        InputRecording { seed, frames: Vec::new() }
+    }
+
+    // Appends a snapshot of 'input' for a frame that lasted 'dt' seconds.
+    pub fn push(&mut self, dt: f32, input: &dyn InputState) {
+        self.frames.push(InputFrame { dt, input: MemoryInput::from_state(input) });
+    }
+
+    pub fn get_frames(&self) -> &[InputFrame] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // Length of the session in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|f| f.dt).sum()
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&self.seed.to_le_bytes())?;
+        w.write_all(&(self.frames.len() as u32).to_le_bytes())?;
+
+        let mut prev = MemoryInput::new();
+        for frame in &self.frames {
+            let input = &frame.input;
+
+            let moved = input.mouse_dx() != 0.0 || input.mouse_dy() != 0.0 || input.mouse_dz() != 0.0;
+            let buttons = button_mask(input);
+            let toggled: Vec<u8> = (0..NUM_KEYS)
+                .filter(|k| input.key_down(*k) != prev.key_down(*k))
+                .map(|k| k as u8)
+                .collect();
+
+            let mut flags = 0;
+            if moved { flags |= MOUSE_MOVED; }
+            if buttons != button_mask(&prev) { flags |= BUTTONS; }
+            if !toggled.is_empty() { flags |= KEYS; }
+
+            w.write_all(&frame.dt.to_le_bytes())?;
+            w.write_all(&[flags])?;
+            if moved {
+                for d in [input.mouse_dx(), input.mouse_dy(), input.mouse_dz()] {
+                    w.write_all(&d.to_le_bytes())?;
+                }
+            }
+            if flags & BUTTONS != 0 {
+                w.write_all(&[buttons])?;
+            }
+            if !toggled.is_empty() {
+                w.write_all(&(toggled.len() as u16).to_le_bytes())?;
+                w.write_all(&toggled)?;
+            }
+
+            prev = *input;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(r: &mut R) -> io::Result<InputRecording> {
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an input recording"));
+        }
+
+        let version = u16::from_le_bytes(read_array(r)?);
+        if version != VERSION {
+            return Err(invalid_data(&format!("unsupported input recording version {}", version)));
+        }
+
+        let seed = u64::from_le_bytes(read_array(r)?);
+        let num_frames = u32::from_le_bytes(read_array(r)?) as usize;
+
+        let mut recording = InputRecording::new(seed);
+        // The count comes from the file, so do not trust it for the allocation.
+        recording.frames.reserve(num_frames.min(1 << 16));
+
+        let mut input = MemoryInput::new();
+        for _ in 0..num_frames {
+            let dt = f32::from_le_bytes(read_array(r)?);
+            let [flags] = read_array(r)?;
+            if flags & !(MOUSE_MOVED | BUTTONS | KEYS) != 0 {
+                return Err(invalid_data("bad frame flags"));
+            }
+
+            if flags & MOUSE_MOVED != 0 {
+                let dx = f32::from_le_bytes(read_array(r)?);
+                let dy = f32::from_le_bytes(read_array(r)?);
+                let dz = f32::from_le_bytes(read_array(r)?);
+                input.set_mouse_delta(dx, dy, dz);
+            } else {
+                input.set_mouse_delta(0.0, 0.0, 0.0);
+            }
+
+            if flags & BUTTONS != 0 {
+                let [mask] = read_array(r)?;
+                for button in 0..NUM_MOUSE_BUTTONS as u32 {
+                    input.set_mouse_button(button, mask & (1 << button) != 0);
+                }
+            }
+
+            if flags & KEYS != 0 {
+                let count = u16::from_le_bytes(read_array(r)?) as usize;
+                let mut keys = vec![0u8; count];
+                r.read_exact(&mut keys)?;
+                for key in keys {
+                    let down = input.key_down(key as usize);
+                    input.set_key(key as usize, !down);
+                }
+            }
+
+            recording.frames.push(InputFrame { dt, input });
+        }
+
+        Ok(recording)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write(&mut w)?;
+        w.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<InputRecording> {
+        InputRecording::read(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+// Hands out the frames of a recording one at a time.
+#[derive(Clone, Debug)]
+pub struct InputPlayback {
+    recording: InputRecording,
+    next: usize,
+}
+
+impl InputPlayback {
+    pub fn new(recording: InputRecording) -> InputPlayback {
+        InputPlayback { recording, next: 0 }
+    }
+
+    // The frame next_frame will return.
+    pub fn peek(&self) -> Option<&InputFrame> {
+        self.recording.frames.get(self.next)
+    }
+
+    pub fn next_frame(&mut self) -> Option<InputFrame> {
+        let frame = self.recording.frames.get(self.next).copied();
+        if frame.is_some() {
+            self.next += 1;
+        }
+        frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.recording.frames.len()
+    }
+
+    // Number of frames already played.
+    pub fn get_position(&self) -> usize {
+        self.next
+    }
+
+    pub fn rewind(&mut self) {
+        self.next = 0;
+    }
+
+    pub fn get_recording(&self) -> &InputRecording {
+        &self.recording
+    }
+}
+
+fn button_mask(input: &dyn InputState) -> u8 {
+    (0..NUM_MOUSE_BUTTONS as u32)
+        .filter(|b| input.mouse_button_down(*b))
+        .fold(0, |mask, b| mask | (1 << b))
+}
+
+fn read_array<R: Read, const N: usize>(r: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    r.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER_SIZE: usize = 8 + 2 + 8 + 4;
+    const KEY_W: usize = 0x11;
+    const KEY_SPACE: usize = 0x39;
+
+    fn to_bytes(recording: &InputRecording) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        recording.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn session() -> InputRecording {
+        let mut recording = InputRecording::new(0x1234_5678_9abc_def0);
+        let mut input = MemoryInput::new();
+
+        recording.push(0.016, &input);
+        input.press(KEY_W);
+        recording.push(0.017, &input);
+        input.set_mouse_delta(3.0, -2.0, 120.0);
+        input.set_mouse_button(0, true);
+        recording.push(0.015, &input);
+        input.set_mouse_delta(0.0, 0.0, 0.0);
+        input.press(KEY_SPACE);
+        input.press(255);
+        recording.push(0.033, &input);
+        input.release(KEY_W);
+        input.set_mouse_button(0, false);
+        input.set_mouse_button(7, true);
+        recording.push(0.001, &input);
+        input.clear();
+        recording.push(0.25, &input);
+        recording
+    }
+
+    #[test]
+    fn round_trip_keeps_every_frame() {
+        let recording = session();
+        let read = InputRecording::read(&mut to_bytes(&recording).as_slice()).unwrap();
+
+        assert_eq!(read.seed, recording.seed);
+        assert_eq!(read.len(), recording.len());
+        for (a, b) in read.get_frames().iter().zip(recording.get_frames()) {
+            assert_eq!(a.dt, b.dt);
+            assert_eq!(a.input, b.input);
+        }
+        assert_eq!(read, recording);
+    }
+
+    #[test]
+    fn idle_frames_are_compact() {
+        let mut recording = InputRecording::new(1);
+        let mut input = MemoryInput::new();
+        for _ in 0..100 {
+            recording.push(0.016, &input);
+        }
+        assert_eq!(to_bytes(&recording).len(), HEADER_SIZE + 100 * 5);
+
+        // A held key is only written on the frames it goes down and up.
+        input.press(KEY_W);
+        for _ in 0..100 {
+            recording.push(0.016, &input);
+        }
+        input.release(KEY_W);
+        recording.push(0.016, &input);
+        assert_eq!(to_bytes(&recording).len(), HEADER_SIZE + 201 * 5 + 2 * 3);
+    }
+
+    #[test]
+    fn truncated_input_is_rejected() {
+        let bytes = to_bytes(&session());
+        for len in 0..bytes.len() {
+            let err = InputRecording::read(&mut &bytes[..len]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "{} of {} bytes", len, bytes.len());
+        }
+    }
+
+    #[test]
+    fn bad_header_and_flags_are_rejected() {
+        let bytes = to_bytes(&session());
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        let mut bad_version = bytes.clone();
+        bad_version[8] = 2;
+        let mut bad_flags = bytes;
+        bad_flags[HEADER_SIZE + 4] = 0x80;
+
+        for bad in [bad_magic, bad_version, bad_flags] {
+            let err = InputRecording::read(&mut bad.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// What the demos read from the keyboard and mouse each frame, independent of
+// where it comes from: DirectInput in the demos, a MemoryInput in tools and
+// tests, or a recording.
+
+// Keys are DirectInput scan codes (DIK_*), see keys.rs.  Mouse deltas are in
+// mickeys since the last poll; dz is the wheel.
+pub trait InputState {
+    fn key_down(&self, key: usize) -> bool;
+    fn mouse_dx(&self) -> f32;
+    fn mouse_dy(&self) -> f32;
+    fn mouse_dz(&self) -> f32;
+    fn mouse_button_down(&self, button: u32) -> bool;
+}
+
+pub const NUM_KEYS: usize = 256;
+// DIMOUSESTATE2 has 8 buttons.
+pub const NUM_MOUSE_BUTTONS: usize = 8;
+
+// Input state held in memory and set by hand.  Also a snapshot of any other
+// InputState.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MemoryInput {
+    keys: [bool; NUM_KEYS],
+    buttons: [bool; NUM_MOUSE_BUTTONS],
+    mouse_delta: [f32; 3],
+}
+
+impl Default for MemoryInput {
+    fn default() -> Self {
+        MemoryInput {
+            keys: [false; NUM_KEYS],
+            buttons: [false; NUM_MOUSE_BUTTONS],
+            mouse_delta: [0.0; 3],
+        }
+    }
+}
+
+impl MemoryInput {
+    pub fn new() -> MemoryInput {
+        MemoryInput::default()
+    }
+
+    pub fn from_state(input: &dyn InputState) -> MemoryInput {
+        let mut snapshot = MemoryInput::new();
+        for key in 0..NUM_KEYS {
+            snapshot.keys[key] = input.key_down(key);
+        }
+        for button in 0..NUM_MOUSE_BUTTONS {
+            snapshot.buttons[button] = input.mouse_button_down(button as u32);
+        }
+        snapshot.mouse_delta = [input.mouse_dx(), input.mouse_dy(), input.mouse_dz()];
+        snapshot
+    }
+
+    pub fn set_key(&mut self, key: usize, down: bool) {
+        if key < NUM_KEYS {
+            self.keys[key] = down;
+        }
+    }
+
+    pub fn press(&mut self, key: usize) {
+        self.set_key(key, true);
+    }
+
+    pub fn release(&mut self, key: usize) {
+        self.set_key(key, false);
+    }
+
+    pub fn set_mouse_button(&mut self, button: u32, down: bool) {
+        if (button as usize) < NUM_MOUSE_BUTTONS {
+            self.buttons[button as usize] = down;
+        }
+    }
+
+    pub fn set_mouse_delta(&mut self, dx: f32, dy: f32, dz: f32) {
+        self.mouse_delta = [dx, dy, dz];
+    }
+
+    // Releases everything and zeroes the mouse motion.
+    pub fn clear(&mut self) {
+        *self = MemoryInput::default();
+    }
+
+    pub fn get_keys(&self) -> &[bool; NUM_KEYS] {
+        &self.keys
+    }
+
+    pub fn get_mouse_buttons(&self) -> &[bool; NUM_MOUSE_BUTTONS] {
+        &self.buttons
+    }
+}
+
+impl InputState for MemoryInput {
+    fn key_down(&self, key: usize) -> bool {
+        key < NUM_KEYS && self.keys[key]
+    }
+
+    fn mouse_dx(&self) -> f32 {
+        self.mouse_delta[0]
+    }
+
+    fn mouse_dy(&self) -> f32 {
+        self.mouse_delta[1]
+    }
+
+    fn mouse_dz(&self) -> f32 {
+        self.mouse_delta[2]
+    }
+
+    fn mouse_button_down(&self, button: u32) -> bool {
+        (button as usize) < NUM_MOUSE_BUTTONS && self.buttons[button as usize]
+    }
+}
@@ -1,9 +1,10 @@
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::UI::WindowsAndMessaging::*,
-    Win32::System::SystemServices::*, Win32::Devices::HumanInterfaceDevice::*
+    Win32::System::SystemServices::*,
 };
 
 use d3dx::*;
+use input::*;
 use libc::*;
 use crate::*;
 
@@ -191,22 +192,9 @@ impl SpriteDemo {
     fn update_ship(&mut self, dt: f32) {
         // Check input.
         unsafe {
-            if let Some(dinput) = &mut DIRECT_INPUT {
-                if dinput.key_down(DIK_A as usize) {
-                    self.ship_rotation += 4.0 * dt;
-                }
-
-                if dinput.key_down(DIK_D as usize) {
-                    self.ship_rotation -= 4.0 * dt;
-                }
-
-                if dinput.key_down(DIK_W as usize) {
-                    self.ship_speed += self.ship_accel * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.ship_speed -= self.ship_accel * dt;
-                }
+            if let Some(dinput) = &DIRECT_INPUT {
+                self.ship_rotation += 4.0 * dinput.action_axis("Turn") * dt;
+                self.ship_speed += self.ship_accel * dinput.action_axis("Thrust") * dt;
             }
 
             // Clamp top speed.
@@ -237,11 +225,12 @@ impl SpriteDemo {
             // Accumulate time.
             FIRE_DELAY += dt;
 
-            if let Some(dinput) = &mut DIRECT_INPUT {
-                // Did the user press the spacebar key and has 0.1 seconds passed?
-                // We can only fire one bullet every 0.1 seconds.  If we do not
-                // put this delay in, the ship will fire bullets way too fast.
-                if dinput.key_down(DIK_SPACE as usize) && FIRE_DELAY > 0.1 {
+            if let Some(dinput) = &DIRECT_INPUT {
+                // Did the user press fire (the spacebar by default) and has 0.1
+                // seconds passed?  We can only fire one bullet every 0.1 seconds.
+                // If we do not put this delay in, the ship will fire bullets way
+                // too fast.
+                if dinput.action_down(FIRE) && FIRE_DELAY > 0.1 {
                     let mut bullet: BulletInfo = std::mem::zeroed();
 
                     // Remember the ship is always drawn at the center of the window--
@@ -1,3 +1,5 @@
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -9,9 +11,8 @@ use crate::*;
 pub struct TriGridDemo {
     vb: IDirect3DVertexBuffer9,
     ib: IDirect3DIndexBuffer9,
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
     view: D3DXMATRIX,
     proj: D3DXMATRIX,
     num_vertices: u32,
@@ -31,18 +32,28 @@ impl TriGridDemo {
 
         let gfx_stats = GfxStats::new(d3d_device.clone(), D3DCOLOR_XRGB!(0, 0, 0));
 
-        let (vb, ib) = build_geo_buffers(d3d_device.clone());
+        let (vb, ib) = match build_geo_buffers(d3d_device.clone()) {
+            Ok(grid) => grid,
+            Err(e) => {
+                display_error_then_quit(&format!("gen_tri_grid() Failed: {}", e));
+                return None;
+            }
+        };
 
         let (fx, h_tech, h_wvp) = build_fx(d3d_device.clone());
 
         init_all_vertex_declarations(d3d_device.clone());
 
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(10.0, 5.0));
+        orbit.yaw = 1.2 * D3DX_PI;
+        orbit.pitch = f32::atan2(5.0, 10.0);
+        orbit.min_distance = 5.0;
+
         let mut tri_grid_demo = TriGridDemo {
             vb,
             ib,
-            camera_rotation_y: 1.2 * D3DX_PI,
-            camera_radius: 10.0,
-            camera_height: 5.0,
+            camera: Camera::new(),
+            orbit,
             view: unsafe { std::mem::zeroed() },
             proj: unsafe { std::mem::zeroed() },
             // Save vertex count and triangle count for DrawIndexedPrimitive arguments.
@@ -123,28 +134,8 @@ impl TriGridDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 5.0 {
-                    self.camera_radius = 5.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
             }
         }
 
@@ -208,12 +199,7 @@ impl TriGridDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
     }
 
     fn build_proj_mtx(&mut self) {
@@ -223,13 +209,13 @@ impl TriGridDemo {
     }
 }
 
-fn build_geo_buffers(d3d_device: IDirect3DDevice9) -> (IDirect3DVertexBuffer9, IDirect3DIndexBuffer9){
+fn build_geo_buffers(d3d_device: IDirect3DDevice9) -> Result<(IDirect3DVertexBuffer9, IDirect3DIndexBuffer9), GridError> {
     unsafe {
         let mut verts: Vec<D3DXVECTOR3> = Vec::new();
         let mut indices: Vec<u16> = Vec::new();
 
         gen_tri_grid(100, 100, 1.0, 1.0,
-                     D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices);
+                     D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices)?;
 
         let mut vb: Option<IDirect3DVertexBuffer9> = None;
 
@@ -267,7 +253,7 @@ fn build_geo_buffers(d3d_device: IDirect3DDevice9) -> (IDirect3DVertexBuffer9, I
             HR!(ib.Unlock());
         }
 
-        (vb.unwrap(), ib.unwrap())
+        Ok((vb.unwrap(), ib.unwrap()))
     }
 }
 
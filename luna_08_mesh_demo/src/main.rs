@@ -261,7 +261,7 @@ impl D3DApp {
                     if !self.device_is_lost() {
                         let mut curr_timestamp: i64 = 0;
                         QueryPerformanceCounter(&mut curr_timestamp);
-                        let dt: f32 = ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt;
+                        let dt = get_frame_dt(&mut DIRECT_INPUT, ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt);
 
                         if let Some(mesh_demo) = &mut self.mesh_demo {
                             mesh_demo.update_scene(dt);
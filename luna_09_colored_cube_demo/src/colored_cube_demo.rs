@@ -1,3 +1,5 @@
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -18,9 +20,8 @@ pub const MAGENTA: D3DCOLOR = D3DCOLOR_XRGB!(255, 0, 255);   // 0xffff00ff
 pub struct ColoredCubeDemo {
     vb: Option<IDirect3DVertexBuffer9>,
     ib: Option<IDirect3DIndexBuffer9>,
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
     view: D3DXMATRIX,
     proj: D3DXMATRIX,
     fx: LPD3DXEFFECT,
@@ -42,12 +43,16 @@ impl ColoredCubeDemo {
 
         init_all_vertex_declarations(d3d_device.clone());
 
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(10.0, 5.0));
+        orbit.yaw = 1.2 * D3DX_PI;
+        orbit.pitch = f32::atan2(5.0, 10.0);
+        orbit.min_distance = 5.0;
+
         let mut colored_cube_demo = ColoredCubeDemo {
             vb: ColoredCubeDemo::build_vertex_buffer(d3d_device.clone()),
             ib: ColoredCubeDemo::build_index_buffer(d3d_device.clone()),
-            camera_rotation_y: 1.2 * D3DX_PI,
-            camera_radius: 10.0,
-            camera_height: 5.0,
+            camera: Camera::new(),
+            orbit,
             view: unsafe { std::mem::zeroed() },
             proj: unsafe { std::mem::zeroed() },
             fx,
@@ -126,28 +131,8 @@ impl ColoredCubeDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 5.0 {
-                    self.camera_radius = 5.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
             }
         }
 
@@ -210,12 +195,7 @@ impl ColoredCubeDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
     }
 
     fn build_proj_mtx(&mut self) {
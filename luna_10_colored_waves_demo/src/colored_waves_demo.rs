@@ -1,3 +1,5 @@
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -18,9 +20,8 @@ pub struct ColoredWavesDemo {
     h_wvp: D3DXHANDLE,
     h_time: D3DXHANDLE,
     time: f32,
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
     view: D3DXMATRIX,
     proj: D3DXMATRIX,
 }
@@ -33,12 +34,23 @@ impl ColoredWavesDemo {
 
         let gfx_stats = GfxStats::new(d3d_device.clone(), D3DCOLOR_XRGB!(0, 0, 0));
 
-        let (vb, ib) = ColoredWavesDemo::build_geo_buffers(d3d_device.clone());
+        let (vb, ib) = match ColoredWavesDemo::build_geo_buffers(d3d_device.clone()) {
+            Ok(grid) => grid,
+            Err(e) => {
+                display_error_then_quit(&format!("gen_tri_grid() Failed: {}", e));
+                return None;
+            }
+        };
 
         let (fx, h_tech, h_wvp, h_time) = ColoredWavesDemo::build_fx(d3d_device.clone());
 
         init_all_vertex_declarations(d3d_device.clone());
 
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(25.0, 15.0));
+        orbit.yaw = 1.2 * D3DX_PI;
+        orbit.pitch = f32::atan2(15.0, 25.0);
+        orbit.min_distance = 5.0;
+
         let mut colored_waves_demo = ColoredWavesDemo {
             d3d_pp,
             gfx_stats,
@@ -50,9 +62,8 @@ impl ColoredWavesDemo {
             h_tech,
             h_wvp,
             h_time,
-            camera_radius: 25.0,
-            camera_rotation_y: 1.2 * D3DX_PI,
-            camera_height: 15.0,
+            camera: Camera::new(),
+            orbit,
             time: 0.0,
             view: unsafe { std::mem::zeroed() },
             proj: unsafe { std::mem::zeroed() },
@@ -127,28 +138,8 @@ impl ColoredWavesDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 5.0 {
-                    self.camera_radius = 5.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
 
                 // Accumulate time for simulation.
                 self.time += dt;
@@ -217,12 +208,7 @@ impl ColoredWavesDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
     }
 
     fn build_proj_mtx(&mut self) {
@@ -231,13 +217,13 @@ impl ColoredWavesDemo {
         D3DXMatrixPerspectiveFovLH(&mut self.proj, D3DX_PI * 0.25, w / h, 1.0, 5000.0);
     }
 
-    fn build_geo_buffers(d3d_device: IDirect3DDevice9) -> (IDirect3DVertexBuffer9, IDirect3DIndexBuffer9){
+    fn build_geo_buffers(d3d_device: IDirect3DDevice9) -> Result<(IDirect3DVertexBuffer9, IDirect3DIndexBuffer9), GridError> {
         unsafe {
             let mut verts: Vec<D3DXVECTOR3> = Vec::new();
             let mut indices: Vec<u16> = Vec::new();
 
             gen_tri_grid(100, 100, 0.5, 0.5,
-                         D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices);
+                         D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices)?;
 
             let mut vb: Option<IDirect3DVertexBuffer9> = None;
 
@@ -275,7 +261,7 @@ impl ColoredWavesDemo {
                 HR!(ib.Unlock());
             }
 
-            (vb.unwrap(), ib.unwrap())
+            Ok((vb.unwrap(), ib.unwrap()))
         }
     }
 
@@ -1,3 +1,5 @@
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -24,9 +26,8 @@ pub struct DiffuseDemo {
     diffuse_mtrl: D3DXCOLOR,
     diffuse_light: D3DXCOLOR,
 
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
 
     world: D3DXMATRIX,
     view: D3DXMATRIX,
@@ -41,8 +42,9 @@ impl DiffuseDemo {
 
         let gfx_stats = GfxStats::new(d3d_device.clone(), D3DCOLOR_XRGB!(0, 0, 0));
 
-        let mut teapot: LPD3DXMESH = std::ptr::null_mut();
-        HR!(D3DXCreateTeapot(d3d_device.clone(), &mut teapot, std::ptr::null_mut()));
+        init_all_vertex_declarations(d3d_device.clone());
+
+        let teapot = create_teapot_mesh(d3d_device.clone());
 
         let (fx,
             h_tech,
@@ -60,7 +62,10 @@ impl DiffuseDemo {
         let mut world: D3DXMATRIX = unsafe { std::mem::zeroed() };
         D3DXMatrixIdentity(&mut world);
 
-        init_all_vertex_declarations(d3d_device.clone());
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(10.0, 5.0));
+        orbit.yaw = 1.2 * D3DX_PI;
+        orbit.pitch = f32::atan2(5.0, 10.0);
+        orbit.min_distance = 5.0;
 
         let mut diffuse_demo = DiffuseDemo {
             d3d_pp,
@@ -80,9 +85,8 @@ impl DiffuseDemo {
             diffuse_mtrl,
             diffuse_light,
 
-            camera_radius: 10.0,
-            camera_rotation_y: 1.2 * D3DX_PI,
-            camera_height: 5.0,
+            camera: Camera::new(),
+            orbit,
 
             world,
             view: unsafe { std::mem::zeroed() },
@@ -157,28 +161,8 @@ impl DiffuseDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 5.0 {
-                    self.camera_radius = 5.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
             }
         }
 
@@ -245,12 +229,7 @@ impl DiffuseDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
     }
 
     fn build_proj_mtx(&mut self) {
@@ -1,3 +1,5 @@
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -37,9 +39,8 @@ pub struct AmbientDiffuseSpecularDemo {
     specular_light: D3DXCOLOR,
     specular_power: f32,
 
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
 
     world: D3DXMATRIX,
     view: D3DXMATRIX,
@@ -54,8 +55,9 @@ impl AmbientDiffuseSpecularDemo {
 
         let gfx_stats = GfxStats::new(d3d_device.clone(), D3DCOLOR_XRGB!(0, 0, 0));
 
-        let mut teapot: LPD3DXMESH = std::ptr::null_mut();
-        HR!(D3DXCreateTeapot(d3d_device.clone(), &mut teapot, std::ptr::null_mut()));
+        init_all_vertex_declarations(d3d_device.clone());
+
+        let teapot = create_teapot_mesh(d3d_device.clone());
 
         let (fx,
             h_tech,
@@ -85,7 +87,10 @@ impl AmbientDiffuseSpecularDemo {
         let mut world: D3DXMATRIX = unsafe { std::mem::zeroed() };
         D3DXMatrixIdentity(&mut world);
 
-        init_all_vertex_declarations(d3d_device.clone());
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(6.0, 3.0));
+        orbit.yaw = 1.2 * D3DX_PI;
+        orbit.pitch = f32::atan2(3.0, 6.0);
+        orbit.min_distance = 5.0;
 
         let mut ambient_diffuse_specular_demo = AmbientDiffuseSpecularDemo {
             d3d_pp,
@@ -117,9 +122,8 @@ impl AmbientDiffuseSpecularDemo {
             specular_light,
             specular_power,
 
-            camera_radius: 6.0,
-            camera_rotation_y: 1.2 * D3DX_PI,
-            camera_height: 3.0,
+            camera: Camera::new(),
+            orbit,
 
             world,
             view: unsafe { std::mem::zeroed() },
@@ -194,28 +198,8 @@ impl AmbientDiffuseSpecularDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 5.0 {
-                    self.camera_radius = 5.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
             }
         }
 
@@ -288,12 +272,9 @@ impl AmbientDiffuseSpecularDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
+
+        let pos = to_d3dx_vec3(self.camera.get_pos());
 
         HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_eye_pos, &pos as *const _ as _, std::mem::size_of::<D3DXVECTOR3>() as u32));
     }
@@ -1,3 +1,5 @@
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -53,9 +55,8 @@ pub struct PointLightDemo {
     cylinder_mtrl: Mtrl,
     sphere_mtrl: Mtrl,
 
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
 
     view: D3DXMATRIX,
     proj: D3DXMATRIX,
@@ -79,13 +80,19 @@ impl PointLightDemo {
         let cylinder_mtrl = Mtrl { ambient: RED, diffuse: RED, spec: WHITE, spec_power: 8.0 };
         let sphere_mtrl = Mtrl { ambient: GREEN, diffuse: GREEN, spec: WHITE, spec_power: 8.0 };
 
-        let mut cylinder: LPD3DXMESH = std::ptr::null_mut();
-        HR!(D3DXCreateCylinder(d3d_device.clone(), 1.0, 1.0, 6.0, 20, 20, &mut cylinder, std::ptr::null_mut()));
+        init_all_vertex_declarations(d3d_device.clone());
+
+        let cylinder = create_cylinder_mesh(d3d_device.clone(), 1.0, 1.0, 6.0, 20, 20);
 
-        let mut sphere: LPD3DXMESH = std::ptr::null_mut();
-        HR!(D3DXCreateSphere(d3d_device.clone(), 1.0, 20, 20, &mut sphere, std::ptr::null_mut()));
+        let sphere = create_sphere_mesh(d3d_device.clone(), 1.0, 20, 20);
 
-        let (vb, ib) = PointLightDemo::build_geo_buffers(d3d_device.clone());
+        let (vb, ib) = match PointLightDemo::build_geo_buffers(d3d_device.clone()) {
+            Ok(grid) => grid,
+            Err(e) => {
+                display_error_then_quit(&format!("gen_tri_grid() Failed: {}", e));
+                return None;
+            }
+        };
 
         let (fx, h_tech, h_wvp, h_world_inverse_transpose,
             h_eye_pos, h_world,
@@ -114,7 +121,10 @@ impl PointLightDemo {
             gfx_stats.add_triangles(num_sphere_tris);
         }
 
-        init_all_vertex_declarations(d3d_device.clone());
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(50.0, 20.0));
+        orbit.yaw = 1.2 * D3DX_PI;
+        orbit.pitch = f32::atan2(20.0, 50.0);
+        orbit.min_distance = 5.0;
 
         let mut point_light_demo = PointLightDemo {
             d3d_pp,
@@ -154,9 +164,8 @@ impl PointLightDemo {
             cylinder_mtrl,
             sphere_mtrl,
 
-            camera_radius: 50.0,
-            camera_rotation_y: 1.2 * D3DX_PI,
-            camera_height: 20.0,
+            camera: Camera::new(),
+            orbit,
 
             view: unsafe { std::mem::zeroed() },
             proj: unsafe { std::mem::zeroed() },
@@ -228,28 +237,8 @@ impl PointLightDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 5.0 {
-                    self.camera_radius = 5.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
             }
         }
 
@@ -326,13 +315,13 @@ impl PointLightDemo {
         }
     }
 
-    fn build_geo_buffers(d3d_device: IDirect3DDevice9) -> (IDirect3DVertexBuffer9, IDirect3DIndexBuffer9){
+    fn build_geo_buffers(d3d_device: IDirect3DDevice9) -> Result<(IDirect3DVertexBuffer9, IDirect3DIndexBuffer9), GridError> {
         unsafe {
             let mut verts: Vec<D3DXVECTOR3> = Vec::new();
             let mut indices: Vec<u16> = Vec::new();
 
             gen_tri_grid(100, 100, 1.0, 1.0,
-                         D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices);
+                         D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices)?;
 
             let mut vb: Option<IDirect3DVertexBuffer9> = None;
 
@@ -380,7 +369,7 @@ impl PointLightDemo {
                 HR!(ib.Unlock());
             }
 
-            (vb.unwrap(), ib.unwrap())
+            Ok((vb.unwrap(), ib.unwrap()))
         }
     }
 
@@ -430,12 +419,9 @@ impl PointLightDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
+
+        let pos = to_d3dx_vec3(self.camera.get_pos());
 
         HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_eye_pos, &pos as *const _ as _, std::mem::size_of::<D3DXVECTOR3>() as u32));
     }
@@ -1,6 +1,8 @@
 // Controls: Use mouse to orbit and zoom; use the 'W' and 'S' keys to
 //           alter the height of the camera.
 
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -56,9 +58,8 @@ pub struct SphereCylTexDemo {
     cylinder_mtrl: Mtrl,
     sphere_mtrl: Mtrl,
 
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
 
     view: D3DXMATRIX,
     proj: D3DXMATRIX,
@@ -98,18 +99,12 @@ impl SphereCylTexDemo {
             spec_power: 8.0
         };
 
-        let mut cylinder: LPD3DXMESH = std::ptr::null_mut();
-        HR!(D3DXCreateCylinder(d3d_device.clone(), 1.0, 1.0, 6.0, 20, 20, &mut cylinder, std::ptr::null_mut()));
-
-        let mut sphere: LPD3DXMESH = std::ptr::null_mut();
-        HR!(D3DXCreateSphere(d3d_device.clone(), 1.0, 20, 20, &mut sphere, std::ptr::null_mut()));
-
         init_all_vertex_declarations(d3d_device.clone());
 
-        gen_spherical_tex_coords(d3d_device.clone(), &mut sphere);
-
-        // D3DXCreateCylinder doc says: The created cylinder is centered at the origin, and its axis is aligned with the z-axis.
-        gen_cyl_tex_coords(d3d_device.clone(), &mut cylinder, Axis::Z);
+        // The generated meshes come with tex-coords, so there is no need to clone them
+        // to VertexPNT and generate the tex-coords afterwards.
+        let cylinder = create_cylinder_mesh(d3d_device.clone(), 1.0, 1.0, 6.0, 20, 20);
+        let sphere = create_sphere_mesh(d3d_device.clone(), 1.0, 20, 20);
 
         let mut sphere_tex: *mut c_void = std::ptr::null_mut();
         let mut cyl_tex: *mut c_void = std::ptr::null_mut();
@@ -119,7 +114,13 @@ impl SphereCylTexDemo {
         HR!(D3DXCreateTextureFromFile(d3d_device.clone(), PSTR(b"luna_21_sphere_cyl_tex_demo/stone2.dds\0".as_ptr() as _), &mut cyl_tex));
         HR!(D3DXCreateTextureFromFile(d3d_device.clone(), PSTR(b"luna_21_sphere_cyl_tex_demo/ground0.dds\0".as_ptr() as _), &mut grid_tex));
 
-        let (vb, ib) = SphereCylTexDemo::build_geo_buffers(d3d_device.clone());
+        let (vb, ib) = match SphereCylTexDemo::build_geo_buffers(d3d_device.clone()) {
+            Ok(grid) => grid,
+            Err(e) => {
+                display_error_then_quit(&format!("gen_tri_grid() Failed: {}", e));
+                return None;
+            }
+        };
 
         let (fx, h_tech, h_wvp, h_world_inverse_transpose,
             h_eye_pos, h_world,
@@ -149,6 +150,11 @@ impl SphereCylTexDemo {
             gfx_stats.add_triangles(num_sphere_tris);
         }
 
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(50.0, 20.0));
+        orbit.yaw = 1.2 * D3DX_PI;
+        orbit.pitch = f32::atan2(20.0, 50.0);
+        orbit.min_distance = 5.0;
+
         let mut sphere_cyl_tex_demo = SphereCylTexDemo {
             d3d_pp,
             gfx_stats,
@@ -192,9 +198,8 @@ impl SphereCylTexDemo {
             cylinder_mtrl,
             sphere_mtrl,
 
-            camera_radius: 50.0,
-            camera_rotation_y: 1.2 * D3DX_PI,
-            camera_height: 20.0,
+            camera: Camera::new(),
+            orbit,
 
             view: unsafe { std::mem::zeroed() },
             proj: unsafe { std::mem::zeroed() },
@@ -266,28 +271,8 @@ impl SphereCylTexDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 5.0 {
-                    self.camera_radius = 5.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
             }
         }
 
@@ -348,13 +333,13 @@ impl SphereCylTexDemo {
         }
     }
 
-    fn build_geo_buffers(d3d_device: IDirect3DDevice9) -> (IDirect3DVertexBuffer9, IDirect3DIndexBuffer9){
+    fn build_geo_buffers(d3d_device: IDirect3DDevice9) -> Result<(IDirect3DVertexBuffer9, IDirect3DIndexBuffer9), GridError> {
         unsafe {
             let mut verts: Vec<D3DXVECTOR3> = Vec::new();
             let mut indices: Vec<u16> = Vec::new();
 
             gen_tri_grid(100, 100, 1.0, 1.0,
-                         D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices);
+                         D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices)?;
 
             let mut vb: Option<IDirect3DVertexBuffer9> = None;
 
@@ -408,7 +393,7 @@ impl SphereCylTexDemo {
                 HR!(ib.Unlock());
             }
 
-            (vb.unwrap(), ib.unwrap())
+            Ok((vb.unwrap(), ib.unwrap()))
         }
     }
 
@@ -460,12 +445,9 @@ impl SphereCylTexDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
+
+        let pos = to_d3dx_vec3(self.camera.get_pos());
 
         HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_eye_pos, &pos as *const _ as _, std::mem::size_of::<D3DXVECTOR3>() as u32));
     }
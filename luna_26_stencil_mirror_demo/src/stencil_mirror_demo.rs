@@ -1,3 +1,5 @@
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -46,9 +48,8 @@ pub struct StencilMirrorDemo {
     diffuse_light: D3DXCOLOR,
     specular_light: D3DXCOLOR,
 
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
 
     room_world: D3DXMATRIX,
     teapot_world: D3DXMATRIX,
@@ -95,11 +96,8 @@ impl StencilMirrorDemo {
         HR!(D3DXCreateTextureFromFile(d3d_device.clone(), PSTR(b"luna_26_stencil_mirror_demo/ice.dds\0".as_ptr() as _), &mut mirror_tex));
         HR!(D3DXCreateTextureFromFile(d3d_device.clone(), PSTR(b"luna_26_stencil_mirror_demo/brick1.dds\0".as_ptr() as _), &mut teapot_tex));
 
-        let mut teapot: LPD3DXMESH = std::ptr::null_mut();
-        HR!(D3DXCreateTeapot(d3d_device.clone(), &mut teapot, std::ptr::null_mut()));
-
-        // Generate texture coordinates for the teapot.
-        gen_spherical_tex_coords(d3d_device.clone(), &mut teapot);
+        // Comes with spherically mapped tex-coords.
+        let teapot = create_teapot_mesh(d3d_device.clone());
 
         // Room geometry count.
         if let Some(gfx_stats) = &mut gfx_stats {
@@ -130,6 +128,11 @@ impl StencilMirrorDemo {
             h_tex) =
             StencilMirrorDemo::build_fx(d3d_device.clone());
 
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(15.0, 5.0));
+        orbit.yaw = 1.4 * D3DX_PI;
+        orbit.pitch = f32::atan2(5.0, 15.0);
+        orbit.min_distance = 5.0;
+
         let mut stencil_mirror_demo = StencilMirrorDemo {
             d3d_pp,
             gfx_stats,
@@ -166,9 +169,8 @@ impl StencilMirrorDemo {
             diffuse_light,
             specular_light,
 
-            camera_radius: 15.0,
-            camera_rotation_y: 1.4 * D3DX_PI,
-            camera_height: 5.0,
+            camera: Camera::new(),
+            orbit,
 
             room_world,
             teapot_world,
@@ -243,28 +245,8 @@ impl StencilMirrorDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 5.0 {
-                    self.camera_radius = 5.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
             }
         }
 
@@ -324,12 +306,9 @@ impl StencilMirrorDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
+
+        let pos = to_d3dx_vec3(self.camera.get_pos());
 
         HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_eye_pos, &pos as *const _ as _, std::mem::size_of::<D3DXVECTOR3>() as u32));
     }
@@ -1,4 +1,6 @@
 use common::mtrl::Mtrl;
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -41,9 +43,9 @@ pub struct XFileDemo {
 
     light: DirLight,
 
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    // Yaw and Pitch circle the mesh, Zoom moves in and out.
+    camera: Camera,
+    orbit: OrbitController,
 
     world: D3DXMATRIX,
     view: D3DXMATRIX,
@@ -70,7 +72,7 @@ impl XFileDemo {
             dir_w: light_dir_w,
         };
 
-        let (mesh, mtrl, tex) =
+        let (mesh, mtrl, tex, _) =
             // load_x_file(BASE_PATH, "bigship1.x", d3d_device.clone());
             // load_x_file(BASE_PATH, "car.x", d3d_device.clone());
             // load_x_file(BASE_PATH, "Dwarf.x", d3d_device.clone());
@@ -79,6 +81,13 @@ impl XFileDemo {
         let mut world = unsafe { std::mem::zeroed() };
         D3DXMatrixIdentity(&mut world);
 
+        // Start where the old fixed-height camera was: 12 units out and 4 above
+        // the target, at 1.2 pi around the y-axis.
+        let mut orbit = OrbitController::new(Vec3::new(0.0, 2.0, 0.0), 160.0f32.sqrt());
+        orbit.yaw = 1.2 * D3DX_PI;
+        orbit.pitch = f32::atan2(4.0, 12.0);
+        orbit.min_distance = 2.0;
+
         let mut white_tex = unsafe { std::mem::zeroed() };
         HR!(D3DXCreateTextureFromFile(d3d_device.clone(),
             PSTR(c_resource_path(BASE_PATH, "whitetex.dds").as_str().as_ptr() as _), &mut white_tex));
@@ -123,9 +132,8 @@ impl XFileDemo {
 
             light,
 
-            camera_radius: 12.0,
-            camera_rotation_y: 1.2 * D3DX_PI,
-            camera_height: 6.0,
+            camera: Camera::new(),
+            orbit,
 
             world,
 
@@ -200,28 +208,8 @@ impl XFileDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 2.0 {
-                    self.camera_radius = 2.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
 
                 // The camera position/orientation relative to world space can
                 // change every frame based on input, so we need to rebuild the
@@ -304,12 +292,9 @@ impl XFileDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 2.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
+
+        let pos = to_d3dx_vec3(self.camera.get_pos());
 
         HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_eye_pos, &pos as *const _ as _, std::mem::size_of::<D3DXVECTOR3>() as u32));
     }
@@ -1,4 +1,6 @@
 use common::mtrl::Mtrl;
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -46,9 +48,8 @@ pub struct BoundingBoxDemo {
 
     light: DirLight,
 
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
 
     world: D3DXMATRIX,
     view: D3DXMATRIX,
@@ -75,7 +76,7 @@ impl BoundingBoxDemo {
             dir_w: light_dir_w,
         };
 
-        let (mesh, mtrl, tex) =
+        let (mesh, mtrl, tex, _) =
             load_x_file(BASE_PATH, "bigship1.x", d3d_device.clone());
             // load_x_file(BASE_PATH, "car.x", d3d_device.clone());
             // load_x_file(BASE_PATH, "skullocc.x", d3d_device.clone());
@@ -85,24 +86,15 @@ impl BoundingBoxDemo {
         D3DXMatrixIdentity(&mut world);
 
         // Compute the bounding box.
-        let mut v: *mut c_void = std::ptr::null_mut();
-        HR!(ID3DXBaseMesh_LockVertexBuffer(mesh, 0, &mut v));
-
-        let num_vertices: usize = ID3DXBaseMesh_GetNumVertices(mesh) as usize;
-        let mut bounding_box: AABB = Default::default();
-
-        HR!(D3DXComputeBoundingBox(v.cast(), num_vertices as u32, std::mem::size_of::<VertexPNT>() as u32,
-            &mut bounding_box.min_pt, &mut bounding_box.max_pt));
-
-        HR!(ID3DXBaseMesh_UnlockVertexBuffer(mesh));
+        let vertices: Vec<geometry::VertexPNT> = read_d3dx_vertices(mesh);
+        let bounding_box = AABB::from_aabb(&geometry::Aabb::from_points(&vertices));
 
         let width: f32  = bounding_box.max_pt.x - bounding_box.min_pt.x;
         let height: f32 = bounding_box.max_pt.y - bounding_box.min_pt.y;
         let depth: f32  = bounding_box.max_pt.z - bounding_box.min_pt.z;
 
         // Build a box mesh so that we can render the bounding box visually.
-        let mut box_mesh: LPD3DXMESH = std::ptr::null_mut();
-        HR!(D3DXCreateBox(d3d_device.clone(), width, height, depth, &mut box_mesh, std::ptr::null_mut()));
+        let box_mesh = create_box_mesh(d3d_device.clone(), width, height, depth);
 
         // It is possible that the mesh was not centered about the origin
         // when it was modeled.  But the bounding box mesh is built around the
@@ -148,6 +140,11 @@ impl BoundingBoxDemo {
             h_tex) =
             BoundingBoxDemo::build_fx(d3d_device.clone());
 
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(30.0, 10.0));
+        orbit.yaw = 1.2 * D3DX_PI;
+        orbit.pitch = f32::atan2(10.0, 30.0);
+        orbit.min_distance = 3.0;
+
         let mut bounding_box_demo = BoundingBoxDemo {
             d3d_pp,
             gfx_stats,
@@ -177,9 +174,8 @@ impl BoundingBoxDemo {
 
             light,
 
-            camera_radius: 30.0,
-            camera_rotation_y: 1.2 * D3DX_PI,
-            camera_height: 10.0,
+            camera: Camera::new(),
+            orbit,
 
             world,
 
@@ -255,28 +251,8 @@ impl BoundingBoxDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 3.0 {
-                    self.camera_radius = 3.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
 
                 // The camera position/orientation relative to world space can
                 // change every frame based on input, so we need to rebuild the
@@ -384,12 +360,9 @@ impl BoundingBoxDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
+
+        let pos = to_d3dx_vec3(self.camera.get_pos());
 
         HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_eye_pos, &pos as *const _ as _, std::mem::size_of::<D3DXVECTOR3>() as u32));
     }
@@ -1,9 +1,11 @@
-// Controls: Use mouse to orbit and zoom; use the 'W' and 'S' keys to
-//           alter the height of the camera.
+// Controls: Use the mouse to orbit and the wheel to zoom.
 //           Use '1', '2', '3', '4', and '5' keys to select the bone
 //           to rotate.  Use the 'A' and 'D' keys to rotate the bone.
+//           These are the default bindings; override them in bindings.cfg.
 
 use common::mtrl::Mtrl;
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -80,9 +82,8 @@ pub struct RobotArmDemo {
 
     light: DirLight,
 
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
 
     world: D3DXMATRIX,
     view: D3DXMATRIX,
@@ -109,7 +110,7 @@ impl RobotArmDemo {
             dir_w: light_dir_w,
         };
 
-        let (bone_mesh, mtrl, tex) =
+        let (bone_mesh, mtrl, tex, _) =
             load_x_file(BASE_PATH, "bone.x", d3d_device.clone());
 
         let mut world = unsafe { std::mem::zeroed() };
@@ -160,6 +161,10 @@ impl RobotArmDemo {
             h_tex) =
             RobotArmDemo::build_fx(d3d_device.clone());
 
+        let mut orbit = OrbitController::new(Vec3::ZERO, 9.0);
+        orbit.yaw = 1.5 * D3DX_PI;
+        orbit.min_distance = 2.0;
+
         let mut robot_arm_demo = RobotArmDemo {
             d3d_pp,
             gfx_stats,
@@ -187,9 +192,8 @@ impl RobotArmDemo {
 
             light,
 
-            camera_radius: 9.0,
-            camera_rotation_y: 1.5 * D3DX_PI,
-            camera_height: 0.0,
+            camera: Camera::new(),
+            orbit,
 
             world,
 
@@ -268,63 +272,23 @@ impl RobotArmDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
                 // Allow the user to select a bone (zero based index)
-                if dinput.key_down(DIK_1 as usize) {
-                    self.bone_selected = 0;
-                }
-
-                if dinput.key_down(DIK_2 as usize) {
-                    self.bone_selected = 1;
-                }
-
-                if dinput.key_down(DIK_3 as usize) {
-                    self.bone_selected = 2;
-                }
-
-                if dinput.key_down(DIK_4 as usize) {
-                    self.bone_selected = 3;
-                }
-
-                if dinput.key_down(DIK_5 as usize) {
-                    self.bone_selected = 4;
+                for bone in 0..NUM_BONES {
+                    if dinput.action_down(&format!("SelectBone{}", bone + 1)) {
+                        self.bone_selected = bone;
+                    }
                 }
 
                 // Allow the user to rotate a bone.
-                if dinput.key_down(DIK_A as usize) {
-                    self.bones[self.bone_selected].z_angle += 1.0 * dt;
-                }
-
-                if dinput.key_down(DIK_D as usize) {
-                    self.bones[self.bone_selected].z_angle -= 1.0 * dt;
-                }
+                self.bones[self.bone_selected].z_angle += 1.0 * dinput.action_axis("RotateBone") * dt;
 
                 // If we rotate over 360 degrees, just roll back to 0
                 if self.bones[self.bone_selected].z_angle.abs() >= 2.0 * D3DX_PI {
                     self.bones[self.bone_selected].z_angle = 0.0;
                 }
 
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 2.0 {
-                    self.camera_radius = 2.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
 
                 // The camera position/orientation relative to world space can
                 // change every frame based on input, so we need to rebuild the
@@ -419,12 +383,9 @@ impl RobotArmDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
+
+        let pos = to_d3dx_vec3(self.camera.get_pos());
 
         HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_eye_pos, &pos as *const _ as _, std::mem::size_of::<D3DXVECTOR3>() as u32));
     }
@@ -1,4 +1,6 @@
 use common::mtrl::Mtrl;
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -8,6 +10,10 @@ use crate::*;
 
 const BASE_PATH: &str = "luna_31_solar_system_demo/";
 
+// Chases the next planet with the follow camera, and after the last one goes
+// back to orbiting the sun.  F by default.
+const FOLLOW_PLANET: &str = "FollowPlanet";
+
 // Colors
 const WHITE: D3DXCOLOR = D3DXCOLOR { r: 1.0, g: 1.0, b: 1.0, a: 1.0 };
 
@@ -76,9 +82,13 @@ pub struct SolarSystemDemo {
     h_world: D3DXHANDLE,
     h_tex: D3DXHANDLE,
 
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
+    follow: FollowController,
+    // The object the follow camera chases, or None to orbit the sun.
+    following: Option<usize>,
+    // To act once per press.
+    follow_down: bool,
 
     world: D3DXMATRIX,
     view: D3DXMATRIX,
@@ -107,9 +117,7 @@ impl SolarSystemDemo {
         };
 
         // Create a sphere to represent a solar object.
-        let mut sphere: LPD3DXMESH = std::ptr::null_mut();
-        HR!(D3DXCreateSphere(d3d_device.clone(), 1.0, 30, 30, &mut sphere, std::ptr::null_mut()));
-        gen_spherical_tex_coords(d3d_device.clone(), &mut sphere);
+        let sphere = create_sphere_mesh(d3d_device.clone(), 1.0, 30, 30);
 
         let mut world = unsafe { std::mem::zeroed() };
         D3DXMatrixIdentity(&mut world);
@@ -277,6 +285,14 @@ impl SolarSystemDemo {
             h_tex) =
             SolarSystemDemo::build_fx(d3d_device.clone());
 
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(25.0, 10.0));
+        orbit.yaw = 1.2 * D3DX_PI;
+        orbit.pitch = f32::atan2(10.0, 25.0);
+        orbit.min_distance = 2.0;
+
+        let mut follow = FollowController::new(6.0, 2.0);
+        follow.min_distance = 2.0;
+
         let mut solar_system_demo = SolarSystemDemo {
             d3d_pp,
             gfx_stats,
@@ -305,9 +321,11 @@ impl SolarSystemDemo {
             h_world,
             h_tex,
 
-            camera_radius: 25.0,
-            camera_rotation_y: 1.2 * D3DX_PI,
-            camera_height: 10.0,
+            camera: Camera::new(),
+            orbit,
+            follow,
+            following: None,
+            follow_down: false,
 
             world,
             view: unsafe { std::mem::zeroed() },
@@ -387,34 +405,6 @@ impl SolarSystemDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 2.0 {
-                    self.camera_radius = 2.0;
-                }
-
-                // The camera position/orientation relative to world space can
-                // change every frame based on input, so we need to rebuild the
-                // view matrix every frame with the latest changes.
-                self.build_view_mtx();
-
                 //================================================
                 // Animate the solar objects with respect to time.
 
@@ -436,6 +426,38 @@ impl SolarSystemDemo {
                         self.object[i].y_angle = 0.0;
                     }
                 }
+
+                // Objects 1 to 3 are the planets.
+                let follow_down = dinput.action_down(FOLLOW_PLANET);
+                if follow_down && !self.follow_down {
+                    self.following = match self.following {
+                        None => Some(1),
+                        Some(3) => None,
+                        Some(i) => Some(i + 1),
+                    };
+                    self.follow.snap();
+                }
+                self.follow_down = follow_down;
+
+                match self.following {
+                    // Chase the planet with the Yaw and Zoom actions.
+                    Some(i) => {
+                        self.build_object_world_transforms();
+                        let pos = to_mat4(&self.object[i].to_world_x_form).transform_coord(Vec3::ZERO);
+
+                        // The planets circle the sun at the origin, so they
+                        // travel at right angles to the direction to it.
+                        self.follow.set_target(pos, Vec3::Y.cross(pos));
+                        self.follow.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
+                    }
+                    // Orbit with the Yaw, Pitch and Zoom actions.
+                    None => self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt),
+                }
+
+                // The camera position/orientation relative to world space can
+                // change every frame based on input, so we need to rebuild the
+                // view matrix every frame with the latest changes.
+                self.build_view_mtx();
             }
         }
     }
@@ -520,12 +542,9 @@ impl SolarSystemDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
+
+        let pos = to_d3dx_vec3(self.camera.get_pos());
 
         HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_eye_pos, &pos as *const _ as _, std::mem::size_of::<D3DXVECTOR3>() as u32));
     }
@@ -1,4 +1,6 @@
 use std::slice::from_raw_parts_mut;
+use controllers::*;
+use geometry::{Camera, Vec3};
 use libc::c_void;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
@@ -28,9 +30,8 @@ pub struct BasicTerrainDemo {
     h_tech: D3DXHANDLE,
     h_view_proj: D3DXHANDLE,
 
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    camera: Camera,
+    orbit: OrbitController,
 
     view: D3DXMATRIX,
     proj: D3DXMATRIX,
@@ -67,7 +68,13 @@ impl BasicTerrainDemo {
         HR!(D3DXCreateTextureFromFile(d3d_device.clone(),
             PSTR(c_resource_path(BASE_PATH, "blend_hm17.dds").as_str().as_ptr() as _), &mut blend_map));
 
-        let terrain_mesh = BasicTerrainDemo::build_grid_geometry(d3d_device.clone(), &heightmap);
+        let terrain_mesh = match BasicTerrainDemo::build_grid_geometry(d3d_device.clone(), &heightmap) {
+            Ok(grid) => grid,
+            Err(e) => {
+                display_error_then_quit(&format!("gen_tri_grid() Failed: {}", e));
+                return None;
+            }
+        };
 
         if let Some(gfx_stats) = &mut gfx_stats {
             gfx_stats.add_vertices(ID3DXBaseMesh_GetNumVertices(terrain_mesh));
@@ -92,6 +99,11 @@ impl BasicTerrainDemo {
         let d = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
         HR!(ID3DXBaseEffect_SetValue(fx, h_dir_to_sun_w, &d as *const _ as _, std::mem::size_of::<D3DXVECTOR3>() as u32));
 
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(80.0, 40.0));
+        orbit.yaw = 1.2 * D3DX_PI;
+        orbit.pitch = f32::atan2(40.0, 80.0);
+        orbit.min_distance = 5.0;
+
         let mut basic_terrain_demo = BasicTerrainDemo {
             d3d_pp,
             gfx_stats,
@@ -108,9 +120,8 @@ impl BasicTerrainDemo {
             h_tech,
             h_view_proj,
 
-            camera_radius: 80.0,
-            camera_rotation_y: 1.2 * D3DX_PI,
-            camera_height: 40.0,
+            camera: Camera::new(),
+            orbit,
 
             view: unsafe { std::mem::zeroed() },
             proj: unsafe { std::mem::zeroed() },
@@ -188,28 +199,8 @@ impl BasicTerrainDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
-
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
-                }
-
-                // Don't let radius get too small.
-                if self.camera_radius < 5.0 {
-                    self.camera_radius = 5.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
 
                 // The camera position/orientation relative to world space can
                 // change every frame based on input, so we need to rebuild the
@@ -265,7 +256,7 @@ impl BasicTerrainDemo {
         }
     }
 
-    fn build_grid_geometry(d3d_device: IDirect3DDevice9, heightmap: &Heightmap) -> LPD3DXMESH {
+    fn build_grid_geometry(d3d_device: IDirect3DDevice9, heightmap: &Heightmap) -> Result<LPD3DXMESH, GridError> {
         unsafe {
             let mut verts: Vec<D3DXVECTOR3> = Vec::new();
             let mut indices: Vec<u16> = Vec::new();
@@ -275,7 +266,7 @@ impl BasicTerrainDemo {
             let dx = 1.0;
             let dz = 1.0;
             gen_tri_grid(vert_rows, vert_cols, dx, dz,
-                         D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices);
+                         D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices)?;
 
             let num_verts = vert_rows * vert_cols;
             let num_tris = (vert_rows - 1) * (vert_cols - 1) * 2;
@@ -347,7 +338,7 @@ impl BasicTerrainDemo {
             HR!(ID3DXMesh_OptimizeInPlace(terrain_mesh, D3DXMESHOPT_VERTEXCACHE | D3DXMESHOPT_ATTRSORT,
                 adj.as_ptr(), std::ptr::null_mut(), std::ptr::null_mut(), std::ptr::null_mut()));
 
-            terrain_mesh
+            Ok(terrain_mesh)
         }
     }
 
@@ -386,12 +377,7 @@ impl BasicTerrainDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
     }
 
     fn build_proj_mtx(&mut self) {
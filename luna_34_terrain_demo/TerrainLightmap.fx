@@ -0,0 +1,149 @@
+//=============================================================================
+// TerrainLightmap.fx
+//
+// Terrain.fx with baked lighting: the three tiled textures blended by the
+// blend map, lit by the sun's N.L and the sky as in terrain::Lightmap.  The
+// lightmap's green channel is the sun's visibility (shadows) and its blue
+// channel the ambient occlusion; N.L comes from the vertex normals, so it
+// follows sculpted terrain before the lightmap is rebaked.
+//=============================================================================
+
+uniform extern float4x4 gViewProj;
+uniform extern float3   gDirToSunW;
+uniform extern texture  gTex0;
+uniform extern texture  gTex1;
+uniform extern texture  gTex2;
+uniform extern texture  gBlendMap;
+uniform extern texture  gLightMap;
+
+// The ambient and diffuse intensities of terrain::SunLight::new.
+static float gAmbient = 0.35f;
+static float gDiffuse = 0.65f;
+
+static float gTexScale = 16.0f;
+
+sampler Tex0S = sampler_state
+{
+	Texture = <gTex0>;
+	MinFilter = Anisotropic;
+	MagFilter = LINEAR;
+	MipFilter = LINEAR;
+	MaxAnisotropy = 8;
+	AddressU  = WRAP;
+	AddressV  = WRAP;
+};
+
+sampler Tex1S = sampler_state
+{
+	Texture = <gTex1>;
+	MinFilter = Anisotropic;
+	MagFilter = LINEAR;
+	MipFilter = LINEAR;
+	MaxAnisotropy = 8;
+	AddressU  = WRAP;
+	AddressV  = WRAP;
+};
+
+sampler Tex2S = sampler_state
+{
+	Texture = <gTex2>;
+	MinFilter = Anisotropic;
+	MagFilter = LINEAR;
+	MipFilter = LINEAR;
+	MaxAnisotropy = 8;
+	AddressU  = WRAP;
+	AddressV  = WRAP;
+};
+
+sampler BlendMapS = sampler_state
+{
+	Texture = <gBlendMap>;
+	MinFilter = LINEAR;
+	MagFilter = LINEAR;
+	MipFilter = LINEAR;
+	AddressU  = WRAP;
+	AddressV  = WRAP;
+};
+
+// Clamped so the texels along the terrain's edges do not blend with the
+// opposite edge.
+sampler LightMapS = sampler_state
+{
+	Texture = <gLightMap>;
+	MinFilter = LINEAR;
+	MagFilter = LINEAR;
+	MipFilter = LINEAR;
+	AddressU  = CLAMP;
+	AddressV  = CLAMP;
+};
+
+struct OutputVS
+{
+	float4 posH         : POSITION0;
+	float2 tiledTexC    : TEXCOORD0;
+	float2 nonTiledTexC : TEXCOORD1;
+	float  diffuse      : TEXCOORD2;
+};
+
+OutputVS TerrainLightmapVS(float3 posW : POSITION0,  // We assume terrain geometry is specified
+                           float3 normalW : NORMAL0, // directly in world space.
+                           float2 tex0: TEXCOORD0)
+{
+	// Zero out our output.
+	OutputVS outVS = (OutputVS)0;
+
+	// Just compute a grayscale diffuse factor; the lightmap shadows it.
+	outVS.diffuse = max(0.0f, dot(normalW, gDirToSunW));
+
+	// Transform to homogeneous clip space.
+	outVS.posH = mul(float4(posW, 1.0f), gViewProj);
+
+	outVS.tiledTexC    = tex0 * gTexScale; // Scale tex-coord to tile.
+	outVS.nonTiledTexC = tex0; // Blend map and lightmap not tiled.
+
+	// Done--return the output.
+	return outVS;
+}
+
+float4 TerrainLightmapPS(float2 tiledTexC : TEXCOORD0,
+                         float2 nonTiledTexC : TEXCOORD1,
+                         float diffuse : TEXCOORD2) : COLOR
+{
+	// Layer maps are tiled
+	float3 c0 = tex2D(Tex0S, tiledTexC).rgb;
+	float3 c1 = tex2D(Tex1S, tiledTexC).rgb;
+	float3 c2 = tex2D(Tex2S, tiledTexC).rgb;
+
+	// Blend map and lightmap are not tiled.
+	float3 B = tex2D(BlendMapS, nonTiledTexC).rgb;
+	float3 L = tex2D(LightMapS, nonTiledTexC).rgb;
+
+	// Find the inverse of all the blend weights so that we can
+	// scale the total color to the range [0, 1].
+	float totalInverse = 1.0f / (B.r + B.g + B.b);
+
+	// Scale the colors by each layer by its corresponding weight
+	// stored in the blendmap.
+	c0 *= B.r * totalInverse;
+	c1 *= B.g * totalInverse;
+	c2 *= B.b * totalInverse;
+
+	// The sky's light where it is not occluded, and the sun's where it is not
+	// shadowed.
+	float shade = saturate(gAmbient * L.b + gDiffuse * diffuse * L.g);
+
+	// Sum the colors and modulate with the shade.
+	float3 final = (c0 + c1 + c2) * shade;
+
+	return float4(final, 1.0f);
+}
+
+technique TerrainLightmapTech
+{
+	pass P0
+	{
+		// Specify the vertex and pixel shader associated with this pass.
+		vertexShader = compile vs_2_0 TerrainLightmapVS();
+		pixelShader  = compile ps_2_0 TerrainLightmapPS();
+	}
+}
@@ -1,5 +1,4 @@
 pub mod terrain_demo;
-pub mod terrain;
 
 use windows::{
     Win32::Foundation::*, Win32::System::LibraryLoader::GetModuleHandleA,
@@ -40,7 +39,7 @@ fn main() {
 
         if let Some(d3d_app) = &mut D3D_APP {
             if let Some(d3d_device) = D3D_DEVICE.clone() {
-                let terrain_demo = TerrainDemo::new(d3d_device.clone(), &d3d_app.d3d_pp);
+                let terrain_demo = TerrainDemo::new(d3d_app.main_wnd, d3d_device.clone(), &d3d_app.d3d_pp);
                 d3d_app.terrain_demo = terrain_demo;
 
                 DIRECT_INPUT = DirectInput::new(d3d_app.app_inst,
@@ -261,7 +260,7 @@ impl D3DApp {
                     if !self.device_is_lost() {
                         let mut curr_timestamp: i64 = 0;
                         QueryPerformanceCounter(&mut curr_timestamp);
-                        let dt: f32 = ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt;
+                        let dt = get_frame_dt(&mut DIRECT_INPUT, ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt);
 
                         if let Some(terrain_demo) = &mut self.terrain_demo {
                             terrain_demo.update_scene(dt);
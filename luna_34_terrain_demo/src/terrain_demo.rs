@@ -3,27 +3,53 @@ use windows::{
 };
 
 use crate::*;
-use crate::terrain::Terrain;
+use common::{Terrain, TerrainDesc, TerrainEffect};
+use controllers::*;
+use geometry::{Camera, Vec3};
+use terrain::{bake_horizon_map, Brush, BrushFalloff, BrushOp, HorizonMap, HorizonMapDesc, SunLight};
 
 pub const BASE_PATH: &str = "luna_34_terrain_demo/";
 
+// Where F5 saves the sculpted heightmap.
+const EDITED_HEIGHTMAP_FILE: &str = "heightmap17_257_edited.raw";
+
+// Where the baked lighting is written for the effect to load, one texel for
+// every two cells.
+const LIGHTMAP_SIZE: usize = 128;
+
+// Radians above the horizon; the arrow keys turn the sun around.
+const SUN_ELEVATION: f32 = 0.5;
+
 // Sample demo
 pub struct TerrainDemo {
+    hwnd: HWND,
     d3d_pp: *const D3DPRESENT_PARAMETERS,
     gfx_stats: Option<GfxStats>,
 
     terrain: Terrain,
 
-    camera_rotation_y: f32,
-    camera_radius: f32,
-    camera_height: f32,
+    // Sculpting: the brush, the height a flatten stroke started at, and
+    // whether the save key was down the last frame.
+    brush: Brush,
+    stroke_height: Option<f32>,
+    save_down: bool,
+
+    // Baked lighting: the terrain's horizons, relit into the lightmap when the
+    // sun stops turning, and whether the bake key was down the last frame.
+    horizon_map: HorizonMap,
+    sun_azimuth: f32,
+    sun_turning: bool,
+    bake_down: bool,
+
+    camera: Camera,
+    orbit: OrbitController,
 
     view: D3DXMATRIX,
     proj: D3DXMATRIX,
 }
 
 impl TerrainDemo {
-    pub fn new(d3d_device: IDirect3DDevice9, d3d_pp: *const D3DPRESENT_PARAMETERS) -> Option<TerrainDemo> {
+    pub fn new(hwnd: HWND, d3d_device: IDirect3DDevice9, d3d_pp: *const D3DPRESENT_PARAMETERS) -> Option<TerrainDemo> {
         if !TerrainDemo::check_device_caps() {
             display_error_then_quit("checkDeviceCaps() Failed");
         }
@@ -32,23 +58,22 @@ impl TerrainDemo {
 
         let mut gfx_stats = GfxStats::new(d3d_device.clone(), D3DCOLOR_XRGB!(0, 0, 0));
 
-        let terrain =
-            Terrain::new(d3d_device.clone(),
-                         257,
-                         257,
-                         0.5,
-                         0.5,
-                         "heightmap17_257.raw",
-                         "grass.dds",
-                         "dirt.dds",
-                         "stone.dds",
-                         "blend_hm17.dds",
-                         BASE_PATH,
-                         0.2,
-                         0.0);
-
-        let d = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        terrain.set_dir_to_sun_w(d);
+        let mut desc = TerrainDesc::new(257,
+                                        257,
+                                        0.5,
+                                        0.5,
+                                        "heightmap17_257.raw",
+                                        "grass.dds",
+                                        "dirt.dds",
+                                        "stone.dds",
+                                        "blend_hm17.dds",
+                                        0.2,
+                                        0.0);
+        desc.effect = TerrainEffect::lightmapped();
+        let terrain = Terrain::from_desc(d3d_device.clone(), &desc, BASE_PATH);
+
+        let horizon_map = bake_horizon_map(terrain.get_height_field(),
+                                           &HorizonMapDesc::new(LIGHTMAP_SIZE, LIGHTMAP_SIZE));
 
         let mut world = unsafe { std::mem::zeroed() };
         D3DXMatrixIdentity(&mut world);
@@ -58,20 +83,35 @@ impl TerrainDemo {
             gfx_stats.add_triangles(terrain.get_num_triangles());
         }
 
+        let mut orbit = OrbitController::new(Vec3::ZERO, f32::hypot(75.0, 35.0));
+        orbit.yaw = 1.3 * D3DX_PI;
+        orbit.pitch = f32::atan2(35.0, 75.0);
+        orbit.min_distance = 2.0;
+
         let mut terrain_demo = TerrainDemo {
+            hwnd,
             d3d_pp,
             gfx_stats,
 
             terrain,
 
-            camera_radius: 75.0,
-            camera_rotation_y: 1.3 * D3DX_PI,
-            camera_height: 35.0,
+            brush: TerrainDemo::make_brush(BrushOp::Raise, 8.0),
+            stroke_height: None,
+            save_down: false,
+
+            horizon_map,
+            sun_azimuth: 0.25 * D3DX_PI,
+            sun_turning: false,
+            bake_down: false,
+
+            camera: Camera::new(),
+            orbit,
 
             view: unsafe { std::mem::zeroed() },
             proj: unsafe { std::mem::zeroed() },
         };
 
+        terrain_demo.relight();
         terrain_demo.on_reset_device();
 
         Some(terrain_demo)
@@ -138,28 +178,18 @@ impl TerrainDemo {
             if let Some(dinput) = &mut DIRECT_INPUT {
                 dinput.poll();
 
-                // Check input.
-                if dinput.key_down(DIK_W as usize) {
-                    self.camera_height += 25.0 * dt;
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    self.camera_height -= 25.0 * dt;
-                }
-
-                // Divide by 50 to make mouse less sensitive.
-                self.camera_rotation_y += dinput.mouse_dx() / 100.0;
-                self.camera_radius += dinput.mouse_dy() / 25.0;
+                self.update_brush(dinput, dt);
+                self.update_sun(dinput, dt);
 
-                // If we rotate over 360 degrees, just roll back to 0
-                if self.camera_rotation_y.abs() >= 2.0 * D3DX_PI {
-                    self.camera_rotation_y = 0.0;
+                // Sculpt under the cursor; the camera holds still during a stroke.
+                if dinput.action_down("Sculpt") {
+                    self.sculpt(dt);
+                    return;
                 }
+                self.stroke_height = None;
 
-                // Don't let radius get too small.
-                if self.camera_radius < 2.0 {
-                    self.camera_radius = 2.0;
-                }
+                // Orbit with the Yaw, Pitch and Zoom actions.
+                self.orbit.update(&mut self.camera, dinput, dinput.get_bindings(), dt);
 
                 // The camera position/orientation relative to world space can
                 // change every frame based on input, so we need to rebuild the
@@ -169,6 +199,107 @@ impl TerrainDemo {
         }
     }
 
+    // Brush selection, radius and saving.
+    fn update_brush(&mut self, dinput: &DirectInput, dt: f32) {
+        let ops = [
+            ("RaiseBrush", BrushOp::Raise),
+            ("LowerBrush", BrushOp::Lower),
+            ("FlattenBrush", BrushOp::Flatten(0.0)),
+            ("SmoothBrush", BrushOp::Smooth),
+            ("NoiseBrush", BrushOp::Noise { scale: 4.0, seed: 0 }),
+        ];
+        for (action, op) in ops {
+            if dinput.action_down(action) {
+                self.brush = TerrainDemo::make_brush(op, self.brush.radius);
+            }
+        }
+
+        self.brush.radius = (self.brush.radius + 10.0 * dinput.action_axis("BrushRadius") * dt).clamp(1.0, 50.0);
+
+        let save_down = dinput.action_down("SaveHeightmap");
+        if save_down && !self.save_down {
+            let path = format!("{}{}", BASE_PATH, EDITED_HEIGHTMAP_FILE);
+            match self.terrain.save_heightmap(&path, 0.2, 0.0) {
+                Ok(()) => println!("[TerrainDemo] saved {}", path),
+                Err(err) => println!("[TerrainDemo] {}: {}", path, err),
+            }
+        }
+        self.save_down = save_down;
+    }
+
+    // Turns the sun, relighting when it stops, and rebakes the horizons of the
+    // sculpted terrain.
+    fn update_sun(&mut self, dinput: &DirectInput, dt: f32) {
+        let turn = dinput.action_axis("SunAzimuth");
+        if turn != 0.0 {
+            // N.L follows the sun at once; the shadows catch up when it stops.
+            self.sun_azimuth = (self.sun_azimuth + 0.5 * turn * dt).rem_euclid(2.0 * D3DX_PI);
+            self.terrain.set_dir_to_sun_w(self.get_dir_to_sun());
+            self.sun_turning = true;
+        } else if self.sun_turning {
+            self.sun_turning = false;
+            self.relight();
+        }
+
+        let bake_down = dinput.action_down("BakeLighting");
+        if bake_down && !self.bake_down {
+            self.horizon_map = bake_horizon_map(self.terrain.get_height_field(),
+                                                &HorizonMapDesc::new(LIGHTMAP_SIZE, LIGHTMAP_SIZE));
+            self.relight();
+        }
+        self.bake_down = bake_down;
+    }
+
+    fn get_sun(&self) -> SunLight {
+        let (sin_el, cos_el) = SUN_ELEVATION.sin_cos();
+        SunLight::new(Vec3::new(cos_el * self.sun_azimuth.cos(), sin_el, cos_el * self.sun_azimuth.sin()))
+    }
+
+    fn get_dir_to_sun(&self) -> D3DXVECTOR3 {
+        let d = self.get_sun().dir_to_sun;
+        D3DXVECTOR3 { x: d.x, y: d.y, z: d.z }
+    }
+
+    // Lights the lightmap for the sun from the horizon map and uploads it to
+    // the terrain's gLightMap texture.
+    fn relight(&mut self) {
+        self.terrain.set_dir_to_sun_w(self.get_dir_to_sun());
+
+        let lightmap = self.horizon_map.relight(self.terrain.get_height_field(), &self.get_sun());
+        self.terrain.set_layer_bgra8("gLightMap", lightmap.get_width(), lightmap.get_height(),
+                                     &lightmap.to_bgra8());
+    }
+
+    // Per second: height units for raise, lower and noise, and the fraction
+    // of the way for flatten and smooth.
+    fn make_brush(op: BrushOp, radius: f32) -> Brush {
+        let strength = match op {
+            BrushOp::Raise | BrushOp::Lower => 10.0,
+            BrushOp::Flatten(_) | BrushOp::Smooth => 2.0,
+            BrushOp::Noise { .. } => 5.0,
+        };
+        Brush::new(op, radius, BrushFalloff::Smooth, strength)
+    }
+
+    // Applies the brush where the cursor's ray hits the terrain.  Flatten
+    // strokes level to the height they started at.
+    fn sculpt(&mut self, dt: f32) {
+        unsafe {
+            if let Some(d3d_device) = &D3D_DEVICE {
+                let hit = get_cursor_picking_ray(self.hwnd, d3d_device, &self.view, &self.proj)
+                    .and_then(|ray| self.terrain.get_height_field().intersect_ray(&ray, 0.0, f32::MAX));
+
+                if let Some(hit) = hit {
+                    let mut brush = self.brush;
+                    if let BrushOp::Flatten(_) = brush.op {
+                        brush.op = BrushOp::Flatten(*self.stroke_height.get_or_insert(hit.pos.y));
+                    }
+                    self.terrain.apply_brush(&brush, hit.pos.x, hit.pos.z, dt);
+                }
+            }
+        }
+    }
+
     pub fn draw_scene(&mut self) {
         unsafe {
             if let Some(d3d_device) = &D3D_DEVICE {
@@ -183,7 +314,10 @@ impl TerrainDemo {
 
                 HR!(d3d_device.BeginScene());
 
-                self.terrain.draw(&self.view, &self.proj);
+                let mut view_proj: D3DXMATRIX = std::mem::zeroed();
+                D3DXMatrixMultiply(&mut view_proj, &self.view, &self.proj);
+                let eye_pos = to_d3dx_vec3(self.camera.get_pos());
+                self.terrain.draw(&view_proj, &eye_pos);
 
                 if let Some(gfx_stats) = &self.gfx_stats {
                     gfx_stats.display();
@@ -202,12 +336,7 @@ impl TerrainDemo {
     }
 
     fn build_view_mtx(&mut self) {
-        let x: f32 = self.camera_radius * self.camera_rotation_y.cos();
-        let z: f32 = self.camera_radius * self.camera_rotation_y.sin();
-        let pos = D3DXVECTOR3 { x, y: self.camera_height, z };
-        let target = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-        let up = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-        D3DXMatrixLookAtLH(&mut self.view, &pos, &target, &up);
+        self.view = to_d3dx_matrix(self.camera.get_view());
     }
 
     fn build_proj_mtx(&mut self) {
@@ -1,65 +1,107 @@
-use windows::Win32::Devices::HumanInterfaceDevice::DIK_W;
 use d3dx::*;
+use common::*;
+use geometry::Vec3;
+use controllers::*;
+use input::*;
 use crate::*;
 
-#[derive(Copy, Clone)]
+// Actions switching between the controllers, on 1 to 4 by default.
+pub const WALK_CAMERA: &str = "WalkCamera";
+pub const FLY_CAMERA: &str = "FlyCamera";
+pub const ORBIT_CAMERA: &str = "OrbitCamera";
+pub const PATH_CAMERA: &str = "PathCamera";
+
+// Recording and playing the camera path: AddPathKey adds the current view as a
+// key, PlayPath plays or pauses the path and ScrubPath moves along it.
+pub const ADD_PATH_KEY: &str = "AddPathKey";
+pub const PLAY_PATH: &str = "PlayPath";
+pub const SCRUB_PATH: &str = "ScrubPath";
+
+// Saved on every new key, and loaded at start.
+pub const PATH_FILE: &str = "camera_path.txt";
+
+// Seconds between recorded keys.  The path plays at constant speed, so this
+// only sets how long it takes.
+const KEY_INTERVAL: f32 = 2.0;
+
+// Seconds of path per second of ScrubPath.
+const SCRUB_SPEED: f32 = 4.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CameraMode {
+    Walk,
+    Fly,
+    Orbit,
+    Path,
+}
+
+// The demo's camera: a geometry::Camera moved by one of the shared controllers,
+// with its view-projection matrix kept in D3DX form for the effects.
 pub struct Camera {
-    // Save camera related matrices.
-    view: D3DXMATRIX,
-    proj: D3DXMATRIX,
+    camera: geometry::Camera,
     view_proj: D3DXMATRIX,
 
-    // Relative to world space.
-    pos_w: D3DXVECTOR3,
-    right_w: D3DXVECTOR3,
-    up_w: D3DXVECTOR3,
-    look_w: D3DXVECTOR3,
+    mode: CameraMode,
+    walk: WalkController,
+    fly: FlyController,
+    orbit: OrbitController,
+    path: PathPlayer,
 
-    // Camera speed.
-    speed: f32,
+    // To act once per press.
+    add_key_down: bool,
+    play_down: bool,
 }
 
 impl Camera {
     pub fn new() -> Camera {
-        unsafe {
-            let mut view: D3DXMATRIX = std::mem::zeroed();
-            D3DXMatrixIdentity(&mut view);
-
-            let mut proj: D3DXMATRIX = std::mem::zeroed();
-            D3DXMatrixIdentity(&mut proj);
-
-            let mut view_proj: D3DXMATRIX = std::mem::zeroed();
-            D3DXMatrixIdentity(&mut view_proj);
-
-            let pos_w = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-            let right_w = D3DXVECTOR3 { x: 1.0, y: 0.0, z: 0.0 };
-            let up_w = D3DXVECTOR3 { x: 0.0, y: 1.0, z: 0.0 };
-            let look_w = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 1.0 };
-
-            // Client should adjust to a value that makes sense for application's
-            // unit scale, and the object the camera is attached to--e.g., car, jet,
-            // human walking, etc.
-            let speed = 100.0;
-
-            Camera {
-                view,
-                proj,
-                view_proj,
-                pos_w,
-                right_w,
-                up_w,
-                look_w,
-                speed,
+        let camera = geometry::Camera::new();
+
+        // Client should adjust the speeds to values that make sense for
+        // application's unit scale.
+        let mut orbit = OrbitController::new(Vec3::ZERO, 1500.0);
+        orbit.pitch = 0.5;
+        orbit.max_distance = 4000.0;
+
+        let path = match CameraPath::load(path_file()) {
+            Ok(path) => path,
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    println!("[Camera::new] {}: {}", path_file(), err);
+                }
+                CameraPath::new()
             }
+        };
+
+        Camera {
+            camera,
+            view_proj: to_d3dx_matrix(camera.get_view_proj()),
+            mode: CameraMode::Fly,
+            // Eyes about a person's height above the demo's terrain.
+            walk: WalkController::new(100.0, 5.0),
+            fly: FlyController::new(100.0),
+            orbit,
+            path: PathPlayer::new(path),
+            add_key_down: false,
+            play_down: false,
         }
     }
 
     pub fn set_pos(&mut self, pos: D3DXVECTOR3) {
-        self.pos_w = pos;
+        self.camera.set_pos(to_vec3(&pos));
+        self.view_proj = to_d3dx_matrix(self.camera.get_view_proj());
     }
 
     pub fn set_speed(&mut self, s: f32) {
-        self.speed = s;
+        self.walk.speed = s;
+        self.fly.speed = s;
+    }
+
+    pub fn get_mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    pub fn get_pos(&self) -> D3DXVECTOR3 {
+        to_d3dx_vec3(self.camera.get_pos())
     }
 
     pub fn get_view_proj(&self) -> &D3DXMATRIX {
@@ -67,127 +109,85 @@ impl Camera {
     }
 
     pub fn look_at(&mut self, pos: &D3DXVECTOR3, target: &D3DXVECTOR3, up: &D3DXVECTOR3) {
-        let mut l: D3DXVECTOR3 = unsafe { std::mem::zeroed() };
-        D3DXVec3Subtract(&mut l, target, pos);
-        D3DXVec3Normalize(&mut l, &l);
-
-        let mut r: D3DXVECTOR3 = unsafe { std::mem::zeroed() };
-        D3DXVec3Cross(&mut r, up, &l);
-        D3DXVec3Normalize(&mut r, &r);
-
-        let mut u: D3DXVECTOR3 = unsafe { std::mem::zeroed() };
-        D3DXVec3Cross(&mut u, &l, &r);
-        D3DXVec3Normalize(&mut u, &u);
-
-        self.pos_w = pos.clone();
-        self.right_w = r;
-        self.up_w = u;
-        self.look_w = l;
-
-        self.build_view();
-
-        self.view_proj = unsafe { std::mem::zeroed() };
-        D3DXMatrixMultiply(&mut self.view_proj, &self.view, &self.proj);
+        self.camera.look_at(to_vec3(pos), to_vec3(target), to_vec3(up));
+        self.view_proj = to_d3dx_matrix(self.camera.get_view_proj());
     }
 
     pub fn set_lens(&mut self, fov: f32, aspect: f32, near_z: f32, far_z: f32) {
-        D3DXMatrixPerspectiveFovLH(&mut self.proj, fov, aspect, near_z, far_z);
-
-        self.view_proj = unsafe { std::mem::zeroed() };
-        D3DXMatrixMultiply(&mut self.view_proj, &self.view, &self.proj);
+        self.camera.set_lens(fov, aspect, near_z, far_z);
+        self.view_proj = to_d3dx_matrix(self.camera.get_view_proj());
     }
 
-    pub fn update(&mut self, dt: f32) {
+    pub fn update(&mut self, dt: f32, ground: &dyn Ground) {
         unsafe {
-            if let Some(dinput) = &mut DIRECT_INPUT {
-                // Find the net direction the camera is traveling in (since the
-                // camera could be running and strafing).
-                let mut dir = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-
-                if dinput.key_down(DIK_W as usize) {
-                    D3DXVec3Add(&mut dir, &dir, &self.look_w);
-                }
-
-                if dinput.key_down(DIK_S as usize) {
-                    D3DXVec3Subtract(&mut dir, &dir, &self.look_w);
-                }
-
-                if dinput.key_down(DIK_D as usize) {
-                    D3DXVec3Add(&mut dir, &dir, &self.right_w);
-                }
-
-                if dinput.key_down(DIK_A as usize) {
-                    D3DXVec3Subtract(&mut dir, &dir, &self.right_w);
-                }
-
-                // Move at mSpeed along net direction.
-                D3DXVec3Normalize(&mut dir, &dir);
-
-                let mut res: D3DXVECTOR3 = std::mem::zeroed();
-                D3DXVec3Scale(&mut res, &dir, self.speed * dt);
-                D3DXVec3Add(&mut self.pos_w, &self.pos_w, &res);
-
-                // We rotate at a fixed speed.
-                let pitch: f32 = dinput.mouse_dy() / 150.0;
-                let y_angle: f32 = dinput.mouse_dx() / 150.0;
-
-                // Rotate camera's look and up vectors around the camera's right vector.
-                let mut r: D3DXMATRIX = std::mem::zeroed();
-                D3DXMatrixRotationAxis(&mut r, &self.right_w, pitch);
-                D3DXVec3TransformCoord(&mut self.look_w, &self.look_w, &r);
-                D3DXVec3TransformCoord(&mut self.up_w, &self.up_w, &r);
-
-                // Rotate camera axes about the world's y-axis.
-                D3DXMatrixRotationY(&mut r, y_angle);
-                D3DXVec3TransformCoord(&mut self.right_w, &self.right_w, &r);
-                D3DXVec3TransformCoord(&mut self.up_w, &self.up_w, &r);
-                D3DXVec3TransformCoord(&mut self.look_w, &self.look_w, &r);
-
-                // Rebuild the view matrix to reflect changes.
-                self.build_view();
-
-                self.view_proj = std::mem::zeroed();
-                D3DXMatrixMultiply(&mut self.view_proj, &self.view, &self.proj);
+            if let Some(dinput) = &DIRECT_INPUT {
+                self.update_with_input(dinput, dinput.get_bindings(), dt, ground);
             }
         }
     }
 
-    fn build_view(&mut self) {
-        // Keep camera's axes orthogonal to each other and of unit length.
-        D3DXVec3Normalize(&mut self.look_w, &self.look_w);
+    // Switches controllers with the WalkCamera, FlyCamera, OrbitCamera and
+    // PathCamera actions and lets the current one move the camera, so any
+    // InputState can drive it.  Walking keeps the eyes above 'ground'.
+    pub fn update_with_input(&mut self, input: &dyn InputState, bindings: &ActionMap, dt: f32, ground: &dyn Ground) {
+        if bindings.down(input, WALK_CAMERA) {
+            self.mode = CameraMode::Walk;
+        } else if bindings.down(input, FLY_CAMERA) {
+            self.mode = CameraMode::Fly;
+        } else if bindings.down(input, ORBIT_CAMERA) {
+            self.mode = CameraMode::Orbit;
+        } else if bindings.down(input, PATH_CAMERA) {
+            self.mode = CameraMode::Path;
+        }
 
-        D3DXVec3Cross(&mut self.up_w, &self.look_w, &self.right_w);
-        D3DXVec3Normalize(&mut self.up_w, &self.up_w);
+        let add_key = bindings.down(input, ADD_PATH_KEY);
+        if add_key && !self.add_key_down && self.mode != CameraMode::Path {
+            self.add_path_key();
+        }
+        self.add_key_down = add_key;
+
+        let play = bindings.down(input, PLAY_PATH);
+        if play && !self.play_down {
+            if self.path.is_playing() {
+                self.path.pause();
+            } else {
+                self.mode = CameraMode::Path;
+                self.path.play();
+            }
+        }
+        self.play_down = play;
 
-        D3DXVec3Cross(&mut self.right_w, &self.up_w, &self.look_w);
-        D3DXVec3Normalize(&mut self.right_w, &self.right_w);
+        if self.mode == CameraMode::Path {
+            let scrub = bindings.axis(input, SCRUB_PATH);
+            if scrub != 0.0 {
+                self.path.pause();
+                self.path.seek(self.path.get_time() + scrub * SCRUB_SPEED * dt);
+            }
+        }
+
+        match self.mode {
+            // The one controller that needs the ground.
+            CameraMode::Walk => self.walk.walk(&mut self.camera, input, bindings, dt, Some(ground)),
+            CameraMode::Fly => self.fly.update(&mut self.camera, input, bindings, dt),
+            CameraMode::Orbit => self.orbit.update(&mut self.camera, input, bindings, dt),
+            CameraMode::Path => self.path.update(&mut self.camera, input, bindings, dt),
+        }
 
-        // Fill in the view matrix entries.
+        self.view_proj = to_d3dx_matrix(self.camera.get_view_proj());
+    }
 
-        let x: f32 = -D3DXVec3Dot(&self.pos_w, &self.right_w);
-        let y: f32 = -D3DXVec3Dot(&self.pos_w, &self.up_w);
-        let z: f32 = -D3DXVec3Dot(&self.pos_w, &self.look_w);
+    // Appends the current view to the path and saves it.
+    fn add_path_key(&mut self) {
+        let path = self.path.get_path_mut();
+        let time = if path.is_empty() { 0.0 } else { path.get_end_time() + KEY_INTERVAL };
+        path.add_key(CameraKey::from_camera(&self.camera, time));
 
-        unsafe {
-            self.view.Anonymous.m[0] = self.right_w.x;
-            self.view.Anonymous.m[4] = self.right_w.y;
-            self.view.Anonymous.m[8] = self.right_w.z;
-            self.view.Anonymous.m[12] = x;
-
-            self.view.Anonymous.m[1] = self.up_w.x;
-            self.view.Anonymous.m[5] = self.up_w.y;
-            self.view.Anonymous.m[9] = self.up_w.z;
-            self.view.Anonymous.m[13] = y;
-
-            self.view.Anonymous.m[2] = self.look_w.x;
-            self.view.Anonymous.m[6] = self.look_w.y;
-            self.view.Anonymous.m[10] = self.look_w.z;
-            self.view.Anonymous.m[14] = z;
-
-            self.view.Anonymous.m[3] = 0.0;
-            self.view.Anonymous.m[7] = 0.0;
-            self.view.Anonymous.m[11] = 0.0;
-            self.view.Anonymous.m[15] = 1.0;
+        if let Err(err) = path.save(path_file()) {
+            println!("[Camera::add_path_key] {}: {}", path_file(), err);
         }
     }
-}
\ No newline at end of file
+}
+
+fn path_file() -> String {
+    format!("{}{}", BASE_PATH, PATH_FILE)
+}
@@ -3,7 +3,7 @@ use windows::{
 };
 
 use crate::*;
-use crate::terrain::Terrain;
+use common::Terrain;
 
 pub const BASE_PATH: &str = "luna_35_camera_demo/";
 
@@ -137,7 +137,7 @@ impl CameraDemo {
                 dinput.poll();
 
                 if let Some(camera) = &mut CAMERA {
-                    camera.update(dt);
+                    camera.update(dt, self.terrain.get_height_field());
                 }
             }
         }
@@ -157,7 +157,8 @@ impl CameraDemo {
 
                 HR!(d3d_device.BeginScene());
 
-                self.terrain.draw();
+                let camera: &Camera = CAMERA.as_ref().expect("Camera has not been created");
+                self.terrain.draw(camera.get_view_proj(), &camera.get_pos());
 
                 if let Some(gfx_stats) = &self.gfx_stats {
                     gfx_stats.display();
@@ -3,7 +3,7 @@ use windows::{
 };
 
 use crate::*;
-use crate::terrain::Terrain;
+use common::Terrain;
 
 pub const BASE_PATH: &str = "luna_36_walk_terrain_demo/";
 
@@ -132,7 +132,7 @@ impl WalkTerrainDemo {
                 dinput.poll();
 
                 if let Some(camera) = &mut CAMERA {
-                    camera.update(dt, Some(&self.terrain), 2.5);
+                    camera.update(dt, self.terrain.get_height_field());
                 }
             }
         }
@@ -152,7 +152,8 @@ impl WalkTerrainDemo {
 
                 HR!(d3d_device.BeginScene());
 
-                self.terrain.draw();
+                let camera: &Camera = CAMERA.as_ref().expect("Camera has not been created");
+                self.terrain.draw(camera.get_view_proj(), &camera.get_pos());
 
                 if let Some(gfx_stats) = &self.gfx_stats {
                     gfx_stats.display();
@@ -3,7 +3,7 @@ use windows::{
 };
 
 use crate::*;
-use crate::terrain::Terrain;
+use common::Terrain;
 
 pub const BASE_PATH: &str = "luna_37_culling_demo/";
 
@@ -160,9 +160,20 @@ impl CullingDemo {
 
                 HR!(d3d_device.BeginScene());
 
-                self.terrain.draw();
-
-                if let Some(gfx_stats) = &self.gfx_stats {
+                let camera: &Camera = CAMERA.as_ref().expect("Camera has not been created");
+                self.terrain.draw(camera.get_view_proj(), &camera.get_pos());
+
+                // How much of the terrain the quadtree culled, and with how many
+                // box tests compared to testing every sub-grid.
+                let cull_stats = *self.terrain.get_cull_stats();
+                let num_sub_grids = self.terrain.get_geomipmap().get_sub_grids().len();
+                if let Some(gfx_stats) = &mut self.gfx_stats {
+                    gfx_stats.set_info(&format!("Sub-grids Visible = {} of {}\n\
+                        Quadtree Nodes Visited = {} ({} culled, {} inside)\n\
+                        Box Tests = {} (linear: {})",
+                        cull_stats.num_visible, num_sub_grids,
+                        cull_stats.num_nodes_visited, cull_stats.num_nodes_culled, cull_stats.num_nodes_inside,
+                        cull_stats.num_tests, num_sub_grids));
                     gfx_stats.display();
                 }
 
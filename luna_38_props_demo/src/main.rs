@@ -1,8 +1,10 @@
 pub mod camera;
 pub mod props_demo;
-pub mod terrain;
 pub mod water;
 
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
 use windows::{
     Win32::Foundation::*, Win32::System::LibraryLoader::GetModuleHandleA,
     Win32::Graphics::Direct3D9::*, Win32::Graphics::Gdi::*, Win32::UI::WindowsAndMessaging::*,
@@ -47,14 +49,18 @@ fn main() {
 
         if let Some(d3d_app) = &mut D3D_APP {
             if let Some(d3d_device) = D3D_DEVICE.clone() {
-                let props_demo = PropsDemo::new(d3d_device.clone(), &d3d_app.d3d_pp);
-                d3d_app.props_demo = props_demo;
-
+                // Input first, since a replayed session also replays the grass made
+                // from its random seed.
                 DIRECT_INPUT = DirectInput::new(d3d_app.app_inst,
                                                 d3d_app.main_wnd,
                                                 DISCL_NONEXCLUSIVE | DISCL_FOREGROUND,
                                                 DISCL_NONEXCLUSIVE | DISCL_FOREGROUND);
 
+                let seed = DIRECT_INPUT.as_ref().map_or(0, |dinput| dinput.get_seed());
+
+                let props_demo = PropsDemo::new(d3d_device.clone(), &d3d_app.d3d_pp, StdRng::seed_from_u64(seed));
+                d3d_app.props_demo = props_demo;
+
                 let exit_code = d3d_app.run();
 
                 if let Some(props_demo) = &d3d_app.props_demo {
@@ -268,7 +274,7 @@ impl D3DApp {
                     if !self.device_is_lost() {
                         let mut curr_timestamp: i64 = 0;
                         QueryPerformanceCounter(&mut curr_timestamp);
-                        let dt: f32 = ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt;
+                        let dt = get_frame_dt(&mut DIRECT_INPUT, ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt);
 
                         if let Some(props_demo) = &mut self.props_demo {
                             props_demo.update_scene(dt);
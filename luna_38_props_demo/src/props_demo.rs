@@ -5,11 +5,12 @@ use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
 };
 use common::mtrl::Mtrl;
-use rand::{Rng, thread_rng};
-use rand::rngs::ThreadRng;
+use rand::rngs::StdRng;
 
 use crate::*;
-use crate::terrain::Terrain;
+use common::Terrain;
+use geometry::{select_lod, Aabb, Frustum, LodChain, LodOptions, QuadTree, Vec2, Vec3};
+use terrain::{scatter, ExclusionZone, ScatterDesc};
 use crate::water::Water;
 
 pub const BASE_PATH: &str = "luna_38_props_demo/Art/";
@@ -28,33 +29,56 @@ struct DirLight {
     dir_w: D3DXVECTOR3,
 }
 
+// Levels of detail of the castle and tree meshes, and the most pixels a
+// level's simplification may move on screen.
+const NUM_PROP_LODS: usize = 4;
+const MAX_LOD_PIXEL_ERROR: f32 = 1.0;
+
 pub struct Object3D {
-    mesh: LPD3DXMESH,
+    // Finest first: the loaded mesh, then simplified versions of it for far
+    // away objects.
+    lods: Vec<LPD3DXMESH>,
+    lod_errors: Vec<f32>,
     mtrls: Vec<Mtrl>,
     textures: Vec<*mut c_void>,
     bounding_box: AABB,
 }
 
 impl Object3D {
-    pub fn new() -> Object3D {
-        Object3D {
-            mesh: std::ptr::null_mut(),
-            mtrls: vec![],
-            textures: vec![],
-            bounding_box: AABB::default()
+    // Loads the mesh and builds its bounding box and levels of detail.
+    pub fn from_x_file(file: &str, d3d_device: IDirect3DDevice9) -> Object3D {
+        let (mesh, mtrls, textures, _) = load_x_file(BASE_PATH, file, d3d_device.clone());
+
+        let cpu_mesh: geometry::Mesh<geometry::VertexPNT> = read_d3dx_mesh(mesh);
+        let bounding_box = AABB::from_aabb(&Aabb::from_points(&cpu_mesh.vertices));
+
+        // Build the simplified detail levels.  Level 0 is the loaded mesh itself.
+        let lod_chain = LodChain::generate(&cpu_mesh,
+            &LodOptions { num_levels: NUM_PROP_LODS, ..Default::default() });
+
+        let mut lods = vec![mesh];
+        for level in lod_chain.levels.iter().skip(1) {
+            lods.push(unsafe {
+                create_d3dx_mesh(d3d_device.clone(), &VERTEX_PNT_DECL, &level.mesh, D3DXMESH_MANAGED)
+            });
         }
+
+        Object3D { lods, lod_errors: lod_chain.errors(), mtrls, textures, bounding_box }
     }
 
     pub fn release_com_objects(&self) {
-        ReleaseCOM(self.mesh);
+        for mesh in &self.lods {
+            ReleaseCOM(*mesh);
+        }
         for tex in &self.textures {
             ReleaseCOM(tex.cast());
         }
     }
 }
 
+// The most trees and grass blocks scattered.
 const NUM_TREES: usize = 200;
-const NUM_GRASS_BLOCKS: u32 = 4000;
+const NUM_GRASS_BLOCKS: usize = 4000;
 
 // Sample demo
 pub struct PropsDemo {
@@ -70,7 +94,12 @@ pub struct PropsDemo {
     castle_world: D3DXMATRIX,
 
     trees: [Object3D; 4],
-    tree_worlds: [D3DXMATRIX; NUM_TREES],
+    // Index into trees and world matrix of each tree.
+    tree_worlds: Vec<(usize, D3DXMATRIX)>,
+
+    // Over the world boxes of the castle (item 0) and the trees (item i + 1
+    // for tree i).
+    props_tree: QuadTree,
 
     grass_mesh: LPD3DXMESH,
     grass_tex: *mut c_void,
@@ -99,7 +128,8 @@ pub struct PropsDemo {
 }
 
 impl PropsDemo {
-    pub fn new(d3d_device: IDirect3DDevice9, d3d_pp: *const D3DPRESENT_PARAMETERS) -> Option<PropsDemo> {
+    pub fn new(d3d_device: IDirect3DDevice9, d3d_pp: *const D3DPRESENT_PARAMETERS,
+               mut rng: StdRng) -> Option<PropsDemo> {
         if !PropsDemo::check_device_caps() {
             display_error_then_quit("checkDeviceCaps() Failed");
         }
@@ -132,8 +162,13 @@ impl PropsDemo {
         // Setup water.
         let mut water_world: D3DXMATRIX = D3DXMATRIX::default();
         D3DXMatrixTranslation(&mut water_world, 8.0, 35.0, -80.0);
-        let water = Water::new(BASE_PATH, 33, 33, 20.0, 20.0,
-                               &water_world, &d3d_device);
+        let water = match Water::new(BASE_PATH, 33, 33, 20.0, 20.0, &water_world, &d3d_device) {
+            Ok(water) => water,
+            Err(e) => {
+                display_error_then_quit(&format!("gen_tri_grid_32() Failed: {}", e));
+                return None;
+            }
+        };
 
         // Initialize camera.
         unsafe {
@@ -144,8 +179,10 @@ impl PropsDemo {
         }
 
         let (castle, castle_world) = PropsDemo::build_castle(d3d_device.clone());
-        let (trees, tree_worlds) = PropsDemo::build_trees(&terrain, d3d_device.clone());
-        let grass_mesh = PropsDemo::build_grass(&terrain, d3d_device.clone());
+        let castle_zone = PropsDemo::get_castle_zone(&castle);
+        let (trees, tree_worlds) = PropsDemo::build_trees(&terrain, castle_zone, d3d_device.clone());
+        let grass_mesh = PropsDemo::build_grass(&terrain, castle_zone, &mut rng, d3d_device.clone());
+        let props_tree = PropsDemo::build_props_tree(&castle, &castle_world, &trees, &tree_worlds);
 
         let mut grass_tex = unsafe { std::mem::zeroed() };
         HR!(D3DXCreateTextureFromFile(d3d_device.clone(),
@@ -196,12 +233,14 @@ impl PropsDemo {
             gfx_stats.add_triangles(terrain.get_num_triangles());
             gfx_stats.add_vertices(water.get_num_vertices());
             gfx_stats.add_triangles(water.get_num_triangles());
-            gfx_stats.add_vertices(ID3DXBaseMesh_GetNumVertices(castle.mesh));
-            gfx_stats.add_triangles(ID3DXBaseMesh_GetNumFaces(castle.mesh));
 
-            for i in 0..4 {
-                gfx_stats.add_vertices(ID3DXBaseMesh_GetNumVertices(trees[i].mesh) * NUM_TREES as u32 / 4);
-                gfx_stats.add_triangles(ID3DXBaseMesh_GetNumFaces(trees[i].mesh) * NUM_TREES as u32 / 4);
+            // The castle and trees at full detail.
+            gfx_stats.add_vertices(ID3DXBaseMesh_GetNumVertices(castle.lods[0]));
+            gfx_stats.add_triangles(ID3DXBaseMesh_GetNumFaces(castle.lods[0]));
+
+            for (i, _) in &tree_worlds {
+                gfx_stats.add_vertices(ID3DXBaseMesh_GetNumVertices(trees[*i].lods[0]));
+                gfx_stats.add_triangles(ID3DXBaseMesh_GetNumFaces(trees[*i].lods[0]));
             }
 
             gfx_stats.add_vertices(ID3DXBaseMesh_GetNumVertices(grass_mesh));
@@ -222,6 +261,8 @@ impl PropsDemo {
             trees,
             tree_worlds,
 
+            props_tree,
+
             grass_mesh,
             grass_tex,
 
@@ -257,6 +298,11 @@ impl PropsDemo {
         self.terrain.release_com_objects();
         self.water.release_com_objects();
 
+        self.castle.release_com_objects();
+        for tree in &self.trees {
+            tree.release_com_objects();
+        }
+
         ReleaseCOM(self.white_tex);
         ReleaseCOM(self.fx);
         ReleaseCOM(self.grass_mesh);
@@ -381,7 +427,15 @@ impl PropsDemo {
                 HR!(ID3DXEffect_Begin(self.fx, &mut num_passes, 0));
                 HR!(ID3DXEffect_BeginPass(self.fx, 0));
 
-                self.draw_object(&self.castle, &self.castle_world);
+                // Only the castle and trees the quadtree finds in the frustum are
+                // drawn, nearest first.
+                let frustum = Frustum::from_matrix(&to_mat4(camera.get_view_proj()));
+                let mut visible = Vec::new();
+                self.props_tree.cull(&frustum, to_vec3(&camera.get_pos()), &mut visible);
+
+                if visible.contains(&0) {
+                    self.draw_object(&self.castle, &self.castle_world);
+                }
 
                 // Use alpha test to block non leaf pixels from being rendered in the
                 // trees (i.e., use alpha mask).
@@ -389,17 +443,10 @@ impl PropsDemo {
                 HR!(d3d_device.SetRenderState(D3DRS_ALPHAFUNC, D3DCMP_GREATEREQUAL.0 as u32));
                 HR!(d3d_device.SetRenderState(D3DRS_ALPHAREF, 200));
 
-                // Draw the trees: NUM_TREES/4 of each of the four types.
-                for i in 0..NUM_TREES {
-                    if i < NUM_TREES / 4 {
-                        self.draw_object(&self.trees[0], &self.tree_worlds[i]);
-                    } else if i < 2 * NUM_TREES / 4 {
-                        self.draw_object(&self.trees[1], &self.tree_worlds[i]);
-                    }else if i < 3 * NUM_TREES / 4 {
-                        self.draw_object(&self.trees[2], &self.tree_worlds[i]);
-                    } else {
-                        self.draw_object(&self.trees[3], &self.tree_worlds[i]);
-                    }
+                // Draw the trees, each with its type of the four.
+                for item in visible.iter().filter(|item| **item > 0) {
+                    let (i, world) = &self.tree_worlds[*item - 1];
+                    self.draw_object(&self.trees[*i], world);
                 }
 
                 HR!(d3d_device.SetRenderState(D3DRS_ALPHATESTENABLE, 0));
@@ -422,7 +469,7 @@ impl PropsDemo {
                 HR!(ID3DXEffect_EndPass(self.grass_fx));
                 HR!(ID3DXEffect_End(self.grass_fx));
 
-                self.terrain.draw();
+                self.terrain.draw(camera.get_view_proj(), &camera.get_pos());
 
                 self.water.draw(); // draw alpha blended objects last.
 
@@ -509,68 +556,56 @@ impl PropsDemo {
          fx, h_tech, h_wvp, h_world_inv_trans, h_mtrl, h_light, h_eye_pos_w, h_world, h_tex)
     }
 
+    // Culling is done by the caller (see props_tree).  Like the asteroids
+    // demo, the coarsest level of detail whose error stays under a pixel at
+    // the object's distance is drawn.
     pub fn draw_object(&self, obj: &Object3D, to_world: &D3DXMATRIX) {
         unsafe {
             let camera: &Camera = &CAMERA.expect("Camera has not been created");
 
-            // Transform AABB into the world space.
-            let mut bounding_box: AABB = std::mem::zeroed();
-            obj.bounding_box.xform(to_world, &mut bounding_box);
-
-            // Only draw if AABB is visible.
-            if camera.is_visible(&bounding_box) {
-                let mut wvp: D3DXMATRIX = std::mem::zeroed();
-                D3DXMatrixMultiply(&mut wvp, to_world, camera.get_view_proj());
-                HR!(ID3DXBaseEffect_SetMatrix(self.fx, self.h_wvp, &wvp));
-
-                let mut world_inverse_transpose: D3DXMATRIX = std::mem::zeroed();
-                D3DXMatrixInverse(&mut world_inverse_transpose, 0.0, to_world);
-                D3DXMatrixTranspose(&mut world_inverse_transpose, &world_inverse_transpose);
-                HR!(ID3DXBaseEffect_SetMatrix(self.fx, self.h_world_inv_trans, &world_inverse_transpose));
-                HR!(ID3DXBaseEffect_SetMatrix(self.fx, self.h_world, to_world));
-
-                for j in 0..obj.mtrls.len() {
-                    HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_mtrl,
-                        &obj.mtrls[j] as *const _ as _,
-                        std::mem::size_of::<Mtrl>() as u32));
-
-                    // If there is a texture, then use.
-                    if !obj.textures[j].is_null() {
-                        HR!(ID3DXBaseEffect_SetTexture(self.fx, self.h_tex, obj.textures[j]));
-                    } else {
-                        // But if not, then set a pure white texture.  When the texture color
-                        // is multiplied by the color from lighting, it is like multiplying by
-                        // 1 and won't change the color from lighting.
-                        HR!(ID3DXBaseEffect_SetTexture(self.fx, self.h_tex, self.white_tex));
-                    }
-
-                    HR!(ID3DXEffect_CommitChanges(self.fx));
-                    HR!(ID3DXBaseMesh_DrawSubset(obj.mesh, j as u32));
+            let world = to_mat4(to_world);
+            let pos = Vec3::new(world.m[3][0], world.m[3][1], world.m[3][2]);
+            let scale = Vec3::new(world.m[0][0], world.m[0][1], world.m[0][2]).length();
+            let distance = pos.distance(to_vec3(&camera.get_pos()));
+            let viewport_height = (*self.d3d_pp).BackBufferHeight as f32;
+            let lod = select_lod(&obj.lod_errors, distance, scale, &to_mat4(camera.get_proj()),
+                                 viewport_height, MAX_LOD_PIXEL_ERROR);
+            let mesh = obj.lods[lod];
+
+            let mut wvp: D3DXMATRIX = std::mem::zeroed();
+            D3DXMatrixMultiply(&mut wvp, to_world, camera.get_view_proj());
+            HR!(ID3DXBaseEffect_SetMatrix(self.fx, self.h_wvp, &wvp));
+
+            let mut world_inverse_transpose: D3DXMATRIX = std::mem::zeroed();
+            D3DXMatrixInverse(&mut world_inverse_transpose, 0.0, to_world);
+            D3DXMatrixTranspose(&mut world_inverse_transpose, &world_inverse_transpose);
+            HR!(ID3DXBaseEffect_SetMatrix(self.fx, self.h_world_inv_trans, &world_inverse_transpose));
+            HR!(ID3DXBaseEffect_SetMatrix(self.fx, self.h_world, to_world));
+
+            for j in 0..obj.mtrls.len() {
+                HR!(ID3DXBaseEffect_SetValue(self.fx, self.h_mtrl,
+                    &obj.mtrls[j] as *const _ as _,
+                    std::mem::size_of::<Mtrl>() as u32));
+
+                // If there is a texture, then use.
+                if !obj.textures[j].is_null() {
+                    HR!(ID3DXBaseEffect_SetTexture(self.fx, self.h_tex, obj.textures[j]));
+                } else {
+                    // But if not, then set a pure white texture.  When the texture color
+                    // is multiplied by the color from lighting, it is like multiplying by
+                    // 1 and won't change the color from lighting.
+                    HR!(ID3DXBaseEffect_SetTexture(self.fx, self.h_tex, self.white_tex));
                 }
+
+                HR!(ID3DXEffect_CommitChanges(self.fx));
+                HR!(ID3DXBaseMesh_DrawSubset(mesh, j as u32));
             }
         }
     }
 
     fn build_castle(d3d_device: IDirect3DDevice9) -> (Object3D, D3DXMATRIX) {
-        // Load the castle mesh.
-        let mut castle = Object3D::new();
-        let (mesh, mtrls, textures) =
-            load_x_file(BASE_PATH, "castle.x", d3d_device.clone());
-        castle.mesh = mesh;
-        castle.mtrls = mtrls;
-        castle.textures = textures;
-
-        // Compute castle AABB.
-        let mut v: *mut c_void = std::ptr::null_mut();
-        HR!(ID3DXBaseMesh_LockVertexBuffer(castle.mesh, 0, &mut v));
-
-        let num_vertices = ID3DXBaseMesh_GetNumVertices(castle.mesh);
-        let num_bytes_per_vertex = ID3DXBaseMesh_GetNumBytesPerVertex(castle.mesh);
-
-        HR!(D3DXComputeBoundingBox(v.cast(), num_vertices, num_bytes_per_vertex,
-            &mut castle.bounding_box.min_pt, &mut castle.bounding_box.max_pt));
-
-        HR!(ID3DXBaseMesh_UnlockVertexBuffer(castle.mesh));
+        // Load the castle mesh, with its AABB and levels of detail.
+        let mut castle = Object3D::from_x_file("castle.x", d3d_device);
 
         // Manually set castle materials.
         for i in 0..castle.mtrls.len() {
@@ -593,100 +628,104 @@ impl PropsDemo {
         (castle, castle_world)
     }
 
-    fn build_trees(terrain: &Terrain, d3d_device: IDirect3DDevice9) -> ([Object3D; 4], [D3DXMATRIX; NUM_TREES]) {
-        // Load 4 unique meshes.  To draw more than 4 trees, we just draw these
-        // 4 trees repeatedly, with different world matrices applied.
-        let mut trees: [Object3D; 4] = unsafe { std::mem::zeroed() };
-        let mut tree_worlds = [D3DXMATRIX::default(); NUM_TREES];
-
-        let (mesh, mtrls, textures) =
-            load_x_file(BASE_PATH, "tree0.x", d3d_device.clone());
-        trees[0].mesh = mesh;
-        trees[0].mtrls = mtrls;
-        trees[0].textures = textures;
-
-        let (mesh, mtrls, textures) =
-            load_x_file(BASE_PATH, "tree1.x", d3d_device.clone());
-        trees[1].mesh = mesh;
-        trees[1].mtrls = mtrls;
-        trees[1].textures = textures;
-
-        let (mesh, mtrls, textures) =
-            load_x_file(BASE_PATH, "tree2.x", d3d_device.clone());
-        trees[2].mesh = mesh;
-        trees[2].mtrls = mtrls;
-        trees[2].textures = textures;
-
-        let (mesh, mtrls, textures) =
-            load_x_file(BASE_PATH, "tree3.x", d3d_device.clone());
-        trees[3].mesh = mesh;
-        trees[3].mtrls = mtrls;
-        trees[3].textures = textures;
-
-        // Build tree bounding boxes.
-        for i in 0..4 {
-            let mut v: *mut c_void = std::ptr::null_mut();
-            HR!(ID3DXBaseMesh_LockVertexBuffer(trees[i].mesh, 0, &mut v));
+    fn build_props_tree(castle: &Object3D, castle_world: &D3DXMATRIX, trees: &[Object3D; 4],
+                        tree_worlds: &[(usize, D3DXMATRIX)]) -> QuadTree {
+        let world_bounds = |obj: &Object3D, to_world: &D3DXMATRIX| {
+            let mut bounding_box = AABB::default();
+            obj.bounding_box.xform(to_world, &mut bounding_box);
+            bounding_box.to_aabb()
+        };
 
-            HR!(D3DXComputeBoundingBox(v.cast(),
-                ID3DXBaseMesh_GetNumVertices(trees[i].mesh),
-                ID3DXBaseMesh_GetNumBytesPerVertex(trees[i].mesh),
-                &mut trees[i].bounding_box.min_pt, &mut trees[i].bounding_box.max_pt));
+        let mut bounds = vec![world_bounds(castle, castle_world)];
+        bounds.extend(tree_worlds.iter().map(|(i, world)| world_bounds(&trees[*i], world)));
+        QuadTree::build(&bounds)
+    }
 
-            HR!(ID3DXBaseMesh_UnlockVertexBuffer(trees[i].mesh));
+    // The castle's footprint on the xz-plane, plus a margin, for the trees and
+    // grass to keep out of.  Matches the rotation and translation of the
+    // castle's world matrix in build_castle.
+    fn get_castle_zone(castle: &Object3D) -> ExclusionZone {
+        let margin = 2.0;
+        let min_pt = castle.bounding_box.min_pt;
+        let max_pt = castle.bounding_box.max_pt;
+        ExclusionZone::Rect {
+            min: Vec2::new(8.0 - max_pt.x - margin, -80.0 - max_pt.z - margin),
+            max: Vec2::new(8.0 - min_pt.x + margin, -80.0 - min_pt.z + margin),
         }
+    }
 
-        // Build world matrices for NUM_TREES trees.  To do this, we generate a
-        // random position on the terrain surface for each tree.  In reality,
-        // this is not the best way to do it, as we'd like to have more control and
-        // manually place trees in the scene by an artist.  Nevertheless, this is
-        // an easy way to get trees in the scene of our demo.  To prevent trees
-        // from being placed on mountain peaks, or in the water, we can specify to
-        // only generate trees in an allowed height range.  By inspecting the heightmap
-        // used in this demo, castlehm257.raw, the range [35, 50] seems to be a good
-        // one to generate trees in.  Note that this method does not prevent trees from
-        // interpenetrating with one another and it does not prevent the trees from
-        // interpenetrating with the castle.
-
-        // Scale down a bit do we ignore the borders of the terrain as candidates.
-        let w: i32 = (terrain.get_width() * 0.8) as i32;
-        let d: i32 = (terrain.get_depth() * 0.8) as i32;
-
-        let mut s = D3DXMATRIX::default();
-        let mut t = D3DXMATRIX::default();
-
-        let mut rng = thread_rng();
-
-        for i in 0..NUM_TREES {
-            loop {
-                let x: f32 = (rng.gen_range(0..32767) % w) as f32 - (w as f32 * 0.5);
-                let z: f32 = (rng.gen_range(0..32767) % d) as f32 - (d as f32 * 0.5);
-
-                // Subtract off height to embed trunk in ground.
-                let y: f32 = terrain.get_height(x, z) - 0.5;
-
-                // Trees modeled to a different scale then ours, so scale them down to make sense.
-                // Also randomize the height a bit.
-                let tree_scale = get_random_float(&mut rng, 0.15, 0.25);
-
-                // Build tree's world matrix.
-                D3DXMatrixTranslation(&mut t, x, y, z);
-                D3DXMatrixScaling(&mut s, tree_scale, tree_scale, tree_scale);
-                D3DXMatrixMultiply(&mut tree_worlds[i], &s, &t);
-
-                // Only generate trees in this height range.  If the height
-                // is outside this range, generate a new random position and
-                // try again.
-                if !(y < 35.0 || y > 50.0) { // We are trying again if out of valid height range
-                    break;
-                }
-            }
-        }
+    fn build_trees(terrain: &Terrain, castle_zone: ExclusionZone, d3d_device: IDirect3DDevice9)
+                   -> ([Object3D; 4], Vec<(usize, D3DXMATRIX)>) {
+        // Load 4 unique meshes, with their AABBs and levels of detail.  To draw
+        // more than 4 trees, we just draw these 4 trees repeatedly, with
+        // different world matrices applied.
+        let trees: [Object3D; 4] = [
+            Object3D::from_x_file("tree0.x", d3d_device.clone()),
+            Object3D::from_x_file("tree1.x", d3d_device.clone()),
+            Object3D::from_x_file("tree2.x", d3d_device.clone()),
+            Object3D::from_x_file("tree3.x", d3d_device.clone()),
+        ];
+
+        // Scatter up to NUM_TREES trees over the terrain, at least 6 units apart
+        // so they do not interpenetrate.  To prevent trees from being placed on
+        // mountain peaks, or in the water, we only allow them in a height range.
+        // By inspecting the heightmap used in this demo, castlehm257.raw, the range
+        // [35, 50] seems to be a good one to generate trees in.  Trees are also
+        // kept off steep slopes and out of the castle.
+        let w = terrain.get_width() * 0.8;
+        let d = terrain.get_depth() * 0.8;
+        let desc = ScatterDesc {
+            // Scale down a bit so we ignore the borders of the terrain as candidates.
+            region: Some((Vec2::new(-0.5 * w, -0.5 * d), Vec2::new(0.5 * w, 0.5 * d))),
+            min_distance: 6.0,
+            max_count: NUM_TREES,
+            min_height: 35.0,
+            max_height: 50.0,
+            max_slope: 0.6,
+            exclusion_zones: vec![castle_zone],
+            // Trees modeled to a different scale then ours, so scale them down to make sense.
+            // Also randomize the height a bit.
+            min_scale: 0.15,
+            max_scale: 0.25,
+            num_variants: trees.len(),
+            // Subtract off height to embed trunk in ground.
+            y_offset: -0.5,
+            seed: 38,
+            ..Default::default()
+        };
+
+        let tree_worlds = scatter(terrain.get_height_field(), &desc).iter()
+            .map(|tree| (tree.variant, to_d3dx_matrix(&tree.get_transform())))
+            .collect();
 
         (trees, tree_worlds)
     }
 
-    fn build_grass(terrain: &Terrain, d3d_device: IDirect3DDevice9) -> LPD3DXMESH {
+    fn build_grass(terrain: &Terrain, castle_zone: ExclusionZone, rng: &mut StdRng,
+                   d3d_device: IDirect3DDevice9) -> LPD3DXMESH {
+        // Scatter grass blocks (three intersecting quads) in a scaled down region
+        // of the terrain, shifted to place it in the world, in the height range
+        // [35, 50] (similar to the trees).  The blocks are billboarded by the
+        // effect, so they are not rotated.
+        let w = terrain.get_width() * 0.15;
+        let d = terrain.get_depth() * 0.15;
+        let desc = ScatterDesc {
+            region: Some((Vec2::new(-0.5 * w - 30.0, -0.5 * d - 20.0), Vec2::new(0.5 * w - 30.0, 0.5 * d - 20.0))),
+            min_distance: 0.6,
+            max_count: NUM_GRASS_BLOCKS,
+            min_height: 35.0,
+            max_height: 50.0,
+            exclusion_zones: vec![castle_zone],
+            min_scale: 0.75,
+            max_scale: 1.25,
+            uniform_scale: false,
+            random_yaw: false,
+            seed: 39,
+            ..Default::default()
+        };
+        let blocks = scatter(terrain.get_height_field(), &desc);
+        let num_blocks = blocks.len() as u32;
+
         unsafe {
             let mut elems: [D3DVERTEXELEMENT9; MAX_FVF_DECL_SIZE as usize] = [D3DVERTEXELEMENT9::default(); MAX_FVF_DECL_SIZE as usize];
             let mut num_elems = 0;
@@ -696,7 +735,7 @@ impl PropsDemo {
             }
 
             let mut grass_mesh = std::ptr::null_mut();
-            HR!(D3DXCreateMesh(NUM_GRASS_BLOCKS * 2, NUM_GRASS_BLOCKS * 4, D3DXMESH_MANAGED,
+            HR!(D3DXCreateMesh(num_blocks * 2, num_blocks * 4, D3DXMESH_MANAGED,
                 elems.as_mut_ptr(), d3d_device.clone(), &mut grass_mesh));
 
             let mut v: *mut c_void = std::ptr::null_mut();
@@ -707,50 +746,14 @@ impl PropsDemo {
 
             let mut index_offset = 0;
 
-            // Scale down the region in which we generate grass.
-            let w: i32 = (terrain.get_width() * 0.15) as i32;
-            let d: i32 = (terrain.get_depth() * 0.15) as i32;
-
-            let mut rng = thread_rng();
-
             let mut v_offset = 0;
             let mut k_offset = 0;
 
-            // Randomly generate a grass block (three intersecting quads) around the
-            // terrain in the height range [35, 50] (similar to the trees).
-            for _i in 0..NUM_GRASS_BLOCKS {
-                let mut x: f32;
-                let mut z: f32;
-                let mut y: f32;
-
-                loop {
-                    //============================================
-                    // Construct vertices.
-
-                    // Generate random position in region.  Note that we also shift
-                    // this region to place it in the world.
-                    x = ((rng.gen_range(0..32767) % w) as f32 - (w as f32 * 0.5)) - 30.0;
-                    z = ((rng.gen_range(0..32767) % d) as f32 - (d as f32 * 0.5)) - 20.0;
-                    y = terrain.get_height(x, z);
-
-                    // Only generate grass blocks in this height range.  If the height
-                    // is outside this range, generate a new random position and
-                    // try again.
-                    if !(y < 35.0 || y > 50.0) { // We are trying again if out of valid height range
-                        break;
-                    }
-                }
-
-                let sx: f32 = get_random_float(&mut rng, 0.75, 1.25);
-
-                let sy: f32 = get_random_float(&mut rng, 0.75, 1.25);
-
-                let sz: f32 = get_random_float(&mut rng, 0.75, 1.25);
-
-                let pos = D3DXVECTOR3 { x, y, z };
-                let scale = D3DXVECTOR3 { x: sx, y: sy, z: sz };
+            for block in &blocks {
+                let pos = to_d3dx_vec3(block.pos);
+                let scale = to_d3dx_vec3(block.scale);
 
-                PropsDemo::build_grass_fin(&mut rng, v.offset(v_offset),
+                PropsDemo::build_grass_fin(rng, v.offset(v_offset),
                                            k.offset(k_offset), &mut index_offset,
                                            pos, scale);
 
@@ -784,7 +787,7 @@ impl PropsDemo {
         }
     }
 
-    fn build_grass_fin(rng: &mut ThreadRng, v: *mut c_void /*VertexGrass*/, k: *mut c_void /*u16*/,
+    fn build_grass_fin(rng: &mut StdRng, v: *mut c_void /*VertexGrass*/, k: *mut c_void /*u16*/,
                        index_offset: &mut i32, world_pos: D3DXVECTOR3,
                        scale: D3DXVECTOR3) {
         unsafe {
@@ -19,7 +19,7 @@ pub struct Water {
 
 impl Water {
     pub fn new(base_path: &str, m: i32, n: i32, dx: f32, dz: f32,
-               to_world: &D3DXMATRIX, d3d_device: &IDirect3DDevice9) -> Water {
+               to_world: &D3DXMATRIX, d3d_device: &IDirect3DDevice9) -> Result<Water, GridError> {
         unsafe {
             // let vert_rows = m;
             // let vert_cols = n;
@@ -30,6 +30,11 @@ impl Water {
             let num_tris = (m - 1) * (n - 1) * 2;
             let num_verts = m * n;
 
+            let mut verts: Vec<D3DXVECTOR3> = Vec::new();
+            let mut indices: Vec<u32> = Vec::new();
+            gen_tri_grid_32(m, n, dx, dz,
+                            D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices)?;
+
             //===============================================================
             // Allocate the mesh.
 
@@ -51,11 +56,6 @@ impl Water {
             HR!(ID3DXBaseMesh_LockVertexBuffer(mesh, 0, &mut v));
             let mut v_slice: &mut [VertexPos] = from_raw_parts_mut(v.cast(), num_verts as usize);
 
-            let mut verts: Vec<D3DXVECTOR3> = Vec::new();
-            let mut indices: Vec<u32> = Vec::new();
-            gen_tri_grid_32(m, n, dx, dz,
-                            D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 }, &mut verts, &mut indices);
-
             let num_vertices: usize = ID3DXBaseMesh_GetNumVertices(mesh) as usize;
 
             for i in 0..num_vertices {
@@ -111,13 +111,13 @@ impl Water {
             HR!(ID3DXEffect_SetTechnique(fx, h_tech));
             HR!(ID3DXBaseEffect_SetMatrix(fx, h_world, to_world));
 
-            Water {
+            Ok(Water {
                 mesh,
                 to_world: to_world.clone(),
                 fx,
                 h_wvp,
                 h_eye_pos_w,
-            }
+            })
         }
     }
 
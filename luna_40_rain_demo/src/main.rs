@@ -1,9 +1,10 @@
 pub mod camera;
 pub mod rain_demo;
-pub mod terrain;
 mod rain_psystem;
 
-use rand::thread_rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
 use windows::{
     Win32::Foundation::*, Win32::System::LibraryLoader::GetModuleHandleA,
     Win32::Graphics::Direct3D9::*, Win32::Graphics::Gdi::*, Win32::UI::WindowsAndMessaging::*,
@@ -48,16 +49,20 @@ fn main() {
 
         if let Some(d3d_app) = &mut D3D_APP {
             if let Some(d3d_device) = D3D_DEVICE.clone() {
-                let rain_demo =
-                    RainDemo::new(d3d_app.get_main_wnd(), d3d_device.clone(),
-                                  &d3d_app.d3d_pp, thread_rng());
-                d3d_app.rain_demo = rain_demo;
-
+                // Input first, since a replayed session also replays the random
+                // particles made from its seed.
                 DIRECT_INPUT = DirectInput::new(d3d_app.app_inst,
                                                 d3d_app.main_wnd,
                                                 DISCL_NONEXCLUSIVE | DISCL_FOREGROUND,
                                                 DISCL_NONEXCLUSIVE | DISCL_FOREGROUND);
 
+                let seed = DIRECT_INPUT.as_ref().map_or(0, |dinput| dinput.get_seed());
+
+                let rain_demo =
+                    RainDemo::new(d3d_app.get_main_wnd(), d3d_device.clone(),
+                                  &d3d_app.d3d_pp, StdRng::seed_from_u64(seed));
+                d3d_app.rain_demo = rain_demo;
+
                 let exit_code = d3d_app.run();
 
                 if let Some(rain_demo) = &d3d_app.rain_demo {
@@ -275,7 +280,7 @@ impl D3DApp {
                     if !self.device_is_lost() {
                         let mut curr_timestamp: i64 = 0;
                         QueryPerformanceCounter(&mut curr_timestamp);
-                        let dt: f32 = ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt;
+                        let dt = get_frame_dt(&mut DIRECT_INPUT, ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt);
 
                         if let Some(rain_demo) = &mut self.rain_demo {
                             rain_demo.update_scene(dt);
@@ -1,11 +1,11 @@
-use rand::prelude::ThreadRng;
+use rand::rngs::StdRng;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
 };
 
 use crate::*;
 use crate::rain_psystem::RainPSystem;
-use crate::terrain::Terrain;
+use common::Terrain;
 
 pub const BASE_PATH: &str = "luna_40_rain_demo/";
 
@@ -22,7 +22,7 @@ pub struct RainDemo {
 }
 
 impl RainDemo {
-    pub fn new(hwnd: HWND, d3d_device: IDirect3DDevice9, d3d_pp: *const D3DPRESENT_PARAMETERS, rng: ThreadRng) -> Option<RainDemo> {
+    pub fn new(hwnd: HWND, d3d_device: IDirect3DDevice9, d3d_pp: *const D3DPRESENT_PARAMETERS, rng: StdRng) -> Option<RainDemo> {
         if !RainDemo::check_device_caps() {
             display_error_then_quit("checkDeviceCaps() Failed");
         }
@@ -189,7 +189,8 @@ impl RainDemo {
 
                 HR!(d3d_device.BeginScene());
 
-                self.terrain.draw();
+                let camera: &Camera = CAMERA.as_ref().expect("Camera has not been created");
+                self.terrain.draw(camera.get_view_proj(), &camera.get_pos());
                 self.psys.draw();
 
                 if let Some(gfx_stats) = &self.gfx_stats {
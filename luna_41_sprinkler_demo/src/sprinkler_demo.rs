@@ -1,11 +1,11 @@
-use rand::prelude::ThreadRng;
+use rand::rngs::StdRng;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
 };
 
 use crate::*;
 use crate::sprinkler_psystem::SprinklerPSystem;
-use crate::terrain::Terrain;
+use common::Terrain;
 
 pub const BASE_PATH: &str = "luna_41_sprinkler_demo/";
 
@@ -22,7 +22,7 @@ pub struct SprinklerDemo {
 }
 
 impl SprinklerDemo {
-    pub fn new(hwnd: HWND, d3d_device: IDirect3DDevice9, d3d_pp: *const D3DPRESENT_PARAMETERS, rng: ThreadRng) -> Option<SprinklerDemo> {
+    pub fn new(hwnd: HWND, d3d_device: IDirect3DDevice9, d3d_pp: *const D3DPRESENT_PARAMETERS, rng: StdRng) -> Option<SprinklerDemo> {
         if !SprinklerDemo::check_device_caps() {
             display_error_then_quit("checkDeviceCaps() Failed");
         }
@@ -192,7 +192,8 @@ impl SprinklerDemo {
 
                 HR!(d3d_device.BeginScene());
 
-                self.terrain.draw();
+                let camera: &Camera = CAMERA.as_ref().expect("Camera has not been created");
+                self.terrain.draw(camera.get_view_proj(), &camera.get_pos());
                 self.psys.draw();
 
                 if let Some(gfx_stats) = &self.gfx_stats {
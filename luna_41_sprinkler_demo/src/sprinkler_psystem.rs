@@ -1,7 +1,7 @@
 use std::ffi::CStr;
 use std::slice::from_raw_parts_mut;
 use libc::c_void;
-use rand::rngs::ThreadRng;
+use rand::rngs::StdRng;
 use windows::Win32::Foundation::PSTR;
 use windows::Win32::Graphics::Direct3D9::*;
 use common::*;
@@ -36,7 +36,7 @@ pub struct SprinklerPSystem {
     alive_particles: Vec<usize>,
     dead_particles: Vec<usize>,
 
-    rng: ThreadRng,
+    rng: StdRng,
 
     hwnd: HWND,
 }
@@ -45,7 +45,7 @@ impl SprinklerPSystem {
     pub fn new(base_path: &str, fx_name: &str, tech_name: &str, tex_name: &str,
                accel: &D3DXVECTOR3, bounding_box: &AABB, max_num_particles: u32,
                time_per_particle: f32, hwnd: HWND, d3d_device: IDirect3DDevice9,
-               rng: ThreadRng) -> SprinklerPSystem {
+               rng: StdRng) -> SprinklerPSystem {
 
         // Allocate memory for maximum number of particles.
         let mut particles: Vec<Particle> = Vec::new();
@@ -1,11 +1,12 @@
-use rand::prelude::ThreadRng;
+use input::FIRE;
+use rand::rngs::StdRng;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
 };
 
 use crate::*;
 use crate::gun_psystem::GunPSystem;
-use crate::terrain::Terrain;
+use common::Terrain;
 
 pub const BASE_PATH: &str = "luna_42_gun_demo/";
 
@@ -22,7 +23,7 @@ pub struct GunDemo {
 }
 
 impl GunDemo {
-    pub fn new(hwnd: HWND, d3d_device: IDirect3DDevice9, d3d_pp: *const D3DPRESENT_PARAMETERS, rng: ThreadRng) -> Option<GunDemo> {
+    pub fn new(hwnd: HWND, d3d_device: IDirect3DDevice9, d3d_pp: *const D3DPRESENT_PARAMETERS, rng: StdRng) -> Option<GunDemo> {
         if !GunDemo::check_device_caps() {
             display_error_then_quit("checkDeviceCaps() Failed");
         }
@@ -176,7 +177,7 @@ impl GunDemo {
                 // Can only fire once every tenth of a second.
                 static mut DELAY: f32 = 0.0;
 
-                if dinput.key_down(DIK_SPACE as usize) && DELAY <= 0.0 {
+                if dinput.action_down(FIRE) && DELAY <= 0.0 {
                     DELAY = 0.1;
                     self.psys.add_particle();
                 }
@@ -199,7 +200,8 @@ impl GunDemo {
 
                 HR!(d3d_device.BeginScene());
 
-                self.terrain.draw();
+                let camera: &Camera = CAMERA.as_ref().expect("Camera has not been created");
+                self.terrain.draw(camera.get_view_proj(), &camera.get_pos());
                 self.psys.draw();
 
                 if let Some(gfx_stats) = &self.gfx_stats {
@@ -4,6 +4,7 @@ use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
 };
 use common::mtrl::Mtrl;
+use input::PICK;
 
 use crate::*;
 
@@ -33,6 +34,10 @@ pub struct TriPickDemo {
     mesh_mtrls: Vec<Mtrl>,
     mesh_textures: Vec<*mut c_void>,
 
+    // CPU copy of the car and its BVH, for picking.
+    cpu_mesh: geometry::Mesh<geometry::VertexPNT>,
+    mesh_bvh: geometry::Bvh,
+
     // General light/texture FX
     fx: LPD3DXEFFECT,
     h_tech: D3DXHANDLE,
@@ -59,9 +64,14 @@ impl TriPickDemo {
         let mut gfx_stats = GfxStats::new(d3d_device.clone(), D3DCOLOR_XRGB!(0, 0, 0));
 
         // Load the mesh data.
-        let (mesh, mesh_mtrls, mesh_textures) =
+        let (mesh, mesh_mtrls, mesh_textures, _) =
             load_x_file(BASE_PATH, "car.x", d3d_device.clone());
 
+        // Faces keep the numbering of the D3DX mesh, so picked faces can be drawn
+        // straight from its index buffer.
+        let cpu_mesh: geometry::Mesh<geometry::VertexPNT> = read_d3dx_mesh(mesh);
+        let mesh_bvh = geometry::Bvh::build(&cpu_mesh);
+
         // Initialize camera.
         unsafe {
             if let Some(camera) = &mut CAMERA {
@@ -114,6 +124,9 @@ impl TriPickDemo {
             mesh_mtrls,
             mesh_textures,
 
+            cpu_mesh,
+            mesh_bvh,
+
             fx,
             h_tech,
             h_wvp,
@@ -274,28 +287,16 @@ impl TriPickDemo {
                 HR!(d3d_device.SetRenderState(D3DRS_FILLMODE, D3DFILL_SOLID.0 as u32));
 
                 // Did we pick anything?
-                let mut origin_w = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-                let mut dir_w = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-
-                if let Some(dinput) = &mut DIRECT_INPUT {
-                    if dinput.mouse_button_down(0) {
-
-                        self.get_world_picking_ray(&mut origin_w, &mut dir_w);
-
-                        let mut hit: i32 = 0;
-                        let mut face_index: u32 = u32::MAX;
-                        let mut u: f32 = 0.0;
-                        let mut v: f32 = 0.0;
-                        let mut dist: f32 = 0.0;
-
-                        let mut all_hits: LPD3DXBUFFER = std::ptr::null_mut();
-                        let mut num_hits: u32 = 0;
-                        HR!(D3DXIntersect(self.mesh, &origin_w, &dir_w, &mut hit,
-                            &mut face_index, &mut u, &mut v, &mut dist, &mut all_hits, &mut num_hits));
-                        ReleaseCOM(all_hits);
+                if let Some(dinput) = &DIRECT_INPUT {
+                    if dinput.action_down(PICK) {
+                        // The car's world matrix is the identity, so the world space ray
+                        // is also the object space ray.
+                        // No ray, and no pick, if the camera matrices cannot be inverted.
+                        let hit = get_cursor_picking_ray(self.hwnd, d3d_device, camera.get_view(), camera.get_proj())
+                            .and_then(|ray| self.mesh_bvh.intersect(&self.cpu_mesh, &ray));
 
                         // We hit anything?
-                        if hit != 0 {
+                        if let Some(hit) = hit {
                             // Yes, draw the picked triangle in solid mode.
                             let mut vb: Option<IDirect3DVertexBuffer9> = None;
                             let mut ib: Option<IDirect3DIndexBuffer9> = None;
@@ -305,9 +306,9 @@ impl TriPickDemo {
                             HR!(d3d_device.SetVertexDeclaration(&VERTEX_PNT_DECL));
                             HR!(d3d_device.SetStreamSource(0, vb, 0, std::mem::size_of::<VertexPNT>() as u32));
 
-                            // faceIndex identifies the picked triangle to draw.
+                            // hit.face identifies the picked triangle to draw.
                             HR!(d3d_device.DrawIndexedPrimitive(D3DPT_TRIANGLELIST, 0, 0,
-                                ID3DXBaseMesh_GetNumVertices(self.mesh), face_index * 3, 1));
+                                ID3DXBaseMesh_GetNumVertices(self.mesh), hit.face * 3, 1));
                         }
                     }
                 }
@@ -331,44 +332,6 @@ impl TriPickDemo {
         }
     }
 
-    pub fn get_world_picking_ray(&self, origin_w: &mut D3DXVECTOR3, dir_w: &mut D3DXVECTOR3) {
-        unsafe {
-            let camera: &Camera = &CAMERA.expect("Camera has not been created");
-
-            // Get the screen point clicked.
-            let mut s: POINT = POINT::default();
-            GetCursorPos(&mut s);
-
-            // Make it relative to the client area window.
-            ScreenToClient(self.hwnd, &mut s);
-
-            // By the way we've been constructing things, the entire
-            // backbuffer is the viewport.
-            let w: f32 = (*self.d3d_pp).BackBufferWidth as f32;
-            let h: f32 = (*self.d3d_pp).BackBufferHeight as f32;
-
-            let proj: &D3DXMATRIX = camera.get_proj();
-            let x: f32 = ( 2.0 * (s.x as f32) / w - 1.0) / (*proj).Anonymous.m[0]; //proj(0, 0)
-            let y: f32 = (-2.0 * (s.y as f32) / h + 1.0) / (*proj).Anonymous.m[5]; //proj(1, 1)
-
-            // Build picking ray in view space.
-            let origin = D3DXVECTOR3 { x:0.0, y: 0.0, z: 0.0 };
-            let dir = D3DXVECTOR3 { x, y, z: 1.0 };
-
-            // So if the view matrix transforms coordinates from
-            // world space to view space, then the inverse of the
-            // view matrix transforms coordinates from view space
-            // to world space.
-            let mut inv_view = D3DXMATRIX::default();
-            D3DXMatrixInverse(&mut inv_view, 0.0, camera.get_view());
-
-            // Transform picking ray to world space.
-            D3DXVec3TransformCoord(origin_w, &origin, &inv_view);
-            D3DXVec3TransformNormal(dir_w, &dir, &inv_view);
-            D3DXVec3Normalize(dir_w, dir_w);
-        }
-    }
-
     fn build_fx(d3d_device: IDirect3DDevice9) -> (LPD3DXEFFECT, D3DXHANDLE, D3DXHANDLE, D3DXHANDLE,
                                                   D3DXHANDLE, D3DXHANDLE, D3DXHANDLE, D3DXHANDLE,
                                                   D3DXHANDLE) {
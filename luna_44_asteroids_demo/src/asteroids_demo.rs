@@ -1,10 +1,11 @@
 use std::ffi::CStr;
 use libc::c_void;
-use rand::rngs::ThreadRng;
+use rand::rngs::StdRng;
 use windows::{
     Win32::Foundation::*, Win32::Graphics::Direct3D9::*, Win32::System::SystemServices::*,
 };
 use common::mtrl::Mtrl;
+use input::PICK;
 
 use crate::*;
 use crate::firework_psystem::FireWorkPSystem;
@@ -46,6 +47,10 @@ pub struct Asteroid {
 
 const NUM_ASTEROIDS: i32 = 300;
 
+// Asteroid detail levels, and the largest simplification error allowed on screen.
+const NUM_ASTEROID_LODS: usize = 4;
+const MAX_LOD_PIXEL_ERROR: f32 = 1.0;
+
 // Sample demo
 pub struct AsteroidsDemo {
     d3d_pp: *const D3DPRESENT_PARAMETERS,
@@ -66,7 +71,9 @@ pub struct AsteroidsDemo {
 
     // We only need one actual mesh, as we just draw the same mesh several
     // times per frame in different positions to simulate multiple asteroids.
-    asteroid_mesh: LPD3DXMESH,
+    // Far away asteroids use simplified versions of it, finest first.
+    asteroid_lods: Vec<LPD3DXMESH>,
+    asteroid_lod_errors: Vec<f32>,
     asteroid_mtrls: Vec<Mtrl>,
     asteroid_textures: Vec<*mut c_void>,
     asteroid_box: AABB,
@@ -87,7 +94,7 @@ pub struct AsteroidsDemo {
 
 impl AsteroidsDemo {
     pub fn new(hwnd: HWND, d3d_device: IDirect3DDevice9,
-               d3d_pp: *const D3DPRESENT_PARAMETERS, mut rng: ThreadRng) -> Option<AsteroidsDemo> {
+               d3d_pp: *const D3DPRESENT_PARAMETERS, mut rng: StdRng) -> Option<AsteroidsDemo> {
         unsafe {
             if !AsteroidsDemo::check_device_caps() {
                 display_error_then_quit("checkDeviceCaps() Failed");
@@ -98,18 +105,21 @@ impl AsteroidsDemo {
             let gfx_stats = GfxStats::new(d3d_device.clone(), D3DCOLOR_XRGB!(0, 0, 0));
 
             // Load the asteroid mesh and compute its bounding box in local space.
-            let (asteroid_mesh, asteroid_mtrls, asteroid_textures) =
+            let (asteroid_mesh, asteroid_mtrls, asteroid_textures, _) =
                 load_x_file(BASE_PATH, "asteroid.x", d3d_device.clone());
 
-            let mut v: *mut c_void = std::ptr::null_mut();
-            HR!(ID3DXBaseMesh_LockVertexBuffer(asteroid_mesh, 0, &mut v));
+            let cpu_mesh: geometry::Mesh<geometry::VertexPNT> = read_d3dx_mesh(asteroid_mesh);
+            let asteroid_box = AABB::from_aabb(&geometry::Aabb::from_points(&cpu_mesh.vertices));
 
-            let mut asteroid_box = AABB::default();
-            HR!(D3DXComputeBoundingBox(v.cast(), ID3DXBaseMesh_GetNumVertices(asteroid_mesh),
-                    ID3DXBaseMesh_GetNumBytesPerVertex(asteroid_mesh),
-                    &mut asteroid_box.min_pt, &mut asteroid_box.max_pt));
+            // Build the simplified detail levels.  Level 0 is the loaded mesh itself.
+            let lod_chain = geometry::LodChain::generate(&cpu_mesh,
+                &geometry::LodOptions { num_levels: NUM_ASTEROID_LODS, ..Default::default() });
 
-            HR!(ID3DXBaseMesh_UnlockVertexBuffer(asteroid_mesh));
+            let mut asteroid_lods = vec![asteroid_mesh];
+            for level in lod_chain.levels.iter().skip(1) {
+                asteroid_lods.push(create_d3dx_mesh(d3d_device.clone(), &VERTEX_PNT_DECL, &level.mesh, D3DXMESH_MANAGED));
+            }
+            let asteroid_lod_errors = lod_chain.errors();
 
             // Initialize camera.
             if let Some(camera) = &mut CAMERA {
@@ -181,7 +191,8 @@ impl AsteroidsDemo {
 
                 // We only need one actual mesh, as we just draw the same mesh several
                 // times per frame in different positions to simulate multiple asteroids.
-                asteroid_mesh,
+                asteroid_lods,
+                asteroid_lod_errors,
                 asteroid_mtrls,
                 asteroid_textures,
                 asteroid_box,
@@ -214,7 +225,9 @@ impl AsteroidsDemo {
 
         self.fire_work.release_com_objects();
 
-        ReleaseCOM(self.asteroid_mesh);
+        for mesh in &self.asteroid_lods {
+            ReleaseCOM(*mesh);
+        }
         for tex in &self.asteroid_textures {
             ReleaseCOM(tex.cast());
         }
@@ -276,8 +289,6 @@ impl AsteroidsDemo {
     pub fn update_scene(&mut self, dt: f32) {
         if let Some(gfx_stats) = &mut self.gfx_stats {
             gfx_stats.update(dt);
-            gfx_stats.set_tri_count(ID3DXBaseMesh_GetNumFaces(self.asteroid_mesh) * self.asteroids.len() as u32);
-            gfx_stats.set_vertex_count(ID3DXBaseMesh_GetNumVertices(self.asteroid_mesh) * self.asteroids.len() as u32);
         }
 
         unsafe {
@@ -339,15 +350,18 @@ impl AsteroidsDemo {
                 HR!(ID3DXEffect_BeginPass(self.fx, 0));
 
                 // Did we pick anything?
-                let mut origin_w = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
-                let mut dir_w = D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 };
+                let mut picking_ray: Option<geometry::Ray> = None;
 
-                let mut mouse_button_pressed = false;
+                // Triangles and vertices actually drawn, which depend on the detail levels picked.
+                let mut num_tris: u32 = 0;
+                let mut num_vertices: u32 = 0;
 
-                if let Some(dinput) = &mut DIRECT_INPUT {
-                    if dinput.mouse_button_down(0) {
-                        self.get_world_picking_ray(&mut origin_w, &mut dir_w);
-                        mouse_button_pressed = true;
+                let proj = to_mat4(camera.get_proj());
+                let viewport_height = (*self.d3d_pp).BackBufferHeight as f32;
+
+                if let Some(dinput) = &DIRECT_INPUT {
+                    if dinput.action_down(PICK) {
+                        picking_ray = get_cursor_picking_ray(self.hwnd, d3d_device, camera.get_view(), camera.get_proj());
                     }
                 }
 
@@ -364,14 +378,16 @@ impl AsteroidsDemo {
                     let mut to_world = D3DXMATRIX::default();
                     D3DXMatrixMultiply(&mut to_world, &r, &t);
 
+                    // Distance to the camera, used to pick the detail level.
+                    let distance = to_vec3(&asteroid.pos).distance(to_vec3(&camera.get_pos()));
+
                     // Transform AABB to world space.
                     let mut bounding_box = AABB::default();
                     self.asteroid_box.xform(&to_world, &mut bounding_box);
 
                     // Did we pick it?
-                    if mouse_button_pressed {
-                        if D3DXBoxBoundProbe(&bounding_box.min_pt, &bounding_box.max_pt,
-                                             &origin_w, &dir_w) != 0 {
+                    if let Some(ray) = &picking_ray {
+                        if bounding_box.to_aabb().intersect_ray(ray, 0.0, f32::MAX).is_some() {
                             // Create a firework instance.
                             let inst = FireWorkInstance {
                                 time: 0.0,
@@ -391,6 +407,14 @@ impl AsteroidsDemo {
 
                     // Only draw if AABB is visible.
                     if camera.is_visible(&bounding_box) {
+                        // Pick the coarsest level whose error stays under a pixel.
+                        let lod = geometry::select_lod(&self.asteroid_lod_errors, distance, 1.0,
+                                                       &proj, viewport_height, MAX_LOD_PIXEL_ERROR);
+                        let mesh = self.asteroid_lods[lod];
+
+                        num_tris += ID3DXBaseMesh_GetNumFaces(mesh);
+                        num_vertices += ID3DXBaseMesh_GetNumVertices(mesh);
+
                         let mut wvp: D3DXMATRIX = std::mem::zeroed();
                         D3DXMatrixMultiply(&mut wvp, &to_world, camera.get_view_proj());
                         HR!(ID3DXBaseEffect_SetMatrix(self.fx, self.h_wvp, &wvp));
@@ -416,7 +440,7 @@ impl AsteroidsDemo {
                             }
 
                             HR!(ID3DXEffect_CommitChanges(self.fx));
-                            HR!(ID3DXBaseMesh_DrawSubset(self.asteroid_mesh, j as u32));
+                            HR!(ID3DXBaseMesh_DrawSubset(mesh, j as u32));
                         }
                     }
                 }
@@ -433,7 +457,9 @@ impl AsteroidsDemo {
                     self.fire_work.draw();
                 }
 
-                if let Some(gfx_stats) = &self.gfx_stats {
+                if let Some(gfx_stats) = &mut self.gfx_stats {
+                    gfx_stats.set_tri_count(num_tris);
+                    gfx_stats.set_vertex_count(num_vertices);
                     gfx_stats.display();
                 }
 
@@ -449,45 +475,7 @@ impl AsteroidsDemo {
         }
     }
 
-    pub fn get_world_picking_ray(&self, origin_w: &mut D3DXVECTOR3, dir_w: &mut D3DXVECTOR3) {
-        unsafe {
-            let camera: &Camera = &CAMERA.expect("Camera has not been created");
-
-            // Get the screen point clicked.
-            let mut s: POINT = POINT::default();
-            GetCursorPos(&mut s);
-
-            // Make it relative to the client area window.
-            ScreenToClient(self.hwnd, &mut s);
-
-            // By the way we've been constructing things, the entire
-            // backbuffer is the viewport.
-            let w: f32 = (*self.d3d_pp).BackBufferWidth as f32;
-            let h: f32 = (*self.d3d_pp).BackBufferHeight as f32;
-
-            let proj: &D3DXMATRIX = camera.get_proj();
-            let x: f32 = ( 2.0 * (s.x as f32) / w - 1.0) / (*proj).Anonymous.m[0]; //proj(0, 0)
-            let y: f32 = (-2.0 * (s.y as f32) / h + 1.0) / (*proj).Anonymous.m[5]; //proj(1, 1)
-
-            // Build picking ray in view space.
-            let origin = D3DXVECTOR3 { x:0.0, y: 0.0, z: 0.0 };
-            let dir = D3DXVECTOR3 { x, y, z: 1.0 };
-
-            // So if the view matrix transforms coordinates from
-            // world space to view space, then the inverse of the
-            // view matrix transforms coordinates from view space
-            // to world space.
-            let mut inv_view = D3DXMATRIX::default();
-            D3DXMatrixInverse(&mut inv_view, 0.0, camera.get_view());
-
-            // Transform picking ray to world space.
-            D3DXVec3TransformCoord(origin_w, &origin, &inv_view);
-            D3DXVec3TransformNormal(dir_w, &dir, &inv_view);
-            D3DXVec3Normalize(dir_w, dir_w);
-        }
-    }
-
-    fn init_asteroids(mut rng: &mut ThreadRng) -> Vec<Asteroid> {
+    fn init_asteroids(mut rng: &mut StdRng) -> Vec<Asteroid> {
         let mut asteroids: Vec<Asteroid> = Vec::new();
 
         for _i in 0..NUM_ASTEROIDS {
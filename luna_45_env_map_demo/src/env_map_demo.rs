@@ -66,7 +66,7 @@ impl EnvMapDemo {
                 dir_w: light_dir_w,
             };
 
-            let (scene_mesh, scene_mtrls, scene_textures) =
+            let (scene_mesh, scene_mtrls, scene_textures, _) =
                 load_x_file(BASE_PATH, "skullocc.x", d3d_device.clone());
 
             let mut scene_world = D3DXMATRIX::default();
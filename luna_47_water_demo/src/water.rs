@@ -48,7 +48,7 @@ pub struct WaterInitInfo {
 }
 
 impl Water {
-    pub fn new(base_path: &str, init_info: WaterInitInfo, d3d_device: IDirect3DDevice9) -> Water {
+    pub fn new(base_path: &str, init_info: WaterInitInfo, d3d_device: IDirect3DDevice9) -> Result<Water, GridError> {
         unsafe {
             let wave_map_offset0 = D3DXVECTOR2 { x: 0.0, y: 0.0 };
             let wave_map_offset1 = D3DXVECTOR2 { x: 0.0, y: 0.0 };
@@ -56,6 +56,12 @@ impl Water {
             let num_tris: u32 = (init_info.vert_rows - 1) * (init_info.vert_cols - 1) * 2;
             let num_verts: u32 = init_info.vert_rows * init_info.vert_cols;
 
+            let mut verts: Vec<D3DXVECTOR3> = Vec::new();
+            let mut indices: Vec<u32> = Vec::new();
+            gen_tri_grid_32(init_info.vert_rows as i32, init_info.vert_cols as i32,
+                            init_info.dx, init_info.dz, D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 },
+                            &mut verts, &mut indices)?;
+
             //===============================================================
             // Allocate the mesh.
 
@@ -77,12 +83,6 @@ impl Water {
             HR!(ID3DXBaseMesh_LockVertexBuffer(mesh, 0, &mut v));
             let mut v_slice: &mut [VertexPT] = from_raw_parts_mut(v.cast(), num_verts as usize);
 
-            let mut verts: Vec<D3DXVECTOR3> = Vec::new();
-            let mut indices: Vec<u32> = Vec::new();
-            gen_tri_grid_32(init_info.vert_rows as i32, init_info.vert_cols as i32,
-                            init_info.dx, init_info.dz, D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 },
-                            &mut verts, &mut indices);
-
             for i in 0..init_info.vert_rows {
                 for j in 0..init_info.vert_cols {
                     let index: usize = (i * init_info.vert_cols + j) as usize;
@@ -170,7 +170,7 @@ impl Water {
             HR!(ID3DXBaseEffect_SetValue(fx, h_light, &init_info.dir_light as *const _ as _, std::mem::size_of::<DirLight>() as u32));
             HR!(ID3DXBaseEffect_SetValue(fx, h_mtrl, &init_info.mtrl as *const _ as _, std::mem::size_of::<Mtrl>() as u32));
 
-            Water {
+            Ok(Water {
                 mesh,
                 fx,
                 wave_map0,
@@ -183,7 +183,7 @@ impl Water {
                 h_wave_map_offset0,
                 h_wave_map_offset1,
                 h_env_map,
-            }
+            })
         }
     }
 
@@ -108,42 +108,36 @@ impl WaterDemo {
                 to_world: water_world
             };
 
-            let water = Water::new(BASE_PATH, water_init_info, d3d_device.clone());
+            let water = match Water::new(BASE_PATH, water_init_info, d3d_device.clone()) {
+                Ok(water) => water,
+                Err(e) => {
+                    display_error_then_quit(&format!("gen_tri_grid_32() Failed: {}", e));
+                    return None;
+                }
+            };
             water.set_env_map(sky.get_env_map());
 
-            let (temp_mesh, scene_mtrls, scene_textures) =
+            let (temp_mesh, scene_mtrls, scene_textures, _) =
                 load_x_file(BASE_PATH, "BasicColumnScene.x", d3d_device.clone());
 
-            // Get the vertex declaration for the NMapVertex.
-            let mut elems: [D3DVERTEXELEMENT9; MAX_FVF_DECL_SIZE as usize] = [D3DVERTEXELEMENT9::default(); MAX_FVF_DECL_SIZE as usize];
-            let mut num_elems: u32 = 0;
-            if let Some(decl) = &NMAP_VERTEX_DECL {
-                HR!(decl.GetDeclaration(elems.as_mut_ptr(), &mut num_elems));
+            // Build the TNB-basis for each vertex in the mesh on the CPU and upload
+            // the result as an NMapVertex mesh.
+            let temp_cpu_mesh: geometry::Mesh<geometry::VertexPNT> = read_d3dx_mesh(temp_mesh);
+            let mut tangent_frame = geometry::compute_tangent_frame(&temp_cpu_mesh,
+                &geometry::TangentFrameOptions::default());
+
+            // The D3DXComputeTangentFrameEx call this replaces wrote the u partial
+            // derivative to the binormal element and the v partial to the tangent
+            // element.  Keep that layout, the shaders are written against it.
+            for v in &mut tangent_frame.mesh.vertices {
+                std::mem::swap(&mut v.tangent, &mut v.binormal);
             }
 
-            // Clone the mesh to the NMapVertex format.
-            let mut cloned_temp_mesh: LPD3DXMESH = std::ptr::null_mut();
-            HR!(ID3DXBaseMesh_CloneMesh(temp_mesh, D3DXMESH_MANAGED, elems.as_mut_ptr(),
-                d3d_device.clone(), &mut cloned_temp_mesh));
-
-            // Now use D3DXComputeTangentFrameEx to build the TNB-basis for each vertex
-            // in the mesh.
-            let mut scene_mesh: LPD3DXMESH = std::ptr::null_mut();
-            HR!(D3DXComputeTangentFrameEx(
-                cloned_temp_mesh,                   // Input mesh
-                D3DDECLUSAGE_TEXCOORD.0 as u32, 0,  // Vertex element of input tex-coords.
-                D3DDECLUSAGE_BINORMAL.0 as u32, 0,  // Vertex element to output binormal.
-                D3DDECLUSAGE_TANGENT.0 as u32, 0,   // Vertex element to output tangent.
-                D3DDECLUSAGE_NORMAL.0 as u32, 0,    // Vertex element to output normal.
-                0,                                  // Options
-                std::ptr::null(),                   // Adjacency
-                0.01, 0.25, 0.01,                   // Thresholds for handling errors
-                &mut scene_mesh,                    // Output mesh
-                std::ptr::null_mut()));             // Vertex Remapping
-
-            // Done with temps.
+            let scene_mesh = create_d3dx_mesh(d3d_device.clone(), &NMAP_VERTEX_DECL,
+                                              &tangent_frame.mesh, D3DXMESH_MANAGED);
+
+            // Done with temp.
             ReleaseCOM(temp_mesh);
-            ReleaseCOM(cloned_temp_mesh);
 
             let mut scene_world = D3DXMATRIX::default();
             D3DXMatrixIdentity(&mut scene_world);
@@ -2,7 +2,6 @@ pub mod camera;
 pub mod drawable_tex2d;
 pub mod render_to_tex_demo;
 pub mod sky;
-pub mod terrain;
 
 use windows::{
     Win32::Foundation::*, Win32::System::LibraryLoader::GetModuleHandleA,
@@ -271,7 +270,7 @@ impl D3DApp {
                     if !self.device_is_lost() {
                         let mut curr_timestamp: i64 = 0;
                         QueryPerformanceCounter(&mut curr_timestamp);
-                        let dt: f32 = ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt;
+                        let dt = get_frame_dt(&mut DIRECT_INPUT, ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt);
 
                         if let Some(render_to_tex_demo) = &mut self.render_to_tex_demo {
                             render_to_tex_demo.update_scene(dt);
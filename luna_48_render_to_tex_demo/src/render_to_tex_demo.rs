@@ -6,7 +6,7 @@ use windows::core::HSTRING;
 use crate::*;
 use crate::drawable_tex2d::DrawableTex2D;
 use crate::sky::Sky;
-use crate::terrain::Terrain;
+use common::Terrain;
 
 pub const BASE_PATH: &str = "luna_48_render_to_tex_demo/";
 
@@ -309,7 +309,7 @@ impl RenderToTexDemo {
 
                 CAMERA = Some(self.birds_eye_camera.clone());
 
-                self.terrain.draw();
+                self.terrain.draw(self.birds_eye_camera.get_view_proj(), &self.birds_eye_camera.get_pos());
 
                 self.radar_map.end_scene();
 
@@ -317,7 +317,7 @@ impl RenderToTexDemo {
 
                 HR!(d3d_device.BeginScene());
                 self.sky.draw();
-                self.terrain.draw();
+                self.terrain.draw(self.first_person_camera.get_view_proj(), &self.first_person_camera.get_pos());
 
                 HR!(d3d_device.SetStreamSource(0, &self.radar_vb, 0, std::mem::size_of::<VertexPT>() as u32));
                 HR!(d3d_device.SetVertexDeclaration(&VERTEX_PT_DECL));
@@ -1,7 +1,6 @@
 pub mod camera;
 pub mod proj_tex_demo;
 pub mod sky;
-pub mod terrain;
 pub mod water;
 
 use windows::{
@@ -269,7 +268,7 @@ impl D3DApp {
                     if !self.device_is_lost() {
                         let mut curr_timestamp: i64 = 0;
                         QueryPerformanceCounter(&mut curr_timestamp);
-                        let dt: f32 = ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt;
+                        let dt = get_frame_dt(&mut DIRECT_INPUT, ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt);
 
                         if let Some(proj_tex_demo) = &mut self.proj_tex_demo {
                             proj_tex_demo.update_scene(dt);
@@ -66,7 +66,7 @@ impl ProjTexDemo {
             let sky = Sky::new(BASE_PATH, d3d_device.clone(),
                                "grassenvmap1024.dds", 10000.0);
 
-            let (scene_mesh, scene_mtrls, scene_textures) =
+            let (scene_mesh, scene_mtrls, scene_textures, _) =
                 load_x_file(BASE_PATH, "shapes.x", d3d_device.clone());
 
             let mut scene_world = D3DXMATRIX::default();
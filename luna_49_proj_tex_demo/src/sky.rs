@@ -16,8 +16,7 @@ pub struct Sky {
 
 impl Sky {
     pub fn new(base_path: &str, d3d_device: IDirect3DDevice9, env_map_file_name: &str, sky_radius: f32) -> Sky {
-        let mut sphere: LPD3DXMESH = std::ptr::null_mut();
-        HR!(D3DXCreateSphere(d3d_device.clone(), sky_radius, 30, 30, &mut sphere, std::ptr::null_mut()));
+        let sphere = create_sphere_mesh(d3d_device.clone(), sky_radius, 30, 30);
 
         let mut env_map: *mut c_void = std::ptr::null_mut();
         HR!(D3DXCreateCubeTextureFromFile(d3d_device.clone(),
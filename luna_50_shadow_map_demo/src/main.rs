@@ -1,7 +1,6 @@
 pub mod camera;
 pub mod shadow_map_demo;
 pub mod sky;
-pub mod terrain;
 mod drawable_tex2d;
 
 use windows::{
@@ -275,7 +274,7 @@ impl D3DApp {
                     if !self.device_is_lost() {
                         let mut curr_timestamp: i64 = 0;
                         QueryPerformanceCounter(&mut curr_timestamp);
-                        let dt: f32 = ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt;
+                        let dt = get_frame_dt(&mut DIRECT_INPUT, ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt);
 
                         if let Some(shadow_map_demo) = &mut self.shadow_map_demo {
                             shadow_map_demo.update_scene(dt);
@@ -73,13 +73,13 @@ impl ShadowMapDemo {
             let sky = Sky::new(BASE_PATH, d3d_device.clone(),
                                "grassenvmap1024.dds", 10000.0);
 
-            let (scene_mesh, scene_mtrls, scene_textures) =
+            let (scene_mesh, scene_mtrls, scene_textures, _) =
                 load_x_file(BASE_PATH, "BasicColumnScene.x", d3d_device.clone());
 
             let mut scene_world = D3DXMATRIX::default();
             D3DXMatrixIdentity(&mut scene_world);
 
-            let (car_mesh, car_mtrls, car_textures) =
+            let (car_mesh, car_mtrls, car_textures, _) =
                 load_x_file(BASE_PATH, "car.x", d3d_device.clone());
 
             let mut s = D3DXMATRIX::default();
@@ -84,7 +84,13 @@ impl DisplacementMapDemo {
                 to_world: water_world
             };
 
-            let water = WaterDMap::new(BASE_PATH, water_init_info, d3d_device.clone());
+            let water = match WaterDMap::new(BASE_PATH, water_init_info, d3d_device.clone()) {
+                Ok(water) => water,
+                Err(e) => {
+                    display_error_then_quit(&format!("gen_tri_grid_32() Failed: {}", e));
+                    return None;
+                }
+            };
 
             // Initialize camera.
             if let Some(camera) = &mut CAMERA {
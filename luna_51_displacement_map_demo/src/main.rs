@@ -2,7 +2,6 @@ pub mod camera;
 pub mod displacement_map_demo;
 pub mod drawable_tex2d;
 pub mod sky;
-pub mod terrain;
 pub mod water_dmap;
 
 use windows::{
@@ -281,7 +280,7 @@ impl D3DApp {
                     if !self.device_is_lost() {
                         let mut curr_timestamp: i64 = 0;
                         QueryPerformanceCounter(&mut curr_timestamp);
-                        let dt: f32 = ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt;
+                        let dt = get_frame_dt(&mut DIRECT_INPUT, ((curr_timestamp - prev_timestamp) as f32) * secs_per_cnt);
 
                         if let Some(displacement_map_demo) = &mut self.displacement_map_demo {
                             displacement_map_demo.update_scene(dt);
@@ -68,7 +68,7 @@ pub struct WaterDMapInitInfo {
 }
 
 impl WaterDMap {
-    pub fn new(base_path: &str, init_info: WaterDMapInitInfo, d3d_device: IDirect3DDevice9) -> WaterDMap {
+    pub fn new(base_path: &str, init_info: WaterDMapInitInfo, d3d_device: IDirect3DDevice9) -> Result<WaterDMap, GridError> {
         unsafe {
             let width: f32 = (init_info.vert_cols - 1) as f32 * init_info.dx;
             let depth: f32 = (init_info.vert_rows - 1) as f32  * init_info.dz;
@@ -82,6 +82,12 @@ impl WaterDMap {
             let num_tris: u32 = (init_info.vert_rows - 1) * (init_info.vert_cols - 1) * 2;
             let num_verts: u32 = init_info.vert_rows * init_info.vert_cols;
 
+            let mut verts: Vec<D3DXVECTOR3> = Vec::new();
+            let mut indices: Vec<u32> = Vec::new();
+            gen_tri_grid_32(init_info.vert_rows as i32, init_info.vert_cols as i32,
+                            init_info.dx, init_info.dz, D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 },
+                            &mut verts, &mut indices)?;
+
             //===============================================================
             // Allocate the mesh.
 
@@ -103,12 +109,6 @@ impl WaterDMap {
             HR!(ID3DXBaseMesh_LockVertexBuffer(mesh, 0, &mut v));
             let mut v_slice: &mut [WaterDMapVertex] = from_raw_parts_mut(v.cast(), num_verts as usize);
 
-            let mut verts: Vec<D3DXVECTOR3> = Vec::new();
-            let mut indices: Vec<u32> = Vec::new();
-            gen_tri_grid_32(init_info.vert_rows as i32, init_info.vert_cols as i32,
-                            init_info.dx, init_info.dz, D3DXVECTOR3 { x: 0.0, y: 0.0, z: 0.0 },
-                            &mut verts, &mut indices);
-
             for i in 0..init_info.vert_rows {
                 for j in 0..init_info.vert_cols {
                     let index: usize = (i * init_info.vert_cols + j) as usize;
@@ -225,7 +225,7 @@ impl WaterDMap {
             let step_sizes = D3DXVECTOR2 { x: init_info.dx, y: init_info.dz };
             HR!(ID3DXBaseEffect_SetValue(fx, h_grid_step_size_l, &step_sizes as *const _ as _, std::mem::size_of::<D3DXVECTOR2>() as u32));
 
-            WaterDMap {
+            Ok(WaterDMap {
                 mesh,
                 fx,
                 wave_map0,
@@ -245,7 +245,7 @@ impl WaterDMap {
                 h_wave_nmap_offset1,
                 h_wave_dmap_offset0,
                 h_wave_dmap_offset1,
-            }
+            })
         }
     }
 
@@ -0,0 +1,148 @@
+// Brushes for sculpting a height field in place.  A brush covers the vertices
+// within its radius of a point, weighted by its falloff curve from 1 at the
+// center to 0 at the radius, and changes their heights in the heightmap.
+//
+// Applying a brush returns the vertices it changed; the sub-grids with
+// vertices there need new bounds and level errors
+// (Geomipmap::update_sub_grids), and the vertices one further need new normals
+// (HeightField::build_vertex_normals).
+
+use crate::height_field::*;
+use crate::splat::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BrushOp {
+    // By 'strength' height units at the center.
+    Raise,
+    Lower,
+    // Towards the height, by 'strength' of the way (clamped to 1) at the center.
+    Flatten(f32),
+    // Towards the average of the 3x3 vertices around, by 'strength' of the way.
+    Smooth,
+    // Up or down by value noise of 'strength' height units, with features
+    // 'scale' world units across.
+    Noise { scale: f32, seed: u32 },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BrushFalloff {
+    // Full weight up to the radius.
+    Constant,
+    Linear,
+    // Smoothstep, flat at the center and at the radius.
+    Smooth,
+    // A hemisphere, round at the center and steep at the radius.
+    Spherical,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Brush {
+    pub op: BrushOp,
+    pub radius: f32,
+    pub falloff: BrushFalloff,
+    pub strength: f32,
+}
+
+impl Brush {
+    pub fn new(op: BrushOp, radius: f32, falloff: BrushFalloff, strength: f32) -> Brush {
+        Brush { op, radius, falloff, strength }
+    }
+
+    // Weight of a vertex 'distance' from the center, in [0, 1].
+    pub fn get_weight(&self, distance: f32) -> f32 {
+        if distance >= self.radius {
+            return 0.0;
+        }
+        let t = distance / self.radius;
+        match self.falloff {
+            BrushFalloff::Constant => 1.0,
+            BrushFalloff::Linear => 1.0 - t,
+            BrushFalloff::Smooth => {
+                let s = 1.0 - t;
+                s * s * (3.0 - 2.0 * s)
+            }
+            BrushFalloff::Spherical => (1.0 - t * t).sqrt(),
+        }
+    }
+
+    // Applies the brush centered on (x, z), scaled by 'amount' (the frame time
+    // for brushes held down).  Returns the changed vertices, None when the brush
+    // misses the field or has no size (a radius or noise scale of 0 or less).
+    pub fn apply(&self, field: &mut HeightField, x: f32, z: f32, amount: f32) -> Option<VertexRect> {
+        if self.radius <= 0.0 {
+            return None;
+        }
+        if let BrushOp::Noise { scale, .. } = self.op {
+            if scale <= 0.0 || scale.is_nan() {
+                return None;
+            }
+        }
+
+        // The vertices of the cells under the brush's square.
+        let (top, left) = field.to_cell(x - self.radius, z + self.radius);
+        let (bottom, right) = field.to_cell(x + self.radius, z - self.radius);
+        let last_row = (field.num_rows() - 1) as f32;
+        let last_col = (field.num_cols() - 1) as f32;
+        if bottom < 0.0 || right < 0.0 || top > last_row || left > last_col {
+            return None;
+        }
+        let row = top.max(0.0).ceil() as usize;
+        let col = left.max(0.0).ceil() as usize;
+        let end_row = bottom.min(last_row).floor() as usize + 1;
+        let end_col = right.min(last_col).floor() as usize + 1;
+        if end_row <= row || end_col <= col {
+            return None;
+        }
+        let rect = VertexRect::new(row, col, end_row - row, end_col - col);
+
+        // Smoothing reads the heights from before the stroke.
+        let around = rect.grow(1, field.num_rows(), field.num_cols());
+        let before: Vec<f32> = (around.row..around.row + around.num_rows)
+            .flat_map(|i| (around.col..around.col + around.num_cols).map(move |j| (i, j)))
+            .map(|(i, j)| field.get_heightmap().at(i, j))
+            .collect();
+        let height_before = |i: usize, j: usize| before[(i - around.row) * around.num_cols + j - around.col];
+
+        let mut changed = false;
+        for i in rect.row..rect.row + rect.num_rows {
+            for j in rect.col..rect.col + rect.num_cols {
+                let p = field.get_vertex_pos(i, j);
+                let weight = self.get_weight(((p.x - x) * (p.x - x) + (p.z - z) * (p.z - z)).sqrt());
+                if weight <= 0.0 {
+                    continue;
+                }
+
+                let h = p.y;
+                let blend = |target: f32| h + (target - h) * (self.strength * amount).min(1.0) * weight;
+                let new_h = match self.op {
+                    BrushOp::Raise => h + self.strength * amount * weight,
+                    BrushOp::Lower => h - self.strength * amount * weight,
+                    BrushOp::Flatten(target) => blend(target),
+                    BrushOp::Smooth => {
+                        let rows = i.saturating_sub(1)..(i + 2).min(field.num_rows());
+                        let mut sum = 0.0;
+                        let mut count = 0;
+                        for r in rows {
+                            for c in j.saturating_sub(1)..(j + 2).min(field.num_cols()) {
+                                sum += height_before(r, c);
+                                count += 1;
+                            }
+                        }
+                        blend(sum / count as f32)
+                    }
+                    BrushOp::Noise { scale, seed } => {
+                        let noise = fractal_noise(p.x / scale, p.z / scale, 4, seed);
+                        h + noise * self.strength * amount * weight
+                    }
+                };
+
+                if new_h != h {
+                    field.get_heightmap_mut().set(i, j, new_h);
+                    changed = true;
+                }
+            }
+        }
+
+        if changed { Some(rect) } else { None }
+    }
+}
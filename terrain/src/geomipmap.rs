@@ -0,0 +1,383 @@
+// Geomipmapping of the sub-grids: level L of a sub-grid uses every 2^L-th
+// vertex, and each frame picks per sub-grid the coarsest level whose height
+// error projects to at most a few pixels.
+//
+// Neighbouring sub-grids differ by at most one level.  Along an edge shared with
+// a coarser neighbour, the finer sub-grid collapses its odd edge vertices onto
+// the even ones, so both sides end on the same vertices: one index list per
+// level and mask of coarser neighbours, shared by all sub-grids of a size.
+//
+// Vertices that the next coarser level drops morph towards the coarser surface
+// as the error approaches the threshold; at a morph of 1 a level looks exactly
+// like the next one, so switching does not pop.  The vertices of a shared edge
+// morph the same way on both sides (see Geomipmap::get_edge_lods).
+
+use geometry::*;
+use crate::height_field::*;
+use crate::sub_grid::*;
+
+// The edges of a sub-grid, as bits of a stitch mask: the first row (+z side),
+// the last column (+x side), the last row and the first column.
+pub const EDGE_TOP: u32 = 1;
+pub const EDGE_RIGHT: u32 = 2;
+pub const EDGE_BOTTOM: u32 = 4;
+pub const EDGE_LEFT: u32 = 8;
+
+pub const NUM_STITCH_MASKS: u32 = 16;
+
+const EDGES: [u32; 4] = [EDGE_TOP, EDGE_RIGHT, EDGE_BOTTOM, EDGE_LEFT];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GeomipmapOptions {
+    // Height error, in pixels, a level may show.  0 always draws full resolution.
+    pub max_pixel_error: f32,
+    // Vertices start morphing towards the next level when its error is within
+    // this fraction above max_pixel_error.  0 switches levels without morphing.
+    pub morph_range: f32,
+    // Morphs are rounded to multiples of 1 / morph_steps, so the heights of a
+    // sub-grid only change when its morph moves a whole step rather than every
+    // frame the camera moves.  0 keeps them exact.
+    pub morph_steps: u32,
+}
+
+impl Default for GeomipmapOptions {
+    fn default() -> Self {
+        GeomipmapOptions {
+            max_pixel_error: 2.0,
+            morph_range: 0.5,
+            morph_steps: 16,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SubGridLod {
+    pub level: usize,
+    // In [0, 1], from the level itself to the next coarser one.
+    pub morph: f32,
+}
+
+pub struct Geomipmap {
+    sub_grids: Vec<SubGrid>,
+    // Per sub-grid, the height error of each of its levels, never decreasing.
+    errors: Vec<Vec<f32>>,
+    // Per sub-grid, the sub-grids across its top, right, bottom and left edges.
+    neighbours: Vec<[Option<usize>; 4]>,
+}
+
+impl Geomipmap {
+    pub fn new(field: &HeightField, sub_grids: Vec<SubGrid>) -> Geomipmap {
+        let errors = sub_grids.iter().map(|sub_grid| get_level_errors(field, sub_grid)).collect();
+
+        let find = |row: usize, col: usize| sub_grids.iter().position(|s| s.row == row && s.col == col);
+        let neighbours = sub_grids.iter()
+            .map(|s| {
+                let last_row = s.row + s.num_rows - 1;
+                let last_col = s.col + s.num_cols - 1;
+                [
+                    sub_grids.iter().position(|n| n.col == s.col && n.row + n.num_rows - 1 == s.row),
+                    find(s.row, last_col),
+                    find(last_row, s.col),
+                    sub_grids.iter().position(|n| n.row == s.row && n.col + n.num_cols - 1 == s.col),
+                ]
+            })
+            .collect();
+
+        Geomipmap { sub_grids, errors, neighbours }
+    }
+
+    // Sub-grids of several fields selected together, with their level errors
+    // (see get_errors) and their neighbours across the fields' edges.
+    pub fn from_parts(sub_grids: Vec<SubGrid>, errors: Vec<Vec<f32>>, neighbours: Vec<[Option<usize>; 4]>) -> Geomipmap {
+        assert!(sub_grids.len() == errors.len() && sub_grids.len() == neighbours.len(),
+                "Geomipmap parts do not have the same number of sub-grids");
+        Geomipmap { sub_grids, errors, neighbours }
+    }
+
+    // Bounds and level errors of the sub-grids with vertices in 'rect', after
+    // their heights were edited.  Returns the sub-grids.
+    pub fn update_sub_grids(&mut self, field: &HeightField, rect: &VertexRect) -> Vec<usize> {
+        let mut updated = Vec::new();
+        for (i, sub_grid) in self.sub_grids.iter_mut().enumerate() {
+            if sub_grid.get_rect().intersects(rect) {
+                sub_grid.update_bounds(field);
+                self.errors[i] = get_level_errors(field, sub_grid);
+                updated.push(i);
+            }
+        }
+        updated
+    }
+
+    pub fn get_sub_grids(&self) -> &[SubGrid] {
+        &self.sub_grids
+    }
+
+    pub fn get_num_levels(&self, i: usize) -> usize {
+        self.errors[i].len()
+    }
+
+    // Largest height difference between each level and the full resolution
+    // surface.
+    pub fn get_errors(&self, i: usize) -> &[f32] {
+        &self.errors[i]
+    }
+
+    // The sub-grids across the top, right, bottom and left edges.
+    pub fn get_neighbours(&self, i: usize) -> [Option<usize>; 4] {
+        self.neighbours[i]
+    }
+
+    // Levels of all the sub-grids (culled ones too, their neighbours stitch to
+    // them) seen from 'eye' through 'view_proj' in a viewport 'viewport_height'
+    // pixels high.
+    pub fn select(&self, eye: Vec3, view_proj: &Mat4, viewport_height: f32, options: &GeomipmapOptions)
+        -> Vec<SubGridLod> {
+        let pixel_errors: Vec<Vec<f32>> = self.sub_grids.iter()
+            .zip(&self.errors)
+            .map(|(sub_grid, errors)| {
+                let distance = sub_grid.bounds.distance_sq(eye).sqrt();
+                errors.iter().map(|e| get_pixel_error(*e, distance, view_proj, viewport_height)).collect()
+            })
+            .collect();
+
+        let mut lods: Vec<SubGridLod> = pixel_errors.iter()
+            .map(|pixels| SubGridLod {
+                level: (0..pixels.len()).rev().find(|l| pixels[*l] <= options.max_pixel_error).unwrap_or(0),
+                morph: 0.0,
+            })
+            .collect();
+
+        // Refine the sub-grids more than one level coarser than a neighbour,
+        // until none are left.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in 0..lods.len() {
+                for n in self.neighbours[i].iter().flatten() {
+                    if lods[i].level > lods[*n].level + 1 {
+                        lods[i].level = lods[*n].level + 1;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        if options.max_pixel_error > 0.0 && options.morph_range > 0.0 {
+            for (lod, pixels) in lods.iter_mut().zip(&pixel_errors) {
+                if let Some(next) = pixels.get(lod.level + 1) {
+                    let r = next / options.max_pixel_error;
+                    lod.morph = ((1.0 + options.morph_range - r) / options.morph_range).clamp(0.0, 1.0);
+                    if options.morph_steps > 0 {
+                        let steps = options.morph_steps as f32;
+                        lod.morph = (lod.morph * steps).round() / steps;
+                    }
+                }
+            }
+        }
+
+        lods
+    }
+
+    // Edges along a coarser neighbour, which the index list has to stitch.
+    pub fn get_stitch_mask(&self, lods: &[SubGridLod], i: usize) -> u32 {
+        let mut mask = 0;
+        for (edge, n) in EDGES.iter().zip(self.neighbours[i]) {
+            if let Some(n) = n {
+                if lods[n].level > lods[i].level {
+                    mask |= edge;
+                }
+            }
+        }
+        mask
+    }
+
+    // The level and morph each edge's vertices follow, the same from both
+    // sides of the edge: the coarser side's, or the larger morph when both have
+    // the same level.
+    pub fn get_edge_lods(&self, lods: &[SubGridLod], i: usize) -> [SubGridLod; 4] {
+        let own = lods[i];
+        self.neighbours[i].map(|n| match n {
+            Some(n) if lods[n].level > own.level => lods[n],
+            Some(n) if lods[n].level == own.level => SubGridLod { level: own.level, morph: own.morph.max(lods[n].morph) },
+            _ => own,
+        })
+    }
+
+    // Heights of the sub-grid's vertices, row by row, morphed for 'lods'.
+    // Only the vertices of the selected level are meaningful.
+    pub fn morph_heights(&self, field: &HeightField, lods: &[SubGridLod], i: usize) -> Vec<f32> {
+        let sub_grid = &self.sub_grids[i];
+        let own = lods[i];
+        let edge_lods = self.get_edge_lods(lods, i);
+
+        let last_row = sub_grid.num_rows - 1;
+        let last_col = sub_grid.num_cols - 1;
+        let height = |r: usize, c: usize| field.get_heightmap().at(sub_grid.row + r, sub_grid.col + c);
+
+        let mut heights = Vec::with_capacity(sub_grid.num_rows * sub_grid.num_cols);
+        for r in 0..sub_grid.num_rows {
+            for c in 0..sub_grid.num_cols {
+                let h = height(r, c);
+                let on_row_edge = r == 0 || r == last_row;
+                let on_col_edge = c == 0 || c == last_col;
+
+                let morphed = match (on_row_edge, on_col_edge) {
+                    // Corners are on every level.
+                    (true, true) => h,
+                    (true, false) => {
+                        let lod = edge_lods[if r == 0 { 0 } else { 2 }];
+                        let step = 2 << lod.level;
+                        let b = c % step;
+                        if lod.morph > 0.0 && b != 0 {
+                            let s = b as f32 / step as f32;
+                            let target = height(r, c - b) + s * (height(r, c - b + step) - height(r, c - b));
+                            h + lod.morph * (target - h)
+                        } else {
+                            h
+                        }
+                    }
+                    (false, true) => {
+                        let lod = edge_lods[if c == last_col { 1 } else { 3 }];
+                        let step = 2 << lod.level;
+                        let a = r % step;
+                        if lod.morph > 0.0 && a != 0 {
+                            let t = a as f32 / step as f32;
+                            let target = height(r - a, c) + t * (height(r - a + step, c) - height(r - a, c));
+                            h + lod.morph * (target - h)
+                        } else {
+                            h
+                        }
+                    }
+                    (false, false) => {
+                        if own.morph > 0.0 {
+                            let target = interpolate_height(field, sub_grid, r, c, 2 << own.level);
+                            h + own.morph * (target - h)
+                        } else {
+                            h
+                        }
+                    }
+                };
+                heights.push(morphed);
+            }
+        }
+        heights
+    }
+}
+
+// Levels of a sub-grid: level L needs 2^L to divide its number of cells along
+// both sides.
+pub fn get_num_lod_levels(num_rows: usize, num_cols: usize) -> usize {
+    assert!(num_rows >= 2 && num_cols >= 2, "A sub-grid needs at least one cell");
+    ((num_rows - 1).trailing_zeros().min((num_cols - 1).trailing_zeros()) + 1) as usize
+}
+
+// Errors of all the sub-grid's levels, never decreasing.
+fn get_level_errors(field: &HeightField, sub_grid: &SubGrid) -> Vec<f32> {
+    let mut max_error = 0.0f32;
+    (0..get_num_lod_levels(sub_grid.num_rows, sub_grid.num_cols))
+        .map(|level| {
+            max_error = max_error.max(get_level_error(field, sub_grid, level));
+            max_error
+        })
+        .collect()
+}
+
+// Largest difference between the full resolution heights of the sub-grid and
+// its surface at 'level'.
+pub fn get_level_error(field: &HeightField, sub_grid: &SubGrid, level: usize) -> f32 {
+    let mut error = 0.0f32;
+    for i in 0..sub_grid.num_rows {
+        for j in 0..sub_grid.num_cols {
+            let h = field.get_heightmap().at(sub_grid.row + i, sub_grid.col + j);
+            error = error.max((interpolate_height(field, sub_grid, i, j, 1 << level) - h).abs());
+        }
+    }
+    error
+}
+
+// Height of the sub-grid's surface through every 'step'-th vertex at its
+// vertex (i, j), on the same ABC/CBD triangles as HeightField::get_height.
+fn interpolate_height(field: &HeightField, sub_grid: &SubGrid, i: usize, j: usize, step: usize) -> f32 {
+    let height = |i: usize, j: usize| field.get_heightmap().at(sub_grid.row + i, sub_grid.col + j);
+
+    let row = (i / step * step).min(sub_grid.num_rows - 1 - step);
+    let col = (j / step * step).min(sub_grid.num_cols - 1 - step);
+
+    let cell_a = height(row, col);
+    let cell_b = height(row, col + step);
+    let cell_c = height(row + step, col);
+    let cell_d = height(row + step, col + step);
+
+    let s = (j - col) as f32 / step as f32;
+    let t = (i - row) as f32 / step as f32;
+
+    if t < 1.0 - s {
+        cell_a + s * (cell_b - cell_a) + t * (cell_c - cell_a)
+    } else {
+        cell_d + (1.0 - s) * (cell_c - cell_d) + (1.0 - t) * (cell_b - cell_d)
+    }
+}
+
+// Pixels a height error covers at 'distance' from the eye.  The projection's
+// y scale is the length of the view-projection's second column (the view
+// transform is rigid), and its last column is (0, 0, 0, 1) only for orthographic
+// projections, which have no perspective divide.
+pub fn get_pixel_error(error: f32, distance: f32, view_proj: &Mat4, viewport_height: f32) -> f32 {
+    let m = &view_proj.m;
+    let scale = (m[0][1] * m[0][1] + m[1][1] * m[1][1] + m[2][1] * m[2][1]).sqrt() * viewport_height * 0.5;
+    if m[0][3] == 0.0 && m[1][3] == 0.0 && m[2][3] == 0.0 {
+        error * scale
+    } else {
+        error * scale / distance.max(1.0e-6)
+    }
+}
+
+// Triangles of a num_rows x num_cols sub-grid at 'level', indexing its vertices
+// row by row, with the odd vertices of the edges in 'stitch_mask' collapsed onto
+// the previous even ones.  Triangulated like build_tri_grid and ordered for the
+// vertex cache.
+pub fn build_lod_indices(num_rows: usize, num_cols: usize, level: usize, stitch_mask: u32) -> Vec<u32> {
+    assert!(level < get_num_lod_levels(num_rows, num_cols), "No level {} for a {}x{} sub-grid", level, num_rows, num_cols);
+
+    let step = 1 << level;
+    let last_row = num_rows - 1;
+    let last_col = num_cols - 1;
+
+    let index = |i: usize, j: usize| -> u32 {
+        let mut i = i;
+        let mut j = j;
+        if ((i == 0 && stitch_mask & EDGE_TOP != 0) || (i == last_row && stitch_mask & EDGE_BOTTOM != 0))
+            && (j / step) % 2 == 1 {
+            j -= step;
+        }
+        if ((j == 0 && stitch_mask & EDGE_LEFT != 0) || (j == last_col && stitch_mask & EDGE_RIGHT != 0))
+            && (i / step) % 2 == 1 {
+            i -= step;
+        }
+        (i * num_cols + j) as u32
+    };
+
+    let mut indices = Vec::new();
+    let mut push = |a: u32, b: u32, c: u32| {
+        // Collapsed triangles are dropped.
+        if a != b && b != c && c != a {
+            indices.extend_from_slice(&[a, b, c]);
+        }
+    };
+
+    for i in (0..last_row).step_by(step) {
+        for j in (0..last_col).step_by(step) {
+            let a = index(i, j);
+            let b = index(i, j + step);
+            let c = index(i + step, j);
+            let d = index(i + step, j + step);
+
+            push(a, b, c);
+            push(c, b, d);
+        }
+    }
+
+    let mut mesh = Mesh::new(vec![Vec3::ZERO; num_rows * num_cols], indices);
+    optimize_vertex_cache(&mut mesh);
+    mesh.indices
+}
@@ -0,0 +1,607 @@
+// A heightmap laid out on the xz-plane, centered on the origin like the demos'
+// grids: vertex (i, j) is at x = -width/2 + j*dx, z = depth/2 - i*dz, so rows
+// go from +z to -z.  Each cell is split into the triangles ABC and CBD of
+// geometry::build_tri_grid, which the height queries follow exactly.
+//
+// A*--*B
+//  | /|
+//  |/ |
+// C*--*D
+//
+// Points off the edges are clamped to the nearest edge by the height and normal
+// queries; try_get_height tells them apart.  Rays walk the cells they cross in
+// order and test the cells' two triangles exactly.
+
+use controllers::Ground;
+use geometry::*;
+use crate::heightmap::*;
+
+// Height added above and below the bounds rays are clipped to.
+const BOUNDS_PADDING: f32 = 1.0e-3;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HeightFieldHit {
+    // Ray parameter of the hit, see Ray::dir.
+    pub distance: f32,
+    pub pos: Vec3,
+    // Of the hit triangle, facing up.
+    pub normal: Vec3,
+    // Cell of the hit triangle.
+    pub row: usize,
+    pub col: usize,
+}
+
+// A rectangle of a field's vertices.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct VertexRect {
+    pub row: usize,
+    pub col: usize,
+    pub num_rows: usize,
+    pub num_cols: usize,
+}
+
+impl VertexRect {
+    pub fn new(row: usize, col: usize, num_rows: usize, num_cols: usize) -> VertexRect {
+        VertexRect { row, col, num_rows, num_cols }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_rows == 0 || self.num_cols == 0
+    }
+
+    pub fn contains(&self, i: usize, j: usize) -> bool {
+        i >= self.row && i < self.row + self.num_rows && j >= self.col && j < self.col + self.num_cols
+    }
+
+    pub fn intersects(&self, other: &VertexRect) -> bool {
+        !self.is_empty() && !other.is_empty()
+            && self.row < other.row + other.num_rows && other.row < self.row + self.num_rows
+            && self.col < other.col + other.num_cols && other.col < self.col + self.num_cols
+    }
+
+    // Grown by 'n' vertices on every side, within a num_rows x num_cols grid.
+    pub fn grow(&self, n: usize, num_rows: usize, num_cols: usize) -> VertexRect {
+        let row = self.row.saturating_sub(n);
+        let col = self.col.saturating_sub(n);
+        let end_row = (self.row + self.num_rows + n).min(num_rows);
+        let end_col = (self.col + self.num_cols + n).min(num_cols);
+        VertexRect::new(row, col, end_row.saturating_sub(row), end_col.saturating_sub(col))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeightField {
+    heightmap: Heightmap,
+
+    dx: f32,
+    dz: f32,
+
+    width: f32,
+    depth: f32,
+}
+
+impl HeightField {
+    // The heightmap needs at least two rows and two columns.
+    pub fn new(heightmap: Heightmap, dx: f32, dz: f32) -> HeightField {
+        assert!(heightmap.num_rows() >= 2 && heightmap.num_cols() >= 2, "A height field needs at least one cell");
+
+        let width = (heightmap.num_cols() - 1) as f32 * dx;
+        let depth = (heightmap.num_rows() - 1) as f32 * dz;
+
+        HeightField { heightmap, dx, dz, width, depth }
+    }
+
+    pub fn get_heightmap(&self) -> &Heightmap {
+        &self.heightmap
+    }
+
+    // For editing; the field's size stays the same.
+    pub fn get_heightmap_mut(&mut self) -> &mut Heightmap {
+        &mut self.heightmap
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.heightmap.num_rows()
+    }
+
+    pub fn num_cols(&self) -> usize {
+        self.heightmap.num_cols()
+    }
+
+    pub fn get_dx(&self) -> f32 {
+        self.dx
+    }
+
+    pub fn get_dz(&self) -> f32 {
+        self.dz
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn get_depth(&self) -> f32 {
+        self.depth
+    }
+
+    // The minimum and maximum corners in x and z.
+    pub fn get_extent(&self) -> (Vec2, Vec2) {
+        (Vec2::new(-0.5 * self.width, -0.5 * self.depth), Vec2::new(0.5 * self.width, 0.5 * self.depth))
+    }
+
+    pub fn get_bounds(&self) -> Aabb {
+        let (min, max) = self.get_extent();
+        let (lo, hi) = self.heightmap.get_height_range();
+        Aabb::new(Vec3::new(min.x, lo, min.y), Vec3::new(max.x, hi, max.y))
+    }
+
+    pub fn get_vertex_pos(&self, i: usize, j: usize) -> Vec3 {
+        Vec3::new(-0.5 * self.width + j as f32 * self.dx, self.heightmap.at(i, j), 0.5 * self.depth - i as f32 * self.dz)
+    }
+
+    // The point (x, z) in "cell" space, as fractional (row, column) vertex
+    // indices.
+    pub fn to_cell(&self, x: f32, z: f32) -> (f32, f32) {
+        ((z - 0.5 * self.depth) / -self.dz, (x + 0.5 * self.width) / self.dx)
+    }
+
+    // Whether (x, z) is over the field, edges included.
+    pub fn contains(&self, x: f32, z: f32) -> bool {
+        let (min, max) = self.get_extent();
+        x >= min.x && x <= max.x && z >= min.y && z <= max.y
+    }
+
+    // Height of the surface over (x, z).  Points off the edges get the height at
+    // the nearest edge.
+    pub fn get_height(&self, x: f32, z: f32) -> f32 {
+        let (row, col, s, t) = self.locate(x, z);
+
+        let cell_a = self.heightmap.at(row, col);
+        let cell_b = self.heightmap.at(row, col + 1);
+        let cell_c = self.heightmap.at(row + 1, col);
+        let cell_d = self.heightmap.at(row + 1, col + 1);
+
+        if t < 1.0 - s {
+            // Upper triangle ABC.
+            cell_a + s * (cell_b - cell_a) + t * (cell_c - cell_a)
+        } else {
+            // Lower triangle DCB.
+            cell_d + (1.0 - s) * (cell_c - cell_d) + (1.0 - t) * (cell_b - cell_d)
+        }
+    }
+
+    // None off the edges.
+    pub fn try_get_height(&self, x: f32, z: f32) -> Option<f32> {
+        if self.contains(x, z) {
+            Some(self.get_height(x, z))
+        } else {
+            None
+        }
+    }
+
+    // Rates of change of the height along x and z on the triangle under (x, z),
+    // clamped like get_height.
+    pub fn get_gradient(&self, x: f32, z: f32) -> Vec2 {
+        let (row, col, s, t) = self.locate(x, z);
+
+        let cell_a = self.heightmap.at(row, col);
+        let cell_b = self.heightmap.at(row, col + 1);
+        let cell_c = self.heightmap.at(row + 1, col);
+        let cell_d = self.heightmap.at(row + 1, col + 1);
+
+        // Rows go towards -z.
+        if t < 1.0 - s {
+            Vec2::new((cell_b - cell_a) / self.dx, (cell_a - cell_c) / self.dz)
+        } else {
+            Vec2::new((cell_d - cell_c) / self.dx, (cell_b - cell_d) / self.dz)
+        }
+    }
+
+    // Upward unit normal of the triangle under (x, z).
+    pub fn get_normal(&self, x: f32, z: f32) -> Vec3 {
+        let g = self.get_gradient(x, z);
+        Vec3::new(-g.x, 1.0, -g.y).normalize()
+    }
+
+    // Angle in radians between the triangle under (x, z) and the horizontal.
+    pub fn get_slope(&self, x: f32, z: f32) -> f32 {
+        self.get_gradient(x, z).length().atan()
+    }
+
+    // Nearest hit in [t_min, t_max] of the ray with the surface.  The ray walks
+    // only the cells it crosses, nearest first.
+    pub fn intersect_ray(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HeightFieldHit> {
+        // Padded so rays along a flat field still enter it.
+        let mut bounds = self.get_bounds();
+        bounds.min.y -= BOUNDS_PADDING;
+        bounds.max.y += BOUNDS_PADDING;
+        let (t0, t1) = bounds.intersect_ray(ray, t_min, t_max)?;
+
+        let last_row = self.num_rows() - 2;
+        let last_col = self.num_cols() - 2;
+
+        // Walk in cell space, where cell (row, col) spans [row, row + 1] x
+        // [col, col + 1].
+        let start = ray.at(t0);
+        let (d, c) = self.to_cell(start.x, start.z);
+        let mut row = (d.floor().max(0.0) as usize).min(last_row);
+        let mut col = (c.floor().max(0.0) as usize).min(last_col);
+
+        let d_row = -ray.dir.z / self.dz;
+        let d_col = ray.dir.x / self.dx;
+
+        // Ray parameters of the next row and column boundaries, and between
+        // boundaries.
+        let next = |pos: f32, cell: usize, rate: f32| {
+            if rate > 0.0 {
+                t0 + (cell as f32 + 1.0 - pos) / rate
+            } else if rate < 0.0 {
+                t0 + (cell as f32 - pos) / rate
+            } else {
+                f32::INFINITY
+            }
+        };
+        let mut t_next_row = next(d, row, d_row);
+        let mut t_next_col = next(c, col, d_col);
+        let t_delta_row = (1.0 / d_row).abs();
+        let t_delta_col = (1.0 / d_col).abs();
+
+        loop {
+            if let Some(hit) = self.intersect_cell(ray, row, col, t_min, t_max) {
+                return Some(hit);
+            }
+
+            if t_next_row.min(t_next_col) > t1 {
+                return None;
+            }
+
+            if t_next_col < t_next_row {
+                if (d_col > 0.0 && col == last_col) || (d_col < 0.0 && col == 0) {
+                    return None;
+                }
+                col = if d_col > 0.0 { col + 1 } else { col - 1 };
+                t_next_col += t_delta_col;
+            } else {
+                if (d_row > 0.0 && row == last_row) || (d_row < 0.0 && row == 0) {
+                    return None;
+                }
+                row = if d_row > 0.0 { row + 1 } else { row - 1 };
+                t_next_row += t_delta_row;
+            }
+        }
+    }
+
+    // First hit of the surface between 'p0' and 'p1', with distances in units of
+    // the segment's length.
+    pub fn intersect_segment(&self, p0: Vec3, p1: Vec3) -> Option<HeightFieldHit> {
+        self.intersect_ray(&Ray::new(p0, p1 - p0), 0.0, 1.0)
+    }
+
+    // Whether the surface leaves the segment between 'p0' and 'p1' unobstructed.
+    pub fn has_line_of_sight(&self, p0: Vec3, p1: Vec3) -> bool {
+        self.intersect_segment(p0, p1).is_none()
+    }
+
+    // The grid's vertices row by row, with smooth normals and tex-coords going
+    // [0, 1] across the whole field (for the blend map).
+    pub fn build_vertices(&self) -> Vec<VertexPNT> {
+        self.build_mesh().vertices
+    }
+
+    // The whole field as one mesh.  Big fields need 32-bit indices; sub-grids
+    // (see build_sub_grids) are what gets drawn.
+    pub fn build_mesh(&self) -> Mesh<VertexPNT> {
+        let (mut vertices, indices) =
+            match build_tri_grid::<VertexPNT, u32>(self.num_rows() as u32, self.num_cols() as u32,
+                                                   self.dx, self.dz, &GridOptions::default()) {
+                Ok(grid) => grid,
+                Err(e) => panic!("HeightField::build_mesh: {}", e),
+            };
+
+        for (v, h) in vertices.iter_mut().zip(self.heightmap.get_heights()) {
+            v.pos.y = *h;
+        }
+
+        let mut mesh = Mesh::new(vertices, indices);
+        mesh.compute_normals();
+        mesh
+    }
+
+    // Normals of the vertices in 'rect', row by row, the same as build_mesh's.
+    // A vertex's normal only depends on the triangles around it, so after an
+    // edit only the vertices within one of the edited ones need new normals.
+    pub fn build_vertex_normals(&self, rect: &VertexRect) -> Vec<Vec3> {
+        // The triangles around the rectangle's vertices.
+        let around = rect.grow(1, self.num_rows(), self.num_cols());
+        if rect.is_empty() || around.num_rows < 2 || around.num_cols < 2 {
+            return Vec::new();
+        }
+
+        let mut positions = Vec::with_capacity(around.num_rows * around.num_cols);
+        for i in around.row..around.row + around.num_rows {
+            for j in around.col..around.col + around.num_cols {
+                positions.push(self.get_vertex_pos(i, j));
+            }
+        }
+        let indices = match build_tri_grid::<Vec3, u32>(around.num_rows as u32, around.num_cols as u32,
+                                                         self.dx, self.dz, &GridOptions::default()) {
+            Ok((_, indices)) => indices,
+            Err(e) => panic!("HeightField::build_vertex_normals: {}", e),
+        };
+        let normals = vertex_normals(&positions, &indices);
+
+        let mut res = Vec::with_capacity(rect.num_rows * rect.num_cols);
+        for i in rect.row..rect.row + rect.num_rows {
+            let first = (i - around.row) * around.num_cols + rect.col - around.col;
+            res.extend_from_slice(&normals[first..first + rect.num_cols]);
+        }
+        res
+    }
+
+    // The cell under (x, z), clamped to the field, and where (x, z) is in it.
+    fn locate(&self, x: f32, z: f32) -> (usize, usize, f32, f32) {
+        let (d, c) = self.to_cell(x, z);
+        let last_row = self.num_rows() - 1;
+        let last_col = self.num_cols() - 1;
+        let d = d.clamp(0.0, last_row as f32);
+        let c = c.clamp(0.0, last_col as f32);
+
+        // The last row and column of vertices close the cells before them.
+        let row = (d.floor() as usize).min(last_row - 1);
+        let col = (c.floor() as usize).min(last_col - 1);
+
+        (row, col, c - col as f32, d - row as f32)
+    }
+
+    // Nearer hit of the cell's two triangles.
+    fn intersect_cell(&self, ray: &Ray, row: usize, col: usize, t_min: f32, t_max: f32) -> Option<HeightFieldHit> {
+        let a = self.get_vertex_pos(row, col);
+        let b = self.get_vertex_pos(row, col + 1);
+        let c = self.get_vertex_pos(row + 1, col);
+        let d = self.get_vertex_pos(row + 1, col + 1);
+
+        [(a, b, c), (c, b, d)].iter()
+            .filter_map(|(p0, p1, p2)| {
+                let (t, _, _) = intersect_triangle(ray, *p0, *p1, *p2)?;
+                if t < t_min || t > t_max {
+                    return None;
+                }
+                // Both triangles wind the same way; the normal faces up.
+                let normal = (*p1 - *p0).cross(*p2 - *p0).normalize();
+                Some(HeightFieldHit { distance: t, pos: ray.at(t), normal, row, col })
+            })
+            .min_by(|h0, h1| h0.distance.total_cmp(&h1.distance))
+    }
+}
+
+impl Ground for HeightField {
+    fn get_height(&self, x: f32, z: f32) -> f32 {
+        HeightField::get_height(self, x, z)
+    }
+
+    fn get_extent(&self) -> Option<(Vec2, Vec2)> {
+        Some(HeightField::get_extent(self))
+    }
+
+    fn get_normal(&self, x: f32, z: f32) -> Vec3 {
+        HeightField::get_normal(self, x, z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f32 = 1.0e-4;
+
+    // A field with the heights of f(x, z) at its vertices.
+    fn field(num_rows: usize, num_cols: usize, dx: f32, dz: f32, f: impl Fn(f32, f32) -> f32) -> HeightField {
+        let mut field = HeightField::new(Heightmap::new(num_rows, num_cols), dx, dz);
+        for i in 0..num_rows {
+            for j in 0..num_cols {
+                let p = field.get_vertex_pos(i, j);
+                field.get_heightmap_mut().set(i, j, f(p.x, p.z));
+            }
+        }
+        field
+    }
+
+    // h = 0.5x + 0.25z + 1.
+    fn ramp() -> HeightField {
+        field(9, 9, 2.0, 1.0, |x, z| 0.5 * x + 0.25 * z + 1.0)
+    }
+
+    // Ray parameter where the ray meets the plane of ramp().
+    fn ramp_distance(ray: &Ray) -> f32 {
+        let (o, d) = (ray.origin, ray.dir);
+        (0.5 * o.x + 0.25 * o.z + 1.0 - o.y) / (d.y - 0.5 * d.x - 0.25 * d.z)
+    }
+
+    // 0 up to x = 0 and 2 from x = 1 on, with the cell between rising linearly.
+    fn step() -> HeightField {
+        field(5, 5, 1.0, 1.0, |x, _| if x > 0.5 { 2.0 } else { 0.0 })
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() <= EPS, "{} != {}", a, b);
+    }
+
+    fn assert_near_vec(a: Vec3, b: Vec3) {
+        assert!((a - b).length() <= EPS, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn layout() {
+        let field = ramp();
+        assert_eq!(field.get_width(), 16.0);
+        assert_eq!(field.get_depth(), 8.0);
+        assert_eq!(field.get_extent(), (Vec2::new(-8.0, -4.0), Vec2::new(8.0, 4.0)));
+        assert_near_vec(field.get_vertex_pos(0, 0), Vec3::new(-8.0, -2.0, 4.0));
+        assert_near_vec(field.get_vertex_pos(8, 8), Vec3::new(8.0, 4.0, -4.0));
+        assert_eq!(field.to_cell(-8.0, 4.0), (0.0, 0.0));
+        assert_eq!(field.to_cell(1.0, 0.5), (3.5, 4.5));
+    }
+
+    #[test]
+    fn flat_heights_and_normals() {
+        let field = field(5, 7, 2.0, 1.5, |_, _| 3.0);
+
+        for (x, z) in [(0.0, 0.0), (-5.9, 2.9), (5.9, -2.9), (0.3, -1.1), (-6.0, 3.0), (6.0, -3.0)] {
+            assert_eq!(field.get_height(x, z), 3.0);
+            assert_eq!(field.get_normal(x, z), Vec3::Y);
+            assert_eq!(field.get_slope(x, z), 0.0);
+        }
+    }
+
+    #[test]
+    fn ramp_heights_normals_and_slope() {
+        let field = ramp();
+        let normal = Vec3::new(-0.5, 1.0, -0.25).normalize();
+        let slope = Vec2::new(0.5, 0.25).length().atan();
+
+        // Vertices, cell centers, points on both triangles and on the diagonals.
+        for (x, z) in [(0.0, 0.0), (-8.0, 4.0), (8.0, -4.0), (1.0, 0.5), (0.4, 0.1), (1.6, 0.9), (1.5, 0.25), (-7.3, -3.6)] {
+            assert_near(field.get_height(x, z), 0.5 * x + 0.25 * z + 1.0);
+            assert_near_vec(Vec3::new(field.get_gradient(x, z).x, 0.0, field.get_gradient(x, z).y),
+                            Vec3::new(0.5, 0.0, 0.25));
+            assert_near_vec(field.get_normal(x, z), normal);
+            assert_near(field.get_slope(x, z), slope);
+        }
+    }
+
+    #[test]
+    fn step_heights_and_slopes() {
+        let field = step();
+
+        assert_eq!(field.get_height(-1.5, 0.2), 0.0);
+        assert_eq!(field.get_height(1.5, 0.2), 2.0);
+        // Both triangles of the cells between x = 0 and x = 1 rise along x only.
+        for (x, z) in [(0.25, 0.9), (0.5, 0.5), (0.75, 0.1), (0.1, -1.7)] {
+            assert_near(field.get_height(x, z), 2.0 * x);
+            assert_near_vec(field.get_normal(x, z), Vec3::new(-2.0, 1.0, 0.0).normalize());
+            assert_near(field.get_slope(x, z), 2.0f32.atan());
+        }
+        assert_eq!(field.get_slope(-1.0, 0.5), 0.0);
+        assert_eq!(field.get_slope(1.5, 0.5), 0.0);
+    }
+
+    #[test]
+    fn off_the_edges() {
+        let field = ramp();
+
+        assert_eq!(field.try_get_height(8.01, 0.0), None);
+        assert_eq!(field.try_get_height(0.0, -4.01), None);
+        assert_eq!(field.try_get_height(-100.0, 100.0), None);
+        assert_eq!(field.try_get_height(f32::NAN, 0.0), None);
+        assert_near(field.try_get_height(8.0, -4.0).unwrap(), 4.0);
+        assert_near(field.try_get_height(1.0, 0.5).unwrap(), 1.625);
+
+        // Clamped to the nearest edge.
+        assert_near(field.get_height(20.0, 0.0), field.get_height(8.0, 0.0));
+        assert_near(field.get_height(-20.0, -20.0), field.get_height(-8.0, -4.0));
+    }
+
+    #[test]
+    fn rays_against_a_plane() {
+        let field = field(9, 9, 1.0, 1.0, |_, _| 3.0);
+
+        let ray = Ray::new(Vec3::new(0.3, 10.0, 0.2), Vec3::new(0.0, -1.0, 0.0));
+        let hit = field.intersect_ray(&ray, 0.0, f32::MAX).unwrap();
+        assert_near(hit.distance, 7.0);
+        assert_near_vec(hit.pos, Vec3::new(0.3, 3.0, 0.2));
+        assert_near_vec(hit.normal, Vec3::Y);
+        assert_eq!((hit.row, hit.col), (3, 4));
+
+        // Unnormalized directions measure distances in their lengths.
+        let ray = Ray::new(Vec3::new(-3.0, 8.0, 1.0), Vec3::new(2.0, -2.0, 1.0));
+        let hit = field.intersect_ray(&ray, 0.0, f32::MAX).unwrap();
+        assert_near(hit.distance, 2.5);
+        assert_near_vec(hit.pos, Vec3::new(2.0, 3.0, 3.5));
+
+        // Limited by t_max, and pointing away.
+        assert_eq!(field.intersect_ray(&ray, 0.0, 2.4), None);
+        assert_eq!(field.intersect_ray(&Ray::new(Vec3::new(0.0, 4.0, 0.0), Vec3::Y), 0.0, f32::MAX), None);
+        assert!(field.has_line_of_sight(Vec3::new(-4.0, 3.5, -4.0), Vec3::new(4.0, 3.5, 4.0)));
+        assert!(!field.has_line_of_sight(Vec3::new(-4.0, 3.5, -4.0), Vec3::new(4.0, 2.5, 4.0)));
+    }
+
+    #[test]
+    fn rays_against_a_ramp() {
+        let field = ramp();
+
+        for (origin, dir) in [
+            (Vec3::new(0.0, 20.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+            (Vec3::new(-7.0, 10.0, 3.0), Vec3::new(1.0, -1.0, -0.5)),
+            (Vec3::new(6.0, 12.0, -3.0), Vec3::new(-0.7, -1.3, 0.4)),
+            // Shallow, crossing many cells.
+            (Vec3::new(-7.9, 3.0, -3.9), Vec3::new(1.0, -0.05, 0.5)),
+        ] {
+            let ray = Ray::new(origin, dir);
+            let hit = field.intersect_ray(&ray, 0.0, f32::MAX).unwrap();
+            assert_near(hit.distance, ramp_distance(&ray));
+            assert_near(hit.pos.y, field.get_height(hit.pos.x, hit.pos.z));
+            assert_near_vec(hit.normal, Vec3::new(-0.5, 1.0, -0.25).normalize());
+        }
+    }
+
+    #[test]
+    fn rays_grazing_cell_edges() {
+        let field = ramp();
+
+        // Down a column line, along a row line and through vertices along a
+        // diagonal, where the ray touches two or four cells at once.
+        for (origin, dir) in [
+            (Vec3::new(2.0, 10.0, 3.5), Vec3::new(0.0, -1.0, -0.25)),
+            (Vec3::new(-7.5, 10.0, 1.0), Vec3::new(1.0, -1.0, 0.0)),
+            (Vec3::new(-6.0, 10.0, 3.0), Vec3::new(2.0, -1.0, -1.0)),
+            (Vec3::new(4.0, 10.0, 2.0), Vec3::new(0.0, -1.0, 0.0)),
+        ] {
+            let ray = Ray::new(origin, dir);
+            let hit = field.intersect_ray(&ray, 0.0, f32::MAX).unwrap();
+            assert_near(hit.distance, ramp_distance(&ray));
+        }
+    }
+
+    #[test]
+    fn rays_from_outside_the_field() {
+        let field = ramp();
+
+        // From beside the field, entering through a side.
+        let ray = Ray::new(Vec3::new(-20.0, 0.0, 0.5), Vec3::new(1.0, -0.1, 0.0));
+        let hit = field.intersect_ray(&ray, 0.0, f32::MAX).unwrap();
+        assert_near(hit.distance, ramp_distance(&ray));
+        assert!(field.contains(hit.pos.x, hit.pos.z));
+
+        // From above and beyond a corner, looking back over it.
+        let ray = Ray::new(Vec3::new(12.0, 20.0, -8.0), Vec3::new(-1.0, -1.5, 0.5));
+        let hit = field.intersect_ray(&ray, 0.0, f32::MAX).unwrap();
+        assert_near(hit.distance, ramp_distance(&ray));
+
+        // Passing beside it, above it, and hitting the ramp's plane only beyond
+        // its edge.
+        assert_eq!(field.intersect_ray(&Ray::new(Vec3::new(-20.0, 0.0, 5.0), Vec3::X), 0.0, f32::MAX), None);
+        assert_eq!(field.intersect_ray(&Ray::new(Vec3::new(-20.0, 10.0, 0.0), Vec3::X), 0.0, f32::MAX), None);
+        assert_eq!(field.intersect_ray(&Ray::new(Vec3::new(20.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+                                       0.0, f32::MAX), None);
+    }
+
+    #[test]
+    fn rays_against_a_step() {
+        let field = step();
+
+        // Level with the middle of the step's face.
+        let ray = Ray::new(Vec3::new(-10.0, 1.0, 0.3), Vec3::X);
+        let hit = field.intersect_ray(&ray, 0.0, f32::MAX).unwrap();
+        assert_near(hit.distance, 10.5);
+        assert_eq!(hit.col, 2);
+        assert_near_vec(hit.normal, Vec3::new(-2.0, 1.0, 0.0).normalize());
+
+        // Over the top, and coming down onto the upper level past the step.
+        assert_eq!(field.intersect_ray(&Ray::new(Vec3::new(-10.0, 2.5, 0.3), Vec3::X), 0.0, f32::MAX), None);
+        let ray = Ray::new(Vec3::new(0.5, 3.0, 0.3), Vec3::new(1.0, -1.0, 0.0));
+        let hit = field.intersect_ray(&ray, 0.0, f32::MAX).unwrap();
+        assert_near(hit.distance, 1.0);
+        assert_eq!(hit.col, 3);
+        assert_near_vec(hit.normal, Vec3::Y);
+    }
+}
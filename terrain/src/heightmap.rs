@@ -0,0 +1,187 @@
+// Grid of heights, row by row, loaded from 8-bit RAW files and smoothed, and
+// saved back to them after editing.
+
+use std::fs::{read, write};
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Heightmap {
+    num_rows: usize,
+    num_cols: usize,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    // Flat, at height 0.
+    pub fn new(num_rows: usize, num_cols: usize) -> Heightmap {
+        Heightmap { num_rows, num_cols, heights: vec![0.0; num_rows * num_cols] }
+    }
+
+    // 'heights' row by row.
+    pub fn from_heights(num_rows: usize, num_cols: usize, heights: Vec<f32>) -> Heightmap {
+        assert_eq!(heights.len(), num_rows * num_cols, "Heightmap size does not match the number of heights");
+        Heightmap { num_rows, num_cols, heights }
+    }
+
+    // One byte per height, scaled and offset, then smoothed with filter3x3
+    // because 256 height steps are rather coarse.
+    pub fn from_raw(num_rows: usize, num_cols: usize, data: &[u8], height_scale: f32,
+                    height_offset: f32) -> io::Result<Heightmap> {
+        let num_heights = num_rows * num_cols;
+        if data.len() < num_heights {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("{} bytes of RAW data for a {}x{} heightmap", data.len(), num_rows, num_cols)));
+        }
+
+        let heights = data[..num_heights].iter().map(|h| *h as f32 * height_scale + height_offset).collect();
+        let mut res = Heightmap { num_rows, num_cols, heights };
+        res.filter3x3();
+        Ok(res)
+    }
+
+    pub fn load_raw<P: AsRef<Path>>(num_rows: usize, num_cols: usize, path: P, height_scale: f32,
+                                    height_offset: f32) -> io::Result<Heightmap> {
+        Heightmap::from_raw(num_rows, num_cols, &read(path)?, height_scale, height_offset)
+    }
+
+    // One byte per height, the inverse of from_raw's scale and offset, rounded
+    // and clamped to [0, 255].  Loading smooths the heights again.
+    pub fn to_raw(&self, height_scale: f32, height_offset: f32) -> Vec<u8> {
+        self.heights.iter()
+            .map(|h| ((h - height_offset) / height_scale).round().clamp(0.0, 255.0) as u8)
+            .collect()
+    }
+
+    pub fn save_raw<P: AsRef<Path>>(&self, path: P, height_scale: f32, height_offset: f32) -> io::Result<()> {
+        write(path, self.to_raw(height_scale, height_offset))
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    pub fn num_cols(&self) -> usize {
+        self.num_cols
+    }
+
+    pub fn at(&self, i: usize, j: usize) -> f32 {
+        self.heights[i * self.num_cols + j]
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, height: f32) {
+        self.heights[i * self.num_cols + j] = height;
+    }
+
+    pub fn get_heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    // Lowest and highest heights; (0, 0) when empty.
+    pub fn get_height_range(&self) -> (f32, f32) {
+        if self.heights.is_empty() {
+            return (0.0, 0.0);
+        }
+        self.heights.iter().fold((f32::MAX, f32::MIN), |(lo, hi), h| (lo.min(*h), hi.max(*h)))
+    }
+
+    // Replaces each height by the average of itself and its eight neighbours.
+    // Edge heights have fewer neighbours and average only those.
+    pub fn filter3x3(&mut self) {
+        let mut filtered = Vec::with_capacity(self.heights.len());
+        for i in 0..self.num_rows {
+            for j in 0..self.num_cols {
+                filtered.push(self.sample_height3x3(i, j));
+            }
+        }
+        self.heights = filtered;
+    }
+
+    fn sample_height3x3(&self, i: usize, j: usize) -> f32 {
+        // ----------
+        // | 1| 2| 3|
+        // ----------
+        // |4 |ij| 6|
+        // ----------
+        // | 7| 8| 9|
+        // ----------
+        let mut avg = 0.0;
+        let mut num = 0.0;
+
+        for m in i.saturating_sub(1)..=(i + 1).min(self.num_rows - 1) {
+            for n in j.saturating_sub(1)..=(j + 1).min(self.num_cols - 1) {
+                avg += self.at(m, n);
+                num += 1.0;
+            }
+        }
+
+        avg / num
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_raw_scales_and_smooths() {
+        // Raw heights 2v + 1 over
+        //   0 1 2
+        //   3 4 5
+        //   6 7 8
+        // averaged over each height's neighbourhood.
+        let data: Vec<u8> = (0..9).collect();
+        let map = Heightmap::from_raw(3, 3, &data, 2.0, 1.0).unwrap();
+        assert_eq!(map.num_rows(), 3);
+        assert_eq!(map.num_cols(), 3);
+        assert_eq!(map.get_heights(), &[5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 13.0]);
+
+        // Bytes past the grid are ignored; too few is an error.
+        let mut longer = data.clone();
+        longer.extend_from_slice(&[255; 4]);
+        assert_eq!(Heightmap::from_raw(3, 3, &longer, 2.0, 1.0).unwrap(), map);
+        let e = Heightmap::from_raw(3, 3, &data[..8], 2.0, 1.0).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn filter3x3_averages_neighbourhoods() {
+        // A single spike of 9 at (1, 1) on a 4x5 grid.
+        let mut map = Heightmap::new(4, 5);
+        map.set(1, 1, 9.0);
+        map.filter3x3();
+
+        // Interior heights average nine, edges six and corners four.
+        assert_eq!(map.at(1, 1), 1.0);
+        assert_eq!(map.at(2, 2), 1.0);
+        assert_eq!(map.at(0, 0), 9.0 / 4.0);
+        assert_eq!(map.at(0, 2), 1.5);
+        assert_eq!(map.at(2, 0), 1.5);
+        assert_eq!(map.at(1, 3), 0.0);
+        assert_eq!(map.at(3, 4), 0.0);
+        assert_eq!(map.get_height_range(), (0.0, 9.0 / 4.0));
+
+        // Flat stays flat, including a single height.
+        let mut flat = Heightmap::from_heights(3, 4, vec![2.5; 12]);
+        flat.filter3x3();
+        assert_eq!(flat.get_heights(), &[2.5; 12]);
+        let mut one = Heightmap::from_heights(1, 1, vec![3.0]);
+        one.filter3x3();
+        assert_eq!(one.at(0, 0), 3.0);
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let map = Heightmap::from_heights(2, 3, vec![-1.0, 0.0, 1.0, 50.0, 999.0, -999.0]);
+        let raw = map.to_raw(0.5, -1.0);
+        assert_eq!(raw, vec![0, 2, 4, 102, 255, 0]);
+
+        // Back through from_raw the heights come out smoothed, not as saved.
+        let back = Heightmap::from_raw(2, 3, &raw, 0.5, -1.0).unwrap();
+        let mut expect = Heightmap::from_heights(2, 3, raw.iter().map(|v| *v as f32 * 0.5 - 1.0).collect());
+        expect.filter3x3();
+        assert_eq!(back, expect);
+
+        assert_eq!(Heightmap::default().get_height_range(), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,19 @@
+pub mod brush;
+pub mod geomipmap;
+pub mod height_field;
+pub mod heightmap;
+pub mod lightmap;
+pub mod scatter;
+pub mod splat;
+pub mod sub_grid;
+pub mod tiled_world;
+
+pub use crate::brush::*;
+pub use crate::geomipmap::*;
+pub use crate::height_field::*;
+pub use crate::heightmap::*;
+pub use crate::lightmap::*;
+pub use crate::scatter::*;
+pub use crate::splat::*;
+pub use crate::sub_grid::*;
+pub use crate::tiled_world::*;
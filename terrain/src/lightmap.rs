@@ -0,0 +1,504 @@
+// Baked terrain lighting: the shadows the terrain casts on itself and ambient
+// occlusion, found by marching the height field, written to a lightmap laid
+// out like BlendMap (the tex-coords of HeightField::build_vertices) for the
+// terrain effect to multiply in.
+//
+// A texel's horizon in a direction is the highest elevation angle of the
+// terrain seen from it that way.  The sun lights the texel when it is above
+// the horizon towards it, fading over the sun's angular size for soft shadows,
+// and the sky is occluded by the horizons all around.  bake_lightmap marches
+// towards the sun on every bake; a HorizonMap keeps the horizons of a fixed
+// set of directions instead, so relighting it for a moving sun only
+// interpolates them.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use geometry::{Vec2, Vec3};
+use crate::height_field::*;
+use crate::splat::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SunLight {
+    // Unit vector towards the sun.
+    pub dir_to_sun: Vec3,
+    // Radius of the sun's disk in radians, over which shadows fade.  0 for
+    // hard shadows.
+    pub angular_radius: f32,
+    // Intensities of the sky's light and of the sun's N.L light.
+    pub ambient: f32,
+    pub diffuse: f32,
+}
+
+impl SunLight {
+    // Somewhat soft shadows, and the sky a third of the light.
+    pub fn new(dir_to_sun: Vec3) -> SunLight {
+        SunLight {
+            dir_to_sun: dir_to_sun.normalize(),
+            angular_radius: 0.02,
+            ambient: 0.35,
+            diffuse: 0.65,
+        }
+    }
+
+    // Radians above the horizontal.
+    pub fn get_elevation(&self) -> f32 {
+        self.dir_to_sun.y.clamp(-1.0, 1.0).asin()
+    }
+
+    // Radians from +x towards +z, in [0, 2pi).
+    pub fn get_azimuth(&self) -> f32 {
+        self.dir_to_sun.z.atan2(self.dir_to_sun.x).rem_euclid(2.0 * PI)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LightmapDesc {
+    // Resolution of the lightmap.
+    pub width: usize,
+    pub height: usize,
+
+    pub sun: SunLight,
+
+    // Directions the sky's occlusion is sampled in, 0 for none.
+    pub num_ao_directions: usize,
+
+    // How far the horizons are searched in world units.  The steps start a
+    // vertex apart and grow by step_growth of the distance covered, since far
+    // away terrain needs to be much higher to matter.
+    pub max_distance: f32,
+    pub step_growth: f32,
+}
+
+impl LightmapDesc {
+    pub fn new(width: usize, height: usize, sun: SunLight) -> LightmapDesc {
+        LightmapDesc {
+            width,
+            height,
+            sun,
+            num_ao_directions: 8,
+            max_distance: 512.0,
+            step_growth: 0.05,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LightmapTexel {
+    // ambient * ambient_occlusion + diffuse * N.L * sun_visibility, to multiply
+    // the ground's color by in place of the effect's N.L.
+    pub light: f32,
+    // In [0, 1], 0 in full shadow.
+    pub sun_visibility: f32,
+    // Part of the sky seen, in [0, 1].
+    pub ambient_occlusion: f32,
+}
+
+// Row by row, row 0 is the field's first row (the +z edge).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lightmap {
+    width: usize,
+    height: usize,
+    texels: Vec<LightmapTexel>,
+}
+
+impl Lightmap {
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn at(&self, x: usize, y: usize) -> LightmapTexel {
+        self.texels[y * self.width + x]
+    }
+
+    pub fn get_texels(&self) -> &[LightmapTexel] {
+        &self.texels
+    }
+
+    // The light in red, the sun's visibility in green and the ambient
+    // occlusion in blue, 8 bits each in the layout of D3DFMT_A8R8G8B8.
+    pub fn to_bgra8(&self) -> Vec<u8> {
+        let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        self.texels.iter()
+            .flat_map(|t| [to_u8(t.ambient_occlusion), to_u8(t.sun_visibility), to_u8(t.light), 255])
+            .collect()
+    }
+
+    // Uncompressed A8R8G8B8 .dds file, like BlendMap::write_dds.
+    pub fn write_dds<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_dds_a8r8g8b8(writer, self.width, self.height, &self.to_bgra8())
+    }
+
+    pub fn save_dds<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_dds(&mut writer)?;
+        writer.flush()
+    }
+}
+
+// Lights the field at the texel centers, marching towards the sun and, for
+// the ambient occlusion, in num_ao_directions directions around.
+pub fn bake_lightmap(field: &HeightField, desc: &LightmapDesc) -> Lightmap {
+    let sun = &desc.sun;
+    let elevation = sun.get_elevation();
+    let sun_dir = Vec2::new(sun.dir_to_sun.x, sun.dir_to_sun.z);
+    let sun_dir_length = sun_dir.length();
+
+    let mut texels = Vec::with_capacity(desc.width * desc.height);
+    for y in 0..desc.height {
+        for x in 0..desc.width {
+            let (px, pz) = get_texel_pos(field, x, y, desc.width, desc.height);
+
+            // No terrain rises past the sun straight overhead, and once the
+            // horizon is above the sun's disk the texel is in full shadow.
+            let sun_visibility = if sun_dir_length > 1e-6 {
+                let horizon = march_horizon(field, px, pz, sun_dir * (1.0 / sun_dir_length), desc.max_distance,
+                                            desc.step_growth, elevation + sun.angular_radius);
+                get_sun_visibility(elevation, horizon, sun.angular_radius)
+            } else {
+                get_sun_visibility(elevation, -FRAC_PI_2, sun.angular_radius)
+            };
+
+            let horizons: Vec<f32> = (0..desc.num_ao_directions)
+                .map(|k| {
+                    let azimuth = 2.0 * PI * k as f32 / desc.num_ao_directions as f32;
+                    march_horizon(field, px, pz, Vec2::new(azimuth.cos(), azimuth.sin()), desc.max_distance,
+                                  desc.step_growth, FRAC_PI_2)
+                })
+                .collect();
+
+            texels.push(get_texel(field, px, pz, sun, sun_visibility, get_ambient_occlusion(&horizons)));
+        }
+    }
+
+    Lightmap { width: desc.width, height: desc.height, texels }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HorizonMapDesc {
+    // Resolution of the horizon map, and of the lightmaps relit from it.
+    pub width: usize,
+    pub height: usize,
+
+    // Directions around each texel, evenly spaced from +x towards +z.
+    pub num_directions: usize,
+
+    // As LightmapDesc.
+    pub max_distance: f32,
+    pub step_growth: f32,
+}
+
+impl HorizonMapDesc {
+    pub fn new(width: usize, height: usize) -> HorizonMapDesc {
+        HorizonMapDesc {
+            width,
+            height,
+            num_directions: 16,
+            max_distance: 512.0,
+            step_growth: 0.05,
+        }
+    }
+}
+
+// Horizon angles of each texel in num_directions directions, in radians
+// above the horizontal (negative where the terrain falls away).
+#[derive(Clone, Debug, PartialEq)]
+pub struct HorizonMap {
+    width: usize,
+    height: usize,
+    num_directions: usize,
+    horizons: Vec<f32>,
+}
+
+impl HorizonMap {
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_num_directions(&self) -> usize {
+        self.num_directions
+    }
+
+    // The horizons of texel (x, y), one per direction.
+    pub fn get_horizons(&self, x: usize, y: usize) -> &[f32] {
+        let first = (y * self.width + x) * self.num_directions;
+        &self.horizons[first..first + self.num_directions]
+    }
+
+    // Horizon towards 'azimuth' (radians from +x towards +z), interpolated
+    // between the two nearest directions; -pi/2 without directions.
+    pub fn get_horizon(&self, x: usize, y: usize, azimuth: f32) -> f32 {
+        let horizons = self.get_horizons(x, y);
+        let n = self.num_directions;
+        if n == 0 {
+            return -FRAC_PI_2;
+        }
+        let k = azimuth.rem_euclid(2.0 * PI) / (2.0 * PI) * n as f32;
+        let k0 = (k.floor() as usize).min(n - 1);
+        let t = k - k0 as f32;
+        horizons[k0] + t * (horizons[(k0 + 1) % n] - horizons[k0])
+    }
+
+    pub fn get_ambient_occlusion(&self, x: usize, y: usize) -> f32 {
+        get_ambient_occlusion(self.get_horizons(x, y))
+    }
+
+    // The lightmap for 'sun' from the stored horizons, without marching.
+    // 'field' must be the field the map was baked from; it gives the normals.
+    pub fn relight(&self, field: &HeightField, sun: &SunLight) -> Lightmap {
+        let elevation = sun.get_elevation();
+        let azimuth = sun.get_azimuth();
+
+        let mut texels = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (px, pz) = get_texel_pos(field, x, y, self.width, self.height);
+                let sun_visibility = get_sun_visibility(elevation, self.get_horizon(x, y, azimuth), sun.angular_radius);
+                texels.push(get_texel(field, px, pz, sun, sun_visibility, self.get_ambient_occlusion(x, y)));
+            }
+        }
+
+        Lightmap { width: self.width, height: self.height, texels }
+    }
+}
+
+// Marches the field from every texel center in each of the desc's directions.
+pub fn bake_horizon_map(field: &HeightField, desc: &HorizonMapDesc) -> HorizonMap {
+    let dirs: Vec<Vec2> = (0..desc.num_directions)
+        .map(|k| {
+            let azimuth = 2.0 * PI * k as f32 / desc.num_directions as f32;
+            Vec2::new(azimuth.cos(), azimuth.sin())
+        })
+        .collect();
+
+    let mut horizons = Vec::with_capacity(desc.width * desc.height * desc.num_directions);
+    for y in 0..desc.height {
+        for x in 0..desc.width {
+            let (px, pz) = get_texel_pos(field, x, y, desc.width, desc.height);
+            for dir in &dirs {
+                horizons.push(march_horizon(field, px, pz, *dir, desc.max_distance, desc.step_growth, FRAC_PI_2));
+            }
+        }
+    }
+
+    HorizonMap { width: desc.width, height: desc.height, num_directions: desc.num_directions, horizons }
+}
+
+// Center of texel (x, y) of a width x height map over the field, as in
+// generate_blend_map.
+fn get_texel_pos(field: &HeightField, x: usize, y: usize, width: usize, height: usize) -> (f32, f32) {
+    let (min, max) = field.get_extent();
+    (min.x + (x as f32 + 0.5) / width as f32 * (max.x - min.x),
+     max.y - (y as f32 + 0.5) / height as f32 * (max.y - min.y))
+}
+
+fn get_texel(field: &HeightField, x: f32, z: f32, sun: &SunLight, sun_visibility: f32,
+             ambient_occlusion: f32) -> LightmapTexel {
+    // Normal from central differences one vertex apart, like the slopes of
+    // generate_blend_map.
+    let dx = field.get_dx();
+    let dz = field.get_dz();
+    let dhdx = (field.get_height(x + dx, z) - field.get_height(x - dx, z)) / (2.0 * dx);
+    let dhdz = (field.get_height(x, z + dz) - field.get_height(x, z - dz)) / (2.0 * dz);
+    let normal = Vec3::new(-dhdx, 1.0, -dhdz).normalize();
+
+    let n_dot_l = normal.dot(sun.dir_to_sun).max(0.0);
+    LightmapTexel {
+        light: sun.ambient * ambient_occlusion + sun.diffuse * n_dot_l * sun_visibility,
+        sun_visibility,
+        ambient_occlusion,
+    }
+}
+
+// Highest elevation angle of the terrain seen from (x, z) along the unit
+// vector 'dir', -pi/2 when the march leaves the field at once.  Stops early
+// once the horizon reaches 'stop', returning at least 'stop' so that rounding
+// in atan(tan(stop)) cannot put the horizon back below it.
+fn march_horizon(field: &HeightField, x: f32, z: f32, dir: Vec2, max_distance: f32, step_growth: f32,
+                 stop: f32) -> f32 {
+    let h0 = field.get_height(x, z);
+    let min_step = field.get_dx().min(field.get_dz());
+    let stop_tan = if stop < FRAC_PI_2 { stop.tan() } else { f32::INFINITY };
+
+    let mut max_tan = f32::NEG_INFINITY;
+    let mut distance = min_step;
+    while distance <= max_distance {
+        let (px, pz) = (x + dir.x * distance, z + dir.y * distance);
+        if !field.contains(px, pz) {
+            break;
+        }
+
+        max_tan = max_tan.max((field.get_height(px, pz) - h0) / distance);
+        if max_tan >= stop_tan {
+            return max_tan.atan().max(stop);
+        }
+        distance += min_step.max(distance * step_growth);
+    }
+
+    if max_tan == f32::NEG_INFINITY { -FRAC_PI_2 } else { max_tan.atan() }
+}
+
+// How much of the sun's disk is above the horizon, eased with smoothstep; all
+// or nothing for a point sun.
+fn get_sun_visibility(elevation: f32, horizon: f32, angular_radius: f32) -> f32 {
+    if angular_radius <= 0.0 {
+        return if elevation > horizon { 1.0 } else { 0.0 };
+    }
+    let t = ((elevation - horizon) / (2.0 * angular_radius) + 0.5).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// The part of the sky above the horizons, averaged over the directions.
+fn get_ambient_occlusion(horizons: &[f32]) -> f32 {
+    if horizons.is_empty() {
+        return 1.0;
+    }
+    let occluded: f32 = horizons.iter().map(|h| h.sin().max(0.0)).sum();
+    1.0 - occluded / horizons.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heightmap::*;
+
+    // 129x129 vertices one unit apart, x and z in [-64, 64], with heights f(x, z).
+    fn field(f: impl Fn(f32, f32) -> f32) -> HeightField {
+        let mut heights = Vec::with_capacity(129 * 129);
+        for i in 0..129 {
+            for j in 0..129 {
+                heights.push(f(j as f32 - 64.0, 64.0 - i as f32));
+            }
+        }
+        HeightField::new(Heightmap::from_heights(129, 129, heights), 1.0, 1.0)
+    }
+
+    // The x of the center of texel column x in a 128 wide map: -63.5 to 63.5.
+    fn texel_x(x: usize) -> f32 {
+        x as f32 - 63.5
+    }
+
+    // Sun at 'elevation' radians, from azimuth 'azimuth', with hard shadows.
+    fn sun(azimuth: f32, elevation: f32) -> SunLight {
+        let dir = Vec3::new(elevation.cos() * azimuth.cos(), elevation.sin(), elevation.cos() * azimuth.sin());
+        SunLight { angular_radius: 0.0, ..SunLight::new(dir) }
+    }
+
+    #[test]
+    fn flat_field_is_fully_lit() {
+        let flat = field(|_, _| 5.0);
+        let sun = sun(1.0, 0.3);
+        let lightmap = bake_lightmap(&flat, &LightmapDesc::new(16, 16, sun));
+        assert_eq!(lightmap.get_texels().len(), 16 * 16);
+        for t in lightmap.get_texels() {
+            assert_eq!(t.sun_visibility, 1.0);
+            assert_eq!(t.ambient_occlusion, 1.0);
+            assert!((t.light - (sun.ambient + sun.diffuse * 0.3f32.sin())).abs() < 1e-5);
+        }
+
+        let horizons = bake_horizon_map(&flat, &HorizonMapDesc::new(16, 16));
+        assert_eq!(horizons.relight(&flat, &sun), lightmap);
+    }
+
+    #[test]
+    fn wall_shadow_length() {
+        // A wall 10 high over x in [-2, 0], lit from -x with tan(elevation) =
+        // 0.5, shades the ground past it for 10 / 0.5 = 20 units.  The march
+        // sees the wall's top at texel centers half a unit from its edge, so
+        // allow a texel either side.
+        let wall = field(|x, _| if (-2.0..=0.0).contains(&x) { 10.0 } else { 0.0 });
+        let elevation = 0.5f32.atan();
+        let lightmap = bake_lightmap(&wall, &LightmapDesc::new(128, 128, sun(PI, elevation)));
+
+        for y in [0, 40, 127] {
+            for x in 0..128 {
+                let (px, visibility) = (texel_x(x), lightmap.at(x, y).sun_visibility);
+                if px < -2.0 {
+                    assert_eq!(visibility, 1.0, "sunny side at x = {}", px);
+                } else if px > 1.0 && px < 20.0 - 1.0 {
+                    assert_eq!(visibility, 0.0, "shadow at x = {}", px);
+                } else if px > 20.0 + 1.0 {
+                    assert_eq!(visibility, 1.0, "past the shadow at x = {}", px);
+                }
+            }
+        }
+
+        // Shadowed texels get the sky's light only.
+        let t = lightmap.at(10 + 64, 64);
+        assert!(t.ambient_occlusion < 1.0);
+        assert!((t.light - 0.35 * t.ambient_occlusion).abs() < 1e-5);
+
+        // Soft shadows fade in over the sun's disk, and a sun straight overhead
+        // casts none.
+        let soft = SunLight { angular_radius: 0.1, ..sun(PI, elevation) };
+        let lightmap = bake_lightmap(&wall, &LightmapDesc::new(128, 8, soft));
+        let visibility: Vec<f32> = (64 + 2..128).map(|x| lightmap.at(x, 4).sun_visibility).collect();
+        assert!(visibility.windows(2).all(|v| v[1] >= v[0]));
+        assert!(visibility.iter().any(|v| *v > 0.0 && *v < 1.0));
+
+        let overhead = bake_lightmap(&wall, &LightmapDesc::new(32, 32, SunLight::new(Vec3::Y)));
+        assert!(overhead.get_texels().iter().all(|t| t.sun_visibility == 1.0));
+    }
+
+    #[test]
+    fn relight_matches_bake() {
+        let hills = field(|x, z| 8.0 * (x / 5.0).sin() * (z / 7.0).cos() + if x.abs() < 3.0 { 15.0 } else { 0.0 });
+        let horizons = bake_horizon_map(&hills, &HorizonMapDesc::new(32, 32));
+        assert_eq!(horizons.get_num_directions(), 16);
+
+        // Suns on directions 8 and 6 of the horizon map, with the bake's
+        // ambient occlusion sampled in the same 16 directions.
+        for azimuth in [PI, 0.75 * PI] {
+            let sun = SunLight { angular_radius: 0.05, ..sun(azimuth, 0.4) };
+            let desc = LightmapDesc { num_ao_directions: 16, ..LightmapDesc::new(32, 32, sun) };
+            let baked = bake_lightmap(&hills, &desc);
+            let relit = horizons.relight(&hills, &sun);
+
+            let mut shadowed = 0;
+            for (b, r) in baked.get_texels().iter().zip(relit.get_texels()) {
+                assert!((b.sun_visibility - r.sun_visibility).abs() < 1e-3, "{:?} != {:?}", b, r);
+                assert!((b.ambient_occlusion - r.ambient_occlusion).abs() < 1e-5, "{:?} != {:?}", b, r);
+                assert!((b.light - r.light).abs() < 1e-3, "{:?} != {:?}", b, r);
+                shadowed += (b.sun_visibility == 0.0) as usize;
+            }
+            assert!(shadowed > 0);
+        }
+
+        // Between directions the horizon is interpolated.
+        let (h8, h9) = (horizons.get_horizons(20, 16)[8], horizons.get_horizons(20, 16)[9]);
+        let h = horizons.get_horizon(20, 16, PI + 0.25 * (PI / 8.0));
+        assert!((h - (0.75 * h8 + 0.25 * h9)).abs() < 1e-5);
+
+        // Without directions every horizon is below the texel: lit, and the
+        // sky unoccluded.
+        let none = bake_horizon_map(&hills, &HorizonMapDesc { num_directions: 0, ..HorizonMapDesc::new(4, 4) });
+        assert_eq!(none.get_horizon(1, 2, 1.0), -FRAC_PI_2);
+        let relit = none.relight(&hills, &sun(1.0, 0.2));
+        assert!(relit.get_texels().iter().all(|t| t.sun_visibility == 1.0 && t.ambient_occlusion == 1.0));
+    }
+
+    #[test]
+    fn dds_layout() {
+        let flat = field(|_, _| 0.0);
+        let lightmap = bake_lightmap(&flat, &LightmapDesc::new(4, 2, sun(0.0, FRAC_PI_2 / 3.0)));
+        let bgra = lightmap.to_bgra8();
+        assert_eq!(bgra.len(), 4 * 2 * 4);
+        // Ambient occlusion in blue, visibility in green and the light in red.
+        let light = ((0.35 + 0.65 * 0.5f32) * 255.0 + 0.5) as u8;
+        assert_eq!(&bgra[..4], &[255, 255, light, 255]);
+
+        let mut dds = Vec::new();
+        lightmap.write_dds(&mut dds).unwrap();
+        assert_eq!(&dds[..4], b"DDS ");
+        assert_eq!(&dds[128..], &bgra[..]);
+    }
+}
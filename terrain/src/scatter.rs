@@ -0,0 +1,357 @@
+// Rule-based scattering of props (trees, rocks, grass) over a height field.
+//
+// Candidate positions are Poisson-disk samples over the region (Bridson's
+// algorithm): no two closer than the minimum distance, and without the clumps
+// and gaps of uniform random points.  The rules then keep the candidates where
+// the height, slope, density map and exclusion zones allow; dropping points
+// keeps the minimum distance, so the props stay evenly spread.  Each kept
+// point gets a random scale, rotation about y and variant.
+//
+// Everything is drawn from the desc's seed, so the same desc over the same
+// field scatters the same props.
+
+use std::fs::read;
+use std::io;
+use std::path::Path;
+use geometry::{Mat4, Vec2, Vec3};
+use crate::height_field::*;
+use crate::splat::*;
+
+// Most cells of the Poisson-disk sampler's background grid.  A minimum distance
+// too small for the region is raised until the grid fits, which also bounds the
+// number of samples.
+pub const MAX_POISSON_GRID_CELLS: usize = 1 << 22;
+
+// Weights in [0, 1] over the whole field, row by row.  Row 0 is the field's
+// first row (the +z edge), like BlendMap.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DensityMap {
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+}
+
+impl DensityMap {
+    pub fn new(width: usize, height: usize, values: Vec<f32>) -> DensityMap {
+        assert_eq!(values.len(), width * height, "{} values for a {}x{} density map", values.len(), width, height);
+        DensityMap { width, height, values }
+    }
+
+    // One byte per value, 255 for full density.
+    pub fn from_raw(width: usize, height: usize, data: &[u8]) -> io::Result<DensityMap> {
+        let num_values = width * height;
+        if data.len() < num_values {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("{} bytes of RAW data for a {}x{} density map", data.len(), width, height)));
+        }
+
+        Ok(DensityMap::new(width, height, data[..num_values].iter().map(|v| *v as f32 / 255.0).collect()))
+    }
+
+    pub fn load_raw<P: AsRef<Path>>(width: usize, height: usize, path: P) -> io::Result<DensityMap> {
+        DensityMap::from_raw(width, height, &read(path)?)
+    }
+
+    // One channel of a blend map, so props follow the ground they grow on.
+    pub fn from_blend_map(blend_map: &BlendMap, channel: usize) -> DensityMap {
+        let values = blend_map.get_texels().iter().map(|t| t[channel]).collect();
+        DensityMap::new(blend_map.get_width(), blend_map.get_height(), values)
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn at(&self, x: usize, y: usize) -> f32 {
+        self.values[y * self.width + x]
+    }
+
+    // Bilinear, with (u, v) in [0, 1] across the map; clamped outside.
+    pub fn sample(&self, u: f32, v: f32) -> f32 {
+        let x = (u * (self.width - 1) as f32).clamp(0.0, (self.width - 1) as f32);
+        let y = (v * (self.height - 1) as f32).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (s, t) = (x - x0 as f32, y - y0 as f32);
+
+        let top = self.at(x0, y0) + s * (self.at(x1, y0) - self.at(x0, y0));
+        let bottom = self.at(x0, y1) + s * (self.at(x1, y1) - self.at(x0, y1));
+        top + t * (bottom - top)
+    }
+}
+
+// Areas of the xz-plane kept clear of props, such as buildings and paths.
+// Points are (x, z) in Vec2's x and y.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExclusionZone {
+    Circle { center: Vec2, radius: f32 },
+    Rect { min: Vec2, max: Vec2 },
+}
+
+impl ExclusionZone {
+    pub fn contains(&self, p: Vec2) -> bool {
+        match *self {
+            ExclusionZone::Circle { center, radius } => (p - center).length() < radius,
+            ExclusionZone::Rect { min, max } => p.x >= min.x && p.x <= max.x && p.y >= min.y && p.y <= max.y,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScatterDesc {
+    // Minimum and maximum (x, z) corners to scatter in, clipped to the field.
+    // None for the whole field.
+    pub region: Option<(Vec2, Vec2)>,
+
+    // No two props closer than this, and how many candidates are tried around
+    // each sample before giving up on it (30 in Bridson's paper).  Nothing is
+    // scattered for a distance <= 0; see MAX_POISSON_GRID_CELLS for tiny ones.
+    pub min_distance: f32,
+    pub max_attempts: u32,
+
+    // The most props kept, picked at random from all the allowed positions.
+    pub max_count: usize,
+
+    // Allowed heights, and slopes in radians from the horizontal.
+    pub min_height: f32,
+    pub max_height: f32,
+    pub min_slope: f32,
+    pub max_slope: f32,
+
+    // Chance of keeping a point, over the whole field.  None keeps them all.
+    pub density: Option<DensityMap>,
+    pub exclusion_zones: Vec<ExclusionZone>,
+
+    // Scale picked in [min_scale, max_scale], the same on all axes or on each
+    // axis on its own.
+    pub min_scale: f32,
+    pub max_scale: f32,
+    pub uniform_scale: bool,
+
+    // Random rotation about y, or none.
+    pub random_yaw: bool,
+
+    // Variant picked in [0, num_variants).
+    pub num_variants: usize,
+
+    // Added to the ground height, e.g. negative to sink trunks into the ground.
+    pub y_offset: f32,
+
+    pub seed: u32,
+}
+
+impl Default for ScatterDesc {
+    // Everywhere on the field, a meter apart.
+    fn default() -> Self {
+        ScatterDesc {
+            region: None,
+            min_distance: 1.0,
+            max_attempts: 30,
+            max_count: usize::MAX,
+            min_height: f32::MIN,
+            max_height: f32::MAX,
+            min_slope: 0.0,
+            max_slope: std::f32::consts::FRAC_PI_2,
+            density: None,
+            exclusion_zones: Vec::new(),
+            min_scale: 1.0,
+            max_scale: 1.0,
+            uniform_scale: true,
+            random_yaw: true,
+            num_variants: 1,
+            y_offset: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScatterInstance {
+    pub pos: Vec3,
+    pub scale: Vec3,
+    // Rotation about y in radians.
+    pub yaw: f32,
+    pub variant: usize,
+}
+
+impl ScatterInstance {
+    // Scales, then rotates, then moves the prop into place.
+    pub fn get_transform(&self) -> Mat4 {
+        Mat4::scaling(self.scale.x, self.scale.y, self.scale.z)
+            * Mat4::rotation_y(self.yaw)
+            * Mat4::translation(self.pos.x, self.pos.y, self.pos.z)
+    }
+}
+
+// Props placed on 'field' by the desc's rules, in random order.
+pub fn scatter(field: &HeightField, desc: &ScatterDesc) -> Vec<ScatterInstance> {
+    let (field_min, field_max) = field.get_extent();
+    let (min, max) = match desc.region {
+        Some((min, max)) => (
+            Vec2::new(min.x.max(field_min.x), min.y.max(field_min.y)),
+            Vec2::new(max.x.min(field_max.x), max.y.min(field_max.y)),
+        ),
+        None => (field_min, field_max),
+    };
+
+    let mut random = Random::new(desc.seed);
+    let mut points: Vec<Vec2> = sample_poisson_disk(&mut random, min, max, desc.min_distance, desc.max_attempts)
+        .into_iter()
+        .filter(|p| is_allowed(field, desc, *p))
+        .collect();
+
+    if let Some(density) = &desc.density {
+        points.retain(|p| {
+            let u = (p.x - field_min.x) / field.get_width();
+            let v = (field_max.y - p.y) / field.get_depth();
+            random.next_f32() < density.sample(u, v)
+        });
+    }
+
+    // Bridson's samples grow out from the first one, so shuffle before
+    // truncating to keep the props spread over the whole region.
+    for i in (1..points.len()).rev() {
+        points.swap(i, random.next_u32() as usize % (i + 1));
+    }
+    points.truncate(desc.max_count);
+
+    points.into_iter().map(|p| {
+        let scale = if desc.uniform_scale {
+            let s = random.range(desc.min_scale, desc.max_scale);
+            Vec3::new(s, s, s)
+        } else {
+            Vec3::new(random.range(desc.min_scale, desc.max_scale),
+                      random.range(desc.min_scale, desc.max_scale),
+                      random.range(desc.min_scale, desc.max_scale))
+        };
+        let yaw = if desc.random_yaw { random.range(0.0, 2.0 * std::f32::consts::PI) } else { 0.0 };
+        let variant = random.next_u32() as usize % desc.num_variants.max(1);
+
+        ScatterInstance {
+            pos: Vec3::new(p.x, field.get_height(p.x, p.y) + desc.y_offset, p.y),
+            scale,
+            yaw,
+            variant,
+        }
+    }).collect()
+}
+
+// Poisson-disk samples in the rectangle from 'min' to 'max', no two closer than
+// 'min_distance', trying 'max_attempts' candidates around each sample.  Empty
+// for a distance <= 0 or an inverted or unbounded rectangle.
+pub fn poisson_disk(min: Vec2, max: Vec2, min_distance: f32, max_attempts: u32, seed: u32) -> Vec<Vec2> {
+    sample_poisson_disk(&mut Random::new(seed), min, max, min_distance, max_attempts)
+}
+
+fn is_allowed(field: &HeightField, desc: &ScatterDesc, p: Vec2) -> bool {
+    let height = field.get_height(p.x, p.y);
+    let slope = field.get_slope(p.x, p.y);
+    height >= desc.min_height && height <= desc.max_height
+        && slope >= desc.min_slope && slope <= desc.max_slope
+        && !desc.exclusion_zones.iter().any(|z| z.contains(p))
+}
+
+fn sample_poisson_disk(random: &mut Random, min: Vec2, max: Vec2, min_distance: f32, max_attempts: u32) -> Vec<Vec2> {
+    let size = max - min;
+    if size.x < 0.0 || size.y < 0.0 || !size.x.is_finite() || !size.y.is_finite()
+        || min_distance <= 0.0 || min_distance.is_nan() {
+        return Vec::new();
+    }
+
+    // Cells small enough to hold one sample each, so only the 5x5 cells around
+    // a candidate need checking.
+    let mut min_distance = min_distance;
+    let mut cell_size = min_distance / std::f32::consts::SQRT_2;
+    let num_cells = |cell_size: f32| ((size.x / cell_size) as usize + 1).saturating_mul((size.y / cell_size) as usize + 1);
+    while num_cells(cell_size) > MAX_POISSON_GRID_CELLS {
+        min_distance *= 1.25;
+        cell_size = min_distance / std::f32::consts::SQRT_2;
+    }
+    let num_cols = (size.x / cell_size) as usize + 1;
+    let num_rows = (size.y / cell_size) as usize + 1;
+    let mut grid: Vec<Option<usize>> = vec![None; num_rows * num_cols];
+    let to_cell = |p: Vec2| {
+        ((((p.y - min.y) / cell_size) as usize).min(num_rows - 1),
+         (((p.x - min.x) / cell_size) as usize).min(num_cols - 1))
+    };
+
+    let first = Vec2::new(min.x + random.next_f32() * size.x, min.y + random.next_f32() * size.y);
+    let (row, col) = to_cell(first);
+    grid[row * num_cols + col] = Some(0);
+    let mut samples = vec![first];
+    let mut active = vec![0];
+
+    while !active.is_empty() {
+        let a = random.next_u32() as usize % active.len();
+        let center = samples[active[a]];
+
+        let mut found = false;
+        for _ in 0..max_attempts {
+            // Uniform over the annulus from min_distance to twice that.
+            let angle = random.range(0.0, 2.0 * std::f32::consts::PI);
+            let r = min_distance * (1.0 + 3.0 * random.next_f32()).sqrt();
+            let p = Vec2::new(center.x + r * angle.cos(), center.y + r * angle.sin());
+            if p.x < min.x || p.x > max.x || p.y < min.y || p.y > max.y {
+                continue;
+            }
+
+            let (row, col) = to_cell(p);
+            let rows = row.saturating_sub(2)..(row + 3).min(num_rows);
+            let too_close = rows.flat_map(|i| (col.saturating_sub(2)..(col + 3).min(num_cols)).map(move |j| (i, j)))
+                .filter_map(|(i, j)| grid[i * num_cols + j])
+                .any(|s| (samples[s] - p).length() < min_distance);
+            if too_close {
+                continue;
+            }
+
+            grid[row * num_cols + col] = Some(samples.len());
+            active.push(samples.len());
+            samples.push(p);
+            found = true;
+            break;
+        }
+
+        if !found {
+            active.swap_remove(a);
+        }
+    }
+
+    samples
+}
+
+// Small seeded generator (xorshift32), so scattering needs no crates and gives
+// the same result on every platform.
+struct Random {
+    state: u32,
+}
+
+impl Random {
+    fn new(seed: u32) -> Random {
+        // Any state but 0, spread out so nearby seeds start far apart.
+        let mut state = seed.wrapping_mul(0x9E37_79B9) ^ 0xA511_E9B3;
+        if state == 0 {
+            state = 1;
+        }
+        Random { state }
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+
+    // In [0, 1).
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    fn range(&mut self, a: f32, b: f32) -> f32 {
+        a + (b - a) * self.next_f32()
+    }
+}
@@ -0,0 +1,315 @@
+// Blend (splat) maps generated from the shape of a height field instead of
+// painted by hand.  Each rule puts weight on one channel where the height and
+// slope are inside its bands; the weights are then normalized across the
+// channels, so the terrain effect's layers always add up to one.
+//
+// Bands fade out smoothly over their falloff widths, and the height each rule
+// sees is perturbed by fractal value noise so the borders between layers are
+// not contour lines.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::height_field::*;
+
+pub const NUM_SPLAT_CHANNELS: usize = 4;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SplatRule {
+    // 0 to 3 for red, green, blue and alpha.
+    pub channel: usize,
+
+    // Full weight between the heights, fading to 0 over height_falloff outside.
+    pub min_height: f32,
+    pub max_height: f32,
+    pub height_falloff: f32,
+
+    // Same for the slope, in radians from the horizontal.
+    pub min_slope: f32,
+    pub max_slope: f32,
+    pub slope_falloff: f32,
+
+    // Height the noise moves this rule's view of the terrain up or down by.
+    pub noise_amplitude: f32,
+
+    pub weight: f32,
+}
+
+impl Default for SplatRule {
+    // Everywhere, at full weight.
+    fn default() -> Self {
+        SplatRule {
+            channel: 0,
+            min_height: f32::MIN,
+            max_height: f32::MAX,
+            height_falloff: 0.0,
+            min_slope: 0.0,
+            max_slope: std::f32::consts::FRAC_PI_2,
+            slope_falloff: 0.0,
+            noise_amplitude: 0.0,
+            weight: 1.0,
+        }
+    }
+}
+
+impl SplatRule {
+    // Weight of the rule at a height and slope, before normalization.
+    pub fn get_weight(&self, height: f32, slope: f32) -> f32 {
+        self.weight
+            * band(height, self.min_height, self.max_height, self.height_falloff)
+            * band(slope, self.min_slope, self.max_slope, self.slope_falloff)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SplatDesc {
+    // Resolution of the blend map.
+    pub width: usize,
+    pub height: usize,
+
+    pub rules: Vec<SplatRule>,
+    // Gets all the weight where no rule applies.
+    pub default_channel: usize,
+
+    // Size in world units of the noise's largest features, its number of
+    // octaves and its seed.
+    pub noise_scale: f32,
+    pub noise_octaves: u32,
+    pub seed: u32,
+}
+
+impl SplatDesc {
+    // The layout of the book's terrain effect for heights in [min_height,
+    // max_height]: grass (red) on the low flat ground, dirt (green) higher up
+    // and rock (blue) on the steep slopes and peaks.
+    pub fn grass_dirt_rock(width: usize, height: usize, min_height: f32, max_height: f32) -> SplatDesc {
+        let range = max_height - min_height;
+        SplatDesc {
+            width,
+            height,
+            rules: vec![
+                SplatRule {
+                    channel: 0,
+                    max_height: min_height + 0.35 * range,
+                    height_falloff: 0.1 * range,
+                    max_slope: 0.45,
+                    slope_falloff: 0.15,
+                    noise_amplitude: 0.05 * range,
+                    ..Default::default()
+                },
+                SplatRule {
+                    channel: 1,
+                    min_height: min_height + 0.35 * range,
+                    max_height: min_height + 0.75 * range,
+                    height_falloff: 0.1 * range,
+                    max_slope: 0.7,
+                    slope_falloff: 0.15,
+                    noise_amplitude: 0.05 * range,
+                    ..Default::default()
+                },
+                SplatRule {
+                    channel: 2,
+                    min_slope: 0.7,
+                    slope_falloff: 0.15,
+                    ..Default::default()
+                },
+                SplatRule {
+                    channel: 2,
+                    min_height: min_height + 0.75 * range,
+                    height_falloff: 0.1 * range,
+                    noise_amplitude: 0.05 * range,
+                    ..Default::default()
+                },
+            ],
+            default_channel: 0,
+            noise_scale: 64.0,
+            noise_octaves: 4,
+            seed: 0,
+        }
+    }
+}
+
+// Weights of the channels, row by row, each texel adding up to one.  Row 0 is
+// the field's first row (the +z edge), like the tex-coords of
+// HeightField::build_vertices.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlendMap {
+    width: usize,
+    height: usize,
+    texels: Vec<[f32; NUM_SPLAT_CHANNELS]>,
+}
+
+impl BlendMap {
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn at(&self, x: usize, y: usize) -> [f32; NUM_SPLAT_CHANNELS] {
+        self.texels[y * self.width + x]
+    }
+
+    pub fn get_texels(&self) -> &[[f32; NUM_SPLAT_CHANNELS]] {
+        &self.texels
+    }
+
+    // 8 bits per channel in B, G, R, A order, the layout of D3DFMT_A8R8G8B8.
+    pub fn to_bgra8(&self) -> Vec<u8> {
+        let to_u8 = |w: f32| (w.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        self.texels.iter()
+            .flat_map(|t| [to_u8(t[2]), to_u8(t[1]), to_u8(t[0]), to_u8(t[3])])
+            .collect()
+    }
+
+    // Uncompressed A8R8G8B8 .dds file, which D3DXCreateTextureFromFile loads
+    // like the hand-painted blend maps.
+    pub fn write_dds<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_dds_a8r8g8b8(writer, self.width, self.height, &self.to_bgra8())
+    }
+
+    pub fn save_dds<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_dds(&mut writer)?;
+        writer.flush()
+    }
+}
+
+// Samples the field at the texel centers.  Slopes come from central
+// differences one vertex apart, which smooths out the faceting of the
+// triangles.
+pub fn generate_blend_map(field: &HeightField, desc: &SplatDesc) -> BlendMap {
+    assert!(desc.default_channel < NUM_SPLAT_CHANNELS, "No channel {}", desc.default_channel);
+    assert!(desc.rules.iter().all(|r| r.channel < NUM_SPLAT_CHANNELS), "Rules use channels 0 to 3");
+
+    let (min, max) = field.get_extent();
+    let dx = field.get_dx();
+    let dz = field.get_dz();
+
+    let mut texels = Vec::with_capacity(desc.width * desc.height);
+    for y in 0..desc.height {
+        for x in 0..desc.width {
+            let px = min.x + (x as f32 + 0.5) / desc.width as f32 * (max.x - min.x);
+            let pz = max.y - (y as f32 + 0.5) / desc.height as f32 * (max.y - min.y);
+
+            let h = field.get_height(px, pz);
+            let dhdx = (field.get_height(px + dx, pz) - field.get_height(px - dx, pz)) / (2.0 * dx);
+            let dhdz = (field.get_height(px, pz + dz) - field.get_height(px, pz - dz)) / (2.0 * dz);
+            let slope = (dhdx * dhdx + dhdz * dhdz).sqrt().atan();
+
+            let noise = fractal_noise(px / desc.noise_scale, pz / desc.noise_scale, desc.noise_octaves, desc.seed);
+
+            let mut weights = [0.0; NUM_SPLAT_CHANNELS];
+            for rule in &desc.rules {
+                weights[rule.channel] += rule.get_weight(h + noise * rule.noise_amplitude, slope);
+            }
+
+            let total: f32 = weights.iter().sum();
+            if total > 0.0 {
+                for w in weights.iter_mut() {
+                    *w /= total;
+                }
+            } else {
+                weights[desc.default_channel] = 1.0;
+            }
+            texels.push(weights);
+        }
+    }
+
+    BlendMap { width: desc.width, height: desc.height, texels }
+}
+
+// The header of an uncompressed A8R8G8B8 .dds file and the texels, 'bgra' in
+// the layout of D3DFMT_A8R8G8B8.
+pub(crate) fn write_dds_a8r8g8b8<W: Write>(writer: &mut W, width: usize, height: usize, bgra: &[u8]) -> io::Result<()> {
+    const DDSD_CAPS: u32 = 0x1;
+    const DDSD_HEIGHT: u32 = 0x2;
+    const DDSD_WIDTH: u32 = 0x4;
+    const DDSD_PITCH: u32 = 0x8;
+    const DDSD_PIXELFORMAT: u32 = 0x1000;
+    const DDPF_ALPHAPIXELS: u32 = 0x1;
+    const DDPF_RGB: u32 = 0x40;
+    const DDSCAPS_TEXTURE: u32 = 0x1000;
+
+    let mut header: Vec<u32> = vec![
+        124,
+        DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT,
+        height as u32,
+        width as u32,
+        width as u32 * 4,
+        0,
+        0,
+    ];
+    header.extend_from_slice(&[0; 11]);
+    header.extend_from_slice(&[32, DDPF_RGB | DDPF_ALPHAPIXELS, 0, 32,
+                               0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0xFF00_0000]);
+    header.extend_from_slice(&[DDSCAPS_TEXTURE, 0, 0, 0, 0]);
+
+    writer.write_all(b"DDS ")?;
+    for v in header {
+        writer.write_all(&v.to_le_bytes())?;
+    }
+    writer.write_all(bgra)
+}
+
+// 1 inside [lo, hi], easing to 0 at 'falloff' outside.
+fn band(v: f32, lo: f32, hi: f32, falloff: f32) -> f32 {
+    let outside = (lo - v).max(v - hi);
+    if outside <= 0.0 {
+        1.0
+    } else if outside >= falloff {
+        0.0
+    } else {
+        let t = 1.0 - outside / falloff;
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+// Value noise in [-1, 1] summed over octaves of halving size and amplitude.
+pub(crate) fn fractal_noise(x: f32, z: f32, octaves: u32, seed: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    let mut total = 0.0;
+    for octave in 0..octaves {
+        sum += amplitude * value_noise(x * frequency, z * frequency, seed.wrapping_add(octave));
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    if total > 0.0 { sum / total } else { 0.0 }
+}
+
+fn value_noise(x: f32, z: f32, seed: u32) -> f32 {
+    let x0 = x.floor();
+    let z0 = z.floor();
+    let s = smooth(x - x0);
+    let t = smooth(z - z0);
+
+    let (i, j) = (x0 as i32, z0 as i32);
+    let a = lattice(i, j, seed);
+    let b = lattice(i + 1, j, seed);
+    let c = lattice(i, j + 1, seed);
+    let d = lattice(i + 1, j + 1, seed);
+
+    let ab = a + s * (b - a);
+    let cd = c + s * (d - c);
+    ab + t * (cd - ab)
+}
+
+fn smooth(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Hash of a lattice point to [-1, 1].
+fn lattice(i: i32, j: i32, seed: u32) -> f32 {
+    let mut h = (i as u32).wrapping_mul(0x8DA6_B343)
+        ^ (j as u32).wrapping_mul(0xD816_3841)
+        ^ seed.wrapping_mul(0xCB1A_B31F);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5BD1_E995);
+    h ^= h >> 15;
+    (h & 0xFFFF) as f32 / 32767.5 - 1.0
+}
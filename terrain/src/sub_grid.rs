@@ -0,0 +1,200 @@
+// Partition of a height field into sub-grids, the units the terrain is culled
+// and drawn by.  Neighbouring sub-grids share their border vertices so the
+// meshes meet without cracks.
+//
+// With the default size, a 513x257 field gives 512/32 x 256/32 = 16x8
+// sub-grids.  Fields whose cells do not divide evenly get narrower sub-grids
+// along their last row and column.
+
+use geometry::*;
+use crate::height_field::*;
+
+// Vertices along each side of a sub-grid: 33x33 vertices keep 16-bit indices
+// and cull at a useful granularity.
+pub const DEFAULT_SUB_GRID_SIZE: usize = 33;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SubGrid {
+    // First vertex row and column in the height field.
+    pub row: usize,
+    pub col: usize,
+    // Vertices along each side.
+    pub num_rows: usize,
+    pub num_cols: usize,
+
+    pub bounds: Aabb,
+}
+
+impl SubGrid {
+    pub fn get_rect(&self) -> VertexRect {
+        VertexRect::new(self.row, self.col, self.num_rows, self.num_cols)
+    }
+
+    // Bounds of the sub-grid's vertices, after their heights changed.
+    pub fn update_bounds(&mut self, field: &HeightField) {
+        self.bounds = Aabb::EMPTY;
+        for i in self.row..self.row + self.num_rows {
+            for j in self.col..self.col + self.num_cols {
+                self.bounds.grow(field.get_vertex_pos(i, j));
+            }
+        }
+    }
+
+    // The sub-grid's part of the field's vertices (see
+    // HeightField::build_vertices), triangulated like the field and ordered for
+    // the vertex cache.
+    pub fn build_mesh(&self, field: &HeightField, field_vertices: &[VertexPNT]) -> Mesh<VertexPNT> {
+        let mut vertices = Vec::with_capacity(self.num_rows * self.num_cols);
+        for i in self.row..self.row + self.num_rows {
+            let first = i * field.num_cols() + self.col;
+            vertices.extend_from_slice(&field_vertices[first..first + self.num_cols]);
+        }
+
+        let indices = match build_tri_grid::<Vec3, u32>(self.num_rows as u32, self.num_cols as u32,
+                                                         field.get_dx(), field.get_dz(), &GridOptions::default()) {
+            Ok((_, indices)) => indices,
+            Err(e) => panic!("SubGrid::build_mesh: {}", e),
+        };
+
+        let mut mesh = Mesh::new(vertices, indices);
+        optimize_vertex_cache(&mut mesh);
+        mesh
+    }
+}
+
+// Cuts the field into sub-grids of at most size x size vertices, row by row.
+pub fn build_sub_grids(field: &HeightField, size: usize) -> Vec<SubGrid> {
+    assert!(size >= 2, "A sub-grid needs at least one cell");
+
+    let cells = size - 1;
+    let num_cell_rows = field.num_rows() - 1;
+    let num_cell_cols = field.num_cols() - 1;
+
+    let mut sub_grids = Vec::new();
+    for row in (0..num_cell_rows).step_by(cells) {
+        for col in (0..num_cell_cols).step_by(cells) {
+            let num_rows = cells.min(num_cell_rows - row) + 1;
+            let num_cols = cells.min(num_cell_cols - col) + 1;
+
+            let mut sub_grid = SubGrid { row, col, num_rows, num_cols, bounds: Aabb::EMPTY };
+            sub_grid.update_bounds(field);
+            sub_grids.push(sub_grid);
+        }
+    }
+    sub_grids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heightmap::*;
+
+    fn flat(num_rows: usize, num_cols: usize) -> HeightField {
+        HeightField::new(Heightmap::new(num_rows, num_cols), 1.0, 1.0)
+    }
+
+    // Every cell of the field in exactly one sub-grid, and every vertex in
+    // exactly the sub-grids owning the cells around it, so neighbours share
+    // their borders.
+    fn check_partition(field: &HeightField, sub_grids: &[SubGrid]) {
+        let (num_rows, num_cols) = (field.num_rows(), field.num_cols());
+        let mut owners = vec![Vec::new(); (num_rows - 1) * (num_cols - 1)];
+        for (k, sg) in sub_grids.iter().enumerate() {
+            assert!(sg.num_rows >= 2 && sg.num_cols >= 2);
+            assert!(sg.row + sg.num_rows <= num_rows && sg.col + sg.num_cols <= num_cols);
+            for i in sg.row..sg.row + sg.num_rows - 1 {
+                for j in sg.col..sg.col + sg.num_cols - 1 {
+                    owners[i * (num_cols - 1) + j].push(k);
+                }
+            }
+        }
+        assert!(owners.iter().all(|o| o.len() == 1));
+
+        for i in 0..num_rows {
+            for j in 0..num_cols {
+                let mut around = Vec::new();
+                for ci in i.saturating_sub(1)..=i.min(num_rows - 2) {
+                    for cj in j.saturating_sub(1)..=j.min(num_cols - 2) {
+                        around.push(owners[ci * (num_cols - 1) + cj][0]);
+                    }
+                }
+                around.sort_unstable();
+                around.dedup();
+
+                let holding: Vec<_> = (0..sub_grids.len()).filter(|k| sub_grids[*k].get_rect().contains(i, j)).collect();
+                assert_eq!(holding, around, "vertex ({}, {})", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn power_of_two_plus_one() {
+        let field = flat(65, 129);
+        let sub_grids = build_sub_grids(&field, DEFAULT_SUB_GRID_SIZE);
+        assert_eq!(sub_grids.len(), 2 * 4);
+        assert!(sub_grids.iter().all(|sg| sg.num_rows == 33 && sg.num_cols == 33));
+        check_partition(&field, &sub_grids);
+
+        // Row by row, with neighbours starting on each other's last vertex.
+        let starts: Vec<_> = sub_grids.iter().map(|sg| (sg.row, sg.col)).collect();
+        assert_eq!(starts, vec![(0, 0), (0, 32), (0, 64), (0, 96), (32, 0), (32, 32), (32, 64), (32, 96)]);
+        assert_eq!(sub_grids[0].col + sub_grids[0].num_cols - 1, sub_grids[1].col);
+        assert_eq!(sub_grids[0].row + sub_grids[0].num_rows - 1, sub_grids[4].row);
+
+        // The corner vertex of four sub-grids is in all four.
+        let corner = sub_grids.iter().filter(|sg| sg.get_rect().contains(32, 32)).count();
+        assert_eq!(corner, 4);
+
+        let field = flat(9, 9);
+        let sub_grids = build_sub_grids(&field, 3);
+        assert_eq!(sub_grids.len(), 16);
+        check_partition(&field, &sub_grids);
+    }
+
+    #[test]
+    fn other_sizes() {
+        // 69 cell rows and 39 cell columns: 32 + 32 + 5 by 32 + 7.
+        let field = flat(70, 40);
+        let sub_grids = build_sub_grids(&field, DEFAULT_SUB_GRID_SIZE);
+        let sizes: Vec<_> = sub_grids.iter().map(|sg| (sg.row, sg.col, sg.num_rows, sg.num_cols)).collect();
+        assert_eq!(sizes, vec![(0, 0, 33, 33), (0, 32, 33, 8), (32, 0, 33, 33), (32, 32, 33, 8),
+                               (64, 0, 6, 33), (64, 32, 6, 8)]);
+        check_partition(&field, &sub_grids);
+
+        // A field smaller than one sub-grid is one sub-grid, and a single cell
+        // left over makes a sub-grid two vertices wide.
+        let field = flat(10, 7);
+        let sub_grids = build_sub_grids(&field, DEFAULT_SUB_GRID_SIZE);
+        assert_eq!(sizes_of(&sub_grids), vec![(10, 7)]);
+        let field = flat(6, 5);
+        let sub_grids = build_sub_grids(&field, 5);
+        assert_eq!(sizes_of(&sub_grids), vec![(5, 5), (2, 5)]);
+        check_partition(&field, &sub_grids);
+
+        let field = flat(31, 17);
+        check_partition(&field, &build_sub_grids(&field, 4));
+    }
+
+    fn sizes_of(sub_grids: &[SubGrid]) -> Vec<(usize, usize)> {
+        sub_grids.iter().map(|sg| (sg.num_rows, sg.num_cols)).collect()
+    }
+
+    #[test]
+    fn bounds() {
+        let mut map = Heightmap::new(5, 5);
+        map.set(1, 1, 3.0);
+        map.set(4, 4, -2.0);
+        let field = HeightField::new(map, 2.0, 1.0);
+        let sub_grids = build_sub_grids(&field, 3);
+
+        // The field spans x in [-4, 4] and z in [-2, 2].
+        assert_eq!(sub_grids[0].bounds, Aabb::new(Vec3::new(-4.0, 0.0, 0.0), Vec3::new(0.0, 3.0, 2.0)));
+        assert_eq!(sub_grids[3].bounds, Aabb::new(Vec3::new(0.0, -2.0, -2.0), Vec3::new(4.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn size_without_cells() {
+        build_sub_grids(&flat(5, 5), 1);
+    }
+}
@@ -0,0 +1,712 @@
+// A world of heightmap tiles on a grid, paged in around the eye and out again
+// under a memory budget.  Tiles are loaded and built by worker threads; update
+// picks up the finished ones each frame and reports what changed.
+//
+// Tile (x, z) covers [x*W, (x+1)*W] x [z*D, (z+1)*D] of the xz-plane, with the
+// same tile_size x tile_size vertices, so neighbouring tiles share their border
+// vertices; the tile source has to give them the same heights there.  Height
+// queries then agree along the borders, vertex normals are taken across them
+// once both tiles are in, and the sub-grids of all the resident tiles are
+// selected as one geomipmap, stitched across the tiles.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::read;
+use std::io;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use controllers::Ground;
+use geometry::*;
+use crate::geomipmap::*;
+use crate::height_field::*;
+use crate::heightmap::*;
+use crate::sub_grid::*;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TileCoord {
+    // Tile column, towards +x.
+    pub x: i32,
+    // Tile row, towards +z.
+    pub z: i32,
+}
+
+impl TileCoord {
+    pub fn new(x: i32, z: i32) -> TileCoord {
+        TileCoord { x, z }
+    }
+}
+
+// Where the tiles' heights come from.  Called from the worker threads; a
+// panic is reported as a failed tile.
+pub trait TileSource: Send + Sync {
+    // The tile_size x tile_size heights of the tile, or None where the world
+    // has no tile.
+    fn load_tile(&self, coord: TileCoord, tile_size: usize) -> io::Result<Option<Heightmap>>;
+}
+
+impl<F> TileSource for F
+    where F: Fn(TileCoord, usize) -> io::Result<Option<Heightmap>> + Send + Sync {
+    fn load_tile(&self, coord: TileCoord, tile_size: usize) -> io::Result<Option<Heightmap>> {
+        self(coord, tile_size)
+    }
+}
+
+// 8-bit RAW tiles named tile_<x>_<z>.raw in a directory; missing files are
+// missing tiles.  The heights are not smoothed like Heightmap::load_raw's,
+// which would pull the borders of neighbouring tiles apart.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawTileSource {
+    pub dir: PathBuf,
+    pub height_scale: f32,
+    pub height_offset: f32,
+}
+
+impl TileSource for RawTileSource {
+    fn load_tile(&self, coord: TileCoord, tile_size: usize) -> io::Result<Option<Heightmap>> {
+        let path = self.dir.join(format!("tile_{}_{}.raw", coord.x, coord.z));
+        let data = match read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let num_heights = tile_size * tile_size;
+        if data.len() < num_heights {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("{} bytes of RAW data for a {}x{} tile", data.len(), tile_size, tile_size)));
+        }
+        let heights = data[..num_heights].iter().map(|h| *h as f32 * self.height_scale + self.height_offset).collect();
+        Ok(Some(Heightmap::from_heights(tile_size, tile_size, heights)))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TiledWorldDesc {
+    // Vertices along each side of a tile, 2^n + 1 for the most levels of detail.
+    pub tile_size: usize,
+    // Spacing of the vertices.
+    pub dx: f32,
+    pub dz: f32,
+    pub sub_grid_size: usize,
+
+    // Tiles closer than load_radius to the eye (in xz) are loaded; tiles
+    // farther than evict_radius are dropped.  The gap keeps tiles near the
+    // boundary from being loaded and dropped over and over.
+    pub load_radius: f32,
+    pub evict_radius: f32,
+    // Bytes of resident and loading tiles, see get_tile_memory_size.  The
+    // nearest tiles win when the budget is short.
+    pub memory_budget: usize,
+
+    // 0 loads the tiles on the thread calling update.
+    pub num_workers: usize,
+}
+
+impl Default for TiledWorldDesc {
+    fn default() -> Self {
+        TiledWorldDesc {
+            tile_size: 257,
+            dx: 1.0,
+            dz: 1.0,
+            sub_grid_size: DEFAULT_SUB_GRID_SIZE,
+            load_radius: 512.0,
+            evict_radius: 640.0,
+            memory_budget: 256 << 20,
+            num_workers: 2,
+        }
+    }
+}
+
+impl TiledWorldDesc {
+    pub fn get_tile_width(&self) -> f32 {
+        (self.tile_size - 1) as f32 * self.dx
+    }
+
+    pub fn get_tile_depth(&self) -> f32 {
+        (self.tile_size - 1) as f32 * self.dz
+    }
+
+    // The tile under (x, z); points on a border belong to the tile on its +x or
+    // +z side.
+    pub fn get_tile_coord(&self, x: f32, z: f32) -> TileCoord {
+        TileCoord::new((x / self.get_tile_width()).floor() as i32, (z / self.get_tile_depth()).floor() as i32)
+    }
+
+    // The tile's minimum and maximum corners in x and z.
+    pub fn get_tile_extent(&self, coord: TileCoord) -> (Vec2, Vec2) {
+        let w = self.get_tile_width();
+        let d = self.get_tile_depth();
+        (Vec2::new(coord.x as f32 * w, coord.z as f32 * d), Vec2::new((coord.x + 1) as f32 * w, (coord.z + 1) as f32 * d))
+    }
+
+    // Distance in xz from (x, z) to the nearest point of the tile.
+    pub fn get_tile_distance(&self, coord: TileCoord, x: f32, z: f32) -> f32 {
+        let (min, max) = self.get_tile_extent(coord);
+        let ex = (min.x - x).max(x - max.x).max(0.0);
+        let ez = (min.y - z).max(z - max.y).max(0.0);
+        (ex * ex + ez * ez).sqrt()
+    }
+
+    // Heights and vertices of a tile, what the memory budget counts.
+    pub fn get_tile_memory_size(&self) -> usize {
+        self.tile_size * self.tile_size * (std::mem::size_of::<f32>() + std::mem::size_of::<VertexPNT>())
+    }
+}
+
+// A loaded tile: its height field, offset to the tile's place in the world,
+// its vertices and its sub-grids, in world space.
+pub struct Tile {
+    coord: TileCoord,
+    field: HeightField,
+    // Of the field's origin.
+    center: Vec2,
+    vertices: Vec<VertexPNT>,
+    geomipmap: Geomipmap,
+}
+
+impl Tile {
+    // Normals along the borders only see this tile's heights until
+    // TiledWorld takes them across to the neighbours.
+    pub fn new(coord: TileCoord, heightmap: Heightmap, desc: &TiledWorldDesc) -> Tile {
+        let (min, max) = desc.get_tile_extent(coord);
+        let center = (min + max) * 0.5;
+        let field = HeightField::new(heightmap, desc.dx, desc.dz);
+
+        let n = field.num_cols();
+        let mut vertices = field.build_vertices();
+        for (k, v) in vertices.iter_mut().enumerate() {
+            v.pos.x += center.x;
+            v.pos.z += center.y;
+            let (i, j) = ((k / n) as i64, (k % n) as i64);
+            v.normal = get_vertex_normal(|i, j| get_local_height(&field, i, j), i, j, desc.dx, desc.dz);
+        }
+
+        let mut sub_grids = build_sub_grids(&field, desc.sub_grid_size);
+        let offset = Vec3::new(center.x, 0.0, center.y);
+        for s in sub_grids.iter_mut() {
+            s.bounds.min += offset;
+            s.bounds.max += offset;
+        }
+        let geomipmap = Geomipmap::new(&field, sub_grids);
+
+        Tile { coord, field, center, vertices, geomipmap }
+    }
+
+    pub fn get_coord(&self) -> TileCoord {
+        self.coord
+    }
+
+    // In the tile's own space, centered on get_center.
+    pub fn get_field(&self) -> &HeightField {
+        &self.field
+    }
+
+    pub fn get_center(&self) -> Vec2 {
+        self.center
+    }
+
+    // Row by row, in world space, with tex-coords going [0, 1] across the tile.
+    pub fn get_vertices(&self) -> &[VertexPNT] {
+        &self.vertices
+    }
+
+    // Sub-grids with world space bounds.
+    pub fn get_geomipmap(&self) -> &Geomipmap {
+        &self.geomipmap
+    }
+
+    pub fn get_bounds(&self) -> Aabb {
+        let mut bounds = self.field.get_bounds();
+        let offset = Vec3::new(self.center.x, 0.0, self.center.y);
+        bounds.min += offset;
+        bounds.max += offset;
+        bounds
+    }
+
+    pub fn get_height(&self, x: f32, z: f32) -> f32 {
+        self.field.get_height(x - self.center.x, z - self.center.y)
+    }
+
+    pub fn get_normal(&self, x: f32, z: f32) -> Vec3 {
+        self.field.get_normal(x - self.center.x, z - self.center.y)
+    }
+}
+
+// What an update changed.
+#[derive(Debug, Default)]
+pub struct TileChanges {
+    pub loaded: Vec<TileCoord>,
+    // Resident tiles whose border normals changed for newly loaded neighbours.
+    pub updated: Vec<TileCoord>,
+    pub evicted: Vec<TileCoord>,
+    // Tiles that failed to load, or whose worker is gone; they are not
+    // requested again.
+    pub failed: Vec<(TileCoord, io::Error)>,
+}
+
+impl TileChanges {
+    pub fn is_empty(&self) -> bool {
+        self.loaded.is_empty() && self.updated.is_empty() && self.evicted.is_empty() && self.failed.is_empty()
+    }
+}
+
+type TileResult = (TileCoord, io::Result<Option<Tile>>);
+
+pub struct TiledWorld {
+    desc: TiledWorldDesc,
+    source: Arc<dyn TileSource>,
+
+    tiles: HashMap<TileCoord, Tile>,
+    // Requested from the workers.
+    pending: HashSet<TileCoord>,
+    // Not in the source, or failed to load.
+    missing: HashSet<TileCoord>,
+
+    requests: Option<Sender<TileCoord>>,
+    results: Receiver<TileResult>,
+    workers: Vec<JoinHandle<()>>,
+
+    // The sub-grids of all the resident tiles, and the tile and index in the
+    // tile's geomipmap of each.
+    geomipmap: Geomipmap,
+    sub_grid_tiles: Vec<(TileCoord, usize)>,
+}
+
+impl TiledWorld {
+    pub fn new(desc: TiledWorldDesc, source: Arc<dyn TileSource>) -> TiledWorld {
+        assert!(desc.tile_size >= 2, "A tile needs at least one cell");
+        assert!(desc.evict_radius >= desc.load_radius, "Tiles would be evicted as soon as they are loaded");
+
+        let (request_sender, request_receiver) = channel::<TileCoord>();
+        let (result_sender, results) = channel::<TileResult>();
+        let request_receiver = Arc::new(Mutex::new(request_receiver));
+
+        let workers = (0..desc.num_workers)
+            .map(|_| {
+                let requests = request_receiver.clone();
+                let results = result_sender.clone();
+                let source = source.clone();
+                thread::spawn(move || loop {
+                    // The lock only hands out the requests; loading runs unlocked.
+                    let next = requests.lock().map_err(|_| ()).and_then(|r| r.recv().map_err(|_| ()));
+                    let coord = match next {
+                        Ok(coord) => coord,
+                        Err(_) => break,
+                    };
+                    // A panicking source still answers the request, so nothing
+                    // waits on it.
+                    let result = catch_unwind(AssertUnwindSafe(|| load_tile(source.as_ref(), coord, &desc)))
+                        .unwrap_or_else(|_| Err(io::Error::other(
+                            format!("Loading tile ({}, {}) panicked", coord.x, coord.z))));
+                    if results.send((coord, result)).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        TiledWorld {
+            desc,
+            source,
+
+            tiles: HashMap::new(),
+            pending: HashSet::new(),
+            missing: HashSet::new(),
+
+            requests: Some(request_sender),
+            results,
+            workers,
+
+            geomipmap: Geomipmap::from_parts(Vec::new(), Vec::new(), Vec::new()),
+            sub_grid_tiles: Vec::new(),
+        }
+    }
+
+    pub fn get_desc(&self) -> &TiledWorldDesc {
+        &self.desc
+    }
+
+    pub fn get_tile(&self, coord: TileCoord) -> Option<&Tile> {
+        self.tiles.get(&coord)
+    }
+
+    pub fn get_tiles(&self) -> impl Iterator<Item = &Tile> {
+        self.tiles.values()
+    }
+
+    pub fn get_num_tiles(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn get_num_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn get_memory_size(&self) -> usize {
+        self.tiles.len() * self.desc.get_tile_memory_size()
+    }
+
+    // The sub-grids of all the resident tiles as one geomipmap, with world
+    // space bounds.  Each sub-grid's rows and columns are in its own tile's
+    // field; see get_sub_grid_tile.
+    pub fn get_geomipmap(&self) -> &Geomipmap {
+        &self.geomipmap
+    }
+
+    // The tile of one of get_geomipmap's sub-grids, and the sub-grid's index in
+    // the tile's own geomipmap.
+    pub fn get_sub_grid_tile(&self, i: usize) -> (TileCoord, usize) {
+        self.sub_grid_tiles[i]
+    }
+
+    // Heights of the sub-grid's vertices morphed for 'lods' from
+    // get_geomipmap().select, as Geomipmap::morph_heights.
+    pub fn morph_heights(&self, lods: &[SubGridLod], i: usize) -> Vec<f32> {
+        let (coord, _) = self.sub_grid_tiles[i];
+        self.geomipmap.morph_heights(self.tiles[&coord].get_field(), lods, i)
+    }
+
+    // Takes the tiles the workers finished, evicts the ones too far away or
+    // over the budget, and requests the missing ones near 'eye', nearest first.
+    pub fn update(&mut self, eye: Vec3) -> TileChanges {
+        let mut changes = TileChanges::default();
+        loop {
+            match self.results.try_recv() {
+                Ok(result) => self.receive(result, eye, &mut changes),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.fail_pending(&mut changes);
+                    break;
+                }
+            }
+        }
+        self.page(eye, &mut changes);
+        changes
+    }
+
+    // Like update, but waits for every requested tile, so the tiles around
+    // 'eye' are in when it returns.  For the first frame.  Requests are given
+    // up as failed if the workers are gone.
+    pub fn update_blocking(&mut self, eye: Vec3) -> TileChanges {
+        let mut changes = self.update(eye);
+        while !self.pending.is_empty() {
+            match self.results.recv() {
+                Ok(result) => self.receive(result, eye, &mut changes),
+                Err(_) => self.fail_pending(&mut changes),
+            }
+        }
+        changes.updated.retain(|c| !changes.loaded.contains(c));
+        self.rebuild_geomipmap();
+        changes
+    }
+
+    // Height of the surface over (x, z); 0 where no tile is resident.
+    pub fn get_height(&self, x: f32, z: f32) -> f32 {
+        self.try_get_height(x, z).unwrap_or(0.0)
+    }
+
+    // The minimum and maximum corners in x and z of the rectangle around the
+    // resident tiles, None when there are none.  Tiles missing inside it have
+    // height 0.
+    pub fn get_resident_extent(&self) -> Option<(Vec2, Vec2)> {
+        self.tiles.keys()
+            .map(|c| self.desc.get_tile_extent(*c))
+            .reduce(|(min0, max0), (min1, max1)| {
+                (Vec2::new(min0.x.min(min1.x), min0.y.min(min1.y)), Vec2::new(max0.x.max(max1.x), max0.y.max(max1.y)))
+            })
+    }
+
+    // None where no tile is resident.
+    pub fn try_get_height(&self, x: f32, z: f32) -> Option<f32> {
+        self.tiles.get(&self.desc.get_tile_coord(x, z)).map(|tile| tile.get_height(x, z))
+    }
+
+    // Upward unit normal of the triangle under (x, z); straight up where no
+    // tile is resident.
+    pub fn get_normal(&self, x: f32, z: f32) -> Vec3 {
+        match self.tiles.get(&self.desc.get_tile_coord(x, z)) {
+            Some(tile) => tile.get_normal(x, z),
+            None => Vec3::new(0.0, 1.0, 0.0),
+        }
+    }
+
+    fn receive(&mut self, (coord, result): TileResult, eye: Vec3, changes: &mut TileChanges) {
+        self.pending.remove(&coord);
+        match result {
+            // Dropped when the eye moved away while it loaded.
+            Ok(Some(tile)) if self.desc.get_tile_distance(coord, eye.x, eye.z) <= self.desc.evict_radius => {
+                self.tiles.insert(coord, tile);
+                let updated = self.stitch_normals(coord);
+                changes.loaded.push(coord);
+                for c in updated {
+                    if !changes.updated.contains(&c) {
+                        changes.updated.push(c);
+                    }
+                }
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                self.missing.insert(coord);
+            }
+            Err(err) => {
+                self.missing.insert(coord);
+                changes.failed.push((coord, err));
+            }
+        }
+    }
+
+    // With no worker left to answer them, the pending requests fail.
+    fn fail_pending(&mut self, changes: &mut TileChanges) {
+        for coord in self.pending.drain() {
+            self.missing.insert(coord);
+            changes.failed.push((coord, io::Error::new(io::ErrorKind::BrokenPipe,
+                format!("No worker left to load tile ({}, {})", coord.x, coord.z))));
+        }
+    }
+
+    fn page(&mut self, eye: Vec3, changes: &mut TileChanges) {
+        let max_tiles = (self.desc.memory_budget / self.desc.get_tile_memory_size()).max(1);
+        let distance = |c: TileCoord| self.desc.get_tile_distance(c, eye.x, eye.z);
+
+        // The tiles within the load radius, nearest first, as many as the
+        // budget allows.
+        let r = self.desc.load_radius;
+        let min = self.desc.get_tile_coord(eye.x - r, eye.z - r);
+        let max = self.desc.get_tile_coord(eye.x + r, eye.z + r);
+        let mut wanted: Vec<TileCoord> = (min.z..=max.z)
+            .flat_map(|z| (min.x..=max.x).map(move |x| TileCoord::new(x, z)))
+            .filter(|c| distance(*c) <= r && !self.missing.contains(c))
+            .collect();
+        wanted.sort_by(|a, b| distance(*a).total_cmp(&distance(*b)));
+        wanted.truncate(max_tiles);
+
+        // Evict the unwanted tiles beyond the evict radius, then the farthest
+        // ones until the wanted tiles fit.
+        let mut unwanted: Vec<TileCoord> = self.tiles.keys().filter(|c| !wanted.contains(c)).copied().collect();
+        unwanted.sort_by(|a, b| distance(*b).total_cmp(&distance(*a)));
+        let num_wanted_out = wanted.iter().filter(|c| !self.tiles.contains_key(c) && !self.pending.contains(c)).count();
+        for c in unwanted {
+            let over_budget = self.tiles.len() + self.pending.len() + num_wanted_out > max_tiles;
+            if distance(c) > self.desc.evict_radius || over_budget {
+                self.tiles.remove(&c);
+                changes.evicted.push(c);
+            }
+        }
+
+        for c in wanted {
+            if self.tiles.contains_key(&c) || self.pending.contains(&c) {
+                continue;
+            }
+            if self.tiles.len() + self.pending.len() >= max_tiles {
+                break;
+            }
+            // Loaded here when there are no workers, or none left.
+            let sent = match &self.requests {
+                Some(requests) if !self.workers.is_empty() => requests.send(c).is_ok(),
+                _ => false,
+            };
+            self.pending.insert(c);
+            if !sent {
+                let result = load_tile(self.source.as_ref(), c, &self.desc);
+                self.receive((c, result), eye, changes);
+            }
+        }
+
+        changes.updated.retain(|c| !changes.loaded.contains(c) && !changes.evicted.contains(c));
+        if !changes.loaded.is_empty() || !changes.evicted.is_empty() {
+            self.rebuild_geomipmap();
+        }
+    }
+
+    // Height of a vertex of the world's grid from whichever resident tile has
+    // it.  Vertex (i, j) is at x = j*dx, z = -i*dz.
+    fn get_vertex_height(&self, i: i64, j: i64) -> Option<f32> {
+        let m = (self.desc.tile_size - 1) as i64;
+        let (tile_col, col) = (j.div_euclid(m), j.rem_euclid(m));
+        let (tile_row, row) = (i.div_euclid(m), i.rem_euclid(m));
+
+        // A border vertex is also the last one of the tile before it.
+        let mut cols = vec![(tile_col, col)];
+        if col == 0 {
+            cols.push((tile_col - 1, m));
+        }
+        let mut rows = vec![(-tile_row - 1, row)];
+        if row == 0 {
+            rows.push((-tile_row, m));
+        }
+
+        for (tz, row) in &rows {
+            for (tx, col) in &cols {
+                if let Some(tile) = self.tiles.get(&TileCoord::new(*tx as i32, *tz as i32)) {
+                    return Some(tile.get_field().get_heightmap().at(*row as usize, *col as usize));
+                }
+            }
+        }
+        None
+    }
+
+    // Recomputes the border normals of a new tile and of its resident
+    // neighbours, now that heights are known across their shared borders.
+    // Returns the neighbours.
+    fn stitch_normals(&mut self, coord: TileCoord) -> Vec<TileCoord> {
+        let mut stitched = Vec::new();
+        for dz in -1..=1 {
+            for dx in -1..=1 {
+                let c = TileCoord::new(coord.x + dx, coord.z + dz);
+                if !self.tiles.contains_key(&c) {
+                    continue;
+                }
+
+                let normals = self.get_border_normals(c);
+                if let Some(tile) = self.tiles.get_mut(&c) {
+                    for (k, normal) in normals {
+                        tile.vertices[k].normal = normal;
+                    }
+                }
+                if c != coord {
+                    stitched.push(c);
+                }
+            }
+        }
+        stitched
+    }
+
+    fn get_border_normals(&self, coord: TileCoord) -> Vec<(usize, Vec3)> {
+        let n = self.desc.tile_size;
+        let m = (n - 1) as i64;
+        let first_row = -(coord.z as i64 + 1) * m;
+        let first_col = coord.x as i64 * m;
+
+        (0..n * n)
+            .filter(|k| {
+                let (i, j) = (k / n, k % n);
+                i == 0 || j == 0 || i == n - 1 || j == n - 1
+            })
+            .map(|k| {
+                let (i, j) = ((k / n) as i64, (k % n) as i64);
+                let normal = get_vertex_normal(|i, j| self.get_vertex_height(i, j), first_row + i, first_col + j,
+                                               self.desc.dx, self.desc.dz);
+                (k, normal)
+            })
+            .collect()
+    }
+
+    // One geomipmap of all the resident tiles' sub-grids, with the sub-grids
+    // along a tile's border linked to the ones across it.
+    fn rebuild_geomipmap(&mut self) {
+        let mut coords: Vec<TileCoord> = self.tiles.keys().copied().collect();
+        coords.sort_by_key(|c| (c.z, c.x));
+
+        let mut first = HashMap::new();
+        let mut sub_grid_tiles = Vec::new();
+        for c in &coords {
+            first.insert(*c, sub_grid_tiles.len());
+            let num_sub_grids = self.tiles[c].get_geomipmap().get_sub_grids().len();
+            sub_grid_tiles.extend((0..num_sub_grids).map(|i| (*c, i)));
+        }
+
+        let last = self.desc.tile_size - 1;
+        let mut sub_grids = Vec::with_capacity(sub_grid_tiles.len());
+        let mut errors = Vec::with_capacity(sub_grid_tiles.len());
+        let mut neighbours = Vec::with_capacity(sub_grid_tiles.len());
+        for (c, i) in &sub_grid_tiles {
+            let geomipmap = self.tiles[c].get_geomipmap();
+            let s = geomipmap.get_sub_grids()[*i];
+            sub_grids.push(s);
+            errors.push(geomipmap.get_errors(*i).to_vec());
+
+            // Across the top, right, bottom and left borders: the tiles on the
+            // +z, +x, -z and -x sides, and which of their sub-grids meets s.
+            let across = [
+                TileCoord::new(c.x, c.z + 1),
+                TileCoord::new(c.x + 1, c.z),
+                TileCoord::new(c.x, c.z - 1),
+                TileCoord::new(c.x - 1, c.z),
+            ];
+            let meets = |edge: usize, n: &SubGrid| match edge {
+                0 => n.col == s.col && n.row + n.num_rows - 1 == last,
+                1 => n.row == s.row && n.col == 0,
+                2 => n.col == s.col && n.row == 0,
+                _ => n.row == s.row && n.col + n.num_cols - 1 == last,
+            };
+
+            let own = geomipmap.get_neighbours(*i);
+            let mut linked = [None; 4];
+            for (edge, tile) in across.iter().enumerate() {
+                linked[edge] = match own[edge] {
+                    Some(n) => Some(first[c] + n),
+                    None => self.tiles.get(tile).and_then(|t| {
+                        t.get_geomipmap().get_sub_grids().iter().position(|n| meets(edge, n)).map(|n| first[tile] + n)
+                    }),
+                };
+            }
+            neighbours.push(linked);
+        }
+
+        self.geomipmap = Geomipmap::from_parts(sub_grids, errors, neighbours);
+        self.sub_grid_tiles = sub_grid_tiles;
+    }
+}
+
+impl Drop for TiledWorld {
+    // Closing the request channel stops the workers after their current tile.
+    fn drop(&mut self) {
+        self.requests = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+// The ground ends at the resident tiles, so a character stops where the tiles
+// have not loaded yet instead of walking onto height 0.
+impl Ground for TiledWorld {
+    fn get_height(&self, x: f32, z: f32) -> f32 {
+        TiledWorld::get_height(self, x, z)
+    }
+
+    fn get_extent(&self) -> Option<(Vec2, Vec2)> {
+        self.get_resident_extent()
+    }
+
+    fn get_normal(&self, x: f32, z: f32) -> Vec3 {
+        TiledWorld::get_normal(self, x, z)
+    }
+}
+
+fn load_tile(source: &dyn TileSource, coord: TileCoord, desc: &TiledWorldDesc) -> io::Result<Option<Tile>> {
+    match source.load_tile(coord, desc.tile_size)? {
+        Some(heightmap) if heightmap.num_rows() != desc.tile_size || heightmap.num_cols() != desc.tile_size => {
+            Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("Tile ({}, {}) is {}x{}, not {}x{}", coord.x, coord.z, heightmap.num_rows(), heightmap.num_cols(),
+                        desc.tile_size, desc.tile_size)))
+        }
+        Some(heightmap) => Ok(Some(Tile::new(coord, heightmap, desc))),
+        None => Ok(None),
+    }
+}
+
+fn get_local_height(field: &HeightField, i: i64, j: i64) -> Option<f32> {
+    if i < 0 || j < 0 || i >= field.num_rows() as i64 || j >= field.num_cols() as i64 {
+        None
+    } else {
+        Some(field.get_heightmap().at(i as usize, j as usize))
+    }
+}
+
+// Normal at vertex (i, j) from central differences of the heights around it,
+// one-sided where 'height' has no neighbour.  Rows go towards -z.
+fn get_vertex_normal<F: Fn(i64, i64) -> Option<f32>>(height: F, i: i64, j: i64, dx: f32, dz: f32) -> Vec3 {
+    let h = height(i, j).unwrap_or(0.0);
+    let slope = |lo: Option<f32>, hi: Option<f32>, d: f32| match (lo, hi) {
+        (Some(lo), Some(hi)) => (hi - lo) / (2.0 * d),
+        (Some(lo), None) => (h - lo) / d,
+        (None, Some(hi)) => (hi - h) / d,
+        (None, None) => 0.0,
+    };
+    let dhdx = slope(height(i, j - 1), height(i, j + 1), dx);
+    let dhdz = slope(height(i + 1, j), height(i - 1, j), dz);
+    Vec3::new(-dhdx, 1.0, -dhdz).normalize()
+}